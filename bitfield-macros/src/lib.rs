@@ -0,0 +1,195 @@
+//! The `#[bitfield]` attribute of the [`bitfield`](https://docs.rs/bitfield) crate.
+//!
+//! It declares a bitfield as a struct with named fields, which is expanded to a call of the
+//! `bitfield!` macro, so the generated accessors are the same. The struct-shaped declaration is
+//! understood by the IDEs and formatted by `rustfmt`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use bitfield_macros::bitfield;
+//!
+//! #[bitfield(u16, impl Debug)]
+//! #[derive(Clone, Copy)]
+//! pub struct Control {
+//!     /// The mode of operation.
+//!     #[bits(3..=0)]
+//!     pub mode: u8,
+//!     #[bits(4)]
+//!     pub enable: bool,
+//!     #[bits(7, 5)]
+//!     pub(crate) level: u8,
+//!     #[bits(11..=8)]
+//!     pub lanes: [u8; 2],
+//! }
+//!
+//! let mut control = Control(0);
+//! control.set_mode(5);
+//! control.set_enable(true);
+//! control.set_lanes(1, 3);
+//! assert_eq!(control.mode(), 5);
+//! assert_eq!(control.0, 0x3015);
+//! ```
+//!
+//! The arguments of `#[bitfield(...)]` are the storage, as it would be written between the
+//! parentheses of the struct in `bitfield!`, followed by the `impl` declarations, separated by
+//! commas: `#[bitfield(MSB0 [u8], impl Debug, impl new)]`. The other attributes of the struct, and
+//! its visibility, are kept.
+//!
+//! Every field has a `#[bits(...)]` attribute, with the positions of the field as they would be
+//! written in `bitfield!`: one bit, `msb, lsb`, or `msb..=lsb`. The type of the field is the type
+//! of the getter, and its visibility the one of the accessors. A field `name` has a getter `name`
+//! and a setter `set_name`. For a field of type `[T; N]`, the positions are the ones of the first
+//! element, and the field is an array of `N` elements. For `[bool; N]`, the position is the bit of
+//! the first element, the elements being consecutive bits. The other attributes of the fields, like
+//! the documentation comments, are applied to the accessors.
+//!
+//! The expansion refers to the `bitfield` crate as `::bitfield`, so it must be a dependency of the
+//! crate using the attribute, and must not be renamed.
+
+extern crate proc_macro;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Type};
+
+/// Declares a bitfield as a struct with named fields, see the [crate documentation](crate).
+#[proc_macro_attribute]
+pub fn bitfield(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(args.into(), input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(args: TokenStream, input: DeriveInput) -> syn::Result<TokenStream> {
+    let mut args = split_commas(args).into_iter();
+    let storage = match args.next() {
+        Some(storage) if !storage.is_empty() => storage,
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "expected the storage of the bitfield, like `#[bitfield(u32)]`",
+            ))
+        }
+    };
+    let impls = args
+        .map(|declaration| match declaration.clone().into_iter().next() {
+            Some(TokenTree::Ident(ident)) if ident == "impl" => Ok(declaration),
+            _ => Err(Error::new_spanned(
+                declaration,
+                "expected an `impl` declaration, like `impl Debug`",
+            )),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    if !input.generics.params.is_empty() || input.generics.where_clause.is_some() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "`#[bitfield]` doesn't support generics",
+        ));
+    }
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    data.fields.span(),
+                    "`#[bitfield]` requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "`#[bitfield]` can only be used on a struct",
+            ))
+        }
+    };
+
+    let mut declarations = Vec::new();
+    for field in fields {
+        // The fields are named, so they all have an ident.
+        let getter = field.ident.as_ref().unwrap();
+        let setter = format_ident!("set_{}", getter.unraw());
+        let mut bits = None;
+        let mut attrs = Vec::new();
+        for attr in &field.attrs {
+            if !attr.path().is_ident("bits") {
+                attrs.push(attr);
+            } else if bits.is_some() {
+                return Err(Error::new_spanned(
+                    attr,
+                    "duplicate `#[bits(...)]` attribute",
+                ));
+            } else {
+                let list = attr.meta.require_list()?;
+                if list.tokens.is_empty() {
+                    return Err(Error::new_spanned(
+                        attr,
+                        "expected the positions of the field",
+                    ));
+                }
+                bits = Some(list.tokens.clone());
+            }
+        }
+        let bits = bits.ok_or_else(|| {
+            Error::new(
+                getter.span(),
+                "expected a `#[bits(...)]` attribute with the positions of the field",
+            )
+        })?;
+        let vis = &field.vis;
+        declarations.push(match field.ty {
+            // The elements of the `bool` arrays are single bits, starting at the given bit.
+            Type::Array(ref array) if is_bool(&array.elem) => {
+                let count = &array.len;
+                quote!(#(#attrs)* #vis bool, #getter, #setter: (#bits), (#bits), #count;)
+            }
+            Type::Array(ref array) => {
+                let (ty, count) = (&array.elem, &array.len);
+                quote!(#(#attrs)* #vis #ty, #getter, #setter: #bits, #count;)
+            }
+            ref ty => quote!(#(#attrs)* #vis #ty, #getter, #setter: #bits;),
+        });
+    }
+
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+    let name = &input.ident;
+    Ok(quote! {
+        ::bitfield::bitfield! {
+            #(#attrs)*
+            #vis struct #name(#storage);
+            #(#impls;)*
+            #(#declarations)*
+        }
+    })
+}
+
+fn is_bool(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref path) => path.qself.is_none() && path.path.is_ident("bool"),
+        _ => false,
+    }
+}
+
+/// Splits the arguments of the attribute at the commas outside of any group.
+fn split_commas(tokens: TokenStream) -> Vec<TokenStream> {
+    let mut parts = vec![TokenStream::new()];
+    for token in tokens {
+        match token {
+            TokenTree::Punct(ref punct) if punct.as_char() == ',' => parts.push(TokenStream::new()),
+            token => token.to_tokens(parts.last_mut().unwrap()),
+        }
+    }
+    if parts.last().is_some_and(TokenStream::is_empty) && parts.len() > 1 {
+        parts.pop();
+    }
+    parts
+}
@@ -10,6 +10,10 @@
 //!  See the documentation of the macros for how to use them.
 //!
 //!  Examples and tests are also a great way to understand how to use these macros.
+//!
+//!  The `paste` feature, enabled by default, is needed by the declarations generating names derived
+//!  from the field names, like `field name:`, the arrays of `bitfield!` (for their `_all` setter) or
+//!  `impl Fields;`. Without it, these declarations are rejected at compile time.
 
 /// Generates and dispatches trait implementations for a struct
 ///
@@ -19,46 +23,423 @@
 ///
 /// Supported traits:
 /// * Debug
+/// * Display
+/// * LowerHex, UpperHex, Binary, Octal
 /// * BitAnd
 /// * BitOr
 /// * BitXor
+/// * Not
+/// * Shl, Shr
+/// * Clone, Copy
+/// * PartialEq
+/// * Eq
+/// * PartialOrd
+/// * Ord
+/// * Hash
+/// * From
+/// * TryFrom
 ///
 /// Additional derivations:
 /// * new
-///   * Creates a constructor, including parameters for all fields with a setter
+///   * Creates a constructor, including parameters for all fields with a setter, taking an array of all the
+///     elements for the array fields
 /// * new{constructor_name(setter_name: setter_type, ...)}
 ///   * Creates a constructor using the given name and parameters. In order to compile correctly, each `setter_name`
-///     must be the setter of a field of type `setter_type` specified later in the macro.
+///     must be the setter of a field of type `setter_type` specified later in the macro. The other fields with a
+///     default value are set to it.
+/// * RawBits
+///   * For slice-backed structs, creates the `raw_bits` and `set_raw_bits` methods, accessing the bits from 0 to the
+///     last bit of the fields as an `u128`. For MSB0 structs, the bit 0 is the most significant bit of the value
+/// * Masks
+///   * For integer storages, creates a `X_MASK` constant, of the storage type, for every field `x` declared without a
+///     `mask`, like `mask X_MASK(T),` would
+/// * With
+///   * For every field `x` with a setter, creates a `with_x` method taking `self` by value, setting the field and
+///     returning `self`, to build values fluently: `Control(0).with_mode(3).with_enable(true)`. For write-only fields,
+///     the name of the setter is used instead of `x`
+/// * Builder
+///   * Creates a `TheNameOfTheStructBuilder` struct, with a method per field with a setter taking the value of the field
+///     (an array of all the elements for the array fields), and a `build` method returning the struct, or a
+///     `MissingField` error if a field without a default value isn't set. For integer storages, also creates a
+///     `builder` function. The names of the fields must not be `build`
+/// * Update
+///   * For every field `x` with a getter and a setter, creates an `update_x` method taking a closure, called with the
+///     value of the field, and setting the field to the value it returns: `counter.update_count(|count| count + 1)`.
+///     For arrays, the index of the element is given first
+/// * Flags
+///   * For every single-bit field `x` with a getter and a setter, creates the `is_x`, `raise_x`, `clear_x` and
+///     `toggle_x` methods. The setter is usually named `set_x`, so setting the bit uses `raise_x` instead. Fields
+///     with a conversion, an access word or `valid_if` are skipped
+/// * Modify
+///   * Creates a `modify` method taking a closure, and a `<Name>Writer` struct, dereferencing to a copy of the value,
+///     given to the closure. The copy is then written back with a single assignment to the storage, so that the
+///     setters called in the closure result in a single read-modify-write
+/// * Poll
+///   * For every field `x` with a getter, creates a `wait_for_x(expected, poll, max_polls)` function calling `poll`,
+///     which reads the storage, until the field equals `expected`, and returning the last value read, or
+///     `Err(Timeout)` after `max_polls` reads. For arrays, the index of the element is given first
+/// * Pending
+///   * For every `bool` array field `x` with a setter, creates a `take_x` method returning an iterator over the
+///     indices of the set elements, after clearing them in the struct. For the fields declared `w1c` (write one to
+///     clear), the elements are left set, so that writing the value back to the register acknowledges them
+/// * Complete
+///   * Fails to compile if a bit of the storage isn't part of a field or of a `reserved: msb, lsb;` declaration. For
+///     slice-backed structs, the bits up to the last bit of the fields are checked
+/// * TrySet
+///   * For every setter `set_x` of a field of more than one bit, creates a `try_set_x` method returning
+///     `Err(ValueTooLarge)` instead of truncating a value that doesn't fit in the bits of the field
+/// * Pin
+///   * For every setter `set_x`, creates a `set_x_pinned` method taking `self: Pin<&mut Self>`. The bitfield structs
+///     are `Unpin` as long as their storage is, so a pinned parent can project to its bitfield fields without
+///     `unsafe` and update them through these methods
+/// * Serde
+///   * With the `serde` feature, creates implementations of `Serialize` and `Deserialize` representing the struct as
+///     a map of its fields, using the getters to serialize and the setters to deserialize
+/// * SerdeRaw
+///   * With the `serde` feature, creates implementations of `Serialize` and `Deserialize` using only the storage
+/// * JsonSchema
+///   * With the `schemars` feature, creates an implementation of `JsonSchema` describing the serialization of
+///     `impl Serde;`, with the values of the integer fields bounded by their width
+/// * Values{field: Enum{Name = value, ...}, ...}
+///   * For each given field, creates the `Enum` enum with a variant per named value, and a `field_value` method
+///     returning the named value of the field, or `None`, so that the values can be matched exhaustively
+/// * FromStr
+///   * Creates an implementation of `FromStr` parsing assignments separated by commas, like `"version=4,ttl=0x40"`
+///     or `"lanes[1]=-3"`, starting from the default storage. Only the fields with a setter can be assigned. The
+///     values are integers, with an optional `0x`, `0o` or `0b` prefix, or `true`/`false` for single bit fields.
+///     They are converted to the type taken by the setter, which is then called, so the byte order, the `valid_if`
+///     bit and the conversion of the field apply
+/// * FieldsModule{module_name}
+///   * Creates a `module_name` module, with the visibility of the struct, containing a module per field with the
+///     `OFFSET` and `WIDTH` constants, `COUNT` for arrays, and `MASK` for integer storages
+/// * Cached{cached_name}
+///   * Creates a `cached_name` struct owning the struct, with the same getters and setters, where the getters compute
+///     their value once and clone it until a setter is called. Attributes of the struct can be given before its
+///     name, like `impl Cached{#[derive(Debug)] CachedHeader};`
+/// * ReadOnly{view_name}
+///   * Creates a `view_name` struct wrapping the struct, with only the getters, and a conversion from the struct.
+///     Attributes of the view can be given before its name, like `impl ReadOnly{#[derive(Debug)] View};`
+/// * ReadOnlyStorage
+///   * Fails to compile if a field has a setter. Without setters, the methods of the slice-backed structs only
+///     require `AsRef`, so `Foo<&[u8]>` is the natural instantiation
+/// * CRepr{c_type}
+///   * Creates the `from_c_repr` and `to_c_repr` methods converting from and to `c_type`, typically a
+///     `#[repr(C, packed)]` struct mirroring a C struct with bit-fields. `c_type` must have a field of the same name
+///     and type for every field, which must all have a getter and a setter
+/// * Pod
+///   * With the `bytemuck` feature, makes the struct `#[repr(transparent)]` and creates implementations of `Pod` and
+///     `Zeroable`. The struct must also derive `Clone` and `Copy`, and must not have a `repr` attribute
+/// * CastSlice
+///   * For integer-backed structs, makes the struct `#[repr(transparent)]` and creates the `cast_slice` and
+///     `cast_slice_mut` functions viewing a slice of the storage type as a slice of the struct
+/// * FromBytes, IntoBytes, Unaligned, KnownLayout, Immutable
+///   * With the `zerocopy` feature, makes the struct `#[repr(transparent)]` and derives the `zerocopy` trait. The
+///     derived implementations refer to the crate as `::bitfield::zerocopy`, so it must not be renamed
+/// * Distribution, Distribution{fields}
+///   * With the `rand` feature, creates an implementation of `Distribution<Self>` for `Standard`, so that
+///     `rng.gen()` returns a value with a random storage, or, with `{fields}`, with random fields and the other bits
+///     set to zero
+/// * Arbitrary
+///   * With the `arbitrary` feature, creates an implementation of `Arbitrary` filling the storage from the
+///     unstructured input
+/// * StorageCell
+///   * For slice-backed structs, creates the `with_storage` and `with_storage_mut` methods to access the fields
+///     of a struct whose storage implements `StorageCell`, like a `RefCell`
+/// * Decode
+///   * Creates a `decode_from` function reading the fields from a `BitReader`, in declaration order
+/// * Encode
+///   * Creates an `encode_into` method writing the fields to a `BitWriter`, in declaration order
+/// * FromUninit
+///   * Creates a `from_uninit_written` function that builds the value from the value of every field, like
+///     `new`, and writes it to a `MaybeUninit<Self>` at once. For slice-backed structs, it is implemented for
+///     the arrays of any length
+/// * LayoutHash
+///   * Creates a `LAYOUT_HASH` constant, a hash of the names and positions of the fields computed at compile time
+/// * Default
+///   * Creates an implementation of `Default` that starts from a zeroed storage and calls the setter of every field
+///     that has a default value (`field, set_field: 7, 0 = 0x3;`), and a `reset` method restoring that value
+/// * AnyBitfield
+///   * Implements the object-safe `AnyBitfield` trait, using the `FIELDS` table of `impl Fields;`
+/// * Fields
+///   * Creates a `TheNameOfTheStructField` enum with a variant per field (all listed in its `ALL` constant), a
+///     `FIELDS` table describing the position of each field, a `LAYOUT` descriptor of that table, a `FIELD_DEFAULT`
+///     constant for every field with a default value, the `get_field_raw`/`set_field_raw` methods to access a field
+///     using that table, an `iter_fields` method giving the raw bits of every field in declaration order, a `diff`
+///     method, and a `field_bit_address` function returning the byte address and bit index of a field from the
+///     address of the storage. With the `alloc` feature, also creates the `render_table` and `render_diff` methods.
+///     Implements `HasFields`, used by `assert_field_eq!` and `assert_fields_eq!`.
 #[macro_export(local_inner_macros)]
 macro_rules! bitfield_impl {
-    (Debug for struct $name:ident([$t:ty]); $($rest:tt)*) => {
+    // Only the ordering depends on the bit order, the other implementations are the same as for the
+    // non-MSB0 version.
+    ($trait:ident$({$($trait_arg:tt)*})? for $vis:vis struct $name:ident(MSB0 [$t:ty]); $($rest:tt)*) => {
+        bitfield_impl!{@msb0 $trait$({$($trait_arg)*})? for $vis struct $name([$t]); $($rest)*}
+    };
+    (@msb0 PartialOrd for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]>> $crate::cmp::PartialOrd for $name<T> {
+            fn partial_cmp(&self, other: &Self) -> Option<$crate::cmp::Ordering> {
+                AsRef::<[$t]>::as_ref(&self.0).partial_cmp(AsRef::<[$t]>::as_ref(&other.0))
+            }
+        }
+    };
+    (@msb0 Ord for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]>> $crate::cmp::Ord for $name<T> {
+            fn cmp(&self, other: &Self) -> $crate::cmp::Ordering {
+                AsRef::<[$t]>::as_ref(&self.0).cmp(AsRef::<[$t]>::as_ref(&other.0))
+            }
+        }
+    };
+    (@msb0 Shl for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_impl!{@shift Shl shl ShlAssign shl_assign $name([$t]) big_endian}
+    };
+    (@msb0 Shr for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_impl!{@shift Shr shr ShrAssign shr_assign $name([$t]) big_endian}
+    };
+    (@msb0 Fields for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@fields $vis $name([$t], true));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (@msb0 $($rest:tt)*) => {
+        bitfield_impl!{$($rest)*}
+    };
+    // The `LE` and `BE` storages only change how the elements are read and written. The
+    // implementations using the values of the elements are not supported, the other ones are the
+    // same as for the native storages.
+    ($trait:ident$({$($trait_arg:tt)*})? for $vis:vis struct $name:ident(MSB0 $endian:ident [$t:ty]); $($rest:tt)*) => {
+        bitfield_impl!{@endian $endian true; $trait$({$($trait_arg)*})? for $vis struct $name([$t]); $($rest)*}
+    };
+    ($trait:ident$({$($trait_arg:tt)*})? for $vis:vis struct $name:ident($endian:ident [$t:ty]); $($rest:tt)*) => {
+        bitfield_impl!{@endian $endian false; $trait$({$($trait_arg)*})? for $vis struct $name([$t]); $($rest)*}
+    };
+    (@endian $endian:ident $msb0:tt; Shl for $($_rest:tt)*) => {
+        __bitfield_compile_error!{"`impl Shl;` isn't supported with the `LE` and `BE` storages"}
+    };
+    (@endian $endian:ident $msb0:tt; Shr for $($_rest:tt)*) => {
+        __bitfield_compile_error!{"`impl Shr;` isn't supported with the `LE` and `BE` storages"}
+    };
+    (@endian $endian:ident $msb0:tt; PartialOrd for $($_rest:tt)*) => {
+        __bitfield_compile_error!{"`impl PartialOrd;` isn't supported with the `LE` and `BE` storages"}
+    };
+    (@endian $endian:ident $msb0:tt; Ord for $($_rest:tt)*) => {
+        __bitfield_compile_error!{"`impl Ord;` isn't supported with the `LE` and `BE` storages"}
+    };
+    (@endian $endian:ident $msb0:tt; LowerHex for $($_rest:tt)*) => {
+        __bitfield_compile_error!{"`impl LowerHex;` isn't supported with the `LE` and `BE` storages"}
+    };
+    (@endian $endian:ident $msb0:tt; UpperHex for $($_rest:tt)*) => {
+        __bitfield_compile_error!{"`impl UpperHex;` isn't supported with the `LE` and `BE` storages"}
+    };
+    (@endian $endian:ident $msb0:tt; Binary for $($_rest:tt)*) => {
+        __bitfield_compile_error!{"`impl Binary;` isn't supported with the `LE` and `BE` storages"}
+    };
+    (@endian $endian:ident $msb0:tt; Octal for $($_rest:tt)*) => {
+        __bitfield_compile_error!{"`impl Octal;` isn't supported with the `LE` and `BE` storages"}
+    };
+    (@endian $endian:ident $msb0:tt; Fields for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@fields $vis $name([$t], $msb0, $endian));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (@endian $endian:ident $msb0:tt; $($rest:tt)*) => {
+        bitfield_impl!{$($rest)*}
+    };
+    (Debug for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
         impl<T: AsRef<[$t]> + $crate::fmt::Debug> $crate::fmt::Debug for $name<T> {
             bitfield_debug!{struct $name; $($rest)*}
         }
     };
-    (Debug for struct $name:ident($t:ty); $($rest:tt)*) => {
+    (Debug for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
         impl $crate::fmt::Debug for $name {
             bitfield_debug!{struct $name; $($rest)*}
         }
     };
-    (BitAnd for struct $name:ident([$t:ty]); $($rest:tt)*) => {
+    (Display for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]>> $crate::fmt::Display for $name<T> {
+            bitfield_display!{struct $name; $($rest)*}
+        }
+    };
+    (Display for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::fmt::Display for $name {
+            bitfield_display!{struct $name; $($rest)*}
+        }
+    };
+    (LowerHex for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        bitfield_impl!{@radix LowerHex $name $storage "0x" "{:01$x}" 4}
+    };
+    (UpperHex for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        bitfield_impl!{@radix UpperHex $name $storage "0x" "{:01$X}" 4}
+    };
+    (Binary for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        bitfield_impl!{@radix Binary $name $storage "0b" "{:01$b}" 1}
+    };
+    (Octal for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        bitfield_impl!{@radix Octal $name $storage "0o" "{:01$o}" 3}
+    };
+    (@radix $trait:ident $name:ident([$t:ty]) $prefix:literal $format:literal $bits_per_digit:literal) => {
+        impl<T: AsRef<[$t]>> $crate::fmt::$trait for $name<T> {
+            fn fmt(&self, f: &mut $crate::fmt::Formatter) -> $crate::fmt::Result {
+                let digits = ($crate::size_of::<$t>() * 8).div_ceil($bits_per_digit);
+                if f.alternate() {
+                    f.write_str($prefix)?;
+                }
+                for element in AsRef::<[$t]>::as_ref(&self.0) {
+                    f.write_fmt(__bitfield_format_args!($format, element, digits))?;
+                }
+                Ok(())
+            }
+        }
+    };
+    (@radix $trait:ident $name:ident($t:ty) $prefix:literal $format:literal $bits_per_digit:literal) => {
+        impl $crate::fmt::$trait for $name {
+            fn fmt(&self, f: &mut $crate::fmt::Formatter) -> $crate::fmt::Result {
+                $crate::fmt::$trait::fmt(&self.0, f)
+            }
+        }
+    };
+    (BitAnd for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
         bitfield_impl!{@bitwise BitAnd bitand BitAndAssign bitand_assign $name([$t]) &=}
     };
-    (BitAnd for struct $name:ident($t:ty); $($rest:tt)*) => {
+    (BitAnd for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
         bitfield_impl!{@bitwise BitAnd bitand BitAndAssign bitand_assign $name($t) &=}
     };
-    (BitOr for struct $name:ident([$t:ty]); $($rest:tt)*) => {
+    (BitOr for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
         bitfield_impl!{@bitwise BitOr bitor BitOrAssign bitor_assign $name([$t]) |=}
     };
-    (BitOr for struct $name:ident($t:ty); $($rest:tt)*) => {
+    (BitOr for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
         bitfield_impl!{@bitwise BitOr bitor BitOrAssign bitor_assign $name($t) |=}
     };
-    (BitXor for struct $name:ident([$t:ty]); $($rest:tt)*) => {
+    (BitXor for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
         bitfield_impl!{@bitwise BitXor bitxor BitXorAssign bitxor_assign $name([$t]) ^=}
     };
-    (BitXor for struct $name:ident($t:ty); $($rest:tt)*) => {
+    (BitXor for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
         bitfield_impl!{@bitwise BitXor bitxor BitXorAssign bitxor_assign $name($t) ^=}
     };
+    (Not for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsMut<[$t]>> $crate::ops::Not for $name<T> {
+            type Output = Self;
+            fn not(mut self) -> Self {
+                for element in AsMut::<[$t]>::as_mut(&mut self.0) {
+                    *element = !*element;
+                }
+                self
+            }
+        }
+    };
+    (Not for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::ops::Not for $name {
+            type Output = Self;
+            fn not(self) -> Self {
+                $name(!self.0)
+            }
+        }
+    };
+    (Shl for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        bitfield_impl!{@shift Shl shl ShlAssign shl_assign $name $storage}
+    };
+    (Shr for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        bitfield_impl!{@shift Shr shr ShrAssign shr_assign $name $storage}
+    };
+    (@shift $shift:ident $func:ident $shift_assign:ident $func_assign:ident $name:ident([$t:ty]) $($order:ident)?) => {
+        impl<T: AsMut<[$t]>> $crate::ops::$shift<usize> for $name<T> {
+            type Output = Self;
+            fn $func(mut self, rhs: usize) -> Self {
+                bitfield_impl!{@shift_slice $func $($order)? self.0, rhs, $t}
+                self
+            }
+        }
+        impl<T: AsMut<[$t]>> $crate::ops::$shift_assign<usize> for $name<T> {
+            fn $func_assign(&mut self, rhs: usize) {
+                bitfield_impl!{@shift_slice $func $($order)? self.0, rhs, $t}
+            }
+        }
+    };
+    (@shift $shift:ident $func:ident $shift_assign:ident $func_assign:ident $name:ident($t:ty)) => {
+        impl $crate::ops::$shift<usize> for $name {
+            type Output = Self;
+            fn $func(self, rhs: usize) -> Self {
+                let shifted = $crate::convert::TryFrom::try_from(rhs)
+                    .ok()
+                    .and_then(|rhs| bitfield_impl!{@checked $func self.0, rhs});
+                $name(shifted.unwrap_or(0))
+            }
+        }
+        impl $crate::ops::$shift_assign<usize> for $name {
+            fn $func_assign(&mut self, rhs: usize) {
+                *self = $crate::ops::$shift::$func($name(self.0), rhs);
+            }
+        }
+    };
+    (@checked shl $value:expr, $rhs:ident) => {
+        $value.checked_shl($rhs)
+    };
+    (@checked shr $value:expr, $rhs:ident) => {
+        $value.checked_shr($rhs)
+    };
+    // The MSB0 slices are shifted as a big-endian integer: the element 0 holds the most significant
+    // bits, so that `<<` moves the bits towards the bit 0.
+    (@shift_slice shl big_endian $storage:expr, $rhs:ident, $t:ty) => {{
+        let elements = AsMut::<[$t]>::as_mut(&mut $storage);
+        let bits = $crate::size_of::<$t>() * 8;
+        let (offset, remainder) = ($rhs / bits, $rhs % bits);
+        let len = elements.len();
+        for i in 0..len {
+            let mut element = 0;
+            if offset < len - i {
+                element = elements[i + offset] << remainder;
+                if remainder != 0 && offset + 1 < len - i {
+                    element |= elements[i + offset + 1] >> (bits - remainder);
+                }
+            }
+            elements[i] = element;
+        }
+    }};
+    (@shift_slice shr big_endian $storage:expr, $rhs:ident, $t:ty) => {{
+        let elements = AsMut::<[$t]>::as_mut(&mut $storage);
+        let bits = $crate::size_of::<$t>() * 8;
+        let (offset, remainder) = ($rhs / bits, $rhs % bits);
+        for i in (0..elements.len()).rev() {
+            let mut element = 0;
+            if i >= offset {
+                element = elements[i - offset] >> remainder;
+                if remainder != 0 && i > offset {
+                    element |= elements[i - offset - 1] << (bits - remainder);
+                }
+            }
+            elements[i] = element;
+        }
+    }};
+    // The other slices are shifted as a little-endian integer: the element 0 holds the least
+    // significant bits.
+    (@shift_slice shl $storage:expr, $rhs:ident, $t:ty) => {{
+        let elements = AsMut::<[$t]>::as_mut(&mut $storage);
+        let bits = $crate::size_of::<$t>() * 8;
+        let (offset, remainder) = ($rhs / bits, $rhs % bits);
+        for i in (0..elements.len()).rev() {
+            let mut element = 0;
+            if i >= offset {
+                element = elements[i - offset] << remainder;
+                if remainder != 0 && i > offset {
+                    element |= elements[i - offset - 1] >> (bits - remainder);
+                }
+            }
+            elements[i] = element;
+        }
+    }};
+    (@shift_slice shr $storage:expr, $rhs:ident, $t:ty) => {{
+        let elements = AsMut::<[$t]>::as_mut(&mut $storage);
+        let bits = $crate::size_of::<$t>() * 8;
+        let (offset, remainder) = ($rhs / bits, $rhs % bits);
+        let len = elements.len();
+        for i in 0..len {
+            let mut element = 0;
+            if offset < len - i {
+                element = elements[i + offset] >> remainder;
+                if remainder != 0 && offset + 1 < len - i {
+                    element |= elements[i + offset + 1] << (bits - remainder);
+                }
+            }
+            elements[i] = element;
+        }
+    }};
     (@bitwise $bitwise:ident $func:ident $bitwise_assign:ident $func_assign:ident $name:ident([$t:ty]) $op:tt) => {
         impl<T: AsMut<[$t]> + AsRef<[$t]>> $crate::ops::$bitwise for $name<T> {
             type Output = Self;
@@ -94,884 +475,7723 @@ macro_rules! bitfield_impl {
             as_mut[i] $op rhs[i];
         }
     }};
-    (new for struct $name:ident([$t:ty]); $($rest:tt)*) => {
-        impl<T: AsMut<[$t]> + Default> $name<T> {
-            bitfield_constructor!{() -> {}; $($rest)*}
+    (Clone for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: $crate::clone::Clone> $crate::clone::Clone for $name<T> {
+            fn clone(&self) -> Self {
+                $name($crate::clone::Clone::clone(&self.0))
+            }
         }
     };
-    (new for struct $name:ident($t:ty); $($rest:tt)*) => {
-        impl $name {
-            bitfield_constructor!{() -> {}; $($rest)*}
+    (Clone for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::clone::Clone for $name {
+            fn clone(&self) -> Self {
+                $name($crate::clone::Clone::clone(&self.0))
+            }
         }
     };
-    (new{$new:ident ($($setter_name:ident: $setter_type:ty),*$(,)?)} for struct $name:ident([$t:ty]); $($rest:tt)*) => {
-        impl<T: AsMut<[$t]> + Default> $name<T> {
-            pub fn $new($($setter_name: $setter_type),*) -> Self {
-                let mut value = Self(T::default());
-                $(
-                    value.$setter_name($setter_name);
-                )*
-                value
+    (Copy for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: $crate::marker::Copy> $crate::marker::Copy for $name<T> {}
+    };
+    (Copy for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::marker::Copy for $name {}
+    };
+    (PartialEq for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]>> $crate::cmp::PartialEq for $name<T> {
+            fn eq(&self, other: &Self) -> bool {
+                AsRef::<[$t]>::as_ref(&self.0) == AsRef::<[$t]>::as_ref(&other.0)
             }
         }
     };
-    (new{$new:ident ($($setter_name:ident: $setter_type:ty),*$(,)?)} for struct $name:ident($t:ty); $($rest:tt)*) => {
-        impl $name {
-            pub fn $new($($setter_name: $setter_type),*) -> Self {
-                let mut value = Self($t::default());
-                $(
-                    value.$setter_name($setter_name);
-                )*
-                value
+    (PartialEq for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::cmp::PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
             }
         }
     };
-    // display a more friendly error message when someone tries to use `impl <Trait>;` syntax when not supported
-    ($macro:ident for struct $name:ident $($rest:tt)*) => {
-        ::std::compile_error!(::std::stringify!(Unsupported impl $macro for struct $name));
-    };
-}
-
-/// Declares the fields of struct.
-///
-/// This macro will generate the methods to access the fields of a bitfield. It must be called
-/// from an `impl` block for a type that implements the `BitRange` and/or the `Bit` traits
-/// (which traits are required depending on what type of fields are used).
-///
-/// The syntax of this macro is composed of declarations ended by semicolons. There are two types
-/// of declarations: default type, and fields.
-///
-/// A default type is just a type followed by a semicolon. This will affect all the following field
-/// declarations.
-///
-/// A field declaration is composed of the following:
-///
-/// * Optional attributes (`#[...]`), documentation comments (`///`) are attributes;
-/// * An optional pub keyword to make the methods public
-/// * An optional type followed by a comma
-/// * Optionally, the word `into` followed by a type, followed by a comma
-/// * The getter and setter idents, separated by a comma
-/// * A colon
-/// * One to three expressions of type `usize`
-///
-/// The attributes and pub will be applied to the two methods generated.
-///
-/// If the `into` part is used, the getter will convert the field after reading it.
-///
-/// The getter and setter idents can be `_` to not generate one of the two. For example, if the
-/// setter is `_`, the field will be read-only.
-///
-/// The expressions at the end are the bit positions. Their meaning depends on the number of
-/// expressions:
-///
-///  * One expression: the field is a single bit. The type is ignored and `bool` is used. The trait
-///    `Bit` is used.
-///  * Two expressions: `msb, lsb`, the field is composed of the bits from `msb` to `lsb`, included.
-///  * Three expressions: `msb, lsb, count`, the field is an array. The first element is composed of
-///    the bits from `msb` to `lsb`. The following elements are consecutive bits range of the same
-///    size.
-///
-/// # Example
-///
-/// ```rust
-/// # #[macro_use] extern crate bitfield;
-/// # fn main() {}
-/// # struct FooBar(u64);
-/// # bitfield_bitrange!{struct FooBar(u64)}
-/// # impl From<u32> for FooBar{ fn from(_: u32) -> FooBar {unimplemented!()}}
-/// # impl From<FooBar> for u32{ fn from(_: FooBar) -> u32 {unimplemented!()}}
-/// # impl FooBar {
-/// bitfield_fields!{
-///     // The default type will be `u64
-///     u64;
-///     // filed1 is read-write, public, the methods are inline
-///     #[inline]
-///     pub field1, set_field1: 10, 0;
-///     // `field2` is  read-only, private, and of type bool.
-///     field2, _ : 0;
-///     // `field3` will be read as an `u32` and then converted to `FooBar`.
-///     // The setter is not affected, it still need an `u32` value.
-///     u32, into FooBar, field3, set_field3: 10, 0;
-///     // `field4` will be read as an `u32` and then converted to `FooBar`.
-///     // The setter will take a `FooBar`, and converted back to an `u32`.
-///     u32, from into FooBar, field4, set_field4: 10, 0;
-///     // `field5` will be read as an `u32` and then converted to `FooBar`.
-///     // The setter will take a `FooBar`, and converted back to an `u32`.
-///     // The struct will have an associated constant `FIELD5_MASK` of type u64
-///     //with the bits of field5 set
-///     u32, mask FIELD5_MASK(u64), from into FooBar, field5, set_field5: 10, 0;
-/// }
-/// # }
-/// ```
-#[macro_export(local_inner_macros)]
-macro_rules! bitfield_fields {
-    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, _, $setter:ident: $($exprs:expr),*) => {
-        bitfield_fields!(only mask; @field $(#[$attribute])* ($($vis)*) $t, $mask($mask_t): $($exprs),*);
-    };
-    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $getter:ident, _: $($exprs:expr),*) => {
-        bitfield_fields!(only mask; @field $(#[$attribute])* ($($vis)*) $t, $mask($mask_t): $($exprs),*);
+    (Eq for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]>> $crate::cmp::Eq for $name<T> {}
     };
-    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, __NO_MASK_FOR_FIELD($mask_t:ty): $($exprs:expr),*) => {};
-    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty): $bit:expr) => {
-        $($vis)* const $mask: $mask_t = 1 << $bit;
+    (Eq for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::cmp::Eq for $name {}
     };
-    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty): $msb:expr, $lsb:expr) => {
-        $($vis)* const $mask: $mask_t = {
-            let msb = $msb;
-            let lsb = $lsb;
-            let mut i = lsb;
-            let mut acc = 0;
-            while i <= msb {
-                acc |= 1<<i;
-                i += 1;
+    (PartialOrd for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]>> $crate::cmp::PartialOrd for $name<T> {
+            fn partial_cmp(&self, other: &Self) -> Option<$crate::cmp::Ordering> {
+                let lhs = AsRef::<[$t]>::as_ref(&self.0).iter().rev();
+                lhs.partial_cmp(AsRef::<[$t]>::as_ref(&other.0).iter().rev())
             }
-            acc
-        };
+        }
     };
-    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty): $msb:expr, $lsb:expr, $count:expr) => {
-        $($vis)* const $mask: $mask_t = {
-            let msb = $msb;
-            let lsb = $lsb;
-            let width = msb - lsb;
-            let full_msb = msb + width * $count;
-            let mut i = lsb;
-            let mut acc = 0;
-            while i <= full_msb {
-                acc |= 1<<i;
-                i += 1;
+    (PartialOrd for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::cmp::PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<$crate::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
             }
-            acc
-        };
+        }
     };
-    (only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, _, $setter:ident: $msb:expr,
-     $lsb:expr, $count:expr) => {
-        $(#[$attribute])*
-        #[allow(unknown_lints)]
-        #[allow(eq_op)]
-        $($vis)* fn $setter(&mut self, index: usize, value: $from) {
-            use $crate::BitRangeMut;
-            __bitfield_debug_assert!(index < $count);
-            let width = $msb - $lsb + 1;
-            let lsb = $lsb + index*width;
-            let msb = lsb + width - 1;
-            self.set_bit_range(msb, lsb, $crate::Into::<$t>::into(value));
+    (Ord for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]>> $crate::cmp::Ord for $name<T> {
+            fn cmp(&self, other: &Self) -> $crate::cmp::Ordering {
+                let lhs = AsRef::<[$t]>::as_ref(&self.0).iter().rev();
+                lhs.cmp(AsRef::<[$t]>::as_ref(&other.0).iter().rev())
+            }
         }
     };
-    (only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, _, $setter:ident: $msb:expr,
-     $lsb:expr) => {
-        $(#[$attribute])*
-        $($vis)* fn $setter(&mut self, value: $from) {
-            use $crate::BitRangeMut;
-            self.set_bit_range($msb, $lsb, $crate::Into::<$t>::into(value));
+    (Ord for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::cmp::Ord for $name {
+            fn cmp(&self, other: &Self) -> $crate::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
         }
     };
-    (only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, _, $setter:ident: $bit:expr) => {
-        $(#[$attribute])*
-        $($vis)* fn $setter(&mut self, value: bool) {
-            use $crate::BitMut;
-            self.set_bit($bit, value);
+    (Hash for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]>> $crate::hash::Hash for $name<T> {
+            fn hash<H: $crate::hash::Hasher>(&self, state: &mut H) {
+                $crate::hash::Hash::hash(AsRef::<[$t]>::as_ref(&self.0), state);
+            }
         }
     };
-    (only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, _, $setter:ident: $($exprs:expr),*) => {};
-
-    (only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $getter:ident, _: $msb:expr, $lsb:expr, $count:expr) => {
-        $(#[$attribute])*
-        #[allow(unknown_lints)]
-        #[allow(eq_op)]
-        $($vis)* fn $getter(&self, index: usize) -> $into {
-            use $crate::BitRange;
-            __bitfield_debug_assert!(index < $count);
-            let width = $msb - $lsb + 1;
-            let lsb = $lsb + index*width;
-            let msb = lsb + width - 1;
-            let raw_value: $t = self.bit_range(msb, lsb);
-            $crate::Into::into(raw_value)
+    (Hash for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::hash::Hash for $name {
+            fn hash<H: $crate::hash::Hasher>(&self, state: &mut H) {
+                $crate::hash::Hash::hash(&self.0, state);
+            }
         }
     };
-    (only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $getter:ident, _: $msb:expr,
-     $lsb:expr) => {
-        $(#[$attribute])*
-        $($vis)* fn $getter(&self) -> $into {
-            use $crate::BitRange;
-            let raw_value: $t = self.bit_range($msb, $lsb);
-            $crate::Into::into(raw_value)
+    (From for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<const N: usize> From<[$t; N]> for $name<[$t; N]> {
+            fn from(value: [$t; N]) -> Self {
+                $name(value)
+            }
+        }
+        impl<const N: usize> From<$name<[$t; N]>> for [$t; N] {
+            fn from(value: $name<[$t; N]>) -> Self {
+                value.0
+            }
         }
     };
-    (only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $getter:ident, _: $bit:expr) => {
-        $(#[$attribute])*
-        $($vis)* fn $getter(&self) -> bool {
-            use $crate::Bit;
-            self.bit($bit)
+    (From for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl From<$t> for $name {
+            fn from(value: $t) -> Self {
+                $name(value)
+            }
+        }
+        impl From<$name> for $t {
+            fn from(value: $name) -> Self {
+                value.0
+            }
         }
     };
-    (only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $getter:ident, _: $($exprs:expr),*) => {};
-
-    (only $only:tt; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $getter:ident, $setter:ident:
-     $($exprs:expr),*) => {
-        bitfield_fields!(only $only; @field $(#[$attribute])* ($($vis)*) $t, $mask($mask_t), $from, $into, $getter, _: $($exprs),*);
-        bitfield_fields!(only $only; @field $(#[$attribute])* ($($vis)*) $t, __NO_MASK_FOR_FIELD(u8), $from, $into, _, $setter: $($exprs),*);
+    (TryFrom for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@try_from $name([$t]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
     };
+    (@try_from $name:ident([$t:ty]) [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter $_getter:tt setter $_setter:tt pos [$($exprs:expr),*]
+        $($_rest:tt)*
+    })*]) => {
+        impl<T> $name<T> {
+            /// The number of elements of the storage needed to hold all the fields.
+            #[allow(dead_code)]
+            pub fn required_len() -> usize {
+                #[allow(unused_mut)]
+                let mut required_bits: usize = 0;
+                $(
+                    $(#[$cfg])*
+                    {
+                        let end = bitfield_impl!{@bit_end $($exprs),*};
+                        if end > required_bits {
+                            required_bits = end;
+                        }
+                    }
+                )*
+                required_bits.div_ceil($crate::size_of::<$t>() * 8)
+            }
+        }
 
-    (only $only:tt; $t:ty;) => {};
-    (only $only:tt; $default_ty:ty; pub $($rest:tt)*) => {
-        bitfield_fields!{only $only; $default_ty; () pub $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; #[$attribute:meta] $($rest:tt)*) => {
-        bitfield_fields!{only $only; $default_ty; (#[$attribute]) $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attributes:meta])*) #[$attribute:meta] $($rest:tt)*) => {
-        bitfield_fields!{only $only; $default_ty; ($(#[$attributes])* #[$attribute]) $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub $t:ty, mask $mask:ident($mask_t:ty), from into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $t, $mask($mask_t), $into, $into, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub $t:ty, mask $mask:ident($mask_t:ty), into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $t, $mask($mask_t), $t, $into, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+        impl<'a> $name<&'a [$t]> {
+            /// Creates a bitfield borrowing `slice`, checking that it is long enough to hold all the fields.
+            #[allow(dead_code)]
+            pub fn try_from_slice(slice: &'a [$t]) -> Result<Self, $crate::BufferTooShort> {
+                $crate::BufferTooShort::check(Self::required_len(), slice.len())?;
+                Ok($name(slice))
+            }
+        }
+
+        impl<'a> $name<&'a mut [$t]> {
+            /// Creates a bitfield borrowing `slice` mutably, checking that it is long enough to hold all the
+            /// fields.
+            #[allow(dead_code)]
+            pub fn try_from_mut_slice(slice: &'a mut [$t]) -> Result<Self, $crate::BufferTooShort> {
+                $crate::BufferTooShort::check(Self::required_len(), slice.len())?;
+                Ok($name(slice))
+            }
+        }
+
+        impl<'a> $crate::convert::TryFrom<&'a [$t]> for $name<&'a [$t]> {
+            type Error = $crate::BufferTooShort;
+
+            fn try_from(slice: &'a [$t]) -> Result<Self, Self::Error> {
+                Self::try_from_slice(slice)
+            }
+        }
+
+        impl<'a> $crate::convert::TryFrom<&'a mut [$t]> for $name<&'a mut [$t]> {
+            type Error = $crate::BufferTooShort;
+
+            fn try_from(slice: &'a mut [$t]) -> Result<Self, Self::Error> {
+                Self::try_from_mut_slice(slice)
+            }
+        }
     };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub $t:ty, mask $mask:ident($mask_t:ty), $getter:tt, $setter:tt:  $($exprs:expr),*;
-     $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $t, $mask($mask_t), $t, $t, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub mask $mask:ident($mask_t:ty), from into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $default_ty, $mask($mask_t), $into, $into, $getter, $setter:
-                         $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub mask $mask:ident($mask_t:ty), into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $default_ty, $mask($mask_t), $default_ty, $into, $getter, $setter:
-                         $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub mask $mask:ident($mask_t:ty), $getter:tt, $setter:tt:  $($exprs:expr),*;
-     $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $default_ty, $mask($mask_t), $default_ty, $default_ty, $getter, $setter:
-                                $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (@bit_end $bit:expr) => {
+        $bit + 1
     };
-
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub $t:ty, from into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $t, __NO_MASK_FOR_FIELD(u8), $into, $into, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (@bit_end $msb:expr, $lsb:expr) => {
+        $msb + 1
     };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub $t:ty, into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $t, __NO_MASK_FOR_FIELD(u8), $t, $into, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (@bit_end $msb:expr, $lsb:expr, $count:expr) => {
+        $lsb + $count * ($msb - $lsb + 1)
     };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub $t:ty, $getter:tt, $setter:tt:  $($exprs:expr),*;
-     $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $t, __NO_MASK_FOR_FIELD(u8), $t, $t, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub from into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $default_ty, __NO_MASK_FOR_FIELD(u8), $into, $into, $getter, $setter:
-                         $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $default_ty, __NO_MASK_FOR_FIELD(u8), $default_ty, $into, $getter, $setter:
-                         $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) pub $getter:tt, $setter:tt:  $($exprs:expr),*;
-     $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* (pub) $default_ty, __NO_MASK_FOR_FIELD(u8), $default_ty, $default_ty, $getter, $setter:
-                                $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (@bit_end $msb:expr, $lsb:expr, $count:expr, $stride:expr) => {
+        $lsb + ($count - 1) * $stride + ($msb - $lsb + 1)
     };
-
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) $t:ty, mask $mask:ident($mask_t:ty), from into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $t, $mask($mask_t), $into, $into, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (RawBits for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@raw_bits ($vis) $name([$t]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
     };
-
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) $t:ty, mask $mask:ident($mask_t:ty), into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $t, $mask($mask_t), $t, $into, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (RawBits for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        __bitfield_compile_error!(__bitfield_stringify!(impl RawBits requires a slice storage for struct $name));
     };
+    (@raw_bits ($($vis:tt)*) $name:ident([$t:ty]) [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter $_getter:tt setter $_setter:tt pos [$($exprs:expr),*]
+        $($_rest:tt)*
+    })*]) => {
+        impl<T> $name<T> {
+            /// The number of bits covered by the fields, from the bit 0.
+            #[allow(dead_code)]
+            $($vis)* fn raw_bits_width() -> usize {
+                #[allow(unused_mut)]
+                let mut width: usize = 0;
+                $(
+                    $(#[$cfg])*
+                    {
+                        let end = bitfield_impl!{@bit_end $($exprs),*};
+                        if end > width {
+                            width = end;
+                        }
+                    }
+                )*
+                __bitfield_assert!(width <= 128);
+                width
+            }
+        }
 
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) $t:ty, mask $mask:ident($mask_t:ty), $getter:tt, $setter:tt:  $($exprs:expr),*;
-     $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $t, $mask($mask_t), $t, $t, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) mask $mask:ident($mask_t:ty), from into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $default_ty, $mask($mask_t), $into, $into, $getter, $setter:
-                         $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) mask $mask:ident($mask_t:ty), into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $default_ty, $mask($mask_t), $default_ty, $into, $getter, $setter:
-                         $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) mask $mask:ident($mask_t:ty), $getter:tt, $setter:tt:  $($exprs:expr),*;
-     $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $default_ty, $mask($mask_t), $default_ty, $default_ty, $getter, $setter:
-                                $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+        impl<T: AsRef<[$t]>> $name<T> {
+            /// Returns the bits covered by the fields as an integer, in the bit order of the struct.
+            #[allow(dead_code)]
+            $($vis)* fn raw_bits(&self) -> u128 {
+                match Self::raw_bits_width() {
+                    0 => 0,
+                    width => $crate::BitRange::<u128>::bit_range(self, width - 1, 0),
+                }
+            }
+        }
+
+        impl<T: AsMut<[$t]>> $name<T> {
+            /// Sets the bits covered by the fields from an integer, in the bit order of the struct.
+            #[allow(dead_code)]
+            $($vis)* fn set_raw_bits(&mut self, value: u128) {
+                match Self::raw_bits_width() {
+                    0 => {}
+                    width => $crate::BitRangeMut::<u128>::set_bit_range(self, width - 1, 0, value),
+                }
+            }
+        }
     };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) $t:ty, from into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $t, __NO_MASK_FOR_FIELD(u8), $into, $into, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (FromUninit for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_constructor!(@constructor written (impl<const N: usize> $name<[$t; N]>) ([0; N]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
     };
-
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) $t:ty, into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $t, __NO_MASK_FOR_FIELD(u8), $t, $into, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (FromUninit for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_constructor!(@constructor written (impl $name) (<$t as Default>::default())); $t; $($rest)*}
     };
-
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) $t:ty, $getter:tt, $setter:tt:  $($exprs:expr),*;
-     $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $t, __NO_MASK_FOR_FIELD(u8), $t, $t, $getter, $setter: $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) from into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $default_ty, __NO_MASK_FOR_FIELD(u8), $into, $into, $getter, $setter:
-                         $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) into $into:ty, $getter:tt, $setter:tt:
-     $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $default_ty, __NO_MASK_FOR_FIELD(u8), $default_ty, $into, $getter, $setter:
-                         $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
-    };
-    (only $only:tt; $default_ty:ty; ($(#[$attribute:meta])*) $getter:tt, $setter:tt:  $($exprs:expr),*;
-     $($rest:tt)*) => {
-        bitfield_fields!{only $only; @field $(#[$attribute])* () $default_ty, __NO_MASK_FOR_FIELD(u8), $default_ty, $default_ty, $getter, $setter:
-                                $($exprs),*}
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (Encode for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@encode $name([$t]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
     };
-    (only $only:tt; $previous_default_ty:ty; $default_ty:ty; $($rest:tt)*) => {
-        bitfield_fields!{only $only; $default_ty; $($rest)*}
+    (Encode for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@encode $name($t)); $t; $($rest)*}
     };
-    (only $only:tt; $default_ty:ty; $($rest:tt)*) => {
-        bitfield_fields!{only $only; $default_ty; () $($rest)*}
+    (@encode $name:ident([$t:ty]) $records:tt) => {
+        impl<T> $name<T> {
+            bitfield_impl!{@encode_into $records}
+        }
     };
-    (only $only:tt; $($rest:tt)*) => {
-        bitfield_fields!{only $only; SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    (@encode $name:ident($t:ty) $records:tt) => {
+        impl $name {
+            bitfield_impl!{@encode_into $records}
+        }
     };
-    ($($rest:tt)*) => {
-        bitfield_fields!{only getter; $($rest)*}
-        bitfield_fields!{only setter; $($rest)*}
-        bitfield_fields!{only mask; $($rest)*}
-    }
-}
-
-/// Generates a `fmt::Debug` implementation.
-///
-/// This macros must be called from a `impl Debug for ...` block. It will generate the `fmt` method.
-///
-/// In most of the case, you will not directly call this macros, but use `bitfield`.
-///
-/// The syntax is `struct TheNameOfTheStruct` followed by the syntax of `bitfield_fields`.
-///
-/// The write-only fields are ignored.
-///
-/// # Example
-///
-/// ```rust
-/// # #[macro_use] extern crate bitfield;
-/// struct FooBar(u32);
-/// bitfield_bitrange!{struct FooBar(u32)}
-/// impl FooBar{
-///     bitfield_fields!{
-///        u32;
-///        field1, _: 7, 0;
-///        field2, _: 31, 24;
-///     }
-/// }
-///
-/// impl std::fmt::Debug for FooBar {
-///     bitfield_debug!{
-///        struct FooBar;
-///        field1, _: 7, 0;
-///        field2, _: 31, 24;
-///     }
-/// }
-///
-/// fn main() {
-///     let foobar = FooBar(0x11223344);
-///     println!("{:?}", foobar);
+    (@encode_into [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter $_getter:tt setter $_setter:tt pos [$($exprs:expr),*]
+        $($_rest:tt)*
+    })*]) => {
+        /// The number of bits written by `encode_into`.
+        #[allow(dead_code)]
+        pub const ENCODED_BITS: usize = {
+            let widths: &[usize] = &[
+                $(
+                    $(#[$cfg])*
+                    bitfield_impl!{@encoded_bits $($exprs),*},
+                )*
+            ];
+            let mut bits = 0;
+            let mut i = 0;
+            while i < widths.len() {
+                bits += widths[i];
+                i += 1;
+            }
+            bits
+        };
 
-/// }
-/// ```
-#[macro_export(local_inner_macros)]
-macro_rules! bitfield_debug {
-    (struct $name:ident; $($rest:tt)*) => {
-        fn fmt(&self, f: &mut $crate::fmt::Formatter) -> $crate::fmt::Result {
-            let mut debug_struct = f.debug_struct(__bitfield_stringify!($name));
-            debug_struct.field(".0", &self.0);
-            bitfield_debug!{debug_struct, self, $($rest)*}
-            debug_struct.finish()
+        /// Writes the raw bits of every field to `writer`, in declaration order.
+        ///
+        /// The elements of array fields are written from index 0 upward.
+        #[allow(dead_code)]
+        pub fn encode_into(&self, writer: &mut $crate::BitWriter)
+        where
+            Self: $crate::BitRange<u128>,
+        {
+            $(
+                $(#[$cfg])*
+                bitfield_impl!{@encode_field self, writer: $($exprs),*}
+            )*
         }
     };
-    ($debug_struct:ident, $self:ident, mask $mask:ident($mask_t:ty), $($rest:tt)*) => {
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@encoded_bits $bit:expr) => {
+        1
     };
-    ($debug_struct:ident, $self:ident, #[$attribute:meta] $($rest:tt)*) => {
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@encoded_bits $msb:expr, $lsb:expr $(, $count:expr $(, $_stride:expr)?)?) => {
+        ($msb - $lsb + 1) $(* $count)?
     };
-    ($debug_struct:ident, $self:ident, pub $($rest:tt)*) => {
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@encode_field $self:ident, $writer:ident: $bit:expr) => {
+        $writer.write_bits(1, $crate::BitRange::<u128>::bit_range($self, $bit, $bit));
     };
-    ($debug_struct:ident, $self:ident, _, $setter:tt: $($exprs:expr),*; $($rest:tt)*) => {
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@encode_field $self:ident, $writer:ident: $msb:expr, $lsb:expr) => {
+        $writer.write_bits($msb - $lsb + 1, $crate::BitRange::<u128>::bit_range($self, $msb, $lsb));
     };
-    ($debug_struct:ident, $self:ident, $type:ty; $($rest:tt)*) => {
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@encode_field $self:ident, $writer:ident: $msb:expr, $lsb:expr, $count:expr $(, $stride:expr)?) => {
+        for index in 0..$count {
+            let width = $msb - $lsb + 1;
+            let lsb = $lsb + index * bitfield_fields!(@stride width $(, $stride)?);
+            $writer.write_bits(width, $crate::BitRange::<u128>::bit_range($self, lsb + width - 1, lsb));
+        }
     };
-    ($debug_struct:ident, $self:ident, $getter:ident, $setter:tt: $msb:expr, $lsb:expr, $count:expr;
-     $($rest:tt)*) => {
-        let mut array = [$self.$getter(0); $count];
-        for (i, e) in (&mut array).into_iter().enumerate() {
-            *e = $self.$getter(i);
+    (Decode for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@decode $name([$t]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Decode for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@decode $name($t)); $t; $($rest)*}
+    };
+    (@decode $name:ident([$t:ty]) $records:tt) => {
+        impl<T: Default> $name<T> {
+            bitfield_impl!{@decode_from T::default(), $records}
         }
-        $debug_struct.field(__bitfield_stringify!($getter), &array);
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
     };
-    ($debug_struct:ident, $self:ident, $getter:ident, $setter:tt: $($exprs:expr),*; $($rest:tt)*)
-        => {
-        $debug_struct.field(__bitfield_stringify!($getter), &$self.$getter());
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@decode $name:ident($t:ty) $records:tt) => {
+        impl $name {
+            bitfield_impl!{@decode_from <$t as Default>::default(), $records}
+        }
     };
-    ($debug_struct:ident, $self:ident, from into $into:ty, $($rest:tt)*) => {
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@decode_from $storage:expr, [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter $_getter:tt setter $_setter:tt pos [$($exprs:expr),*]
+        $($_rest:tt)*
+    })*]) => {
+        /// Reads the raw bits of every field from `reader`, in declaration order.
+        ///
+        /// The elements of array fields are read from index 0 upward. The bits that are not part
+        /// of a field are left to their default value.
+        #[allow(dead_code)]
+        pub fn decode_from(reader: &mut $crate::BitReader) -> Result<Self, $crate::EndOfStream>
+        where
+            Self: $crate::BitRangeMut<u128>,
+        {
+            let mut value = Self($storage);
+            $(
+                $(#[$cfg])*
+                bitfield_impl!{@decode_field value, reader: $($exprs),*}
+            )*
+            Ok(value)
+        }
     };
-    ($debug_struct:ident, $self:ident, into $into:ty, $($rest:tt)*) => {
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@decode_field $value:ident, $reader:ident: $bit:expr) => {
+        $crate::BitRangeMut::<u128>::set_bit_range(&mut $value, $bit, $bit, $reader.read_bits(1)?);
     };
-    ($debug_struct:ident, $self:ident, $type:ty, $($rest:tt)*) => {
-        bitfield_debug!{$debug_struct, $self, $($rest)*}
+    (@decode_field $value:ident, $reader:ident: $msb:expr, $lsb:expr) => {
+        $crate::BitRangeMut::<u128>::set_bit_range(&mut $value, $msb, $lsb, $reader.read_bits($msb - $lsb + 1)?);
     };
-    ($debug_struct:ident, $self:ident, ) => {};
-}
+    (@decode_field $value:ident, $reader:ident: $msb:expr, $lsb:expr, $count:expr $(, $stride:expr)?) => {
+        for index in 0..$count {
+            let width = $msb - $lsb + 1;
+            let lsb = $lsb + index * bitfield_fields!(@stride width $(, $stride)?);
+            $crate::BitRangeMut::<u128>::set_bit_range(&mut $value, lsb + width - 1, lsb, $reader.read_bits(width)?);
+        }
+    };
+    (StorageCell for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<C: $crate::StorageCell> $name<C> {
+            /// Calls `f` with a view of the bitfield borrowing the storage.
+            #[allow(dead_code)]
+            pub fn with_storage<R, F: FnOnce(&$name<&C::Storage>) -> R>(&self, f: F) -> R {
+                $crate::StorageCell::with_storage(&self.0, |storage| f(&$name(storage)))
+            }
 
-/// Implements an exhaustive constructor function for a bitfield. Should only be called by `bitfield!` when using `impl new;`
-///
-/// # Examples
-///
-/// ```rs
-/// bitfield_constructor {0; () -> {}; u8; foo1, set_foo1: 2,0; foo2, set_foo2: 7,2}
-/// ```
-/// Generates:
-/// ```rs
-/// pub fn new(set_foo1: u8, set_foo2: u8) -> Self {
-///     let mut value = Self(0);
-///     value.set_foo1(set_foo1);
-///     value.set_foo2(set_foo2);
-///     value
-/// }
-/// ```
-#[macro_export(local_inner_macros)]
-macro_rules! bitfield_constructor {
-    (() -> {}; $($rest:tt)*) => {
-        bitfield_constructor!{@value; () -> {let mut value = Self(Default::default());}; bool; $($rest)*}
+            /// Calls `f` with a view of the bitfield borrowing the storage mutably.
+            #[allow(dead_code)]
+            pub fn with_storage_mut<R, F: FnOnce(&mut $name<&mut C::Storage>) -> R>(&self, f: F) -> R {
+                $crate::StorageCell::with_storage_mut(&self.0, |storage| f(&mut $name(storage)))
+            }
+        }
     };
-    (@$value:ident; ($($param:ident: $ty:ty,)*) -> {$($stmt:stmt;)*}; $old_ty:ty; impl $_trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
-        bitfield_constructor!{@$value; ($($param: $ty,)*) -> {$($stmt;)*}; $old_ty; $($rest)*}
+    (Serde for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records __bitfield_serde!(@serde $name([$t])) []);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
     };
-    (@$value:ident; ($($param:ident: $ty:ty,)*) -> {$($stmt:stmt;)*}; $old_ty:ty; $new_ty:ty; $($rest:tt)*) => {
-        bitfield_constructor!{@$value; ($($param: $ty,)*) -> {$($stmt;)*}; $new_ty; $($rest)*}
+    (Serde for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records __bitfield_serde!(@serde $name($t)) []); $t; $($rest)*}
     };
-    (@$value:ident; ($($param:ident: $ty:ty,)*) -> {$($stmt:stmt;)*}; $default_ty:ty;
-    $(#[$_:meta])* $(pub)? $(into $_into:ty,)?
-    $_getter:ident, $setter:ident: $($_expr:expr),*; $($rest:tt)* ) => {
-        bitfield_constructor!{@$value;
-            ($($param: $ty,)* $setter: $default_ty,) -> {$($stmt;)* $value.$setter($setter);};
-            $default_ty; $($rest)*}
+    (SerdeRaw for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        __bitfield_serde!{@serde_raw $name $storage}
     };
-    (@$value:ident; ($($param:ident: $ty:ty,)*) -> {$($stmt:stmt;)*}; $default_ty:ty;
-    $(#[$_:meta])* $(pub)? $field_type:ty, $(into $_into:ty,)?
-    $_getter:ident, $setter:ident: $($_expr:expr),*; $($rest:tt)* ) => {
-        bitfield_constructor!{@$value;
-            ($($param: $ty,)* $setter: $field_type,) -> {$($stmt;)* $value.$setter($setter);};
-            $default_ty; $($rest)*}
+    (Pod for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        __bitfield_bytemuck!{$name $storage}
     };
-    (@$value:ident; ($($param:ident: $ty:ty,)*) -> {$($stmt:stmt;)*}; $_:ty;) => {
-        #[allow(clippy::too_many_arguments)]
-        pub fn new($($param: $ty),*) -> Self {
-            $($stmt;)*
-            $value
+    (CastSlice for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        __bitfield_compile_error!(__bitfield_stringify!(impl CastSlice requires an integer storage for struct $name));
+    };
+    (CastSlice for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        const _: () = {
+            $crate::__bitfield_assert!($crate::mem::size_of::<$name>() == $crate::mem::size_of::<$t>());
+            $crate::__bitfield_assert!($crate::mem::align_of::<$name>() == $crate::mem::align_of::<$t>());
+        };
+
+        impl $name {
+            /// Views a slice of the storage type as a slice of this bitfield.
+            $vis fn cast_slice(slice: &[$t]) -> &[Self] {
+                // SAFETY: `Self` is `#[repr(transparent)]` over the storage type, so the two have
+                // the same size and alignment and every value of the storage is a valid `Self`.
+                unsafe { $crate::slice::from_raw_parts(slice.as_ptr() as *const Self, slice.len()) }
+            }
+
+            /// Views a mutable slice of the storage type as a mutable slice of this bitfield.
+            $vis fn cast_slice_mut(slice: &mut [$t]) -> &mut [Self] {
+                // SAFETY: see `cast_slice`.
+                unsafe { $crate::slice::from_raw_parts_mut(slice.as_mut_ptr() as *mut Self, slice.len()) }
+            }
         }
     };
-}
+    (JsonSchema for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse __bitfield_schemars!($name([$t]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (JsonSchema for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse __bitfield_schemars!($name($t)); $t; $($rest)*}
+    };
+    (Distribution for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        __bitfield_rand!{@storage $name $storage}
+    };
+    (Distribution{fields} for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse __bitfield_rand!(@fields $name([$t]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Distribution{fields} for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse __bitfield_rand!(@fields $name($t)); $t; $($rest)*}
+    };
+    (Arbitrary for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        __bitfield_arbitrary!{$name $storage}
+    };
+    (Pin for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@pin $name([$t])) []);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Pin for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@pin $name($t)) []); $t; $($rest)*}
+    };
+    (@pin $name:ident([$t:ty]) [$($record:tt)*]) => {
+        impl<T: AsMut<[$t]> + Unpin> $name<T> {
+            $(bitfield_fields!{@setter_record bitfield_impl!(@pin_setter) $record})*
+        }
+    };
+    (@pin $name:ident($t:ty) [$($record:tt)*]) => {
+        impl $name {
+            $(bitfield_fields!{@setter_record bitfield_impl!(@pin_setter) $record})*
+        }
+    };
+    (@pin_setter {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter [_] $($_rest:tt)*
+    }) => {};
+    (@pin_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name $_name:tt getter $_getter:tt setter [$setter:ident] pos [$bit:expr] $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            $($vis)* fn [<$setter _pinned>](self: $crate::pin::Pin<&mut Self>, value: bool) {
+                $crate::pin::Pin::get_mut(self).$setter(value);
+            }
+        }
+    };
+    (@pin_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name $_name:tt getter $_getter:tt setter [$setter:ident]
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            $($vis)* fn [<$setter _pinned>](self: $crate::pin::Pin<&mut Self>, index: usize, value: $from) {
+                $crate::pin::Pin::get_mut(self).$setter(index, value);
+            }
+        }
+    };
+    (@pin_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name $_name:tt getter $_getter:tt setter [$setter:ident] $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            $($vis)* fn [<$setter _pinned>](self: $crate::pin::Pin<&mut Self>, value: $from) {
+                $crate::pin::Pin::get_mut(self).$setter(value);
+            }
+        }
+    };
+    (Modify for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        paste! {
+            #[doc = "The writer given to the closure of `" $name "::modify`, dereferencing to a copy of the value."]
+            $vis struct [<$name Writer>]<T>($name<T>);
 
-/// Implements `BitRange` and `BitRangeMut` for a tuple struct (or "newtype").
-///
-/// This macro will generate an implementation of the `BitRange` trait for an existing single
-/// element tuple struct.
-///
-/// The syntax is more or less the same as declaring a "newtype", **without** the attributes,
-/// documentation comments and pub keyword.
-///
-/// The difference with a normal "newtype" is the type in parentheses. If the type is `[t]` (where
-/// `t` is any of the unsigned integer type), the "newtype" will be generic and implement
-/// `BitRange` for `T: AsRef<[t]>` and `BitRangeMut` for `T: AsMut<[t]>` (for example a slice, an array or a `Vec`). You can
-/// also use `MSB0 [t]`. The difference will be the positions of the bit. You can use the
-/// `bits_positions` example to see where each bits is. If the type is neither of this two, the
-/// "newtype" will wrap a value of the specified type and implements `BitRange` the same ways as
-/// the wrapped type.
-///
-/// # Examples
-///
-/// ```rust
-/// # #[macro_use] extern crate bitfield;
-/// # fn main() {}
-/// struct BitField1(u32);
-/// bitfield_bitrange!{struct BitField1(u32)}
-///
-/// struct BitField2<T>(T);
-/// bitfield_bitrange!{struct BitField2([u8])}
-///
-/// struct BitField3<T>(T);
-/// bitfield_bitrange!{struct BitField3(MSB0 [u8])}
-/// ```
-///
-#[macro_export(local_inner_macros)]
-macro_rules! bitfield_bitrange {
-    (@impl_bitrange_slice $name:ident, $slice_ty:ty, $bitrange_ty:ty) => {
-        impl<T: AsRef<[$slice_ty]>> $crate::BitRange<$bitrange_ty>
-            for $name<T> {
-                fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
-                    let bit_len = $crate::size_of::<$slice_ty>()*8;
-                    let value_bit_len = $crate::size_of::<$bitrange_ty>()*8;
-                    let mut value = 0;
-                    for i in (lsb..=msb).rev() {
-                        value <<= 1;
-                        value |= ((self.0.as_ref()[i/bit_len] >> (i%bit_len)) & 1) as $bitrange_ty;
-                    }
-                    value << (value_bit_len - (msb - lsb + 1)) >> (value_bit_len - (msb - lsb + 1))
+            impl<T> $crate::ops::Deref for [<$name Writer>]<T> {
+                type Target = $name<T>;
+
+                fn deref(&self) -> &$name<T> {
+                    &self.0
                 }
-        }
-        impl<T: AsMut<[$slice_ty]>> $crate::BitRangeMut<$bitrange_ty>
-            for $name<T> {
+            }
 
-                fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
-                    let bit_len = $crate::size_of::<$slice_ty>()*8;
-                    let mut value = value;
-                    for i in lsb..=msb {
-                        self.0.as_mut()[i/bit_len] &= !(1 << (i%bit_len));
-                        self.0.as_mut()[i/bit_len] |= (value & 1) as $slice_ty << (i%bit_len);
-                        value >>= 1;
-                    }
+            impl<T> $crate::ops::DerefMut for [<$name Writer>]<T> {
+                fn deref_mut(&mut self) -> &mut $name<T> {
+                    &mut self.0
                 }
             }
-    };
-    (@impl_bitrange_slice_msb0 $name:ident, $slice_ty:ty, $bitrange_ty:ty) => {
-        impl<T: AsRef<[$slice_ty]>> $crate::BitRange<$bitrange_ty>
-            for $name<T> {
-            fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
-                let bit_len = $crate::size_of::<$slice_ty>()*8;
-                let value_bit_len = $crate::size_of::<$bitrange_ty>()*8;
-                let mut value = 0;
-                for i in lsb..=msb {
-                    value <<= 1;
-                    value |= ((self.0.as_ref()[i/bit_len] >> (bit_len - i%bit_len - 1)) & 1)
-                        as $bitrange_ty;
+
+            impl<T: AsMut<[$t]> + $crate::clone::Clone> $name<T> {
+                /// Calls `f` with a writer on a copy of the value, and writes the result back with a
+                /// single assignment to the storage.
+                #[allow(dead_code)]
+                $vis fn modify<F: FnOnce(&mut [<$name Writer>]<T>)>(&mut self, f: F) {
+                    let mut writer = [<$name Writer>]($name($crate::clone::Clone::clone(&self.0)));
+                    f(&mut writer);
+                    self.0 = (writer.0).0;
                 }
-                value << (value_bit_len - (msb - lsb + 1)) >> (value_bit_len - (msb - lsb + 1))
             }
         }
-        impl<T: AsMut<[$slice_ty]>> $crate::BitRangeMut<$bitrange_ty>
-            for $name<T> {
-            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
-                let bit_len = $crate::size_of::<$slice_ty>()*8;
-                let mut value = value;
-                for i in (lsb..=msb).rev() {
-                    self.0.as_mut()[i/bit_len] &= !(1 << (bit_len - i%bit_len - 1));
-                    self.0.as_mut()[i/bit_len] |= (value & 1) as $slice_ty
-                        << (bit_len - i%bit_len - 1);
-                    value >>= 1;
+    };
+    (Modify for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        paste! {
+            #[doc = "The writer given to the closure of `" $name "::modify`, dereferencing to a copy of the value."]
+            $vis struct [<$name Writer>]($name);
+
+            impl $crate::ops::Deref for [<$name Writer>] {
+                type Target = $name;
+
+                fn deref(&self) -> &$name {
+                    &self.0
+                }
+            }
+
+            impl $crate::ops::DerefMut for [<$name Writer>] {
+                fn deref_mut(&mut self) -> &mut $name {
+                    &mut self.0
+                }
+            }
+
+            impl $name {
+                /// Calls `f` with a writer on a copy of the value, and writes the result back with a
+                /// single assignment to the storage.
+                #[allow(dead_code)]
+                $vis fn modify<F: FnOnce(&mut [<$name Writer>])>(&mut self, f: F) {
+                    let mut writer = [<$name Writer>]($name($crate::clone::Clone::clone(&self.0)));
+                    f(&mut writer);
+                    self.0 = (writer.0).0;
                 }
             }
         }
     };
-    (struct $name:ident([$t:ty])) => {
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, u8);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, u16);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, u32);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, u64);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, u128);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, i8);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, i16);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, i32);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, i64);
-        bitfield_bitrange!(@impl_bitrange_slice $name, $t, i128);
+    (Flags for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@flags (impl<T: AsRef<[$t]> + AsMut<[$t]>> $name<T>));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
     };
-    (struct $name:ident(MSB0 [$t:ty])) => {
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, u8);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, u16);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, u32);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, u64);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, u128);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, i8);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, i16);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, i32);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, i64);
-        bitfield_bitrange!(@impl_bitrange_slice_msb0 $name, $t, i128);
+    (Flags for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@flags (impl $name)); $t; $($rest)*}
     };
-    (struct $name:ident($t:ty)) => {
-        impl<T> $crate::BitRange<T> for $name where $t: $crate::BitRange<T> {
-            fn bit_range(&self, msb: usize, lsb: usize) -> T {
-                self.0.bit_range(msb, lsb)
-            }
-        }
-        impl<T> $crate::BitRangeMut<T> for $name where $t: $crate::BitRangeMut<T> {
-            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: T) {
-                self.0.set_bit_range(msb, lsb, value);
-            }
+    (@flags ($($impl:tt)*) [$($record:tt)*]) => {
+        $($impl)* {
+            $(bitfield_impl!{@flag_helpers $record})*
         }
     };
-}
+    (@flag_helpers {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$name:ident] getter [$getter:ident] setter [$setter:ident] pos [$bit:expr]
+        default $_default:tt conv [] saturating $_saturating:tt fmt $_fmt:tt const_fn $_const_fn:tt
+        consts $_consts:tt access [] bytes $_bytes:tt order $_order:tt valid [] setter_vis [$($setter_vis:tt)*]
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            $($vis)* fn [<is_ $name>](&self) -> bool {
+                self.$getter()
+            }
 
-/// Combines `bitfield_bitrange` and `bitfield_fields`.
-///
+            $(#[$attribute])*
+            $($setter_vis)* fn [<raise_ $name>](&mut self) {
+                self.$setter(true);
+            }
+
+            $(#[$attribute])*
+            $($setter_vis)* fn [<clear_ $name>](&mut self) {
+                self.$setter(false);
+            }
+
+            $(#[$attribute])*
+            $($setter_vis)* fn [<toggle_ $name>](&mut self) {
+                let value = self.$getter();
+                self.$setter(!value);
+            }
+        }
+    };
+    (@flag_helpers $_record:tt) => {};
+    (Masks for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        __bitfield_compile_error!{"`impl Masks;` requires an integer storage"}
+    };
+    (Masks for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@masks $name($t)); $t; $($rest)*}
+    };
+    (@masks $name:ident($t:ty) [$($record:tt)*]) => {
+        impl $name {
+            $(bitfield_impl!{@auto_mask $t, $record})*
+        }
+    };
+    // The fields with a `mask` already have a constant.
+    (@auto_mask $t:ty, {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask [__NO_MASK_FOR_FIELD($_mask_t:ty)]
+        from $_from:tt into $_into:tt name [$name:ident] getter $_getter:tt setter $_setter:tt pos [$($exprs:expr),+]
+        $($_rest:tt)*
+    }) => {
+        paste! {
+            bitfield_fields!{only mask; @field ($($vis)*) $t, [<$name:upper _MASK>]($t): $($exprs),+}
+        }
+    };
+    (@auto_mask $t:ty, $_record:tt) => {};
+    (With for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@with (impl<T: AsMut<[$t]>> $name<T>)) []);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (With for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@with (impl $name)) []); $t; $($rest)*}
+    };
+    (@with ($($impl:tt)*) [$($record:tt)*]) => {
+        $($impl)* {
+            $(bitfield_fields!{@setter_record bitfield_impl!(@with_setter) $record})*
+        }
+    };
+    (@with_setter {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter [_] $($_rest:tt)*
+    }) => {};
+    (@with_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] getter $_getter:tt setter [$setter:ident] pos [$bit:expr] $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            #[must_use]
+            $($vis)* fn [<with_ $name>](mut self, value: bool) -> Self {
+                self.$setter(value);
+                self
+            }
+        }
+    };
+    (@with_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] getter $_getter:tt setter [$setter:ident]
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            #[must_use]
+            $($vis)* fn [<with_ $name>](mut self, index: usize, value: $from) -> Self {
+                self.$setter(index, value);
+                self
+            }
+        }
+    };
+    (@with_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] getter $_getter:tt setter [$setter:ident] $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            #[must_use]
+            $($vis)* fn [<with_ $name>](mut self, value: $from) -> Self {
+                self.$setter(value);
+                self
+            }
+        }
+    };
+    (Update for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@update (impl<T: AsRef<[$t]> + AsMut<[$t]>> $name<T>));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Update for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@update (impl $name)); $t; $($rest)*}
+    };
+    (@update ($($impl:tt)*) [$($record:tt)*]) => {
+        $($impl)* {
+            $(bitfield_fields!{@setter_record bitfield_impl!(@updater) $record})*
+        }
+    };
+    (@updater {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into [$into:ty] name [$name:ident] getter [$getter:ident] setter [$setter:ident] pos $pos:tt default $_default:tt
+        conv $_conv:tt saturating $_saturating:tt fmt $_fmt:tt const_fn $_const_fn:tt consts $_consts:tt access $_access:tt
+        bytes $_bytes:tt order $_order:tt valid $valid:tt $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            bitfield_impl!{@updater_fn ($($vis)*) [<update_ $name>], $getter, $setter, $pos,
+                           bitfield_impl!(@updater_ty $valid $into), bitfield_impl!(@updater_ty $valid $from)}
+        }
+    };
+    (@updater {$($_record:tt)*}) => {};
+    // The fields with a `valid_if` bit are read and written as `Option`s.
+    (@updater_ty [] $t:ty) => { $t };
+    (@updater_ty [$_bit:tt] $t:ty) => { Option<$t> };
+    (@updater_fn ($($vis:tt)*) $update:ident, $getter:ident, $setter:ident, [$bit:expr], $into:ty, $from:ty) => {
+        $($vis)* fn $update(&mut self, f: impl FnOnce(bool) -> bool) {
+            let value = f(self.$getter());
+            self.$setter(value);
+        }
+    };
+    (@updater_fn ($($vis:tt)*) $update:ident, $getter:ident, $setter:ident, [$msb:expr, $lsb:expr], $into:ty, $from:ty) => {
+        $($vis)* fn $update(&mut self, f: impl FnOnce($into) -> $from) {
+            let value = f(self.$getter());
+            self.$setter(value);
+        }
+    };
+    (@updater_fn ($($vis:tt)*) $update:ident, $getter:ident, $setter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?], $into:ty, $from:ty) => {
+        $($vis)* fn $update(&mut self, index: usize, f: impl FnOnce($into) -> $from) {
+            let value = f(self.$getter(index));
+            self.$setter(index, value);
+        }
+    };
+    (Poll for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@poll (impl<T: AsRef<[$t]>> $name<T>) (T));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Poll for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@poll (impl $name) ($t)); $t; $($rest)*}
+    };
+    // `poll` reads the storage, wrapped in the struct before checking the field.
+    (@poll ($($impl:tt)*) $storage:tt [$($record:tt)*]) => {
+        $($impl)* {
+            $(bitfield_impl!{@poll_field $storage $record})*
+        }
+    };
+    (@poll_field $storage:tt {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter [_] $($_rest:tt)*
+    }) => {};
+    (@poll_field ($storage:ty) {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from $_from:tt
+        into [$into:ty] name $_name:tt getter [$getter:ident] setter $_setter:tt
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$cfg])*
+            #[doc = "Reads the storage with `poll` until the element `index` of `" $getter "` equals `expected`, at most `max_polls` times."]
+            #[allow(dead_code)]
+            $($vis)* fn [<wait_for_ $getter>]<V>(
+                index: usize,
+                expected: V,
+                mut poll: impl FnMut() -> $storage,
+                max_polls: usize,
+            ) -> Result<Self, $crate::Timeout>
+            where
+                $into: PartialEq<V>,
+            {
+                $crate::poll_until(|| Self(poll()), |value| value.$getter(index) == expected, max_polls)
+            }
+        }
+    };
+    (@poll_field $storage:tt {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from $_from:tt
+        into [$into:ty] name $_name:tt getter [$getter:ident] setter $_setter:tt pos [$bit:expr]
+        $($_rest:tt)*
+    }) => {
+        bitfield_impl!{@poll_field_fn $storage [$(#[$cfg])*] [$($vis)*] bool, $getter}
+    };
+    (@poll_field $storage:tt {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from $_from:tt
+        into [$into:ty] name $_name:tt getter [$getter:ident] $($_rest:tt)*
+    }) => {
+        bitfield_impl!{@poll_field_fn $storage [$(#[$cfg])*] [$($vis)*] $into, $getter}
+    };
+    (@poll_field_fn ($storage:ty) [$(#[$cfg:meta])*] [$($vis:tt)*] $into:ty, $getter:ident) => {
+        paste! {
+            $(#[$cfg])*
+            #[doc = "Reads the storage with `poll` until `" $getter "` equals `expected`, at most `max_polls` times."]
+            #[allow(dead_code)]
+            $($vis)* fn [<wait_for_ $getter>]<V>(
+                expected: V,
+                mut poll: impl FnMut() -> $storage,
+                max_polls: usize,
+            ) -> Result<Self, $crate::Timeout>
+            where
+                $into: PartialEq<V>,
+            {
+                $crate::poll_until(|| Self(poll()), |value| value.$getter() == expected, max_polls)
+            }
+        }
+    };
+    (Pending for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@pending (impl<T: AsRef<[$t]> + AsMut<[$t]>> $name<T>));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Pending for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@pending (impl $name)); $t; $($rest)*}
+    };
+    (@pending ($($impl:tt)*) [$($record:tt)*]) => {
+        $($impl)* {
+            $(bitfield_fields!{@setter_record bitfield_impl!(@take_pending) $record})*
+        }
+    };
+    (@take_pending {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter [$getter:ident] setter [$setter:ident]
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] default $_default:tt conv [bool] saturating $_saturating:tt
+        fmt $_fmt:tt const_fn $_const_fn:tt consts $_consts:tt access [$($access:ident)?] $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$cfg])*
+            #[doc = "Returns the indices of the set elements of `" $getter "`, and clears them, unless the field is `w1c`."]
+            #[allow(dead_code)]
+            $($vis)* fn [<take_ $getter>](&mut self) -> impl Iterator<Item = usize> {
+                $crate::__bitfield_assert!($count <= 128);
+                let mut pending = 0u128;
+                for index in 0..$count {
+                    if self.$getter(index) {
+                        pending |= 1 << index;
+                        bitfield_impl!{@clear_pending [$($access)?] self.$setter(index, false)}
+                    }
+                }
+                (0..$count).filter(move |index| pending >> index & 1 != 0)
+            }
+        }
+    };
+    (@take_pending {$($_record:tt)*}) => {};
+    // The pending bits of a write-one-to-clear field are left set, so that writing the value back
+    // acknowledges them.
+    (@clear_pending [w1c] $clear:expr) => {};
+    (@clear_pending [$($_access:ident)?] $clear:expr) => {
+        $clear;
+    };
+    (Complete for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@complete (None));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Complete for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@complete (Some($crate::size_of::<$t>() * 8))); $t; $($rest)*}
+    };
+    (@complete ($bits:expr) [$($reserved:tt)*] [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter $_getter:tt setter $_setter:tt pos $pos:tt default $_default:tt
+        conv $conv:tt $($_rest:tt)*
+    })*]) => {
+        const _: () = $crate::__bitfield_assert!($crate::first_uncovered_bit(&[
+            $(bitfield_impl!{@bits_range $reserved []},)*
+            $(
+                $(#[$cfg])*
+                bitfield_impl!{@covered_range $pos $conv},
+            )*
+        ], $bits).is_none());
+    };
+    // The bits between the elements of a strided array would be counted as covered.
+    (@covered_range [$msb:expr, $lsb:expr, $count:expr, $stride:expr] $_conv:tt) => {
+        __bitfield_compile_error!("`impl Complete;` doesn't support the arrays with a stride")
+    };
+    (@covered_range $pos:tt $conv:tt) => {
+        bitfield_impl!{@bits_range $pos $conv}
+    };
+    // Used by `bitfield!` for the integer storages.
+    (@check_bounds ($bits:expr) $($_bit_order:ident)* [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] getter $_getter:tt setter $_setter:tt pos $pos:tt default $_default:tt
+        conv $conv:tt $($_rest:tt)*
+    })*]) => {
+        $(
+            $(#[$cfg])*
+            const _: () = {
+                let (_, end) = bitfield_impl!{@bits_range $pos $conv};
+                $crate::__bitfield_assert!(
+                    bitfield_impl!{@bits_in_order $pos $conv},
+                    __bitfield_concat!("the msb of `", __bitfield_stringify!($field), "` is lower than its lsb")
+                );
+                $crate::__bitfield_assert!(
+                    end <= $bits,
+                    __bitfield_concat!("the bits of `", __bitfield_stringify!($field), "` are out of the storage")
+                );
+            };
+        )*
+    };
+    // The range of bits of a field, or of all the elements of an array, with an exclusive end.
+    (@bits_range [$msb:expr, $lsb:expr, $count:expr] [bool]) => {
+        ($lsb, $lsb + $count)
+    };
+    (@bits_range [$bit:expr] $_conv:tt) => {
+        ($bit, $bit + 1)
+    };
+    (@bits_range [$msb:expr, $lsb:expr] $_conv:tt) => {
+        ($lsb, $msb + 1)
+    };
+    (@bits_range [$msb:expr, $lsb:expr, $count:expr] $_conv:tt) => {
+        ($lsb, $lsb + ($msb - $lsb + 1) * $count)
+    };
+    (@bits_range [$msb:expr, $lsb:expr, $count:expr, $stride:expr] $_conv:tt) => {
+        ($lsb, $lsb + ($count - 1) * $stride + ($msb - $lsb + 1))
+    };
+    // The `msb` of the `bool` arrays is only informative.
+    (@bits_in_order [$msb:expr, $lsb:expr, $count:expr] [bool]) => {
+        true
+    };
+    (@bits_in_order [$msb:expr, $lsb:expr $(, $count:expr $(, $stride:expr)?)?] $_conv:tt) => {
+        $msb >= $lsb
+    };
+    (@bits_in_order [$bit:expr] $_conv:tt) => {
+        true
+    };
+    (TrySet for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@try_setters (impl<T: AsMut<[$t]>> $name<T>));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (TrySet for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@try_setters (impl $name)); $t; $($rest)*}
+    };
+    (@try_setters ($($impl:tt)*) [$($record:tt)*]) => {
+        $($impl)* {
+            $(bitfield_fields!{@setter_record bitfield_impl!(@try_setter) $record})*
+        }
+    };
+    (@try_setter {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter [_] $($_rest:tt)*
+    }) => {};
+    (@try_setter {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter $_setter:tt pos [$bit:expr] $($_rest:tt)*
+    }) => {};
+    (@try_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty [$t:ty] mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$field:ident] getter $_getter:tt setter [$setter:ident]
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $stride:expr)?] default $_default:tt conv $conv:tt $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            $($vis)* fn [<try_ $setter>](&mut self, index: usize, value: $from) -> Result<(), $crate::ValueTooLarge> {
+                __bitfield_debug_assert!(index < $count);
+                let width = $msb - $lsb + 1;
+                let lsb = $lsb + index * bitfield_fields!(@stride width $(, $stride)?);
+                let raw = bitfield_impl!{@try_set_raw $field, $t, $conv, $from, value, width}?;
+                $crate::BitRangeMut::<$t>::set_bit_range(self, lsb + width - 1, lsb, raw);
+                Ok(())
+            }
+        }
+    };
+    (@try_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty [$t:ty] mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$field:ident] getter $_getter:tt setter [$setter:ident]
+        pos [$msb:expr, $lsb:expr] default $_default:tt conv $conv:tt $($_rest:tt)*
+    }) => {
+        paste! {
+            $(#[$attribute])*
+            $($vis)* fn [<try_ $setter>](&mut self, value: $from) -> Result<(), $crate::ValueTooLarge> {
+                let raw = bitfield_impl!{@try_set_raw $field, $t, $conv, $from, value, $msb - $lsb + 1}?;
+                $crate::BitRangeMut::<$t>::set_bit_range(self, $msb, $lsb, raw);
+                Ok(())
+            }
+        }
+    };
+    // Checks that the value is unchanged by a round trip through the bits of the field, which
+    // sign-extends signed values.
+    (@try_set_raw $field:ident, $t:ty, $conv:tt, $from:ty, $value:ident, $width:expr) => {{
+        let raw: $t = bitfield_fields!(@from_value $conv $t, $value);
+        let width = $width;
+        let bits = $crate::size_of::<$t>() * 8;
+        if width >= bits || (raw << (bits - width)) >> (bits - width) == raw {
+            Ok(raw)
+        } else {
+            Err($crate::ValueTooLarge { field: __bitfield_stringify!($field), width })
+        }
+    }};
+    (new for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_constructor!(@constructor new (impl<T: AsMut<[$t]> + Default> $name<T>) (T::default()));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (new for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_constructor!(@constructor new (impl $name) (<$t as Default>::default())); $t; $($rest)*}
+    };
+    // The fields that aren't parameters start with their default value, if any.
+    (new{$new:ident ($($setter_name:ident: $setter_type:ty),*$(,)?)} for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@new (impl<T: AsMut<[$t]> + Default> $name<T>) (T::default())
+                                               $new ($($setter_name: $setter_type),*));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (new{$new:ident ($($setter_name:ident: $setter_type:ty),*$(,)?)} for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@new (impl $name) (<$t as Default>::default()) $new ($($setter_name: $setter_type),*));
+                         $t; $($rest)*}
+    };
+    (@new ($($impl:tt)*) ($storage:expr) $new:ident ($($setter_name:ident: $setter_type:ty),*) $records:tt) => {
+        $($impl)* {
+            pub fn $new($($setter_name: $setter_type),*) -> Self {
+                let mut value = Self($storage);
+                bitfield_impl!{@set_defaults value $records}
+                $(
+                    value.$setter_name($setter_name);
+                )*
+                value
+            }
+        }
+    };
+    (Builder for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@builder ($vis) $name, (<T: AsMut<[$t]> + Default>), ($name<T>),
+                                               (T::default())) []);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Builder for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $name {
+            paste! {
+                /// Creates a builder with none of the fields set.
+                #[allow(dead_code)]
+                pub fn builder() -> [<$name Builder>] {
+                    Default::default()
+                }
+            }
+        }
+
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@builder ($vis) $name, (), ($name), (<$t as Default>::default())) []); $t; $($rest)*}
+    };
+    (@builder ($($vis:tt)*) $name:ident, ($($generics:tt)*), ($($built:tt)*), ($storage:expr) [$($record:tt)*]) => {
+        paste! {
+            bitfield_impl!{@builder_struct (
+                #[doc = __bitfield_concat!("A builder of `", __bitfield_stringify!($name), "`, checking that the fields without a default value are set.")]
+                #[derive(Default)]
+                $($vis)* struct [<$name Builder>]
+            ) [] [$($record)*]}
+
+            impl [<$name Builder>] {
+                $(bitfield_fields!{@setter_record bitfield_impl!(@builder_setter) $record})*
+
+                /// Creates the value, with the default value of the fields that aren't set, or an error
+                /// naming the first field without a default value that isn't set.
+                #[allow(dead_code)]
+                pub fn build $($generics)* (self) -> Result<$($built)*, $crate::MissingField> {
+                    let mut value: $($built)* = $name($storage);
+                    bitfield_impl!{@set_defaults value [$($record)*]}
+                    $(bitfield_impl!{@builder_set value, self, $record})*
+                    Ok(value)
+                }
+            }
+        }
+    };
+    // Only the fields with a setter are in the builder.
+    (@builder_struct ($($decl:tt)*) [$($fields:tt)*] []) => {
+        $($decl)* {
+            $($fields)*
+        }
+    };
+    (@builder_struct $decl:tt [$($fields:tt)*] [{
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter [_] $($_rest:tt)*
+    } $($records:tt)*]) => {
+        bitfield_impl!{@builder_struct $decl [$($fields)*] [$($records)*]}
+    };
+    (@builder_struct $decl:tt [$($fields:tt)*] [{
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] getter $_getter:tt setter $_setter:tt
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] $($_rest:tt)*
+    } $($records:tt)*]) => {
+        bitfield_impl!{@builder_struct $decl [$($fields)* $(#[$cfg])* $name: Option<[$from; $count]>,] [$($records)*]}
+    };
+    (@builder_struct $decl:tt [$($fields:tt)*] [{
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] $($_rest:tt)*
+    } $($records:tt)*]) => {
+        bitfield_impl!{@builder_struct $decl [$($fields)* $(#[$cfg])* $name: Option<$from>,] [$($records)*]}
+    };
+    (@builder_setter {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter [_] $($_rest:tt)*
+    }) => {};
+    (@builder_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] getter $_getter:tt setter $_setter:tt
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] $($_rest:tt)*
+    }) => {
+        $(#[$attribute])*
+        #[must_use]
+        $($vis)* fn $name(mut self, value: [$from; $count]) -> Self {
+            self.$name = Some(value);
+            self
+        }
+    };
+    (@builder_setter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] $($_rest:tt)*
+    }) => {
+        $(#[$attribute])*
+        #[must_use]
+        $($vis)* fn $name(mut self, value: $from) -> Self {
+            self.$name = Some(value);
+            self
+        }
+    };
+    (@builder_set $value:ident, $builder:ident, {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter [_] $($_rest:tt)*
+    }) => {};
+    (@builder_set $value:ident, $builder:ident, {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$name:ident] getter $_getter:tt setter [$setter:ident] pos $pos:tt default $default:tt
+        $($_rest:tt)*
+    }) => {
+        $(#[$cfg])*
+        match $builder.$name {
+            Some(field) => bitfield_impl!{@builder_apply $value, $setter, $pos, field},
+            None => bitfield_impl!{@builder_missing $name, $default},
+        }
+    };
+    (@builder_apply $value:ident, $setter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?], $field:ident) => {
+        for (index, element) in $crate::iter::IntoIterator::into_iter($field).enumerate() {
+            $value.$setter(index, element);
+        }
+    };
+    (@builder_apply $value:ident, $setter:ident, $pos:tt, $field:ident) => {
+        $value.$setter($field)
+    };
+    // The fields with a default value already have it.
+    (@builder_missing $name:ident, [$default:expr]) => {
+        ()
+    };
+    (@builder_missing $name:ident, []) => {
+        return Err($crate::MissingField { field: __bitfield_stringify!($name) })
+    };
+    (FieldsModule{$module:ident} for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@fields_module ($vis) $module, $name, []);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (FieldsModule{$module:ident} for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@fields_module ($vis) $module, $name, [$t]); $t; $($rest)*}
+    };
+    (@fields_module ($($vis:tt)*) $module:ident, $name:ident, $storage:tt [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] getter $_getter:tt setter $_setter:tt pos $pos:tt $($_rest:tt)*
+    })*]) => {
+        #[doc = __bitfield_concat!("The positions of the fields of `", __bitfield_stringify!($name), "`.")]
+        $($vis)* mod $module {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $(
+                $(#[$cfg])*
+                #[doc = __bitfield_concat!("The position of the `", __bitfield_stringify!($field), "` field.")]
+                pub mod $field {
+                    #[allow(unused_imports)]
+                    use super::*;
+
+                    bitfield_impl!{@fields_module_consts $storage $pos}
+                }
+            )*
+        }
+    };
+    (@fields_module_consts $storage:tt [$bit:expr]) => {
+        bitfield_impl!{@fields_module_consts $storage [$bit, $bit]}
+    };
+    (@fields_module_consts $storage:tt [$msb:expr, $lsb:expr]) => {
+        /// The index of the least significant bit of the field.
+        pub const OFFSET: usize = $lsb;
+        /// The number of bits of the field.
+        pub const WIDTH: usize = $msb - $lsb + 1;
+        bitfield_impl!{@fields_module_mask $storage OFFSET, WIDTH, 1, WIDTH}
+    };
+    (@fields_module_consts $storage:tt [$msb:expr, $lsb:expr, $count:expr]) => {
+        /// The index of the least significant bit of the first element.
+        pub const OFFSET: usize = $lsb;
+        /// The number of bits of an element.
+        pub const WIDTH: usize = $msb - $lsb + 1;
+        /// The number of elements.
+        pub const COUNT: usize = $count;
+        bitfield_impl!{@fields_module_mask $storage OFFSET, WIDTH, COUNT, WIDTH}
+    };
+    (@fields_module_consts $storage:tt [$msb:expr, $lsb:expr, $count:expr, $stride:expr]) => {
+        /// The index of the least significant bit of the first element.
+        pub const OFFSET: usize = $lsb;
+        /// The number of bits of an element.
+        pub const WIDTH: usize = $msb - $lsb + 1;
+        /// The number of elements.
+        pub const COUNT: usize = $count;
+        /// The number of bits from the start of an element to the start of the next one.
+        pub const STRIDE: usize = $stride;
+        bitfield_impl!{@fields_module_mask $storage OFFSET, WIDTH, COUNT, STRIDE}
+    };
+    (@fields_module_mask [] $offset:expr, $width:expr, $count:expr, $stride:expr) => {};
+    (@fields_module_mask [$t:ty] $offset:expr, $width:expr, $count:expr, $stride:expr) => {
+        /// The bits of the field in the storage.
+        pub const MASK: $t = {
+            let mut mask = 0;
+            let mut element = 0;
+            while element < $count {
+                let mut i = $offset + element * $stride;
+                while i < $offset + element * $stride + $width {
+                    mask |= 1 << i;
+                    i += 1;
+                }
+                element += 1;
+            }
+            mask
+        };
+    };
+    (Cached{$(#[$cached_attribute:meta])* $cached:ident} for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@cached ($vis) [$(#[$cached_attribute])*] $cached<T>, $name<T>,
+                                               (impl<T: AsRef<[$t]>>), (impl<T: AsMut<[$t]>>), (impl<T>),) []);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Cached{$(#[$cached_attribute:meta])* $cached:ident} for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@cached ($vis) [$(#[$cached_attribute])*] $cached, $name,
+                                               (impl), (impl), (impl),) []);
+                         $t; $($rest)*}
+    };
+    (@cached ($($vis:tt)*) [$(#[$cached_attribute:meta])*] $cached:ident$(<$cached_t:ident>)?, $name:ty,
+     ($($impl_get:tt)*), ($($impl_set:tt)*), ($($impl:tt)*), [$({
+        attrs [$(#[$attribute:meta])*] cfgs [$(#[$cfg:meta])*] vis [$($field_vis:tt)*] ty $_t:tt mask $_mask:tt
+        from [$from:ty] into [$into:ty] name [$field:ident] getter [$getter:tt] setter [$setter:tt] pos $pos:tt
+        $($_rest:tt)*
+    })*]) => {
+        $(#[$cached_attribute])*
+        $($vis)* struct $cached$(<$cached_t>)? {
+            bitfield: $name,
+            $(
+                $(#[$cfg])*
+                $field: bitfield_impl!{@cached_ty $into, $getter, $pos},
+            )*
+        }
+
+        $($impl)* $cached$(<$cached_t>)? {
+            /// Wraps the bitfield, without any cached value.
+            $($vis)* fn new(bitfield: $name) -> Self {
+                $cached {
+                    bitfield,
+                    $(
+                        $(#[$cfg])*
+                        $field: bitfield_impl!{@cached_init $getter, $pos},
+                    )*
+                }
+            }
+
+            /// Returns a reference to the wrapped bitfield.
+            $($vis)* fn get_ref(&self) -> &$name {
+                &self.bitfield
+            }
+
+            /// Returns a mutable reference to the wrapped bitfield, clearing the cached values.
+            $($vis)* fn get_mut(&mut self) -> &mut $name {
+                self.invalidate();
+                &mut self.bitfield
+            }
+
+            /// Unwraps the bitfield.
+            $($vis)* fn into_inner(self) -> $name {
+                self.bitfield
+            }
+
+            fn invalidate(&mut self) {
+                $(
+                    $(#[$cfg])*
+                    {
+                        self.$field = bitfield_impl!{@cached_init $getter, $pos};
+                    }
+                )*
+            }
+        }
+
+        $($impl)* From<$name> for $cached$(<$cached_t>)? {
+            fn from(bitfield: $name) -> Self {
+                $cached::new(bitfield)
+            }
+        }
+
+        $($impl_get)* $cached$(<$cached_t>)? {
+            $(
+                $(#[$cfg])*
+                bitfield_impl!{@cached_getter [$(#[$attribute])*] ($($field_vis)*) $field, $into, $getter, $pos}
+            )*
+        }
+
+        $($impl_set)* $cached$(<$cached_t>)? {
+            $(
+                $(#[$cfg])*
+                bitfield_impl!{@cached_setter [$(#[$attribute])*] ($($field_vis)*) $from, $setter, $pos}
+            )*
+        }
+    };
+    (@cached_ty $into:ty, _, $pos:tt) => { () };
+    (@cached_ty $into:ty, $getter:ident, [$bit:expr]) => { $crate::cell::OnceCell<bool> };
+    (@cached_ty $into:ty, $getter:ident, [$msb:expr, $lsb:expr]) => { $crate::cell::OnceCell<$into> };
+    (@cached_ty $into:ty, $getter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        [$crate::cell::OnceCell<$into>; $count]
+    };
+    (@cached_init _, $pos:tt) => { () };
+    (@cached_init $getter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        $crate::array::from_fn(|_| $crate::cell::OnceCell::new())
+    };
+    (@cached_init $getter:ident, $pos:tt) => {
+        $crate::cell::OnceCell::new()
+    };
+    (@cached_getter $attrs:tt $vis:tt $field:ident, $into:ty, _, $pos:tt) => {};
+    (@cached_getter [$(#[$attribute:meta])*] ($($vis:tt)*) $field:ident, $into:ty, $getter:ident, [$bit:expr]) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self) -> bool {
+            *self.$field.get_or_init(|| self.bitfield.$getter())
+        }
+    };
+    (@cached_getter [$(#[$attribute:meta])*] ($($vis:tt)*) $field:ident, $into:ty, $getter:ident,
+     [$msb:expr, $lsb:expr]) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self) -> $into {
+            $crate::clone::Clone::clone(self.$field.get_or_init(|| self.bitfield.$getter()))
+        }
+    };
+    (@cached_getter [$(#[$attribute:meta])*] ($($vis:tt)*) $field:ident, $into:ty, $getter:ident,
+     [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self, index: usize) -> $into {
+            $crate::clone::Clone::clone(self.$field[index].get_or_init(|| self.bitfield.$getter(index)))
+        }
+    };
+    (@cached_setter $attrs:tt $vis:tt $from:ty, _, $pos:tt) => {};
+    (@cached_setter [$(#[$attribute:meta])*] ($($vis:tt)*) $from:ty, $setter:ident, [$bit:expr]) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, value: bool) {
+            self.invalidate();
+            self.bitfield.$setter(value);
+        }
+    };
+    (@cached_setter [$(#[$attribute:meta])*] ($($vis:tt)*) $from:ty, $setter:ident, [$msb:expr, $lsb:expr]) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, value: $from) {
+            self.invalidate();
+            self.bitfield.$setter(value);
+        }
+    };
+    (@cached_setter [$(#[$attribute:meta])*] ($($vis:tt)*) $from:ty, $setter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, index: usize, value: $from) {
+            self.invalidate();
+            self.bitfield.$setter(index, value);
+        }
+    };
+    (ReadOnlyStorage for $vis:vis struct $name:ident $storage:tt; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@read_only_storage);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (@read_only_storage [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] getter $_getter:tt setter [$setter:tt] $($_rest:tt)*
+    })*]) => {
+        $(
+            $(#[$cfg])*
+            bitfield_impl!{@read_only_field $field $setter}
+        )*
+    };
+    (@read_only_field $field:ident _) => {};
+    (@read_only_field $field:ident $setter:ident) => {
+        __bitfield_compile_error!(__bitfield_concat!(
+            "`", __bitfield_stringify!($field), "` has a setter, but `impl ReadOnlyStorage;` requires `_` as the setter of every field"
+        ));
+    };
+    (ReadOnly{$(#[$view_attribute:meta])* $view:ident} for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        $(#[$view_attribute])*
+        $vis struct $view<T>($name<T>);
+
+        impl<T> From<$name<T>> for $view<T> {
+            fn from(value: $name<T>) -> Self {
+                $view(value)
+            }
+        }
+
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@read_only_getters (impl<T: AsRef<[$t]>> $view<T>)) []);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (ReadOnly{$(#[$view_attribute:meta])* $view:ident} for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        $(#[$view_attribute])*
+        $vis struct $view($name);
+
+        impl From<$name> for $view {
+            fn from(value: $name) -> Self {
+                $view(value)
+            }
+        }
+
+        bitfield_fields!{@parse bitfield_fields!(@valid_records bitfield_impl!(@read_only_getters (impl $view)) []); $t; $($rest)*}
+    };
+    (@read_only_getters ($($impl:tt)*) [$($record:tt)*]) => {
+        $($impl)* {
+            $(bitfield_impl!{@read_only_getter $record})*
+        }
+    };
+    (@read_only_getter {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter [_] $($_rest:tt)*
+    }) => {};
+    (@read_only_getter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter [$getter:ident] setter $_setter:tt pos [$bit:expr] $($_rest:tt)*
+    }) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self) -> bool {
+            self.0.$getter()
+        }
+    };
+    (@read_only_getter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from $_from:tt
+        into [$into:ty] name $_name:tt getter [$getter:ident] setter $_setter:tt
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] $($_rest:tt)*
+    }) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self, index: usize) -> $into {
+            self.0.$getter(index)
+        }
+    };
+    (@read_only_getter {
+        attrs [$(#[$attribute:meta])*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty $_t:tt mask $_mask:tt from $_from:tt
+        into [$into:ty] name $_name:tt getter [$getter:ident] $($_rest:tt)*
+    }) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self) -> $into {
+            self.0.$getter()
+        }
+    };
+    (Values{$($field:ident: $enum:ident {$($(#[$variant_attribute:meta])* $variant:ident = $value:expr),* $(,)?}),* $(,)?}
+     for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        $(bitfield_impl!{@values_enum $vis $enum {$($(#[$variant_attribute])* $variant = $value),*}})*
+
+        impl<T: AsRef<[$t]>> $name<T> {
+            $(bitfield_impl!{@values_getter $vis $field $enum {$($variant = $value),*}})*
+        }
+    };
+    (Values{$($field:ident: $enum:ident {$($(#[$variant_attribute:meta])* $variant:ident = $value:expr),* $(,)?}),* $(,)?}
+     for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        $(bitfield_impl!{@values_enum $vis $enum {$($(#[$variant_attribute])* $variant = $value),*}})*
+
+        impl $name {
+            $(bitfield_impl!{@values_getter $vis $field $enum {$($variant = $value),*}})*
+        }
+    };
+    (@values_enum $vis:vis $enum:ident {$($(#[$variant_attribute:meta])* $variant:ident = $value:expr),*}) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        $vis enum $enum {
+            $(
+                $(#[$variant_attribute])*
+                $variant = $value,
+            )*
+        }
+    };
+    (@values_getter $vis:vis $field:ident $enum:ident {$($variant:ident = $value:expr),*}) => {
+        paste! {
+            #[doc = "Returns the named value of `" $field "`, or `None` if the value has no name."]
+            #[allow(dead_code)]
+            $vis fn [<$field _value>](&self) -> Option<$enum> {
+                match $crate::NamedValue::named_value(self.$field()) {
+                    $(Some(value) if value == $value => Some($enum::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+    (FromStr for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@from_str (impl<T: AsMut<[$t]> + Default>) $name<T>, T::default(),);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (FromStr for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@from_str (impl) $name, <$t as Default>::default(),); $t; $($rest)*}
+    };
+    (@from_str ($($impl:tt)*) $self_ty:ty, $storage:expr, [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty [$field_ty:ty] mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$field:ident] getter $_getter:tt setter [$setter:tt] pos $pos:tt default $_default:tt
+        conv $conv:tt saturating $_saturating:tt fmt $_fmt:tt const_fn $_const_fn:tt consts $_consts:tt
+        access $_access:tt bytes $_bytes:tt order $_order:tt valid $valid:tt $($_rest:tt)*
+    })*]) => {
+        $($impl)* $crate::str::FromStr for $self_ty {
+            type Err = $crate::ParseFieldsError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut value = Self($storage);
+                for assignment in $crate::FieldAssignments::new(s) {
+                    let assignment = assignment?;
+                    $(
+                        $(#[$cfg])*
+                        bitfield_impl!{@from_str_field value, assignment, $field, $field_ty, $from, $conv, $valid, $setter, $pos}
+                    )*
+                    return Err($crate::ParseFieldsError::UnknownField { position: assignment.position });
+                }
+                Ok(value)
+            }
+        }
+    };
+    // The values are converted to the type taken by the setter, which is called with them.
+    (@from_str_field $value:ident, $assignment:ident, $field:ident, $t:ty, $from:ty, $conv:tt, $valid:tt, _, $pos:tt) => {};
+    (@from_str_field $value:ident, $assignment:ident, $field:ident, $t:ty, $from:ty, $conv:tt, $valid:tt, $setter:ident,
+     [$bit:expr]) => {
+        if $assignment.name == __bitfield_stringify!($field) {
+            let (_, raw) = $assignment.raw_value(__bitfield_stringify!($field), 1, None, false)?;
+            $value.$setter(raw != 0);
+            continue;
+        }
+    };
+    (@from_str_field $value:ident, $assignment:ident, $field:ident, $t:ty, $from:ty, $conv:tt, $valid:tt, $setter:ident,
+     [$msb:expr, $lsb:expr]) => {
+        if $assignment.name == __bitfield_stringify!($field) {
+            let width = $msb - $lsb + 1;
+            let (_, raw) = $assignment.raw_value(__bitfield_stringify!($field), width, None, <$t>::MIN != 0)?;
+            let field_value = bitfield_impl!{@from_str_value $field, $t, $from, $conv, raw, width}?;
+            $value.$setter(bitfield_impl!(@from_str_valid $valid field_value));
+            continue;
+        }
+    };
+    (@from_str_field $value:ident, $assignment:ident, $field:ident, $t:ty, $from:ty, $conv:tt, $valid:tt, $setter:ident,
+     [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        if $assignment.name == __bitfield_stringify!($field) {
+            let width = $msb - $lsb + 1;
+            let (index, raw) = $assignment.raw_value(__bitfield_stringify!($field), width, Some($count), <$t>::MIN != 0)?;
+            let field_value = bitfield_impl!{@from_str_value $field, $t, $from, $conv, raw, width}?;
+            $value.$setter(index, field_value);
+            continue;
+        }
+    };
+    // The raw bits are sign-extended to `$t`, and converted like the getter converts them, failing
+    // for the values that don't have a `$from`.
+    (@from_str_value $field:ident, $t:ty, $from:ty, $conv:tt, $raw:ident, $width:expr) => {{
+        let shift = ($crate::size_of::<$t>() * 8).saturating_sub($width);
+        let raw: $t = (($raw as $t) << shift) >> shift;
+        let invalid = $crate::ParseFieldsError::InvalidValue { field: __bitfield_stringify!($field) };
+        bitfield_impl!(@from_str_convert $t, $from, $conv, raw, invalid)
+    }};
+    (@from_str_convert $t:ty, $from:ty, [float], $raw:ident, $invalid:ident) => {
+        Ok::<$from, $crate::ParseFieldsError>(<$from>::from_bits($raw))
+    };
+    (@from_str_convert $t:ty, $from:ty, [flags], $raw:ident, $invalid:ident) => {{
+        let flags = <$from>::from_bits_truncate($crate::Into::into($raw));
+        if $crate::Into::<$t>::into(flags.bits()) == $raw {
+            Ok(flags)
+        } else {
+            Err($invalid)
+        }
+    }};
+    (@from_str_convert $t:ty, $from:ty, [bool], $raw:ident, $invalid:ident) => {
+        Ok::<$from, $crate::ParseFieldsError>($raw != 0)
+    };
+    (@from_str_convert $t:ty, $from:ty, $conv:tt, $raw:ident, $invalid:ident) => {
+        <$from as $crate::convert::TryFrom<$t>>::try_from($raw).map_err(|_| $invalid)
+    };
+    (@from_str_valid [] $field_value:ident) => {
+        $field_value
+    };
+    (@from_str_valid [$_bit:tt] $field_value:ident) => {
+        Some($field_value)
+    };
+    (CRepr{$c:path} for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@c_repr $vis $name([$t]) $c,);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (CRepr{$c:path} for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@c_repr $vis $name($t) $c,); $t; $($rest)*}
+    };
+    (@c_repr $vis:vis $name:ident([$t:ty]) $c:path, $records:tt) => {
+        impl<T: AsMut<[$t]> + Default> $name<T> {
+            bitfield_impl!{@from_c_repr $vis, T::default(), $c, $records}
+        }
+
+        impl<T: AsRef<[$t]>> $name<T> {
+            bitfield_impl!{@to_c_repr $vis $c, $records}
+        }
+    };
+    (@c_repr $vis:vis $name:ident($t:ty) $c:path, $records:tt) => {
+        impl $name {
+            bitfield_impl!{@from_c_repr $vis, <$t as Default>::default(), $c, $records}
+            bitfield_impl!{@to_c_repr $vis $c, $records}
+        }
+    };
+    (@from_c_repr $vis:vis, $storage:expr, $c:path, [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] getter $_getter:tt setter [$setter:tt] pos $pos:tt $($_rest:tt)*
+    })*]) => {
+        /// Creates a value from the C representation, using the setters of the fields.
+        #[allow(dead_code)]
+        $vis fn from_c_repr(c_repr: &$c) -> Self {
+            let mut value = Self($storage);
+            $(
+                $(#[$cfg])*
+                bitfield_impl!{@set_from_c_repr value, c_repr, $field, $setter, $pos}
+            )*
+            value
+        }
+    };
+    (@set_from_c_repr $value:ident, $c_repr:ident, $field:ident, _, $pos:tt) => {
+        __bitfield_compile_error!("`impl CRepr;` requires a setter for every field");
+    };
+    (@set_from_c_repr $value:ident, $c_repr:ident, $field:ident, $setter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        // Copied first, as the fields of a packed struct can't be borrowed.
+        let array = $c_repr.$field;
+        for (index, element) in array.iter().enumerate() {
+            $value.$setter(index, *element);
+        }
+    };
+    (@set_from_c_repr $value:ident, $c_repr:ident, $field:ident, $setter:ident, $pos:tt) => {
+        $value.$setter($c_repr.$field);
+    };
+    (@to_c_repr $vis:vis $c:path, [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] getter [$getter:tt] setter $_setter:tt pos $pos:tt $($_rest:tt)*
+    })*]) => {
+        /// Returns the C representation of the value, using the getters of the fields.
+        #[allow(dead_code)]
+        $vis fn to_c_repr(&self) -> $c {
+            $c {
+                $(
+                    $(#[$cfg])*
+                    $field: bitfield_impl!(@get_to_c_repr self, $getter, $pos),
+                )*
+            }
+        }
+    };
+    (@get_to_c_repr $self:ident, _, $pos:tt) => {
+        __bitfield_compile_error!("`impl CRepr;` requires a getter for every field")
+    };
+    (@get_to_c_repr $self:ident, $getter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        $crate::array::from_fn(|index| $self.$getter(index))
+    };
+    (@get_to_c_repr $self:ident, $getter:ident, $pos:tt) => {
+        $self.$getter()
+    };
+    (Fields for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@fields $vis $name([$t], false));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Fields for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@fields $vis $name($t, false)); $t; $($rest)*}
+    };
+    (@fields $vis:vis $name:ident([$t:ty], $msb0:expr $(, $endian:ident)?) $records:tt) => {
+        bitfield_impl!{@fields_enum $vis $name $records}
+        impl<T> $name<T> {
+            bitfield_impl!{@fields_table $name $records}
+            bitfield_impl!{@fields_address $name, $t, $msb0 $(, $endian)?}
+            bitfield_impl!{@default_consts $records}
+        }
+
+        impl<T> $crate::HasFields for $name<T> {
+            const FIELDS: &'static [$crate::Field] = Self::FIELDS;
+        }
+    };
+    (@fields $vis:vis $name:ident($t:ty, $msb0:expr) $records:tt) => {
+        bitfield_impl!{@fields_enum $vis $name $records}
+        impl $name {
+            bitfield_impl!{@fields_table $name $records}
+            bitfield_impl!{@fields_address $name, $t, $msb0}
+            bitfield_impl!{@default_consts $records}
+        }
+
+        impl $crate::HasFields for $name {
+            const FIELDS: &'static [$crate::Field] = Self::FIELDS;
+        }
+    };
+    (@has_setter _) => {
+        false
+    };
+    (@has_setter $setter:ident) => {
+        true
+    };
+    (@fields_address $name:ident, $t:ty, $msb0:expr $(, $endian:ident)?) => {
+        paste! {
+            /// Returns the address of the byte containing the bit `lsb` of a field (of its first
+            /// element for arrays), and the index of this bit in the byte, 0 being the least
+            /// significant bit, when the storage is at the address `base`.
+            #[allow(dead_code)]
+            pub fn field_bit_address(base: usize, field: [<$name Field>]) -> (usize, u8) {
+                $crate::bit_address(
+                    base,
+                    Self::FIELDS[field as usize].lsb,
+                    $crate::size_of::<$t>(),
+                    $msb0,
+                    bitfield_impl!{@big_endian $($endian)?},
+                )
+            }
+        }
+    };
+    // The native endianness, `cfg!` not being usable with `local_inner_macros`.
+    (@big_endian) => {
+        u16::from_be(1) == 1
+    };
+    (@big_endian LE) => {
+        false
+    };
+    (@big_endian BE) => {
+        true
+    };
+    (@default_consts [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $vis:tt ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$field:ident] getter $_getter:tt setter $_setter:tt pos $pos:tt default $default:tt
+        $($_rest:tt)*
+    })*]) => {
+        $(
+            $(#[$cfg])*
+            bitfield_impl!{@default_const $vis $field, $from, $pos, $default}
+        )*
+    };
+    (@default_const $vis:tt $field:ident, $from:ty, $pos:tt, []) => {};
+    (@default_const [$($vis:tt)*] $field:ident, $from:ty, [$bit:expr], [$default:expr]) => {
+        paste! {
+            #[doc = "The default value of `" $field "`."]
+            $($vis)* const [<$field:upper _DEFAULT>]: bool = $default;
+        }
+    };
+    (@default_const [$($vis:tt)*] $field:ident, $from:ty, $pos:tt, [$default:expr]) => {
+        paste! {
+            #[doc = "The default value of `" $field "` (of each element for arrays)."]
+            $($vis)* const [<$field:upper _DEFAULT>]: $from = $default;
+        }
+    };
+    (@fields_enum $vis:vis $name:ident [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] $($_rest:tt)*
+    })*]) => {
+        paste! {
+            #[doc = "The fields of [`" $name "`], in declaration order."]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            $vis enum [<$name Field>] {
+                $(
+                    $(#[$cfg])*
+                    #[allow(missing_docs)]
+                    [<$field:camel>],
+                )*
+            }
+
+            impl [<$name Field>] {
+                /// All the fields, in declaration order.
+                #[allow(dead_code)]
+                $vis const ALL: &'static [Self] = &[
+                    $(
+                        $(#[$cfg])*
+                        Self::[<$field:camel>],
+                    )*
+                ];
+            }
+        }
+    };
+    (@fields_table $name:ident [$({
+        attrs $attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty [$field_ty:ty] mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] getter $_getter:tt setter [$setter:tt] pos [$($exprs:expr),*]
+        default $default:tt conv $conv:tt saturating $_saturating:tt fmt $fmt:tt $($_rest:tt)*
+    })*]) => {
+        /// The description of every field, indexed by the field enum.
+        pub const FIELDS: &'static [$crate::Field] = &[
+            $(
+                $(#[$cfg])*
+                bitfield_impl!{@field_descriptor $field, $field_ty, $attrs, $default, $conv, $fmt: $($exprs),*},
+            )*
+        ];
+
+        /// The layout of the fields, to check the compatibility with another layout.
+        pub const LAYOUT: $crate::LayoutDescriptor = $crate::LayoutDescriptor {
+            fields: Self::FIELDS,
+            read_only: $crate::all_false(&[
+                $(
+                    $(#[$cfg])*
+                    bitfield_impl!{@has_setter $setter},
+                )*
+            ]),
+        };
+
+        __bitfield_if_alloc! {
+            /// Renders the value of every field as an aligned table, with the first line of its
+            /// documentation.
+            #[allow(dead_code)]
+            pub fn render_table(&self) -> $crate::alloc::string::String
+            where
+                Self: $crate::BitRange<u128>,
+            {
+                $crate::render_table(Self::FIELDS, |msb, lsb| $crate::BitRange::<u128>::bit_range(self, msb, lsb))
+            }
+
+            /// Renders the fields that differ between `self` and `other` as an aligned table,
+            /// optionally with ANSI colors.
+            #[allow(dead_code)]
+            pub fn render_diff(&self, other: &Self, colors: bool) -> $crate::alloc::string::String
+            where
+                Self: $crate::BitRange<u128>,
+            {
+                $crate::render_diff(
+                    Self::FIELDS,
+                    |msb, lsb| $crate::BitRange::<u128>::bit_range(self, msb, lsb),
+                    |msb, lsb| $crate::BitRange::<u128>::bit_range(other, msb, lsb),
+                    colors,
+                )
+            }
+        }
+
+        /// Returns the fields that differ between `self` and `other`.
+        #[allow(dead_code)]
+        pub fn diff<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = $crate::FieldChange> + 'a
+        where
+            Self: $crate::BitRange<u128>,
+        {
+            $crate::diff_fields(
+                Self::FIELDS,
+                move |msb, lsb| $crate::BitRange::<u128>::bit_range(self, msb, lsb),
+                move |msb, lsb| $crate::BitRange::<u128>::bit_range(other, msb, lsb),
+            )
+        }
+
+        paste! {
+            /// Gets the raw bits of a field, using the `FIELDS` table.
+            ///
+            /// For non-array fields, `index` must be 0.
+            #[allow(dead_code)]
+            pub fn get_field_raw(&self, field: [<$name Field>], index: usize) -> u128
+            where
+                Self: $crate::BitRange<u128>,
+            {
+                let field = &Self::FIELDS[field as usize];
+                __bitfield_debug_assert!(index < field.count);
+                let (msb, lsb) = field.element_bits(index);
+                $crate::BitRange::<u128>::bit_range(self, msb, lsb)
+            }
+
+            /// Returns the raw bits of every field, in declaration order. Arrays give one item per
+            /// element.
+            #[allow(dead_code)]
+            pub fn iter_fields<'a>(&'a self) -> impl Iterator<Item = ([<$name Field>], u128)> + 'a
+            where
+                Self: $crate::BitRange<u128>,
+            {
+                [<$name Field>]::ALL.iter().flat_map(move |&field| {
+                    (0..Self::FIELDS[field as usize].count)
+                        .map(move |index| (field, self.get_field_raw(field, index)))
+                })
+            }
+
+            /// Sets the raw bits of a field, using the `FIELDS` table.
+            ///
+            /// For non-array fields, `index` must be 0.
+            #[allow(dead_code)]
+            pub fn set_field_raw(&mut self, field: [<$name Field>], index: usize, value: u128)
+            where
+                Self: $crate::BitRangeMut<u128>,
+            {
+                let field = &Self::FIELDS[field as usize];
+                __bitfield_debug_assert!(index < field.count);
+                let (msb, lsb) = field.element_bits(index);
+                $crate::BitRangeMut::<u128>::set_bit_range(self, msb, lsb, value);
+            }
+        }
+    };
+    (@field_descriptor $field:ident, $t:ty, $attrs:tt, $default:tt, $conv:tt, $fmt:tt: $bit:expr) => {
+        $crate::Field {
+            name: __bitfield_stringify!($field),
+            doc: bitfield_impl!{@field_doc $attrs},
+            lsb: $bit,
+            width: 1,
+            count: 1,
+            stride: 1,
+            kind: $crate::FieldKind::Bool,
+            default: bitfield_impl!{@field_default $default, [], 1},
+            unit: bitfield_impl!{@field_unit $fmt},
+            radix: bitfield_impl!{@field_radix $fmt},
+        }
+    };
+    (@field_descriptor $field:ident, $t:ty, $attrs:tt, $default:tt, $conv:tt, $fmt:tt: $msb:expr, $lsb:expr) => {
+        bitfield_impl!{@field_descriptor $field, $t, $attrs, $default, $conv, $fmt: $msb, $lsb, 1}
+    };
+    (@field_descriptor $field:ident, $t:ty, $attrs:tt, $default:tt, [bool], $fmt:tt: $msb:expr, $lsb:expr, $count:expr) => {
+        $crate::Field {
+            name: __bitfield_stringify!($field),
+            doc: bitfield_impl!{@field_doc $attrs},
+            lsb: $lsb,
+            width: 1,
+            count: $count,
+            stride: 1,
+            kind: $crate::FieldKind::Bool,
+            default: bitfield_impl!{@field_default $default, [bool], 1},
+            unit: bitfield_impl!{@field_unit $fmt},
+            radix: bitfield_impl!{@field_radix $fmt},
+        }
+    };
+    (@field_descriptor $field:ident, $t:ty, $attrs:tt, $default:tt, $conv:tt, $fmt:tt: $msb:expr, $lsb:expr, $count:expr) => {
+        bitfield_impl!{@field_descriptor $field, $t, $attrs, $default, $conv, $fmt: $msb, $lsb, $count, $msb - $lsb + 1}
+    };
+    (@field_descriptor $field:ident, $t:ty, $attrs:tt, $default:tt, $conv:tt, $fmt:tt: $msb:expr, $lsb:expr, $count:expr, $stride:expr) => {
+        $crate::Field {
+            name: __bitfield_stringify!($field),
+            doc: bitfield_impl!{@field_doc $attrs},
+            lsb: $lsb,
+            width: $msb - $lsb + 1,
+            count: $count,
+            stride: $stride,
+            kind: if <$t>::MIN == 0 {
+                $crate::FieldKind::Unsigned
+            } else {
+                $crate::FieldKind::Signed
+            },
+            default: bitfield_impl!{@field_default $default, $conv, $msb - $lsb + 1},
+            unit: bitfield_impl!{@field_unit $fmt},
+            radix: bitfield_impl!{@field_radix $fmt},
+        }
+    };
+    // The presentation hints of the `fmt` record.
+    (@field_unit []) => {
+        ""
+    };
+    (@field_unit [unit [$unit:literal] $($rest:tt)*]) => {
+        $unit
+    };
+    (@field_unit [$_key:ident $_value:tt $($rest:tt)*]) => {
+        bitfield_impl!{@field_unit [$($rest)*]}
+    };
+    (@field_radix []) => {
+        $crate::Radix::Decimal
+    };
+    (@field_radix [radix [dec] $($rest:tt)*]) => {
+        $crate::Radix::Decimal
+    };
+    (@field_radix [radix [hex] $($rest:tt)*]) => {
+        $crate::Radix::Hexadecimal
+    };
+    (@field_radix [radix [bin] $($rest:tt)*]) => {
+        $crate::Radix::Binary
+    };
+    (@field_radix [radix [oct] $($rest:tt)*]) => {
+        $crate::Radix::Octal
+    };
+    (@field_radix [radix [$radix:ident] $($rest:tt)*]) => {
+        __bitfield_compile_error!(__bitfield_concat!("unknown radix `", __bitfield_stringify!($radix), "`, expected `dec`, `hex`, `bin` or `oct`"))
+    };
+    (@field_radix [$_key:ident $_value:tt $($rest:tt)*]) => {
+        bitfield_impl!{@field_radix [$($rest)*]}
+    };
+    // The raw bits of the default value, when it can be converted in a constant.
+    (@field_default [], $conv:tt, $width:expr) => {
+        None
+    };
+    (@field_default [$default:expr], [from], $width:expr) => {
+        None
+    };
+    (@field_default [$default:expr], [newtype], $width:expr) => {
+        Some($default.0 as u128 & (u128::MAX >> (128 - ($width))))
+    };
+    (@field_default [$default:expr], [bool], $width:expr) => {
+        Some($default as u128)
+    };
+    (@field_default [$default:expr], [flags], $width:expr) => {
+        Some($default.bits() as u128 & (u128::MAX >> (128 - ($width))))
+    };
+    (@field_default [$default:expr], [], $width:expr) => {
+        Some($default as u128 & (u128::MAX >> (128 - ($width))))
+    };
+    // The first doc attribute is the first line of the documentation.
+    (@field_doc []) => {
+        ""
+    };
+    (@field_doc [#[doc = $doc:literal] $($rest:tt)*]) => {
+        $crate::trim_doc($doc)
+    };
+    (@field_doc [# $attribute:tt $($rest:tt)*]) => {
+        bitfield_impl!{@field_doc [$($rest)*]}
+    };
+    (LayoutHash for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@layout_hash $name([$t]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (LayoutHash for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@layout_hash $name($t)); $t; $($rest)*}
+    };
+    (@layout_hash $name:ident([$t:ty]) $records:tt) => {
+        impl<T> $name<T> {
+            bitfield_impl!{@layout_hash_const $records}
+        }
+    };
+    (@layout_hash $name:ident($t:ty) $records:tt) => {
+        impl $name {
+            bitfield_impl!{@layout_hash_const $records}
+        }
+    };
+    (@layout_hash_const [$({
+        attrs $attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty [$field_ty:ty] mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] getter $_getter:tt setter $_setter:tt pos [$($exprs:expr),*]
+        default $default:tt conv $conv:tt saturating $_saturating:tt fmt $fmt:tt $($_rest:tt)*
+    })*]) => {
+        /// A hash of the names and positions of the fields, see `bitfield::layout_hash`.
+        pub const LAYOUT_HASH: u64 = $crate::layout_hash(&[
+            $(
+                $(#[$cfg])*
+                bitfield_impl!{@field_descriptor $field, $field_ty, $attrs, $default, $conv, $fmt: $($exprs),*},
+            )*
+        ]);
+    };
+    (AnyBitfield for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        impl<T: AsRef<[$t]> + AsMut<[$t]>> $crate::AnyBitfield for $name<T> {
+            bitfield_impl!{@any_bitfield $name}
+
+            fn bit_len(&self) -> usize {
+                AsRef::<[$t]>::as_ref(&self.0).len() * $crate::size_of::<$t>() * 8
+            }
+        }
+    };
+    (AnyBitfield for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        impl $crate::AnyBitfield for $name {
+            bitfield_impl!{@any_bitfield $name}
+
+            fn bit_len(&self) -> usize {
+                $crate::size_of::<$t>() * 8
+            }
+        }
+    };
+    (@any_bitfield $name:ident) => {
+        fn name(&self) -> &'static str {
+            __bitfield_stringify!($name)
+        }
+
+        fn fields(&self) -> &'static [$crate::Field] {
+            Self::FIELDS
+        }
+
+        fn get_field(&self, index: usize, element: usize) -> u128 {
+            let field = &Self::FIELDS[index];
+            __bitfield_assert!(element < field.count);
+            let (msb, lsb) = field.element_bits(element);
+            $crate::BitRange::<u128>::bit_range(self, msb, lsb)
+        }
+
+        fn set_field(&mut self, index: usize, element: usize, value: u128) {
+            let field = &Self::FIELDS[index];
+            __bitfield_assert!(element < field.count);
+            let (msb, lsb) = field.element_bits(element);
+            $crate::BitRangeMut::<u128>::set_bit_range(self, msb, lsb, value);
+        }
+    };
+    (Default for $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@default $name([$t]));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (Default for $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@default $name($t)); $t; $($rest)*}
+    };
+    (@default $name:ident([$t:ty]) $records:tt) => {
+        impl<T: AsMut<[$t]> + Default> Default for $name<T> {
+            fn default() -> Self {
+                let mut value = Self(T::default());
+                bitfield_impl!{@set_defaults value $records}
+                value
+            }
+        }
+
+        impl<T: AsMut<[$t]>> $name<T> {
+            /// Clears the storage and sets the fields with a default value to it, like `default`.
+            #[allow(dead_code)]
+            pub fn reset(&mut self) {
+                for element in AsMut::<[$t]>::as_mut(&mut self.0) {
+                    *element = <$t as Default>::default();
+                }
+                bitfield_impl!{@set_defaults self $records}
+            }
+        }
+    };
+    (@default $name:ident($t:ty) $records:tt) => {
+        impl Default for $name {
+            fn default() -> Self {
+                let mut value = Self(<$t as Default>::default());
+                bitfield_impl!{@set_defaults value $records}
+                value
+            }
+        }
+
+        impl $name {
+            /// Clears the storage and sets the fields with a default value to it, like `default`.
+            #[allow(dead_code)]
+            pub fn reset(&mut self) {
+                self.0 = <$t as Default>::default();
+                bitfield_impl!{@set_defaults self $records}
+            }
+        }
+    };
+    (@set_defaults $value:ident [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter $_getter:tt setter [$setter:tt] pos $pos:tt default $default:tt
+        $($_rest:tt)*
+    })*]) => {
+        $(
+            $(#[$cfg])*
+            bitfield_impl!{@set_default $value, $setter, $pos, $default}
+        )*
+    };
+    (@set_default $value:ident, $setter:tt, $pos:tt, []) => {};
+    (@set_default $value:ident, _, $pos:tt, [$default:expr]) => {
+        __bitfield_compile_error!("a field with a default value must have a setter");
+    };
+    (@set_default $value:ident, $setter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?], [$default:expr]) => {
+        for index in 0..$count {
+            $value.$setter(index, $default);
+        }
+    };
+    (@set_default $value:ident, $setter:ident, $pos:tt, [$default:expr]) => {
+        $value.$setter($default);
+    };
+    // display a more friendly error message when someone tries to use `impl <Trait>;` syntax when not supported
+    ($macro:ident for $vis:vis struct $name:ident $($rest:tt)*) => {
+        ::std::compile_error!(::std::stringify!(Unsupported impl $macro for struct $name));
+    };
+}
+
+/// Declares the fields of struct.
+///
+/// This macro will generate the methods to access the fields of a bitfield. It must be called
+/// from an `impl` block for a type that implements the `BitRange` and/or the `Bit` traits
+/// (which traits are required depending on what type of fields are used).
+///
+/// The `impl` block can also be a trait implementation, in which case the fields must not be
+/// `pub`, and the masks must be associated constants of the trait. The getters and setters then
+/// implement the methods of the trait with the same names.
+///
+/// The syntax of this macro is composed of declarations ended by semicolons. There are seven types
+/// of declarations: default type, `checked`, `const`, `get_prefix`, `reserved`, `alias`, and
+/// fields.
+///
+/// A default type is just a type followed by a semicolon. This will affect all the following field
+/// declarations.
+///
+/// `checked;` makes the setters of all the following fields `debug_assert!` that the value fits in
+/// the field, instead of silently dropping the upper bits. In release builds, the value is still
+/// truncated. The `saturating` fields are not affected.
+///
+/// `const;` makes the getters and setters of all the following fields `const fn`. They work
+/// directly on the integer stored in `self.0`, so they can only be used with an integer storage,
+/// not in trait implementations, and not with the `into`, `try_into` and `flags` conversions. The
+/// setters take `&mut self`, which requires Rust 1.83 in a `const` context.
+///
+/// `get_prefix;` makes the getters of all the following `field name` declarations `get_name`,
+/// instead of `name`.
+///
+/// `reserved: msb, lsb;` (or `reserved: bit;`, or `reserved: width w;`) declares bits that aren't
+/// used by any field. No method is generated for them, they are only taken into account by
+/// `impl Complete;`.
+///
+/// `alias old = new;` must directly follow the declaration of the field `new`, and gives another
+/// name to its getter, and to its setter with the `set_` prefix: `old` and `set_old`. The names can
+/// also be given like for the fields, `alias old, put_old = new;`. The alias has its own attributes
+/// and visibility, for example to keep an old name as `#[deprecated]` after renaming a field. Only
+/// the getter and the setter are generated for the alias, it is ignored by the other parts of
+/// `bitfield` (like `impl Debug;` or `impl Fields;`).
+///
+/// A field declaration is composed of the following:
+///
+/// * Optional attributes (`#[...]`), documentation comments (`///`) are attributes;
+/// * An optional visibility (`pub`, `pub(crate)`, `pub(super)`, `pub(self)` or `pub(in path)`) for
+///   the methods
+/// * An optional type followed by a comma. The type can be `f32` or `f64`, for the IEEE-754 values
+///   of 32 or 64 bits fields, read as `u32` or `u64` and converted with `from_bits` and `to_bits`
+/// * Optionally, the word `signed` followed by a comma, to read and write the field as the signed
+///   integer of the same size as the type
+/// * Optionally, the word `into` followed by a type, followed by a comma. When the type is one of
+///   the `NonZero*` types, named without a path, the getter returns `None` for 0 and the setter
+///   takes the `NonZero*` type
+/// * Optionally, the word `try_into` followed by a type, followed by a comma
+/// * Optionally, the word `newtype` followed by a name, followed by a comma. `bitfield` then defines
+///   a struct with this name wrapping the type of the field, with the visibility of the field and
+///   `From` implementations in both directions, and the accessors use it like with `from into`.
+///   Each name must only be used once, and not with a second storage
+/// * Optionally, the word `flags` followed by a type with the `from_bits_truncate` and `bits`
+///   methods (like the types generated by the `bitflags` crate), followed by a comma
+/// * Optionally, the word `saturating` followed by a comma, to clamp the values given to the setter
+///   to the range of the field instead of truncating them. It can also be given before the
+///   conversions
+/// * Optionally, `unit` followed by a string literal and/or `radix` followed by `dec`, `hex`, `bin` or
+///   `oct`, each followed by a comma. They are stored in the `FIELDS` table of `impl Fields;`, and
+///   used by `render_table` and `impl Display;`
+/// * Optionally, the word `bytes` followed by a comma. For the slice-backed structs of `bitfield`,
+///   this generates the `x_bytes` and `x_bytes_mut` methods returning the elements of the storage
+///   holding the field, which must start and end on element boundaries
+/// * Optionally, the word `be` or `le` followed by a comma. For the slice-backed structs of
+///   `bitfield`, the field is then read and written as a big-endian or little-endian integer,
+///   whatever the bit numbering of the struct. The field must be a range starting and ending on
+///   byte boundaries
+/// * Optionally, the word `valid_if` followed by the position of a bit and a comma. The getter then
+///   returns `None` when this bit is clear, and the setter takes an `Option`, setting the bit
+///   with the value, or clearing it for `None`. The methods generated from the accessors, like those
+///   of `impl With;`, `impl Builder;` or `impl new;`, also take and return `Option`s. Only the fields
+///   with a msb and a lsb are supported
+/// * Optionally, one of the access words followed by a comma:
+///   * `ro` for the read-only fields, whose setter is not generated
+///   * `wo` for the write-only fields, whose getter is not generated
+///   * `rc` for the read-clear fields, whose getter takes `&mut self` and clears the field after
+///     reading it. The other macros, like `bitfield_debug`, skip these fields
+///   * `w1s` for the write-one-to-set fields, whose setter only sets the bits that are set in the
+///     value, leaving the others unchanged
+///   * `w1c` for the write-one-to-clear fields, used by `impl Pending;`
+/// * Optionally, the word `mask` followed by a name, an optional type in parentheses and a comma,
+///   to generate an associated constant with the bits of the field set. Without a type, the
+///   constant has the type of the storage, given by the `BitfieldStorage` trait. The masks are
+///   not generated for the slice-backed structs of `bitfield`
+/// * Optionally, the word `consts` followed by a prefix and a comma, to generate the `usize`
+///   associated constants `<PREFIX>_OFFSET`, `<PREFIX>_WIDTH`, `<PREFIX>_MSB` and `<PREFIX>_LSB`
+///   (of the first element for arrays). Like the masks, they have the visibility of the field
+/// * The getter and setter idents, separated by a comma, or the word `field` followed by a name,
+///   for a getter with that name and a setter with the `set_` prefix. The setter can be preceded
+///   by its own visibility, like `pub mode, pub(crate) set_mode: 7, 4;`, which then only applies
+///   to the setter and the methods calling it
+/// * A colon
+/// * One to four expressions of type `usize`
+/// * Optionally, `=` followed by a default value, used by `impl Default;` and `impl Fields;` (which
+///   requires a constant expression). In that case, the expressions before the `=` must be single
+///   tokens (literals, constants or parenthesized expressions)
+///
+/// The attributes and pub will be applied to the two methods generated. A line giving the bits of
+/// the field, like ``Bits `7:4`.``, is appended to their documentation.
+///
+/// If the `into` part is used, the getter will convert the field after reading it.
+///
+/// If the `try_into` part is used, the getter will convert the field with `TryFrom` after reading
+/// it, and return the `Result`. The setter is not affected.
+///
+/// If the `flags` part is used, the getter will convert the field with `from_bits_truncate`,
+/// and the setter will take a value of that type, converted with `bits`.
+///
+/// The getter and setter idents can be `_` to not generate one of the two. For example, if the
+/// setter is `_`, the field will be read-only.
+///
+/// The expressions at the end are the bit positions. Their meaning depends on the number of
+/// expressions:
+///
+///  * One expression: the field is a single bit. The type is ignored and `bool` is used. The trait
+///    `Bit` is used.
+///  * Two expressions: `msb, lsb`, the field is composed of the bits from `msb` to `lsb`, included.
+///    If the type is signed, the value is sign-extended from `msb`, for all the storages.
+///  * A range, `msb..=lsb`: the same as two expressions, it can also be followed by a count for
+///    arrays. For MSB0 structs, the bounds are given in increasing order instead, `lsb..=msb`,
+///    matching the numbering of their datasheets. `bitfield` rejects the other order at compile
+///    time. Each bound must be a single token, like a literal, a constant or an expression in
+///    parentheses.
+///  * An offset and a width, `@lsb; width`: the field is composed of the `width` bits starting at
+///    `lsb`. A count can also be given for arrays, `@lsb; width, count`. The offset and the width
+///    must be single tokens.
+///  * A width, `width w` (or `width w, count` for arrays): the field is placed just after the
+///    previous `width` field, starting from the bit 0, which is the most significant bit for MSB0
+///    structs. `reserved: width w;` skips bits the same way. The fields with explicit positions
+///    don't move this cursor. The width must be a single token.
+///  * Several ranges joined by `++`, `[msb, lsb] ++ [msb, lsb] ++ ...`: the field is split in
+///    non-contiguous pieces, the first one holding the most significant bits of the value. Only the
+///    getter and the setter are generated for these fields, they are ignored by the other parts of
+///    `bitfield` (like `impl Debug;` or `impl Fields;`).
+///  * Three expressions: `msb, lsb, count`, the field is an array. The first element is composed of
+///    the bits from `msb` to `lsb`. The following elements are consecutive bits range of the same
+///    size.
+///    If the type is `bool`, given just before the getter and setter, each element is a single bit
+///    starting at `lsb`, and `msb` is only informative: `bool, flags, set_flags: 63, 32, 32;`.
+///  * Four expressions: `msb, lsb, count, stride`, the field is an array whose elements start every
+///    `stride` bits, leaving the bits between them untouched: `channel, set_channel: 2, 0, 4, 4;`.
+///    `impl Complete;` and the `bytes` fields don't support these arrays, as the bits between the
+///    elements would be counted in the field.
+///
+/// The positions must be constant expressions, as they are also used in the constants generated
+/// for the fields. The slice-backed structs are generic over the type of the storage, not over a
+/// const length, so a position can't depend on the size of the buffer: a field at the end of a
+/// variable-length record has to be accessed with the `BitRange` methods instead.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// # struct FooBar(u64);
+/// # bitfield_bitrange!{struct FooBar(u64)}
+/// # impl From<u32> for FooBar{ fn from(_: u32) -> FooBar {unimplemented!()}}
+/// # impl From<FooBar> for u32{ fn from(_: FooBar) -> u32 {unimplemented!()}}
+/// # impl FooBar {
+/// bitfield_fields!{
+///     // The default type will be `u64
+///     u64;
+///     // filed1 is read-write, public, the methods are inline
+///     #[inline]
+///     pub field1, set_field1: 10, 0;
+///     // `field2` is  read-only, private, and of type bool.
+///     field2, _ : 0;
+///     // `field3` will be read as an `u32` and then converted to `FooBar`.
+///     // The setter is not affected, it still need an `u32` value.
+///     u32, into FooBar, field3, set_field3: 10, 0;
+///     // `field4` will be read as an `u32` and then converted to `FooBar`.
+///     // The setter will take a `FooBar`, and converted back to an `u32`.
+///     u32, from into FooBar, field4, set_field4: 10, 0;
+///     // `field5` will be read as an `u32` and then converted to `FooBar`.
+///     // The setter will take a `FooBar`, and converted back to an `u32`.
+///     // The struct will have an associated constant `FIELD5_MASK` of type u64
+///     //with the bits of field5 set
+///     u32, mask FIELD5_MASK(u64), from into FooBar, field5, set_field5: 10, 0;
+///     // `field6` has a default value of 3, which is used by `impl Default;`.
+///     field6, set_field6: 10, 0 = 3;
+///     // `field7` is read as a `Flags`, dropping the unknown bits, and written from a `Flags`.
+///     u8, flags Flags, field7, set_field7: 15, 12;
+///     // `field8` is read as an `i16`, sign-extended from bit 31, and written from an `i16`.
+///     u16, signed, field8, set_field8: 31, 20;
+///     // The struct will have the associated constants `FIELD9_OFFSET` (16), `FIELD9_WIDTH` (4),
+///     // `FIELD9_MSB` (19) and `FIELD9_LSB` (16).
+///     consts FIELD9, field9, set_field9: 19, 16;
+/// }
+/// # }
+/// # struct Flags(u8);
+/// # impl Flags {
+/// #     fn from_bits_truncate(bits: u8) -> Flags { Flags(bits & 0b11) }
+/// #     fn bits(&self) -> u8 { self.0 }
+/// # }
+/// ```
+///
+/// # Merging several invocations
+///
+/// When the fields of a type are declared by several invocations, they can be merged by calling the
+/// macro outside of an `impl` block, with `struct TheNameOfTheStruct as group_name;` before the
+/// declarations. This generates the `impl` block, and a `group_name!` macro holding the
+/// declarations. `struct TheNameOfTheStruct as group_name: previous_group;` also includes the
+/// declarations of `previous_group`, and fails to compile if two fields have the same name. Each
+/// invocation should start with its default type.
+///
+/// `group_name!{path::to::a_macro!{arguments}}` calls `a_macro` with the arguments followed by all
+/// the declarations, which can be used to generate `impl Fields;` and the other implementations
+/// for all the fields at once.
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// pub struct Control(u32);
+/// bitfield_bitrange!{struct Control(u32)}
+///
+/// bitfield_fields!{
+///     struct Control as control_fields;
+///     u8;
+///     enable, set_enable: 0;
+///     mode, set_mode: 7, 4;
+/// }
+///
+/// bitfield_fields!{
+///     struct Control as all_fields: control_fields;
+///     u16;
+///     length, set_length: 31, 16;
+/// }
+///
+/// all_fields!{bitfield_impl!{Fields for struct Control(u32);}}
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! bitfield_fields {
+    (struct $name:ident as $group:ident $(: $previous:ident)?; $($rest:tt)*) => {
+        impl $name {
+            bitfield_fields!{$($rest)*}
+        }
+
+        bitfield_fields!{@group ($) $group $(: $previous)?; $($rest)*}
+    };
+    // `$d` is a `$`, to write the metavariables of the generated macro.
+    (@group ($d:tt) $group:ident: $previous:ident; $($rest:tt)*) => {
+        $previous!{bitfield_fields!{@group ($d) $group;} $($rest)*}
+    };
+    (@group ($d:tt) $group:ident; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@check_names);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+
+        #[allow(unused_macros)]
+        macro_rules! $group {
+            ($d ($d cb:ident)::+ !{$d ($d args:tt)*} $d ($d extra:tt)*) => {
+                $d ($d cb)::+ !{$d ($d args)* $($rest)* $d ($d extra)*}
+            };
+        }
+    };
+    (@check_names [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] $($_rest:tt)*
+    })*]) => {
+        const _: () = {
+            #[allow(dead_code, non_camel_case_types)]
+            enum DuplicateFieldNames {
+                $(
+                    $(#[$cfg])*
+                    $field,
+                )*
+            }
+        };
+    };
+    // Parses the field declarations once and calls `$cb!` with the arguments `$args` followed by
+    // one record per field. The records look like:
+    //
+    // { attrs [...] cfgs [...] vis [...] ty [...] mask [...] from [...] into [...] name [...]
+    //   getter [...] setter [...] pos [...] default [...] conv [...] saturating [...] fmt [...]
+    //   const_fn [...] consts [...] access [...] bytes [...] order [...] valid [...]
+    //   setter_vis [...] }
+    //
+    // `cfgs` only contains the `#[cfg(...)]` attributes, `name` is the getter, or the setter
+    // when there is no getter, `default` is empty if no default value was given, and `conv` is
+    // `[flags]` for `flags` fields, `[from]` for `from into` fields, `[try_into]` for `try_into`
+    // fields, `[nonzero]` for the `into NonZero*` fields, and empty otherwise. The records end with `saturating [saturating]` for the
+    // `saturating` fields, `saturating [checked]` for the fields following `checked;`, and
+    // `saturating []` otherwise, followed by `fmt [...]` with the `unit [...]` and `radix [...]`
+    // given for the field, if any, `const_fn [const]` for the fields following `const;`,
+    // `consts [...]` with the prefix given with `consts`, if any, `access [...]` with the access
+    // word of the field, if any, `bytes [bytes]` for the `bytes` fields, `order [be]` or
+    // `order [le]` for the fields with a byte order, `valid [(bit)]` for the `valid_if` fields,
+    // and `setter_vis [...]` with the visibility of the setter, which is the one of the field
+    // unless another one is given before the setter.
+    (@parse $cb:ident!$args:tt; $default_ty:ty; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; []; []; []; []; []; [0]; [];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty];
+                         $($rest)*}
+    };
+    // Only `impl Complete;` is given the reserved ranges.
+    (@parse_step $cb:ident!(@complete $($args:tt)*); $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;) => {
+        $crate::$cb!{@complete $($args)* $reserved [$($records)*]}
+    };
+    // Only the getters and setters of `bitfield_fields` are given the split fields.
+    (@parse_step $cb:ident!(only $only:tt; @records); $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;) => {
+        $crate::$cb!{only $only; @records [$($records)*]}
+        $crate::$cb!{only $only; @splits $splits}
+    };
+    // The constructors are also given the split fields, which are parameters too.
+    (@parse_step $cb:ident!(@constructor $($args:tt)*); $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;) => {
+        $crate::$cb!{@constructor $($args)* $splits [$($records)*]}
+    };
+    (@parse_step $cb:ident!($($args:tt)*); $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;) => {
+        $crate::$cb!{$($args)* [$($records)*]}
+    };
+    // The documented fields with single token positions are parsed in one step, with their doc
+    // comments, unless an `alias` follows them.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     [$($attrs:tt)*] $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $(#[doc = $doc:literal])+ $getter:ident, $setter:tt: $($pos:tt),+ $(= $default:expr)?;
+     $(#[$alias_attribute:meta])* $alias_vis:vis alias $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         [$($attrs)* $(#[doc = $doc])+] $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: $($pos),+ $(= $default)?; $(#[$alias_attribute])* $alias_vis alias $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     [$($attrs:tt)*] $cfgs:tt [] [] $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $(#[doc = $doc:literal])* pub $getter:ident, $setter:tt: $($pos:tt),+ $(= $default:expr)?;
+     $(#[$alias_attribute:meta])* $alias_vis:vis alias $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         [$($attrs)* $(#[doc = $doc])*] $cfgs [pub] [pub] $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: $($pos),+ $(= $default)?; $(#[$alias_attribute])* $alias_vis alias $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] [$t:ty] [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     $(#[doc = $doc:literal])+ $getter:ident, $setter:ident: $($pos:tt),+ $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)* $(#[doc = $doc])+] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$from] into [$into] name [$getter]
+                             getter [$getter] setter [$setter] pos [$($pos),+] default [$($default)?] conv [$($conv)?] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [] [] [$t:ty] [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     $(#[doc = $doc:literal])* pub $getter:ident, $setter:ident: $($pos:tt),+ $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)* $(#[doc = $doc])*] cfgs [$($cfgs)*] vis [pub] ty [$t]
+                             mask [$mask($mask_t)] from [$from] into [$into] name [$getter]
+                             getter [$getter] setter [$setter] pos [$($pos),+] default [$($default)?] conv [$($conv)?] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [pub]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     [$($attrs:tt)*] [$($cfgs:tt)*] $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; #[cfg($($cfg:tt)*)] $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         [$($attrs)* #[cfg($($cfg)*)]] [$($cfgs)* #[cfg($($cfg)*)]] $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $($rest)*}
+    };
+    // Doc attributes are kept as tokens, so `impl Fields;` can extract the documentation. The doc
+    // comments are collected in one step, with the `pub` following them.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     [$($attrs:tt)*] $cfgs:tt [] [] $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; $(#[doc = $doc:literal])+ pub $next:ident $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         [$($attrs)* $(#[doc = $doc])+] $cfgs [pub] [pub] $t $mask $fmt $access $view $order $valid $conv; $next $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     [$($attrs:tt)*] $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; $(#[doc = $doc:literal])+ $next:ident $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         [$($attrs)* $(#[doc = $doc])+] $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv; $next $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     [$($attrs:tt)*] $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; #[doc = $doc:literal] $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         [$($attrs)* #[doc = $doc]] $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     [$($attrs:tt)*] $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; #[$attribute:meta] $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         [$($attrs)* #[$attribute]] $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt [] [] $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; pub($restriction:ident) $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs [pub($restriction)] [pub($restriction)] $t $mask $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt [] [] $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; pub(in $($path:tt)+) $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs [pub(in $($path)+)] [pub(in $($path)+)] $t $mask $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt [] [] $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; pub $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records; $attrs $cfgs [pub] [pub] $t $mask $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    // A visibility before the setter only applies to the setter, the one before the field applies
+    // to the getter.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $_setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, pub($restriction:ident) $setter:ident: $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis [pub($restriction)] $t $mask $fmt $access $view $order $valid $conv; $getter, $setter: $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $_setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, pub(in $($path:tt)+) $setter:ident: $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis [pub(in $($path)+)] $t $mask $fmt $access $view $order $valid $conv; $getter, $setter: $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $_setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, pub $setter:ident: $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis [pub] $t $mask $fmt $access $view $order $valid $conv; $getter, $setter: $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt [$_old_mask:ident($_old_mask_t:ty) $($consts:ident)?] $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     mask $mask:ident($mask_t:ty), $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t [$mask($mask_t) $($consts)?] $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    // Without a type, the mask has the type of the storage.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt [$_old_mask:ident($_old_mask_t:ty) $($consts:ident)?] $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     mask $mask:ident, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t [$mask(<Self as $crate::BitfieldStorage>::Storage) $($consts)?] $fmt $access $view $order $valid $conv;
+                         $($rest)*}
+    };
+    // The prefix given with `consts` is kept in the mask slot, as it also names constants.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt [$mask:ident($mask_t:ty) $($_old_consts:ident)?] $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     consts $consts:ident, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t [$mask($mask_t) $consts] $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     from into $into:ty, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid [$into, $into, from $(; $saturating)?]; $($rest)*}
+    };
+    // Only `@newtypes` collects the `newtype` declarations, to define their structs.
+    (@parse_step $cb:ident!(@newtypes $($newtypes:tt)*); $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     newtype $newtype:ident, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!(@newtypes $($newtypes)* {$cfgs $vis $t $newtype}); $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid [$newtype, $newtype, newtype $(; $saturating)?]; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     newtype $newtype:ident, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid [$newtype, $newtype, newtype $(; $saturating)?]; $($rest)*}
+    };
+    (@newtypes $({[$($cfgs:tt)*] [$($vis:tt)*] [$t:ty] $newtype:ident})* [$($_records:tt)*]) => {
+        $(
+            $($cfgs)*
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            $($vis)* struct $newtype(pub $t);
+
+            $($cfgs)*
+            impl From<$t> for $newtype {
+                fn from(value: $t) -> Self {
+                    $newtype(value)
+                }
+            }
+
+            $($cfgs)*
+            impl From<$newtype> for $t {
+                fn from(value: $newtype) -> Self {
+                    value.0
+                }
+            }
+        )*
+    };
+    // The `NonZero*` types are recognized by their name, before being parsed as a type.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt [$t:ty] $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     into $into:ident, $($rest:tt)*) => {
+        bitfield_fields!{@nonzero $into [$t] [$($saturating)?]
+                         {@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                          $attrs $cfgs $vis $setter_vis [$t] $mask $fmt $access $view $order $valid}
+                         {$($rest)*}}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt [$t:ty] $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     into $into:ty, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [$t] $mask $fmt $access $view $order $valid [$t, $into $(; $saturating)?]; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt [$t:ty] $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     try_into $into:ty, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [$t] $mask $fmt $access $view $order $valid
+                         [$t, $crate::result::Result<$into, <$into as $crate::convert::TryFrom<$t>>::Error>, try_into
+                          $(; $saturating)?];
+                         $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     flags $flags:ty, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid [$flags, $flags, flags $(; $saturating)?]; $($rest)*}
+    };
+    // `width` fields and reserved bits are placed at the cursor, which is moved after them.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; [$($cursor:tt)*]; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     reserved: width $width:tt; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; [($($cursor)*) + $width]; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         reserved: (($($cursor)*) + $width - 1), ($($cursor)*); $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; [$($cursor:tt)*]; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, $setter:tt: width $width:tt $(, $count:tt)? $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved;
+                         [($($cursor)*) + $width $(* $count)?]; $records; $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: (($($cursor)*) + $width - 1), ($($cursor)*) $(, $count)? $(= $default)?;
+                         $($rest)*}
+    };
+    // `reserved: msb, lsb;` only declares bits that are not used by a field.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; $const_fn:tt; $naming:tt; $splits:tt; [$($reserved:tt)*]; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; reserved: $($pos:expr),+; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; $const_fn; $naming; $splits; [$($reserved)* [$($pos),+]]; $cursor; $records;
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    // `impl <Trait>;` lines can be left in the declarations by `bitfield_impl`.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; impl $_trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records; $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    // The split fields are kept apart from the records, as only their getters and setters are
+    // generated.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; $const_fn:tt; $naming:tt; [$($splits:tt)*]; $reserved:tt; $cursor:tt; $records:tt;
+     [$($attrs:tt)*] $cfgs:tt [$($vis:tt)*] [$($setter_vis:tt)*] [$t:ty] $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     $getter:tt, $setter:tt: [$msb:expr, $lsb:expr] $(+ + [$msbs:expr, $lsbs:expr])+; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; $const_fn; $naming;
+                         [$($splits)* {
+                             attrs [$($attrs)*] vis [$($vis)*] ty [$t] from [$from] into [$into] conv [$($conv)?]
+                             getter [$getter] setter [$setter] pieces [[$msb, $lsb] $([$msbs, $lsbs])+]
+                             setter_vis [$($setter_vis)*]
+                         }];
+                         $reserved; $cursor; $records;
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    // `@offset; width` is turned into `msb, lsb`.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, $setter:tt: @ $offset:tt; $width:tt $(, $count:tt)? $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: ($offset + $width - 1), ($offset) $(, $count)? $(= $default)?; $($rest)*}
+    };
+    // The ranges of positions are turned into `msb, lsb`. Their bounds are checked when the
+    // `bitfield!` struct is checked, with its bit order: decreasing for the LSB0 structs and
+    // increasing for the MSB0 ones.
+    (@parse_step $cb:ident!(@check_bounds $bits:tt MSB0 $($endian:ident)?); $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, $setter:tt: $first:tt ..= $last:tt $(, $count:tt)? $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!(@check_bounds $bits MSB0 $($endian)?); $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: ({
+                             __bitfield_assert!(
+                                 $first <= $last,
+                                 __bitfield_concat!("the range of `", __bitfield_stringify!($getter, $setter),
+                                                    "` must be `lsb..=msb` in an MSB0 struct")
+                             );
+                             $last
+                         }), ($first) $(, $count)? $(= $default)?; $($rest)*}
+    };
+    (@parse_step $cb:ident!(@check_bounds $bits:tt $($bit_order:ident)+); $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, $setter:tt: $first:tt ..= $last:tt $(, $count:tt)? $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!(@check_bounds $bits $($bit_order)+); $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: ({
+                             __bitfield_assert!(
+                                 $first >= $last,
+                                 __bitfield_concat!("the range of `", __bitfield_stringify!($getter, $setter),
+                                                    "` must be `msb..=lsb`, or `lsb..=msb` in an MSB0 struct")
+                             );
+                             $first
+                         }), ($last) $(, $count)? $(= $default)?; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, $setter:tt: $first:tt ..= $last:tt $(, $count:tt)? $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: ($crate::bit_range_bounds($first, $last).0),
+                         ($crate::bit_range_bounds($first, $last).1) $(, $count)? $(= $default)?; $($rest)*}
+    };
+    // The read-only fields have no setter, and the write-only fields no getter.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt [ro] $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, $setter:ident: $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt [ro] $view $order $valid $conv; $getter, _: $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt [wo] $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:ident, $setter:tt: $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt [wo] $view $order $valid $conv; _, $setter: $($rest)*}
+    };
+    // An `alias` is kept with the split fields, with the state of the field it follows, to be
+    // parsed again with its own names, attributes and visibility by the getters and setters.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; [$($splits:tt)*]; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, $setter:tt: $($pos:tt),+ = $default:expr;
+     $(#[$alias_attribute:meta])* $alias_vis:vis alias $($alias:tt),+ = $target:ident; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming;
+                         [$($splits)* {
+                             alias [$default_ty; $checked; $const_fn; [$(#[$alias_attribute])*] $cfgs [$alias_vis] [$alias_vis] $t
+                                    [__NO_MASK_FOR_FIELD(u8)] $fmt $access $view $order $valid $conv]
+                             target [$getter, $setter = $target] names [$($alias),+] pos [$($pos),+ = $default]
+                         }];
+                         $reserved; $cursor; $records; $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: $($pos),+ = $default; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; [$($splits:tt)*]; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     $getter:tt, $setter:tt: $($exprs:expr),*;
+     $(#[$alias_attribute:meta])* $alias_vis:vis alias $($alias:tt),+ = $target:ident; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming;
+                         [$($splits)* {
+                             alias [$default_ty; $checked; $const_fn; [$(#[$alias_attribute])*] $cfgs [$alias_vis] [$alias_vis] $t
+                                    [__NO_MASK_FOR_FIELD(u8)] $fmt $access $view $order $valid $conv]
+                             target [$getter, $setter = $target] names [$($alias),+] pos [$($exprs),*]
+                         }];
+                         $reserved; $cursor; $records; $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         $getter, $setter: $($exprs),*; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     alias $($_rest:tt)*) => {
+        __bitfield_compile_error!("an `alias` must directly follow the field it renames");
+    };
+    // The getter of a read-clear field takes `&mut self`, so it is only kept in `name`, and the
+    // other macros see the field as write-only.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] [$t:ty] [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [rc] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     $getter:ident, $setter:tt: $($pos:tt),+ = $default:expr; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$from] into [$into] name [$getter]
+                             getter [_] setter [$setter] pos [$($pos),+] default [$default] conv [$($conv)?] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [rc] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] [$t:ty] [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [rc] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     $getter:ident, $setter:tt: $($exprs:expr),*; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$from] into [$into] name [$getter]
+                             getter [_] setter [$setter] pos [$($exprs),*] default [] conv [$($conv)?] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [rc] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    // With a default value, the positions must be single tokens, because an expression can't be
+    // followed by `=`.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] [$t:ty] [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [$($access:ident)?] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     _, $setter:tt: $($pos:tt),+ = $default:expr; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$from] into [$into] name [$setter]
+                             getter [_] setter [$setter] pos [$($pos),+] default [$default] conv [$($conv)?] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [$($access)?] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] [$t:ty] [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [$($access:ident)?] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     $getter:tt, $setter:tt: $($pos:tt),+ = $default:expr; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$from] into [$into] name [$getter]
+                             getter [$getter] setter [$setter] pos [$($pos),+] default [$default] conv [$($conv)?] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [$($access)?] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] [$t:ty] [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [$($access:ident)?] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     _, $setter:tt: $($exprs:expr),*; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$from] into [$into] name [$setter]
+                             getter [_] setter [$setter] pos [$($exprs),*] default [] conv [$($conv)?] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [$($access)?] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] [$t:ty] [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [$($access:ident)?] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     $getter:tt, $setter:tt: $($exprs:expr),*; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$from] into [$into] name [$getter]
+                             getter [$getter] setter [$setter] pos [$($exprs),*] default [] conv [$($conv)?] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [$($access)?] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    // After the fields, so that fields named `signed`, `saturating`, `checked`, `unit`, `radix`
+    // or the access keywords still parse.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt [$t:ty] $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     signed, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [<$t as $crate::ToSigned>::Signed] $mask $fmt $access $view $order $valid
+                         [<$t as $crate::ToSigned>::Signed, <$t as $crate::ToSigned>::Signed $(; $saturating)?]; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$from:ty, $into:ty $(, $conv:ident)? $(; $_checked:ident)?];
+     saturating, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid [$from, $into $(, $conv)?; saturating]; $($rest)*}
+    };
+    // `checked;` applies to all the following fields, and is kept in the `checked` slot so that
+    // the conversion slot can be reset to it after each field.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $_checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$from:ty, $into:ty $(, $conv:ident)? $(; $saturating:ident)?];
+     checked; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [checked]; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid [$from, $into $(, $conv)?; checked]; $($rest)*}
+    };
+    // `const;` applies to all the following fields.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $_const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; const; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; [const]; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    // `get_prefix;` applies to all the following `field` declarations.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $_naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; get_prefix; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; [get]; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv; $($rest)*}
+    };
+    // `field name:` is turned into `name, set_name:`, or `get_name, set_name:` after `get_prefix;`.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; []; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     field $name:ident: $($rest:tt)*) => {
+        paste! {
+            bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; []; $splits; $reserved; $cursor; $records;
+                             $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                             $name, [<set_ $name>]: $($rest)*}
+        }
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; [get]; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     field $name:ident: $($rest:tt)*) => {
+        paste! {
+            bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; [get]; $splits; $reserved; $cursor; $records;
+                             $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                             [<get_ $name>], [<set_ $name>]: $($rest)*}
+        }
+    };
+    // `unit` and `radix` are presentation hints, collected in the `fmt` slot until the field.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt [$($fmt:tt)*] $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     unit $unit:literal, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask [$($fmt)* unit [$unit]] $access $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt [$($fmt:tt)*] $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     radix $radix:ident, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask [$($fmt)* radix [$radix]] $access $view $order $valid $conv; $($rest)*}
+    };
+    // The access semantics of the field.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $_access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     w1c, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt [w1c] $view $order $valid $conv; $($rest)*}
+    };
+    // The getters of the `valid_if` fields return `None` when the bit `$bit` is clear.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $_valid:tt $conv:tt;
+     valid_if $bit:expr, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order [($bit)] $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $_access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     w1s, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt [w1s] $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $_access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     ro, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt [ro] $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $_access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     wo, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt [wo] $view $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $_access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     rc, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt [rc] $view $order $valid $conv; $($rest)*}
+    };
+    // `bytes` fields also get accessors to the elements of the slice storages.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $_view:tt $order:tt $valid:tt $conv:tt;
+     bytes, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access [bytes] $order $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $_order:tt $valid:tt $conv:tt;
+     be, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view [be] $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $_order:tt $valid:tt $conv:tt;
+     le, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view [le] $valid $conv; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $old_default_ty:ty; [$($checked:ident)?]; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt; $default_ty:ty; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [$default_ty] $mask $fmt $access $view $order $valid [$default_ty, $default_ty $(; $checked)?]; $($rest)*}
+    };
+    // The `bool` arrays are recognized before their positions are, so they are placed here.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; [$($cursor:tt)*]; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt $conv:tt;
+     bool, $getter:tt, $setter:tt: width $width:tt, $count:tt $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved;
+                         [($($cursor)*) + $width * $count]; $records; $attrs $cfgs $vis $setter_vis $t $mask $fmt $access $view $order $valid $conv;
+                         bool, $getter, $setter: (($($cursor)*) + $width - 1), ($($cursor)*), $count $(= $default)?;
+                         $($rest)*}
+    };
+    // The elements of `bool` arrays are single bits starting at `lsb`, read as `u8`s and converted.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $old_t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     bool, $getter:tt, $setter:tt: $msb:tt, $lsb:tt, $count:tt = $default:expr; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [u8] $mask $fmt $access $view $order $valid [bool, bool, bool $(; $saturating)?];
+                         $getter, $setter: $lsb, $lsb, $count = $default; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $old_t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     bool, $getter:tt, $setter:tt: $msb:expr, $lsb:expr, $count:expr; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [u8] $mask $fmt $access $view $order $valid [bool, bool, bool $(; $saturating)?];
+                         $getter, $setter: $lsb, $lsb, $count; $($rest)*}
+    };
+    // The floating point fields are read as integers of the same size, and converted with
+    // `from_bits` and `to_bits`.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $old_t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     f32, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [u32] $mask $fmt $access $view $order $valid [f32, f32, float $(; $saturating)?]; $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $old_t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     f64, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [u64] $mask $fmt $access $view $order $valid [f64, f64, float $(; $saturating)?]; $($rest)*}
+    };
+    // The typed fields with single token positions are parsed in one step, with their type,
+    // unless an `alias` follows them.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $old_t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     $t:ty, $getter:tt, $setter:tt: $($pos:tt),+ $(= $default:expr)?;
+     $(#[$alias_attribute:meta])* $alias_vis:vis alias $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [$t] $mask $fmt $access $view $order $valid [$t, $t $(; $saturating)?];
+                         $getter, $setter: $($pos),+ $(= $default)?; $(#[$alias_attribute])* $alias_vis alias $($rest)*}
+    };
+    // With the doc comments and the `pub` of the next field, which is most often typed too, so
+    // that it is parsed in one step as well.
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] $old_t:tt [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     $t:ty, $getter:ident, $setter:ident: $($pos:tt),+ $(= $default:expr)?;
+     $(#[doc = $next_doc:literal])* pub $next:ident $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$t] into [$t] name [$getter]
+                             getter [$getter] setter [$setter] pos [$($pos),+] default [$($default)?] conv [] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [$(#[doc = $next_doc])*] [] [pub] [pub] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $next $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] $old_t:tt [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     $t:ty, $getter:ident, $setter:ident: $($pos:tt),+ $(= $default:expr)?;
+     $(#[doc = $next_doc:literal])+ $next:ident $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$t] into [$t] name [$getter]
+                             getter [$getter] setter [$setter] pos [$($pos),+] default [$($default)?] conv [] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [$(#[doc = $next_doc])+] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $next $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; [$($checked:ident)?]; [$($const_fn:ident)?]; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; [$($records:tt)*];
+     [$($attrs:tt)*] [$($cfgs:tt)*] [$($vis:tt)*] [$($setter_vis:tt)*] $old_t:tt [$mask:ident($mask_t:ty) $($consts:ident)?] [$($fmt:tt)*] [] [$($view:ident)?] [$($order:ident)?] [$($valid:tt)?] [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     $t:ty, $getter:ident, $setter:ident: $($pos:tt),+ $(= $default:expr)?; $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; [$($checked)?]; [$($const_fn)?]; $naming; $splits; $reserved; $cursor;
+                         [$($records)* {
+                             attrs [$($attrs)*] cfgs [$($cfgs)*] vis [$($vis)*] ty [$t]
+                             mask [$mask($mask_t)] from [$t] into [$t] name [$getter]
+                             getter [$getter] setter [$setter] pos [$($pos),+] default [$($default)?] conv [] saturating [$($saturating)?] fmt [$($fmt)*] const_fn [$($const_fn)?]
+                             consts [$($consts)?] access [] bytes [$($view)?] order [$($order)?] valid [$($valid)?] setter_vis [$($setter_vis)*]
+                         }];
+                         [] [] [] [] [$default_ty] [__NO_MASK_FOR_FIELD(u8)] [] [] [] [] [] [$default_ty, $default_ty $(; $checked)?];
+                         $($rest)*}
+    };
+    (@parse_step $cb:ident!$args:tt; $default_ty:ty; $checked:tt; $const_fn:tt; $naming:tt; $splits:tt; $reserved:tt; $cursor:tt; $records:tt;
+     $attrs:tt $cfgs:tt $vis:tt $setter_vis:tt $old_t:tt $mask:tt $fmt:tt $access:tt $view:tt $order:tt $valid:tt [$_from:ty, $_into:ty $(, $_conv:ident)? $(; $saturating:ident)?];
+     $t:ty, $($rest:tt)*) => {
+        bitfield_fields!{@parse_step $cb!$args; $default_ty; $checked; $const_fn; $naming; $splits; $reserved; $cursor; $records;
+                         $attrs $cfgs $vis $setter_vis [$t] $mask $fmt $access $view $order $valid [$t, $t $(; $saturating)?]; $($rest)*}
+    };
+    (@from_value [float] $t:ty, $value:expr) => {
+        $value.to_bits()
+    };
+    (@into_value [float] $into:ty, $raw_value:expr) => {
+        <$into>::from_bits($raw_value)
+    };
+    (@from_value [flags] $t:ty, $value:expr) => {
+        $crate::Into::<$t>::into($value.bits())
+    };
+    (@into_value [flags] $into:ty, $raw_value:expr) => {
+        <$into>::from_bits_truncate($crate::Into::into($raw_value))
+    };
+    (@saturate [] $t:ty, $value:expr, $width:expr) => {
+        $value
+    };
+    (@saturate [saturating] $t:ty, $value:expr, $width:expr) => {{
+        let value: $t = $value;
+        let width = $width;
+        if width >= $crate::size_of::<$t>() * 8 {
+            value
+        } else {
+            let one: $t = 1;
+            let (min, max) = if <$t>::MIN != 0 {
+                let max = (one << (width - 1)) - one;
+                (!max, max)
+            } else {
+                (0, (one << width) - one)
+            };
+            if value > max {
+                max
+            } else if value < min {
+                min
+            } else {
+                value
+            }
+        }
+    }};
+    (@saturate [checked] $t:ty, $value:expr, $width:expr) => {{
+        let value: $t = $value;
+        let width = $width;
+        let shift = ($crate::size_of::<$t>() * 8).saturating_sub(width);
+        __bitfield_debug_assert!(
+            shift == 0 || (value << shift) >> shift == value,
+            "value too large for the {} bits of the field",
+            width
+        );
+        value
+    }};
+    (@nonzero NonZeroU8 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroU8 $($rest)*} };
+    (@nonzero NonZeroU16 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroU16 $($rest)*} };
+    (@nonzero NonZeroU32 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroU32 $($rest)*} };
+    (@nonzero NonZeroU64 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroU64 $($rest)*} };
+    (@nonzero NonZeroU128 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroU128 $($rest)*} };
+    (@nonzero NonZeroUsize $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroUsize $($rest)*} };
+    (@nonzero NonZeroI8 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroI8 $($rest)*} };
+    (@nonzero NonZeroI16 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroI16 $($rest)*} };
+    (@nonzero NonZeroI32 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroI32 $($rest)*} };
+    (@nonzero NonZeroI64 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroI64 $($rest)*} };
+    (@nonzero NonZeroI128 $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroI128 $($rest)*} };
+    (@nonzero NonZeroIsize $($rest:tt)*) => { bitfield_fields!{@nonzero_into NonZeroIsize $($rest)*} };
+    (@nonzero $into:ident [$t:ty] [$($saturating:ident)?] {$($step:tt)*} {$($rest:tt)*}) => {
+        bitfield_fields!{$($step)* [$t, $into $(; $saturating)?]; $($rest)*}
+    };
+    // The getters of the `NonZero*` fields return `None` for 0, and their setters take the
+    // `NonZero*` type.
+    (@nonzero_into $into:ident [$t:ty] [$($saturating:ident)?] {$($step:tt)*} {$($rest:tt)*}) => {
+        bitfield_fields!{$($step)* [$into, Option<$into>, nonzero $(; $saturating)?]; $($rest)*}
+    };
+    (@into_value [nonzero] $into:ty, $raw_value:expr) => {
+        $crate::convert::TryInto::try_into($raw_value).ok()
+    };
+    (@from_value [bool] $t:ty, $value:expr) => {
+        $value as $t
+    };
+    (@into_value [bool] $into:ty, $raw_value:expr) => {
+        $raw_value != 0
+    };
+    (@into_value [try_into] $into:ty, $raw_value:expr) => {
+        $crate::convert::TryInto::try_into($raw_value)
+    };
+    (@from_value [$($_conv:ident)?] $t:ty, $value:expr) => {
+        $crate::Into::<$t>::into($value)
+    };
+    (@into_value [$($_conv:ident)?] $into:ty, $raw_value:expr) => {
+        $crate::Into::into($raw_value)
+    };
+    // The elements of an array are contiguous, unless a stride is given.
+    (@stride $width:expr) => {
+        $width
+    };
+    (@stride $width:expr, $stride:expr) => {
+        $stride
+    };
+    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $($exprs:expr),*) => {
+        bitfield_fields!(only mask; @field $(#[$attribute])* ($($vis)*) $t, $mask($mask_t): $($exprs),*);
+    };
+    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $($exprs:expr),*) => {
+        bitfield_fields!(only mask; @field $(#[$attribute])* ($($vis)*) $t, $mask($mask_t): $($exprs),*);
+    };
+    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, __NO_MASK_FOR_FIELD($mask_t:ty): $($exprs:expr),*) => {};
+    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty): $bit:expr) => {
+        $($vis)* const $mask: $mask_t = 1 << $bit;
+    };
+    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty): $msb:expr, $lsb:expr) => {
+        $($vis)* const $mask: $mask_t = {
+            let msb = $msb;
+            let lsb = $lsb;
+            let mut i = lsb;
+            let mut acc = 0;
+            while i <= msb {
+                acc |= 1<<i;
+                i += 1;
+            }
+            acc
+        };
+    };
+    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty): $msb:expr, $lsb:expr, $count:expr $(, $stride:expr)?) => {
+        $($vis)* const $mask: $mask_t = {
+            let msb = $msb;
+            let lsb = $lsb;
+            let width = msb - lsb + 1;
+            let stride = bitfield_fields!(@stride width $(, $stride)?);
+            let mut element = 0;
+            let mut acc = 0;
+            while element < $count {
+                let mut i = lsb + element * stride;
+                while i < lsb + element * stride + width {
+                    acc |= 1<<i;
+                    i += 1;
+                }
+                element += 1;
+            }
+            acc
+        };
+    };
+    (only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $msb:expr,
+     $lsb:expr, $count:expr $(, $stride:expr)?) => {
+        $(#[$attribute])*
+        #[allow(unknown_lints)]
+        #[allow(eq_op)]
+        $($vis)* fn $setter(&mut self, index: usize, value: $from) {
+            use $crate::BitRangeMut;
+            __bitfield_debug_assert!(index < $count);
+            let width = $msb - $lsb + 1;
+            let lsb = $lsb + index*bitfield_fields!(@stride width $(, $stride)?);
+            let msb = lsb + width - 1;
+            let value = bitfield_fields!(@from_value $conv $t, value);
+            self.set_bit_range(msb, lsb, bitfield_fields!(@saturate $saturating $t, value, width));
+        }
+    };
+    (only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $msb:expr,
+     $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, value: $from) {
+            use $crate::BitRangeMut;
+            let value = bitfield_fields!(@from_value $conv $t, value);
+            self.set_bit_range($msb, $lsb, bitfield_fields!(@saturate $saturating $t, value, $msb - $lsb + 1));
+        }
+    };
+    (only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $bit:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, value: bool) {
+            use $crate::BitMut;
+            self.set_bit($bit, value);
+        }
+    };
+    (only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $($exprs:expr),*) => {};
+
+    (only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $msb:expr, $lsb:expr, $count:expr $(, $stride:expr)?) => {
+        $(#[$attribute])*
+        #[allow(unknown_lints)]
+        #[allow(eq_op)]
+        $($vis)* fn $getter(&self, index: usize) -> $into {
+            use $crate::BitRange;
+            __bitfield_debug_assert!(index < $count);
+            let width = $msb - $lsb + 1;
+            let lsb = $lsb + index*bitfield_fields!(@stride width $(, $stride)?);
+            let msb = lsb + width - 1;
+            let raw_value: $t = self.bit_range(msb, lsb);
+            bitfield_fields!(@into_value $conv $into, raw_value)
+        }
+    };
+    (only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $msb:expr,
+     $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self) -> $into {
+            use $crate::BitRange;
+            let raw_value: $t = self.bit_range($msb, $lsb);
+            bitfield_fields!(@into_value $conv $into, raw_value)
+        }
+    };
+    (only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $bit:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self) -> bool {
+            use $crate::Bit;
+            self.bit($bit)
+        }
+    };
+    (only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $($exprs:expr),*) => {};
+
+    // The `_all` setters are only generated by `bitfield!`, as they are not part of the traits
+    // implemented with `bitfield_fields!`.
+    (only setter_all; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:tt, $setter:ident: $msb:expr,
+     $lsb:expr, $count:expr $(, $stride:expr)?) => {
+        paste! {
+            $(#[$attribute])*
+            $($vis)* fn [<$setter _all>](&mut self, values: [$from; $count]) {
+                for (index, value) in IntoIterator::into_iter(values).enumerate() {
+                    self.$setter(index, value);
+                }
+            }
+        }
+    };
+    (only setter_all; @field $($_rest:tt)*) => {};
+    // The read-clear fields without a setter have neither in the records, but still have a mask.
+    (only mask; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, _: $($exprs:expr),*) => {
+        bitfield_fields!(only mask; @field $(#[$attribute])* ($($vis)*) $t, $mask($mask_t): $($exprs),*);
+    };
+    (only $only:tt $($const_fn:ident)?; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, _: $($exprs:expr),*) => {};
+    (only $only:tt; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, $setter:ident:
+     $($exprs:expr),*) => {
+        bitfield_fields!(only $only; @field $(#[$attribute])* ($($vis)*) $t, $mask($mask_t), $from, $into, $conv, $saturating, $getter, _: $($exprs),*);
+        bitfield_fields!(only $only; @field $(#[$attribute])* ($($vis)*) $t, __NO_MASK_FOR_FIELD(u8), $from, $into, $conv, $saturating, _, $setter: $($exprs),*);
+    };
+
+    // The `const fn` accessors of the fields following `const;` work directly on the integer
+    // storage, as the traits can't be used in a `const fn`.
+    (only mask const; $($rest:tt)*) => {
+        bitfield_fields!{only mask; $($rest)*}
+    };
+    (only setter_all const; $($rest:tt)*) => {
+        bitfield_fields!{only setter_all; $($rest)*}
+    };
+    (only getter const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $($exprs:expr),*) => {};
+    (only setter const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $($exprs:expr),*) => {};
+    (only getter const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $msb:expr, $lsb:expr, $count:expr $(, $stride:expr)?) => {
+        $(#[$attribute])*
+        #[allow(unknown_lints)]
+        #[allow(eq_op)]
+        $($vis)* const fn $getter(&self, index: usize) -> $into {
+            __bitfield_debug_assert!(index < $count);
+            let width = $msb - $lsb + 1;
+            let lsb = $lsb + index*bitfield_fields!(@stride width $(, $stride)?);
+            let raw_value = bitfield_fields!(@const_bit_range $t, self.0, lsb + width - 1, lsb);
+            bitfield_fields!(@const_into_value $conv $into, raw_value)
+        }
+    };
+    (only getter const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $msb:expr, $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* const fn $getter(&self) -> $into {
+            let raw_value = bitfield_fields!(@const_bit_range $t, self.0, $msb, $lsb);
+            bitfield_fields!(@const_into_value $conv $into, raw_value)
+        }
+    };
+    (only getter const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, _: $bit:expr) => {
+        $(#[$attribute])*
+        $($vis)* const fn $getter(&self) -> bool {
+            (self.0 as u128 >> $bit) & 1 != 0
+        }
+    };
+    (only setter const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $msb:expr, $lsb:expr, $count:expr $(, $stride:expr)?) => {
+        $(#[$attribute])*
+        #[allow(unknown_lints)]
+        #[allow(eq_op)]
+        $($vis)* const fn $setter(&mut self, index: usize, value: $from) {
+            __bitfield_debug_assert!(index < $count);
+            let width = $msb - $lsb + 1;
+            let lsb = $lsb + index*bitfield_fields!(@stride width $(, $stride)?);
+            let value = bitfield_fields!(@const_from_value $conv $t, value);
+            let value = bitfield_fields!(@const_saturate $saturating $t, value, width);
+            bitfield_fields!(@const_set_bit_range self.0, lsb + width - 1, lsb, value);
+        }
+    };
+    (only setter const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $msb:expr, $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* const fn $setter(&mut self, value: $from) {
+            let value = bitfield_fields!(@const_from_value $conv $t, value);
+            let value = bitfield_fields!(@const_saturate $saturating $t, value, $msb - $lsb + 1);
+            bitfield_fields!(@const_set_bit_range self.0, $msb, $lsb, value);
+        }
+    };
+    (only setter const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:ident: $bit:expr) => {
+        $(#[$attribute])*
+        $($vis)* const fn $setter(&mut self, value: bool) {
+            bitfield_fields!(@const_set_bit_range self.0, $bit, $bit, value);
+        }
+    };
+    (only $only:tt const; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, $setter:ident:
+     $($exprs:expr),*) => {
+        bitfield_fields!(only $only const; @field $(#[$attribute])* ($($vis)*) $t, $mask($mask_t), $from, $into, $conv, $saturating, $getter, _: $($exprs),*);
+        bitfield_fields!(only $only const; @field $(#[$attribute])* ($($vis)*) $t, __NO_MASK_FOR_FIELD(u8), $from, $into, $conv, $saturating, _, $setter: $($exprs),*);
+    };
+    // Reads the bits `msb..=lsb` of an integer, sign-extended if `$t` is signed.
+    (@const_bit_range $t:ty, $storage:expr, $msb:expr, $lsb:expr) => {{
+        let width = $msb - $lsb + 1;
+        let raw = ($storage as u128 >> $lsb) & (u128::MAX >> (128 - width));
+        if <$t>::MIN != 0 {
+            (((raw << (128 - width)) as i128) >> (128 - width)) as $t
+        } else {
+            raw as $t
+        }
+    }};
+    (@const_set_bit_range $storage:expr, $msb:expr, $lsb:expr, $value:expr) => {{
+        let lsb = $lsb;
+        let mask = (u128::MAX >> (127 - ($msb - lsb))) << lsb;
+        $storage = (($storage as u128 & !mask) | (($value as u128) << lsb & mask)) as _;
+    }};
+    (@const_into_value [] $into:ty, $raw_value:expr) => {
+        $raw_value
+    };
+    (@const_into_value [bool] $into:ty, $raw_value:expr) => {
+        $raw_value != 0
+    };
+    (@const_into_value [$conv:ident] $into:ty, $raw_value:expr) => {
+        __bitfield_compile_error!("the fields following `const;` can't use `into`, `try_into` or `flags`")
+    };
+    (@const_from_value [] $t:ty, $value:expr) => {
+        $value
+    };
+    (@const_from_value [bool] $t:ty, $value:expr) => {
+        $value as $t
+    };
+    (@const_from_value [$conv:ident] $t:ty, $value:expr) => {
+        __bitfield_compile_error!("the fields following `const;` can't use `into`, `try_into` or `flags`")
+    };
+    (@const_saturate [checked] $t:ty, $value:expr, $width:expr) => {{
+        let value: $t = $value;
+        let shift = ($crate::size_of::<$t>() * 8).saturating_sub($width);
+        __bitfield_debug_assert!(
+            shift == 0 || (value << shift) >> shift == value,
+            "value too large for the field"
+        );
+        value
+    }};
+    (@const_saturate $saturating:tt $t:ty, $value:expr, $width:expr) => {
+        bitfield_fields!(@saturate $saturating $t, $value, $width)
+    };
+    (only consts; @records [$({
+        attrs $_attrs:tt cfgs $cfgs:tt vis $vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter $_setter:tt pos $pos:tt default $_default:tt conv $_conv:tt
+        saturating $_saturating:tt fmt $_fmt:tt const_fn $_const_fn:tt consts $consts:tt $($_rest:tt)*
+    })*]) => {
+        $(bitfield_fields!{@consts $cfgs $vis $consts $pos})*
+    };
+    (@consts $cfgs:tt $vis:tt [] $pos:tt) => {};
+    (@consts $cfgs:tt $vis:tt $consts:tt [$bit:expr]) => {
+        bitfield_fields!{@consts $cfgs $vis $consts [$bit, $bit]}
+    };
+    (@consts [$(#[$cfg:meta])*] [$($vis:tt)*] [$consts:ident] [$msb:expr, $lsb:expr $(, $count:expr $(, $stride:expr)?)?]) => {
+        paste! {
+            $(#[$cfg])*
+            $($vis)* const [<$consts _OFFSET>]: usize = $lsb;
+            $(#[$cfg])*
+            $($vis)* const [<$consts _WIDTH>]: usize = $msb - $lsb + 1;
+            $(#[$cfg])*
+            $($vis)* const [<$consts _MSB>]: usize = $msb;
+            $(#[$cfg])*
+            $($vis)* const [<$consts _LSB>]: usize = $lsb;
+        }
+    };
+    // Used by `bitfield!` for the slice storages, with the headers of the `impl` blocks of the
+    // getters and of the setters. The setters are skipped for an immutable storage.
+    (@bytes [$($getter_impl:tt)*] [] $t:ty [$({
+        attrs $_attrs:tt cfgs $cfgs:tt vis $vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name [$field:ident] getter $getter:tt setter $setter:tt pos $pos:tt default $_default:tt conv $conv:tt
+        saturating $_saturating:tt fmt $_fmt:tt const_fn $_const_fn:tt consts $_consts:tt access $_access:tt
+        bytes $bytes:tt $($_rest:tt)*
+    })*]) => {
+        $($getter_impl)* {
+            $(bitfield_fields!{@bytes_getter $bytes $t, $cfgs $vis $field $getter $pos $conv})*
+        }
+    };
+    (@bytes [$($getter_impl:tt)*] [$($setter_impl:tt)*] $t:ty [$({
+        attrs $_attrs:tt cfgs $cfgs:tt vis $vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name [$field:ident] getter $getter:tt setter $setter:tt pos $pos:tt default $_default:tt conv $conv:tt
+        saturating $_saturating:tt fmt $_fmt:tt const_fn $_const_fn:tt consts $_consts:tt access $_access:tt
+        bytes $bytes:tt $($_rest:tt)*
+    })*]) => {
+        $($getter_impl)* {
+            $(bitfield_fields!{@bytes_getter $bytes $t, $cfgs $vis $field $getter $pos $conv})*
+        }
+        $($setter_impl)* {
+            $(bitfield_fields!{@bytes_setter $bytes $t, $cfgs $vis $field $setter $pos $conv})*
+        }
+    };
+    (@bytes_getter [bytes] $t:ty, [$(#[$cfg:meta])*] [$($vis:tt)*] $field:ident [$getter:ident] $pos:tt $conv:tt) => {
+        paste! {
+            $(#[$cfg])*
+            #[doc = "Returns the elements of the storage holding `" $field "`."]
+            #[allow(dead_code)]
+            $($vis)* fn [<$field _bytes>](&self) -> &[$t] {
+                let (start, end) = bitfield_fields!{@bytes_range $t, $field $pos $conv};
+                &self.0.as_ref()[start..end]
+            }
+        }
+    };
+    (@bytes_getter $bytes:tt $t:ty, $cfgs:tt $vis:tt $field:ident $getter:tt $pos:tt $conv:tt) => {};
+    (@bytes_setter [bytes] $t:ty, [$(#[$cfg:meta])*] [$($vis:tt)*] $field:ident [$setter:ident] $pos:tt $conv:tt) => {
+        paste! {
+            $(#[$cfg])*
+            #[doc = "Returns the elements of the storage holding `" $field "`, to modify them in place."]
+            #[allow(dead_code)]
+            $($vis)* fn [<$field _bytes_mut>](&mut self) -> &mut [$t] {
+                let (start, end) = bitfield_fields!{@bytes_range $t, $field $pos $conv};
+                &mut self.0.as_mut()[start..end]
+            }
+        }
+    };
+    (@bytes_setter $bytes:tt $t:ty, $cfgs:tt $vis:tt $field:ident $setter:tt $pos:tt $conv:tt) => {};
+    // The elements of the storage would also hold the bits between the elements of the array.
+    (@bytes_range $t:ty, $field:ident [$msb:expr, $lsb:expr, $count:expr, $stride:expr] $conv:tt) => {
+        __bitfield_compile_error!("`bytes` doesn't support the arrays with a stride")
+    };
+    // The range of elements of a field, which must start and end on element boundaries.
+    (@bytes_range $t:ty, $field:ident $pos:tt $conv:tt) => {{
+        const RANGE: (usize, usize) = {
+            let bits = $crate::size_of::<$t>() * 8;
+            let (lsb, end) = bitfield_impl!{@bits_range $pos $conv};
+            $crate::__bitfield_assert!(
+                lsb % bits == 0 && end % bits == 0,
+                __bitfield_concat!("`", __bitfield_stringify!($field), "` isn't aligned on the elements of the storage")
+            );
+            (lsb / bits, end / bits)
+        };
+        RANGE
+    }};
+    (only $only:tt; @records [$({
+        attrs [$($attrs:tt)*] cfgs $_cfgs:tt vis [$($vis:tt)*] ty [$t:ty] mask [$mask:ident($mask_t:ty)]
+        from [$from:ty] into [$into:ty] name $name:tt getter [$getter:tt] setter [$setter:tt]
+        pos [$($exprs:expr),*] default $_default:tt conv $conv:tt saturating $saturating:tt fmt $_fmt:tt
+        const_fn [$($const_fn:tt)?] consts $_consts:tt access $access:tt bytes $_bytes:tt order $order:tt
+        valid $valid:tt setter_vis $setter_vis:tt
+    })*]) => {
+        $(
+            bitfield_fields!{@select_vis $only [$($vis)*] $setter_vis
+                             {@access $access $name @valid $valid @ordered $order only $only $($const_fn)?;
+                              @field $($attrs)* #[doc = ""] #[doc = bitfield_fields!(@pos_doc $($exprs),*)]}
+                             {$t, $mask($mask_t), $from, $into, $conv, $saturating, $getter, $setter: $($exprs),*}}
+        )*
+    };
+    // Gives a record to `$cb!` with the visibility of the setter as `vis`, for the methods calling
+    // the setter.
+    (@setter_record $cb:ident!($($args:tt)*) {
+        attrs $attrs:tt cfgs $cfgs:tt vis $_vis:tt ty $t:tt mask $mask:tt from $from:tt into $into:tt name $name:tt
+        getter $getter:tt setter $setter:tt pos $pos:tt default $default:tt conv $conv:tt saturating $saturating:tt
+        fmt $fmt:tt const_fn $const_fn:tt consts $consts:tt access $access:tt bytes $bytes:tt order $order:tt
+        valid $valid:tt setter_vis $setter_vis:tt
+    }) => {
+        $crate::$cb!{$($args)* {
+            attrs $attrs cfgs $cfgs vis $setter_vis ty $t mask $mask from $from into $into name $name
+            getter $getter setter $setter pos $pos default $default conv $conv saturating $saturating
+            fmt $fmt const_fn $const_fn consts $consts access $access bytes $bytes order $order
+            valid $valid setter_vis $setter_vis
+        }}
+    };
+    // Gives the records to `$cb!` with `Option`s as `from` and `into` for the `valid_if` fields, for
+    // the methods taking or returning the values of the accessors.
+    (@valid_records $cb:ident!($($args:tt)*) [$($done:tt)*] []) => {
+        $crate::$cb!{$($args)* [$($done)*]}
+    };
+    (@valid_records $cb:ident!$args:tt [$($done:tt)*] [{
+        attrs $attrs:tt cfgs $cfgs:tt vis $vis:tt ty $t:tt mask $mask:tt from [$from:ty] into [$into:ty] name $name:tt
+        getter $getter:tt setter $setter:tt pos $pos:tt default $default:tt conv $conv:tt saturating $saturating:tt
+        fmt $fmt:tt const_fn $const_fn:tt consts $consts:tt access $access:tt bytes $bytes:tt order $order:tt
+        valid [$bit:tt] setter_vis $setter_vis:tt
+    } $($records:tt)*]) => {
+        bitfield_fields!{@valid_records $cb!$args [$($done)* {
+            attrs $attrs cfgs $cfgs vis $vis ty $t mask $mask from [Option<$from>] into [Option<$into>] name $name
+            getter $getter setter $setter pos $pos default $default conv $conv saturating $saturating
+            fmt $fmt const_fn $const_fn consts $consts access $access bytes $bytes order $order
+            valid [$bit] setter_vis $setter_vis
+        }] [$($records)*]}
+    };
+    (@valid_records $cb:ident!$args:tt [$($done:tt)*] [$record:tt $($records:tt)*]) => {
+        bitfield_fields!{@valid_records $cb!$args [$($done)* $record] [$($records)*]}
+    };
+    // The setters have the visibility given before the setter, if any.
+    (@select_vis setter $_vis:tt [$($setter_vis:tt)*] {$($head:tt)*} {$($tail:tt)*}) => {
+        bitfield_fields!{$($head)* ($($setter_vis)*) $($tail)*}
+    };
+    (@select_vis setter_all $_vis:tt [$($setter_vis:tt)*] {$($head:tt)*} {$($tail:tt)*}) => {
+        bitfield_fields!{$($head)* ($($setter_vis)*) $($tail)*}
+    };
+    (@select_vis $_only:tt [$($vis:tt)*] $_setter_vis:tt {$($head:tt)*} {$($tail:tt)*}) => {
+        bitfield_fields!{$($head)* ($($vis)*) $($tail)*}
+    };
+    // The doc line appended to the accessors, after the doc comments of the field.
+    (@pos_doc $bit:expr) => {
+        __bitfield_concat!("Bit `", __bitfield_stringify!($bit), "`.")
+    };
+    (@pos_doc $msb:expr, $lsb:expr) => {
+        __bitfield_concat!("Bits `", __bitfield_stringify!($msb), ":", __bitfield_stringify!($lsb), "`.")
+    };
+    (@pos_doc $msb:expr, $lsb:expr, $count:expr) => {
+        __bitfield_concat!(
+            "Bits `", __bitfield_stringify!($msb), ":", __bitfield_stringify!($lsb), "` for the element 0, of `",
+            __bitfield_stringify!($count), "` contiguous elements."
+        )
+    };
+    (@pos_doc $msb:expr, $lsb:expr, $count:expr, $stride:expr) => {
+        __bitfield_concat!(
+            "Bits `", __bitfield_stringify!($msb), ":", __bitfield_stringify!($lsb), "` for the element 0, of `",
+            __bitfield_stringify!($count), "` elements every `", __bitfield_stringify!($stride), "` bits."
+        )
+    };
+    (@pos_doc_pieces [$msb:expr, $lsb:expr] $([$msbs:expr, $lsbs:expr])*) => {
+        __bitfield_concat!(
+            "Bits `", __bitfield_stringify!($msb), ":", __bitfield_stringify!($lsb), "`",
+            $(", `", __bitfield_stringify!($msbs), ":", __bitfield_stringify!($lsbs), "`",)*
+            ", from the most significant piece."
+        )
+    };
+    (@access [rc] [$name:ident] @valid [] @ordered [] only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:tt:
+     $msb:expr, $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $name(&mut self) -> $into {
+            let raw_value: $t = $crate::BitRange::bit_range(self, $msb, $lsb);
+            $crate::BitRangeMut::<$t>::set_bit_range(self, $msb, $lsb, 0);
+            bitfield_fields!(@into_value $conv $into, raw_value)
+        }
+    };
+    (@access [rc] [$name:ident] @valid [] @ordered [] only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:tt:
+     $bit:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $name(&mut self) -> bool {
+            let value = $crate::Bit::bit(self, $bit);
+            $crate::BitMut::set_bit(self, $bit, false);
+            value
+        }
+    };
+    (@access [rc] $name:tt @valid $valid:tt @ordered $order:tt only getter $($rest:tt)*) => {
+        __bitfield_compile_error!{"`rc` can only be used for the non-const, non-array fields without `valid_if` or a byte order"}
+    };
+    (@access [w1s] $name:tt @valid [] @ordered [] only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:tt, _:
+     $($exprs:expr),*) => {};
+    (@access [w1s] $name:tt @valid [] @ordered [] only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:tt, $setter:ident:
+     $msb:expr, $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, value: $from) {
+            let value: $t = bitfield_fields!(@from_value $conv $t, value);
+            let value = bitfield_fields!(@saturate $saturating $t, value, $msb - $lsb + 1);
+            let old_value: $t = $crate::BitRange::bit_range(self, $msb, $lsb);
+            $crate::BitRangeMut::set_bit_range(self, $msb, $lsb, old_value | value);
+        }
+    };
+    (@access [w1s] $name:tt @valid [] @ordered [] only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:tt, $setter:ident:
+     $bit:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, value: bool) {
+            if value {
+                $crate::BitMut::set_bit(self, $bit, true);
+            }
+        }
+    };
+    (@access [w1s] $name:tt @valid $valid:tt @ordered $order:tt only setter $($rest:tt)*) => {
+        __bitfield_compile_error!{"`w1s` can only be used for the non-array fields without `valid_if` or a byte order"}
+    };
+    (@access $access:tt $name:tt @valid [] @ordered [] $($field:tt)*) => {
+        bitfield_fields!{$($field)*}
+    };
+    (@access $access:tt $name:tt $($field:tt)*) => {
+        bitfield_fields!{$($field)*}
+    };
+    (@valid [] $($field:tt)*) => {
+        bitfield_fields!{$($field)*}
+    };
+    (@valid [$bit:tt] @ordered [] only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:tt:
+     $msb:expr, $lsb:expr) => {};
+    (@valid [$bit:tt] @ordered [] only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, $setter:tt:
+     $msb:expr, $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self) -> Option<$into> {
+            if $crate::Bit::bit(self, $bit) {
+                let raw_value: $t = $crate::BitRange::bit_range(self, $msb, $lsb);
+                Some(bitfield_fields!(@into_value $conv $into, raw_value))
+            } else {
+                None
+            }
+        }
+    };
+    (@valid [$bit:tt] @ordered [] only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:tt, _:
+     $msb:expr, $lsb:expr) => {};
+    (@valid [$bit:tt] @ordered [] only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:tt, $setter:ident:
+     $msb:expr, $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, value: Option<$from>) {
+            match value {
+                Some(value) => {
+                    let value: $t = bitfield_fields!(@from_value $conv $t, value);
+                    let value = bitfield_fields!(@saturate $saturating $t, value, $msb - $lsb + 1);
+                    $crate::BitRangeMut::set_bit_range(self, $msb, $lsb, value);
+                    $crate::BitMut::set_bit(self, $bit, true);
+                }
+                None => $crate::BitMut::set_bit(self, $bit, false),
+            }
+        }
+    };
+    (@valid [$bit:tt] @ordered $order:tt only getter $($rest:tt)*) => {
+        __bitfield_compile_error!{"`valid_if` can only be used for the non-const fields with a msb and a lsb, without a byte order"}
+    };
+    (@valid [$bit:tt] @ordered $order:tt only setter $($rest:tt)*) => {
+        __bitfield_compile_error!{"`valid_if` can only be used for the non-const fields with a msb and a lsb, without a byte order"}
+    };
+    (@valid [$bit:tt] $($field:tt)*) => {
+        bitfield_fields!{$($field)*}
+    };
+    (@ordered [] $($field:tt)*) => {
+        bitfield_fields!{$($field)*}
+    };
+    (@ordered [$order:ident] only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, _, $setter:tt:
+     $msb:expr, $lsb:expr) => {};
+    (@ordered [$order:ident] only getter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:ident, $setter:tt:
+     $msb:expr, $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $getter(&self) -> $into {
+            let raw_value: $t = $crate::BitRange::bit_range(self, $msb, $lsb);
+            let raw_value = bitfield_fields!(@reorder_getter $order $t, raw_value, $msb, $lsb);
+            bitfield_fields!(@into_value $conv $into, raw_value)
+        }
+    };
+    (@ordered [$order:ident] only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:tt, _:
+     $msb:expr, $lsb:expr) => {};
+    (@ordered [$order:ident] only setter; @field $(#[$attribute:meta])* ($($vis:tt)*) $t:ty, $mask:ident($mask_t:ty), $from:ty, $into:ty, $conv:tt, $saturating:tt, $getter:tt, $setter:ident:
+     $msb:expr, $lsb:expr) => {
+        $(#[$attribute])*
+        $($vis)* fn $setter(&mut self, value: $from) {
+            let value: $t = bitfield_fields!(@from_value $conv $t, value);
+            let value = bitfield_fields!(@saturate $saturating $t, value, $msb - $lsb + 1);
+            let value = bitfield_fields!(@reorder_setter $order $t, value, $msb, $lsb);
+            $crate::BitRangeMut::set_bit_range(self, $msb, $lsb, value);
+        }
+    };
+    (@ordered [$order:ident] only getter $($rest:tt)*) => {
+        __bitfield_compile_error!{"`be` and `le` can only be used for the non-const fields with a msb and a lsb"}
+    };
+    (@ordered [$order:ident] only setter $($rest:tt)*) => {
+        __bitfield_compile_error!{"`be` and `le` can only be used for the non-const fields with a msb and a lsb"}
+    };
+    (@ordered [$order:ident] $($field:tt)*) => {
+        bitfield_fields!{$($field)*}
+    };
+    // The bytes are swapped when the bit numbering of the struct does not match the byte order of
+    // the field, the raw value holding the field in its least significant bits.
+    (@reorder_getter $order:ident $t:ty, $value:expr, $msb:expr, $lsb:expr) => {{
+        bitfield_fields!(@check_byte_aligned $msb, $lsb);
+        let value: $t = $value;
+        if <Self as $crate::BitOrder>::MSB0 == bitfield_fields!(@is_le $order) {
+            value.swap_bytes() >> ($crate::size_of::<$t>() * 8 - ($msb - $lsb + 1))
+        } else {
+            value
+        }
+    }};
+    (@reorder_setter $order:ident $t:ty, $value:expr, $msb:expr, $lsb:expr) => {{
+        bitfield_fields!(@check_byte_aligned $msb, $lsb);
+        let value: $t = $value;
+        if <Self as $crate::BitOrder>::MSB0 == bitfield_fields!(@is_le $order) {
+            (value << ($crate::size_of::<$t>() * 8 - ($msb - $lsb + 1))).swap_bytes()
+        } else {
+            value
+        }
+    }};
+    (@check_byte_aligned $msb:expr, $lsb:expr) => {
+        const _: () = $crate::__bitfield_assert!(
+            ($lsb) % 8 == 0 && ($msb + 1) % 8 == 0,
+            "the fields with a byte order must start and end on byte boundaries"
+        );
+    };
+    (@is_le be) => { false };
+    (@is_le le) => { true };
+    (only $only:tt; @splits [$($split:tt)*]) => {
+        $(bitfield_fields!{only $only; @split $split})*
+    };
+    (only getter; @split {
+        attrs $attrs:tt vis $vis:tt ty [$t:ty] from $_from:tt into [$into:ty] conv $conv:tt
+        getter [$getter:tt] setter $_setter:tt pieces $pieces:tt setter_vis $_setter_vis:tt
+    }) => {
+        bitfield_fields!{@split_getter $attrs $vis $t, $into, $conv, $getter: $pieces}
+    };
+    (only setter; @split {
+        attrs $attrs:tt vis $_vis:tt ty [$t:ty] from [$from:ty] into $_into:tt conv $conv:tt
+        getter $_getter:tt setter [$setter:tt] pieces $pieces:tt setter_vis $vis:tt
+    }) => {
+        bitfield_fields!{@split_setter $attrs $vis $t, $from, $conv, $setter: $pieces}
+    };
+    // The target of an alias is checked once, with the getters.
+    (only getter; @split {alias $state:tt target $target:tt names $names:tt pos $pos:tt}) => {
+        bitfield_fields!{@alias_check $target $names}
+        bitfield_fields!{@alias_names getter $state $target $names $pos}
+    };
+    (only setter; @split {alias $state:tt target $target:tt names $names:tt pos $pos:tt}) => {
+        bitfield_fields!{@alias_names setter $state $target $names $pos}
+    };
+    (only $only:tt; @split $split:tt) => {};
+    // `alias old = new;` has a setter with the `set_` prefix, when the field has a setter.
+    (@alias_names $only:tt $state:tt [$getter:tt, _ = $target:ident] [$alias:ident] $pos:tt) => {
+        bitfield_fields!{@alias $only $state [$alias, _] $pos}
+    };
+    (@alias_names $only:tt $state:tt $target:tt [$alias:ident] $pos:tt) => {
+        paste! {
+            bitfield_fields!{@alias $only $state [$alias, [<set_ $alias>]] $pos}
+        }
+    };
+    (@alias_names $only:tt $state:tt $target:tt [$alias_getter:tt, $alias_setter:tt] $pos:tt) => {
+        bitfield_fields!{@alias $only $state [$alias_getter, $alias_setter] $pos}
+    };
+    (@alias $only:tt [$default_ty:ty; $checked:tt; $const_fn:tt; $($state:tt)*] [$alias_getter:tt, $alias_setter:tt] [$($pos:tt)*]) => {
+        bitfield_fields!{@parse_step bitfield_fields!(only $only; @records); $default_ty; $checked; $const_fn; []; []; []; [0]; [];
+                         $($state)*; $alias_getter, $alias_setter: $($pos)*;}
+    };
+    // The target must be the name of the field the alias follows, which is its getter, or its
+    // setter when it has no getter.
+    (@alias_check [_, $setter:ident = $target:ident] $names:tt) => {
+        bitfield_fields!{@alias_check [$setter, _ = $target] $names}
+    };
+    (@alias_check [$getter:ident, $setter:tt = $target:ident] [$alias:tt $($_names:tt)*]) => {
+        paste! {
+            #[allow(dead_code, non_upper_case_globals)]
+            const [<__BITFIELD_ALIAS_ $alias:upper>]: () = {
+                #[allow(non_camel_case_types)]
+                enum AliasTarget {
+                    $getter,
+                }
+                let _ = AliasTarget::$target;
+            };
+        }
+    };
+    (@split_getter $attrs:tt $vis:tt $t:ty, $into:ty, $conv:tt, _: $pieces:tt) => {};
+    // The first piece holds the most significant bits.
+    (@split_getter [$($attrs:tt)*] [$($vis:tt)*] $t:ty, $into:ty, $conv:tt, $getter:ident: [$([$msb:expr, $lsb:expr])+]) => {
+        $($attrs)*
+        #[doc = ""]
+        #[doc = bitfield_fields!(@pos_doc_pieces $([$msb, $lsb])+)]
+        $($vis)* fn $getter(&self) -> $into {
+            let mut raw_value: $t = 0;
+            $(
+                let piece: $t = $crate::BitRange::bit_range(self, $msb, $lsb);
+                raw_value = raw_value.checked_shl(($msb - $lsb + 1) as u32).unwrap_or(0) | piece;
+            )+
+            bitfield_fields!(@into_value $conv $into, raw_value)
+        }
+    };
+    (@split_setter $attrs:tt $vis:tt $t:ty, $from:ty, $conv:tt, _: $pieces:tt) => {};
+    (@split_setter [$($attrs:tt)*] [$($vis:tt)*] $t:ty, $from:ty, $conv:tt, $setter:ident: [$([$msb:expr, $lsb:expr])+]) => {
+        $($attrs)*
+        #[doc = ""]
+        #[doc = bitfield_fields!(@pos_doc_pieces $([$msb, $lsb])+)]
+        $($vis)* fn $setter(&mut self, value: $from) {
+            let value: $t = bitfield_fields!(@from_value $conv $t, value);
+            let mut shift = 0 $(+ ($msb - $lsb + 1))+;
+            $(
+                shift -= $msb - $lsb + 1;
+                $crate::BitRangeMut::set_bit_range(self, $msb, $lsb, value.checked_shr(shift as u32).unwrap_or(0));
+            )+
+        }
+    };
+    (only $only:tt; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(only $only; @records);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    ($($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(only getter; @records);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        bitfield_fields!{@parse bitfield_fields!(only setter; @records);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        bitfield_fields!{@parse bitfield_fields!(only mask; @records);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        bitfield_fields!{@parse bitfield_fields!(only consts; @records);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    }
+}
+
+/// Generates a `fmt::Debug` implementation.
+///
+/// This macros must be called from a `impl Debug for ...` block. It will generate the `fmt` method.
+///
+/// In most of the case, you will not directly call this macros, but use `bitfield`.
+///
+/// The syntax is `struct TheNameOfTheStruct` followed by the syntax of `bitfield_fields`.
+///
+/// The write-only fields are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// struct FooBar(u32);
+/// bitfield_bitrange!{struct FooBar(u32)}
+/// impl FooBar{
+///     bitfield_fields!{
+///        u32;
+///        field1, _: 7, 0;
+///        field2, _: 31, 24;
+///     }
+/// }
+///
+/// impl std::fmt::Debug for FooBar {
+///     bitfield_debug!{
+///        struct FooBar;
+///        field1, _: 7, 0;
+///        field2, _: 31, 24;
+///     }
+/// }
+///
+/// fn main() {
+///     let foobar = FooBar(0x11223344);
+///     println!("{:?}", foobar);
+/// }
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! bitfield_debug {
+    (struct $name:ident; $($rest:tt)*) => {
+        fn fmt(&self, f: &mut $crate::fmt::Formatter) -> $crate::fmt::Result {
+            let mut debug_struct = f.debug_struct(__bitfield_stringify!($name));
+            debug_struct.field(".0", &self.0);
+            bitfield_fields!{@parse bitfield_debug!(@fields debug_struct, self,);
+                             SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+            debug_struct.finish()
+        }
+    };
+    (@fields $debug_struct:ident, $self:ident, [$($record:tt)*]) => {
+        $(bitfield_debug!{@field $debug_struct, $self, $record})*
+    };
+    (@field $debug_struct:ident, $self:ident, {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter [_] $($_rest:tt)*
+    }) => {};
+    (@field $debug_struct:ident, $self:ident, {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter [$getter:ident] setter $_setter:tt pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]
+        $($_rest:tt)*
+    }) => {
+        let mut array = [$self.$getter(0); $count];
+        for (i, e) in (&mut array).into_iter().enumerate() {
+            *e = $self.$getter(i);
+        }
+        $debug_struct.field(__bitfield_stringify!($getter), &array);
+    };
+    (@field $debug_struct:ident, $self:ident, {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter [$getter:ident] $($_rest:tt)*
+    }) => {
+        $debug_struct.field(__bitfield_stringify!($getter), &$self.$getter());
+    };
+}
+
+/// Generates a `fmt::Display` implementation.
+///
+/// This macros must be called from a `impl Display for ...` block. It will generate the `fmt` method.
+///
+/// In most of the case, you will not directly call this macros, but use `bitfield`.
+///
+/// The syntax is `struct TheNameOfTheStruct` followed by the syntax of `bitfield_fields`.
+///
+/// The fields are printed on a single line as `name: value` pairs separated by commas, each value
+/// being formatted with its `fmt::Debug` implementation. The write-only fields are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// struct FooBar(u32);
+/// bitfield_bitrange!{struct FooBar(u32)}
+/// impl FooBar{
+///     bitfield_fields!{
+///        u32;
+///        field1, _: 7, 0;
+///        field2, _: 31, 24;
+///     }
+/// }
+///
+/// impl std::fmt::Display for FooBar {
+///     bitfield_display!{
+///        struct FooBar;
+///        field1, _: 7, 0;
+///        field2, _: 31, 24;
+///     }
+/// }
+///
+/// fn main() {
+///     let foobar = FooBar(0x11223344);
+///     assert_eq!(foobar.to_string(), "field1: 68, field2: 17");
+/// }
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! bitfield_display {
+    (struct $name:ident; $($rest:tt)*) => {
+        #[allow(unused_assignments, unused_mut)]
+        fn fmt(&self, f: &mut $crate::fmt::Formatter) -> $crate::fmt::Result {
+            let mut separator = "";
+            bitfield_fields!{@parse bitfield_display!(@fields f, self, separator,);
+                             SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+            Ok(())
+        }
+    };
+    (@fields $f:ident, $self:ident, $separator:ident, [$($record:tt)*]) => {
+        $(bitfield_display!{@field $f, $self, $separator, $record})*
+    };
+    (@field $f:ident, $self:ident, $separator:ident, {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter [_] $($_rest:tt)*
+    }) => {};
+    (@field $f:ident, $self:ident, $separator:ident, {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter [$getter:ident] setter $_setter:tt
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] default $_default:tt conv $_conv:tt
+        saturating $_saturating:tt fmt $fmt:tt $($_rest:tt)*
+    }) => {
+        $(#[$cfg])*
+        {
+            $f.write_fmt(__bitfield_format_args!("{}{}: [", $separator, __bitfield_stringify!($getter)))?;
+            for i in 0..$count {
+                if i > 0 {
+                    $f.write_str(", ")?;
+                }
+                bitfield_display!{@value $f, $self.$getter(i), $fmt}
+            }
+            $f.write_str("]")?;
+            bitfield_display!{@unit $f, $fmt}
+            $separator = ", ";
+        }
+    };
+    (@field $f:ident, $self:ident, $separator:ident, {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter [$getter:ident] setter $_setter:tt pos $_pos:tt
+        default $_default:tt conv $_conv:tt saturating $_saturating:tt fmt $fmt:tt $($_rest:tt)*
+    }) => {
+        $(#[$cfg])*
+        {
+            $f.write_fmt(__bitfield_format_args!("{}{}: ", $separator, __bitfield_stringify!($getter)))?;
+            bitfield_display!{@value $f, $self.$getter(), $fmt}
+            bitfield_display!{@unit $f, $fmt}
+            $separator = ", ";
+        }
+    };
+    // Writes a value in the radix of the field, or with its `fmt::Debug` implementation.
+    (@value $f:ident, $value:expr, []) => {
+        $f.write_fmt(__bitfield_format_args!("{:?}", $value))?;
+    };
+    (@value $f:ident, $value:expr, [radix [dec] $($rest:tt)*]) => {
+        $f.write_fmt(__bitfield_format_args!("{}", $value))?;
+    };
+    (@value $f:ident, $value:expr, [radix [hex] $($rest:tt)*]) => {
+        $f.write_fmt(__bitfield_format_args!("{:#x}", $value))?;
+    };
+    (@value $f:ident, $value:expr, [radix [bin] $($rest:tt)*]) => {
+        $f.write_fmt(__bitfield_format_args!("{:#b}", $value))?;
+    };
+    (@value $f:ident, $value:expr, [radix [oct] $($rest:tt)*]) => {
+        $f.write_fmt(__bitfield_format_args!("{:#o}", $value))?;
+    };
+    (@value $f:ident, $value:expr, [$_key:ident $_value:tt $($rest:tt)*]) => {
+        bitfield_display!{@value $f, $value, [$($rest)*]}
+    };
+    (@unit $f:ident, []) => {};
+    (@unit $f:ident, [unit [$unit:literal] $($rest:tt)*]) => {
+        $f.write_fmt(__bitfield_format_args!(" {}", $unit))?;
+    };
+    (@unit $f:ident, [$_key:ident $_value:tt $($rest:tt)*]) => {
+        bitfield_display!{@unit $f, [$($rest)*]}
+    };
+}
+
+/// Generates `compile_fail` doctests checking that misuses of a bitfield are rejected.
+///
+/// The syntax is `mod the_name_of_the_module;` followed by the syntax of `bitfield`. The struct is
+/// not generated: this macro only adds an empty module, documented with the doctests. They are run
+/// by `cargo test --doc` in the crate using the macro.
+///
+/// The following checks are generated:
+/// * the definition compiles and every setter exists
+/// * every setter rejects a value of another type
+/// * a field after the last bit of the storage is rejected, for the integer storages and the
+///   storages with `assert_size N;`, whose bounds are checked by `bitfield`
+///
+/// No test is generated for overlapping fields, which are accepted by `bitfield`. The `BitStorage`
+/// storage isn't supported.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// bitfield_compile_fail_tests! {
+///     mod header_compile_fail_tests;
+///     pub struct Header(u16);
+///     u8;
+///     pub kind, set_kind: 3, 0;
+///     pub flag, set_flag: 4;
+/// }
+/// # fn main() {}
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! bitfield_compile_fail_tests {
+    ($mod_vis:vis mod $mod_name:ident; $($definition:tt)*) => {
+        bitfield_compile_fail_tests!{@header ($mod_vis) $mod_name [$($definition)*] $($definition)*}
+    };
+    (@header $mod_vis:tt $mod_name:ident $definition:tt $(#[$attribute:meta])* $vis:vis struct $name:ident
+     $storage:tt; $(assert_size $bits:expr;)? no default BitRange; $($rest:tt)*) => {
+        bitfield_compile_fail_tests!{@storage $mod_vis $mod_name $definition $name $storage [$($bits)?]; $($rest)*}
+    };
+    (@header $mod_vis:tt $mod_name:ident $definition:tt $(#[$attribute:meta])* $vis:vis struct $name:ident
+     $storage:tt; assert_size $bits:expr; $($rest:tt)*) => {
+        bitfield_compile_fail_tests!{@storage $mod_vis $mod_name $definition $name $storage [$bits]; $($rest)*}
+    };
+    (@header $mod_vis:tt $mod_name:ident $definition:tt $(#[$attribute:meta])* $vis:vis struct $name:ident
+     $storage:tt; $($rest:tt)*) => {
+        bitfield_compile_fail_tests!{@storage $mod_vis $mod_name $definition $name $storage []; $($rest)*}
+    };
+    // The bounds of the fields are only checked for the integer storages and the sized ones, so the
+    // out-of-range test is generated only for them.
+    (@storage $mod_vis:tt $mod_name:ident $definition:tt $name:ident ([$t:ty]) $bits:tt; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_compile_fail_tests!(@tests $mod_vis $mod_name $definition $bits $name ($name<Vec<$t>>));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (@storage $mod_vis:tt $mod_name:ident $definition:tt $name:ident ($($order:ident)+ [$t:ty]) $bits:tt; $($rest:tt)*) => {
+        bitfield_compile_fail_tests!{@storage $mod_vis $mod_name $definition $name ([$t]) $bits; $($rest)*}
+    };
+    (@storage $mod_vis:tt $mod_name:ident $definition:tt $name:ident (Segments) $bits:tt; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_compile_fail_tests!(@tests $mod_vis $mod_name $definition $bits $name ($name<Vec<Vec<u8>>>));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (@storage $mod_vis:tt $mod_name:ident $definition:tt $name:ident (BitStorage) $bits:tt; $($rest:tt)*) => {
+        __bitfield_compile_error!{"`bitfield_compile_fail_tests` doesn't support the `BitStorage` storage"}
+    };
+    (@storage $mod_vis:tt $mod_name:ident $definition:tt $name:ident ($t:ty) $_bits:tt; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_compile_fail_tests!(@tests $mod_vis $mod_name $definition
+                                                             [(::std::mem::size_of::<$t>() * 8)] $name ($name)); $t;
+                         $($rest)*}
+    };
+    (@tests ($($mod_vis:tt)*) $mod_name:ident $definition:tt $bits:tt $name:ident $self_ty:tt [$({
+        attrs $_attrs:tt cfgs [$(#[cfg($($cfg:tt)*)])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter $_getter:tt setter [$setter:tt] pos $pos:tt $($_rest:tt)*
+    })*]) => {
+        #[doc = __bitfield_concat!("Compile-time checks of `", __bitfield_stringify!($name), "`.")]
+        #[doc = ""]
+        #[doc = "```"]
+        #[doc = "#[macro_use] extern crate bitfield;"]
+        #[doc = bitfield_compile_fail_tests!{@definition $definition}]
+        #[doc = "fn main() {"]
+        $(
+            #[cfg_attr(all($($($cfg)*),*), doc = bitfield_compile_fail_tests!{@setter_exists $self_ty $setter})]
+        )*
+        #[doc = "}"]
+        #[doc = "```"]
+        $(
+            #[cfg_attr(all($($($cfg)*),*), doc = bitfield_compile_fail_tests!{
+                @setter_wrong_type $definition $self_ty $setter $pos
+            })]
+        )*
+        #[doc = bitfield_compile_fail_tests!{@out_of_range $definition $bits}]
+        $($mod_vis)* mod $mod_name {}
+    };
+    (@definition [$($definition:tt)*]) => {
+        __bitfield_concat!("bitfield! { ", __bitfield_stringify!($($definition)*), " }")
+    };
+    (@out_of_range $definition:tt []) => {
+        ""
+    };
+    // The definition is re-emitted with a field just after the last bit of the storage.
+    (@out_of_range [$($definition:tt)*] [$bits:expr]) => {
+        __bitfield_concat!(
+            "```compile_fail\n",
+            "#[macro_use] extern crate bitfield;\n",
+            bitfield_compile_fail_tests!{@definition [$($definition)* u8, __out_of_range, _: ($bits), ($bits);]}, "\n",
+            "fn main() {}\n",
+            "```",
+        )
+    };
+    (@setter_exists $self_ty:tt _) => {
+        ""
+    };
+    (@setter_exists ($($self_ty:tt)*) $setter:ident) => {
+        __bitfield_concat!("    let _ = <", __bitfield_stringify!($($self_ty)*), ">::", __bitfield_stringify!($setter), ";")
+    };
+    (@setter_wrong_type $definition:tt $self_ty:tt _ $pos:tt) => {
+        ""
+    };
+    (@setter_wrong_type $definition:tt $self_ty:tt $setter:ident [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        bitfield_compile_fail_tests!{@wrong_type_test $definition $self_ty $setter "0, "}
+    };
+    (@setter_wrong_type $definition:tt $self_ty:tt $setter:ident $pos:tt) => {
+        bitfield_compile_fail_tests!{@wrong_type_test $definition $self_ty $setter ""}
+    };
+    (@wrong_type_test [$($definition:tt)*] ($($self_ty:tt)*) $setter:ident $index:literal) => {
+        __bitfield_concat!(
+            "```compile_fail\n",
+            "#[macro_use] extern crate bitfield;\n",
+            bitfield_compile_fail_tests!{@definition [$($definition)*]}, "\n",
+            "struct NotTheFieldType;\n",
+            "fn check(value: &mut ", __bitfield_stringify!($($self_ty)*), ") {\n",
+            "    value.", __bitfield_stringify!($setter), "(", $index, "NotTheFieldType);\n",
+            "}\n",
+            "fn main() {}\n",
+            "```",
+        )
+    };
+}
+
+/// Implements an exhaustive constructor function for a bitfield. Should only be called by `bitfield!` when using `impl new;`
+///
+/// It is called by `bitfield_fields!{@parse ...}` with the parsed fields, and has a parameter for
+/// every field with a setter, named after the field and taking the type of its setter, in the order
+/// of the declarations. The split fields, whose ranges are joined by `++`, come after the other
+/// fields. The parameter of an array field is an array of all its elements, set in order.
+///
+/// # Examples
+///
+/// ```rs
+/// bitfield_fields!{@parse bitfield_constructor!(@constructor new (impl Foo) (0)); u8;
+///                  foo1, set_foo1: 2,0; foo2, set_foo2: 7,2}
+/// ```
+/// Generates:
+/// ```rs
+/// impl Foo {
+///     pub fn new(foo1: u8, foo2: u8) -> Self {
+///         let mut value = Self(0);
+///         value.set_foo1(foo1);
+///         value.set_foo2(foo2);
+///         value
+///     }
+/// }
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! bitfield_constructor {
+    (@constructor $kind:ident $impl:tt $storage:tt [$($split:tt)*] [$($record:tt)*]) => {
+        bitfield_constructor!{@params $kind $impl $storage [] [$($record)*] [$($split)*] [$($record)*] [$($split)*]}
+    };
+    // Only the fields with a setter are parameters.
+    (@params $kind:ident $impl:tt $storage:tt [$($params:tt)*] [] [] $records:tt $splits:tt) => {
+        bitfield_constructor!{@$kind $impl $storage [$($params)*] $records $splits}
+    };
+    (@params $kind:ident $impl:tt $storage:tt $params:tt [{
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter [_] $($_rest:tt)*
+    } $($todo:tt)*] $todo_splits:tt $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage $params [$($todo)*] $todo_splits $records $splits}
+    };
+    (@params $kind:ident $impl:tt $storage:tt [$($params:tt)*] [{
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] getter $_getter:tt setter $_setter:tt
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] $($_rest:tt)*
+    } $($todo:tt)*] $todo_splits:tt $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage [$($params)* $(#[$cfg])* $name: [$from; $count],]
+                              [$($todo)*] $todo_splits $records $splits}
+    };
+    // The setters of the `valid_if` fields take an `Option`.
+    (@params $kind:ident $impl:tt $storage:tt [$($params:tt)*] [{
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] getter $_getter:tt setter $_setter:tt pos $_pos:tt default $_default:tt
+        conv $_conv:tt saturating $_saturating:tt fmt $_fmt:tt const_fn $_const_fn:tt consts $_consts:tt
+        access $_access:tt bytes $_bytes:tt order $_order:tt valid [$_bit:tt] $($_rest:tt)*
+    } $($todo:tt)*] $todo_splits:tt $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage [$($params)* $(#[$cfg])* $name: Option<$from>,]
+                              [$($todo)*] $todo_splits $records $splits}
+    };
+    (@params $kind:ident $impl:tt $storage:tt [$($params:tt)*] [{
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$name:ident] getter $_getter:tt setter $_setter:tt pos [$bit:expr] $($_rest:tt)*
+    } $($todo:tt)*] $todo_splits:tt $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage [$($params)* $(#[$cfg])* $name: bool,]
+                              [$($todo)*] $todo_splits $records $splits}
+    };
+    (@params $kind:ident $impl:tt $storage:tt [$($params:tt)*] [{
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$name:ident] $($_rest:tt)*
+    } $($todo:tt)*] $todo_splits:tt $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage [$($params)* $(#[$cfg])* $name: $from,]
+                              [$($todo)*] $todo_splits $records $splits}
+    };
+    (@params $kind:ident $impl:tt $storage:tt $params:tt [] [{alias $($_alias:tt)*} $($todo:tt)*] $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage $params [] [$($todo)*] $records $splits}
+    };
+    (@params $kind:ident $impl:tt $storage:tt $params:tt [] [{
+        attrs $_attrs:tt vis $_vis:tt ty $_t:tt from $_from:tt into $_into:tt conv $_conv:tt
+        getter $_getter:tt setter [_] $($_rest:tt)*
+    } $($todo:tt)*] $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage $params [] [$($todo)*] $records $splits}
+    };
+    (@params $kind:ident $impl:tt $storage:tt [$($params:tt)*] [] [{
+        attrs $_attrs:tt vis $_vis:tt ty $_t:tt from [$from:ty] into $_into:tt conv $_conv:tt
+        getter [_] setter [$setter:ident] $($_rest:tt)*
+    } $($todo:tt)*] $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage [$($params)* $setter: $from,] [] [$($todo)*] $records $splits}
+    };
+    (@params $kind:ident $impl:tt $storage:tt [$($params:tt)*] [] [{
+        attrs $_attrs:tt vis $_vis:tt ty $_t:tt from [$from:ty] into $_into:tt conv $_conv:tt
+        getter [$getter:ident] $($_rest:tt)*
+    } $($todo:tt)*] $records:tt $splits:tt) => {
+        bitfield_constructor!{@params $kind $impl $storage [$($params)* $getter: $from,] [] [$($todo)*] $records $splits}
+    };
+    (@set $value:ident {
+        attrs $_attrs:tt cfgs $_cfgs:tt vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt into $_into:tt
+        name $_name:tt getter $_getter:tt setter [_] $($_rest:tt)*
+    }) => {};
+    (@set $value:ident {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$name:ident] getter $_getter:tt setter [$setter:ident]
+        pos [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?] $($_rest:tt)*
+    }) => {
+        $(#[$cfg])*
+        for (index, element) in $crate::iter::IntoIterator::into_iter($name).enumerate() {
+            $value.$setter(index, element);
+        }
+    };
+    (@set $value:ident {
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$name:ident] getter $_getter:tt setter [$setter:ident] $($_rest:tt)*
+    }) => {
+        $(#[$cfg])*
+        $value.$setter($name);
+    };
+    (@set_split $value:ident {alias $($_alias:tt)*}) => {};
+    (@set_split $value:ident {
+        attrs $_attrs:tt vis $_vis:tt ty $_t:tt from $_from:tt into $_into:tt conv $_conv:tt
+        getter $_getter:tt setter [_] $($_rest:tt)*
+    }) => {};
+    (@set_split $value:ident {
+        attrs $_attrs:tt vis $_vis:tt ty $_t:tt from $_from:tt into $_into:tt conv $_conv:tt
+        getter [_] setter [$setter:ident] $($_rest:tt)*
+    }) => {
+        $value.$setter($setter);
+    };
+    (@set_split $value:ident {
+        attrs $_attrs:tt vis $_vis:tt ty $_t:tt from $_from:tt into $_into:tt conv $_conv:tt
+        getter [$getter:ident] setter [$setter:ident] $($_rest:tt)*
+    }) => {
+        $value.$setter($getter);
+    };
+    (@new ($($impl:tt)*) ($storage:expr) [$($params:tt)*] [$($record:tt)*] [$($split:tt)*]) => {
+        $($impl)* {
+            #[allow(clippy::too_many_arguments)]
+            pub fn new($($params)*) -> Self {
+                let mut value = Self($storage);
+                $(bitfield_constructor!{@set value $record})*
+                $(bitfield_constructor!{@set_split value $split})*
+                value
+            }
+        }
+    };
+    (@written ($($impl:tt)*) ($storage:expr) [$($params:tt)*] [$($record:tt)*] [$($split:tt)*]) => {
+        $($impl)* {
+            /// Builds the value from the value of every field, like `new`, and writes it to `uninit`
+            /// at once, returning a reference to the initialized value.
+            ///
+            /// The value is built in a local variable, from a zeroed storage, before `uninit` is
+            /// written, so the memory of `uninit` is written only once and is never zeroed
+            /// beforehand. The local storage is still zeroed and then copied to `uninit`: an
+            /// integer storage stays in registers, but an array is built on the stack and copied
+            /// unless the compiler builds it in place.
+            #[allow(dead_code, clippy::too_many_arguments)]
+            pub fn from_uninit_written(uninit: &mut $crate::mem::MaybeUninit<Self>, $($params)*) -> &mut Self {
+                let mut value = Self($storage);
+                $(bitfield_constructor!{@set value $record})*
+                $(bitfield_constructor!{@set_split value $split})*
+                uninit.write(value)
+            }
+        }
+    };
+}
+
+/// Implements `BitRange` and `BitRangeMut` for a tuple struct (or "newtype").
+///
+/// This macro will generate an implementation of the `BitRange` trait for an existing single
+/// element tuple struct.
+///
+/// The syntax is more or less the same as declaring a "newtype", **without** the attributes,
+/// documentation comments and pub keyword.
+///
+/// The difference with a normal "newtype" is the type in parentheses. If the type is `[t]` (where
+/// `t` is any of the unsigned integer type), the "newtype" will be generic and implement
+/// `BitRange` for `T: AsRef<[t]>` and `BitRangeMut` for `T: AsMut<[t]>` (for example a slice, an array or a `Vec`). You can
+/// also use `MSB0 [t]`. The difference will be the positions of the bit. You can use the
+/// `bits_positions` example to see where each bits is. If the type is neither of this two, the
+/// "newtype" will wrap a value of the specified type and implements `BitRange` the same ways as
+/// the wrapped type. The type can also be `Segments`, for a generic "newtype" wrapping a
+/// `Segments<S>`, whose buffers are accessed as if they were concatenated, or `BitStorage`, for a
+/// generic "newtype" wrapping a type implementing `BitStorage`. A "newtype" with a
+/// lifetime parameter can wrap a reference to a slice, `&'a [t]` or `&'a mut [t]`, optionally
+/// preceded by `MSB0`. `BitRangeMut` is only implemented for the mutable reference.
+///
+/// The elements of the slices are native integers. With `LE [t]` or `BE [t]`, optionally preceded
+/// by `MSB0`, they are stored in little-endian or big-endian byte order instead. The bits are
+/// numbered as for `[t]` and `MSB0 [t]`, from the values of the elements converted from that byte
+/// order, so they are at the same place in memory on every target.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// struct BitField1(u32);
+/// bitfield_bitrange!{struct BitField1(u32)}
+///
+/// struct BitField2<T>(T);
+/// bitfield_bitrange!{struct BitField2([u8])}
+///
+/// struct BitField3<T>(T);
+/// bitfield_bitrange!{struct BitField3(MSB0 [u8])}
+///
+/// struct BitField4<S>(bitfield::Segments<S>);
+/// bitfield_bitrange!{struct BitField4(Segments)}
+///
+/// struct BitField5<'a>(&'a mut [u8]);
+/// bitfield_bitrange!{struct BitField5<'a>(MSB0 &'a mut [u8])}
+///
+/// struct BitField6<S>(S);
+/// bitfield_bitrange!{struct BitField6(BitStorage)}
+/// ```
+///
+#[macro_export(local_inner_macros)]
+macro_rules! bitfield_bitrange {
+    // The elements of the `LE` and `BE` storages are converted from and to the given byte order
+    // when they are read and written.
+    (@load $slice_ty:ty, $element:expr) => {
+        $element
+    };
+    (@load $slice_ty:ty, $element:expr, LE) => {
+        <$slice_ty>::from_le($element)
+    };
+    (@load $slice_ty:ty, $element:expr, BE) => {
+        <$slice_ty>::from_be($element)
+    };
+    (@store $slice_ty:ty, $element:expr) => {
+        $element
+    };
+    (@store $slice_ty:ty, $element:expr, LE) => {
+        <$slice_ty>::to_le($element)
+    };
+    (@store $slice_ty:ty, $element:expr, BE) => {
+        <$slice_ty>::to_be($element)
+    };
+    (@impl_bitrange_slice $name:ident, $slice_ty:ty, $bitrange_ty:ty $(, $endian:ident)?) => {
+        impl<T: AsRef<[$slice_ty]>> $crate::BitRange<$bitrange_ty>
+            for $name<T> {
+                fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
+                    let bit_len = $crate::size_of::<$slice_ty>()*8;
+                    let value_bit_len = $crate::size_of::<$bitrange_ty>()*8;
+                    let mut value = 0;
+                    for i in (lsb..=msb).rev() {
+                        let element = bitfield_bitrange!(@load $slice_ty, self.0.as_ref()[i/bit_len] $(, $endian)?);
+                        value <<= 1;
+                        value |= ((element >> (i%bit_len)) & 1) as $bitrange_ty;
+                    }
+                    value << (value_bit_len - (msb - lsb + 1)) >> (value_bit_len - (msb - lsb + 1))
+                }
+        }
+        impl<T: AsMut<[$slice_ty]>> $crate::BitRangeMut<$bitrange_ty>
+            for $name<T> {
+
+                fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
+                    let bit_len = $crate::size_of::<$slice_ty>()*8;
+                    let mut value = value;
+                    for i in lsb..=msb {
+                        let storage = &mut self.0.as_mut()[i/bit_len];
+                        let mut element = bitfield_bitrange!(@load $slice_ty, *storage $(, $endian)?);
+                        element &= !(1 << (i%bit_len));
+                        element |= (value & 1) as $slice_ty << (i%bit_len);
+                        *storage = bitfield_bitrange!(@store $slice_ty, element $(, $endian)?);
+                        value >>= 1;
+                    }
+                }
+            }
+    };
+    (@impl_bitrange_slice_msb0 $name:ident, $slice_ty:ty, $bitrange_ty:ty $(, $endian:ident)?) => {
+        impl<T: AsRef<[$slice_ty]>> $crate::BitRange<$bitrange_ty>
+            for $name<T> {
+            fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
+                let bit_len = $crate::size_of::<$slice_ty>()*8;
+                let value_bit_len = $crate::size_of::<$bitrange_ty>()*8;
+                let mut value = 0;
+                for i in lsb..=msb {
+                    let element = bitfield_bitrange!(@load $slice_ty, self.0.as_ref()[i/bit_len] $(, $endian)?);
+                    value <<= 1;
+                    value |= ((element >> (bit_len - i%bit_len - 1)) & 1) as $bitrange_ty;
+                }
+                value << (value_bit_len - (msb - lsb + 1)) >> (value_bit_len - (msb - lsb + 1))
+            }
+        }
+        impl<T: AsMut<[$slice_ty]>> $crate::BitRangeMut<$bitrange_ty>
+            for $name<T> {
+            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
+                let bit_len = $crate::size_of::<$slice_ty>()*8;
+                let mut value = value;
+                for i in (lsb..=msb).rev() {
+                    let storage = &mut self.0.as_mut()[i/bit_len];
+                    let mut element = bitfield_bitrange!(@load $slice_ty, *storage $(, $endian)?);
+                    element &= !(1 << (bit_len - i%bit_len - 1));
+                    element |= (value & 1) as $slice_ty << (bit_len - i%bit_len - 1);
+                    *storage = bitfield_bitrange!(@store $slice_ty, element $(, $endian)?);
+                    value >>= 1;
+                }
+            }
+        }
+    };
+    (@impl_bitrange_slices $impl:ident $name:ident, $t:ty, $msb0:expr $(, $endian:ident)?) => {
+        impl<T> $crate::BitOrder for $name<T> {
+            const MSB0: bool = $msb0;
+        }
+        bitfield_bitrange!(@$impl $name, $t, u8 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, u16 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, u32 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, u64 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, u128 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, i8 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, i16 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, i32 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, i64 $(, $endian)?);
+        bitfield_bitrange!(@$impl $name, $t, i128 $(, $endian)?);
+    };
+    (struct $name:ident(LE [$t:ty])) => {
+        bitfield_bitrange!(@impl_bitrange_slices impl_bitrange_slice $name, $t, false, LE);
+    };
+    (struct $name:ident(BE [$t:ty])) => {
+        bitfield_bitrange!(@impl_bitrange_slices impl_bitrange_slice $name, $t, false, BE);
+    };
+    (struct $name:ident(MSB0 LE [$t:ty])) => {
+        bitfield_bitrange!(@impl_bitrange_slices impl_bitrange_slice_msb0 $name, $t, true, LE);
+    };
+    (struct $name:ident(MSB0 BE [$t:ty])) => {
+        bitfield_bitrange!(@impl_bitrange_slices impl_bitrange_slice_msb0 $name, $t, true, BE);
+    };
+    (struct $name:ident([$t:ty])) => {
+        bitfield_bitrange!(@impl_bitrange_slices impl_bitrange_slice $name, $t, false);
+    };
+    (struct $name:ident(MSB0 [$t:ty])) => {
+        bitfield_bitrange!(@impl_bitrange_slices impl_bitrange_slice_msb0 $name, $t, true);
+    };
+    // The references are wrapped in a slice-backed struct, whose implementations are reused.
+    (@reference $name:ident<$lt:lifetime>, ($($storage:tt)*), $t:ty, $mutability:tt) => {
+        const _: () = {
+            struct __Storage<T>(T);
+            bitfield_bitrange!(struct __Storage($($storage)*));
+
+            impl<$lt> $crate::BitOrder for $name<$lt> {
+                const MSB0: bool = <__Storage<()> as $crate::BitOrder>::MSB0;
+            }
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, u8, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, u16, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, u32, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, u64, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, u128, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, i8, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, i16, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, i32, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, i64, $mutability);
+            bitfield_bitrange!(@impl_bitrange_reference $name<$lt>, $t, i128, $mutability);
+        };
+    };
+    (@impl_bitrange_reference $name:ident<$lt:lifetime>, $t:ty, $bitrange_ty:ty, [$($mut:tt)?]) => {
+        impl<$lt> $crate::BitRange<$bitrange_ty> for $name<$lt> {
+            fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
+                $crate::BitRange::<$bitrange_ty>::bit_range(&__Storage(&*self.0), msb, lsb)
+            }
+        }
+        bitfield_bitrange!(@impl_bitrange_reference_mut [$($mut)?] $name<$lt>, $t, $bitrange_ty);
+    };
+    (@impl_bitrange_reference_mut [] $name:ident<$lt:lifetime>, $t:ty, $bitrange_ty:ty) => {};
+    (@impl_bitrange_reference_mut [mut] $name:ident<$lt:lifetime>, $t:ty, $bitrange_ty:ty) => {
+        impl<$lt> $crate::BitRangeMut<$bitrange_ty> for $name<$lt> {
+            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
+                $crate::BitRangeMut::<$bitrange_ty>::set_bit_range(&mut __Storage(&mut *self.0), msb, lsb, value)
+            }
+        }
+    };
+    (struct $name:ident<$lt:lifetime>($($order:ident)* &$_ref_lt:lifetime mut [$t:ty])) => {
+        bitfield_bitrange!(@reference $name<$lt>, ($($order)* [$t]), $t, [mut]);
+    };
+    (struct $name:ident<$lt:lifetime>($($order:ident)* &$_ref_lt:lifetime [$t:ty])) => {
+        bitfield_bitrange!(@reference $name<$lt>, ($($order)* [$t]), $t, []);
+    };
+    (@impl_bitrange_storage $name:ident, $bitrange_ty:ty) => {
+        impl<S: $crate::BitStorage> $crate::BitRange<$bitrange_ty> for $name<S> {
+            fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
+                let value_bit_len = $crate::size_of::<$bitrange_ty>()*8;
+                let value = $crate::read_storage_bits(&self.0, msb, lsb) as $bitrange_ty;
+                value << (value_bit_len - (msb - lsb + 1)) >> (value_bit_len - (msb - lsb + 1))
+            }
+        }
+        impl<S: $crate::BitStorageMut> $crate::BitRangeMut<$bitrange_ty> for $name<S> {
+            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
+                $crate::write_storage_bits(&mut self.0, msb, lsb, value as u128);
+            }
+        }
+    };
+    (struct $name:ident(BitStorage)) => {
+        impl<S> $crate::BitOrder for $name<S> {
+            const MSB0: bool = false;
+        }
+        bitfield_bitrange!(@impl_bitrange_storage $name, u8);
+        bitfield_bitrange!(@impl_bitrange_storage $name, u16);
+        bitfield_bitrange!(@impl_bitrange_storage $name, u32);
+        bitfield_bitrange!(@impl_bitrange_storage $name, u64);
+        bitfield_bitrange!(@impl_bitrange_storage $name, u128);
+        bitfield_bitrange!(@impl_bitrange_storage $name, i8);
+        bitfield_bitrange!(@impl_bitrange_storage $name, i16);
+        bitfield_bitrange!(@impl_bitrange_storage $name, i32);
+        bitfield_bitrange!(@impl_bitrange_storage $name, i64);
+        bitfield_bitrange!(@impl_bitrange_storage $name, i128);
+    };
+    (struct $name:ident(Segments)) => {
+        impl<S, T> $crate::BitRange<T> for $name<S> where $crate::Segments<S>: $crate::BitRange<T> {
+            fn bit_range(&self, msb: usize, lsb: usize) -> T {
+                self.0.bit_range(msb, lsb)
+            }
+        }
+        impl<S, T> $crate::BitRangeMut<T> for $name<S> where $crate::Segments<S>: $crate::BitRangeMut<T> {
+            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: T) {
+                self.0.set_bit_range(msb, lsb, value);
+            }
+        }
+        impl<S> $crate::BitOrder for $name<S> {
+            const MSB0: bool = false;
+        }
+    };
+    (struct $name:ident($t:ty)) => {
+        impl<T> $crate::BitRange<T> for $name where $t: $crate::BitRange<T> {
+            fn bit_range(&self, msb: usize, lsb: usize) -> T {
+                self.0.bit_range(msb, lsb)
+            }
+        }
+        impl<T> $crate::BitRangeMut<T> for $name where $t: $crate::BitRangeMut<T> {
+            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: T) {
+                self.0.set_bit_range(msb, lsb, value);
+            }
+        }
+    };
+}
+
+/// Combines `bitfield_bitrange` and `bitfield_fields`.
+///
 /// The syntax of this macro is the syntax of a tuple struct, including attributes and
 /// documentation comments, followed by a semicolon, some optional elements, and finally the fields
 /// as described in the `bitfield_fields` documentation.
 ///
-/// The first optional element is `no default BitRange;`. With that, no implementation of
-/// `BitRange` will be generated.
+/// The struct can be followed by `storage OtherName([T; N]);`, with optional attributes before
+/// `OtherName`. This declares a second struct, backed by a slice, with the same optional elements
+/// and fields, and lossless conversions between `OtherName<[T; N]>` and the first struct, whose
+/// storage must be an integer of the same size as `[T; N]`.
+///
+/// The storage can also be `Segments`. The struct then wraps a `Segments<S>`, where `S` is a list of
+/// byte buffers like `&[&[u8]]` or `&[IoSlice]`, read as if they were concatenated, and numbered
+/// like the `[u8]` storage. The `impl` lines are not supported with this storage.
+///
+/// The storage can also be `BitStorage`. The struct then wraps any type `S` implementing
+/// `BitStorage`, like a memory-mapped register, with getters when `S: BitStorage` and setters when
+/// `S: BitStorageMut`. The `impl` lines are not supported with this storage.
+///
+/// A struct with an integer storage can have type parameters, with at most one bound each, and a
+/// where clause. The storage is then followed by a second field using the parameters, usually a
+/// `PhantomData`: `struct Register<M: Mode>(u32, PhantomData<M>) where M: Copy;`. The `impl` lines
+/// are not supported with generic parameters.
+///
+/// A struct with a lifetime parameter can borrow its storage, a reference to a slice optionally
+/// preceded by `MSB0`: `struct Header<'a>(&'a mut [u8]);`. The setters are only generated for a
+/// mutable reference, and the `impl` lines are not supported with a borrowed storage.
+///
+/// The elements of a slice storage are native integers. With `LE [T]` or `BE [T]`, optionally
+/// preceded by `MSB0`, they are instead stored in little-endian or big-endian byte order, so that a
+/// definition reads the same bytes on every target. The bits are numbered from the values of the
+/// elements, as for `[T]`. The `impl` lines using these values, `Shl`, `Shr`, `PartialOrd`, `Ord`,
+/// `LowerHex`, `UpperHex`, `Binary` and `Octal`, are not supported with these storages.
+///
+/// The struct can be directly followed by `assert_size N;`, failing the compilation if an integer
+/// storage isn't `N` bits. For the slice storages, `N` must be a whole number of elements, and the
+/// fields must fit in the first `N` bits.
+///
+/// The first optional element is `no default BitRange;`. With that, no implementation of
+/// `BitRange` will be generated.
+///
+/// The second optional element is a set of lines of the form `impl <Trait>;`. The following traits are supported:
+/// * `Debug`; This will generate an implementation of `fmt::Debug` with the `bitfield_debug` macro.
+/// * `Display`; This will generate an implementation of `fmt::Display` with the `bitfield_display` macro.
+/// * `LowerHex`, `UpperHex`, `Binary`, `Octal`; These will generate implementations of the relevant `fmt` traits that
+///   forward to the storage. For slice-backed structs, the elements are written in storage order, each padded with
+///   zeros to the width of the element type, and only the `#` flag is supported.
+/// * `BitAnd`, `BitOr`, `BitXor`; These will generate implementations of the relevant `ops::Bit___` and `ops::Bit___Assign` traits.
+/// * `Not`; This will generate an implementation of `ops::Not`, complementing every bit of the storage.
+/// * `Shl`, `Shr`; These will generate implementations of `ops::Sh_<usize>` and `ops::Sh_Assign<usize>` shifting the
+///   storage, shifting in zeros. Slice-backed storages (of unsigned elements) are shifted as a little-endian integer,
+///   which matches the bit numbering of the non-MSB0 structs. The MSB0 storages are shifted as a big-endian integer,
+///   the element 0 holding the most significant bits, so `<<` moves the bits towards the bit 0.
+/// * `Clone`, `Copy`; These will generate implementations copying the storage, only requiring the storage type of
+///   slice-backed structs to implement the trait.
+/// * `PartialEq`, `Eq`; These will generate implementations comparing the raw storage, without adding bounds on the
+///   storage type of slice-backed structs other than `AsRef<[T]>`.
+/// * `PartialOrd`, `Ord`; These will generate implementations comparing the raw storage. Slice-backed structs are
+///   compared lexicographically from their most significant element, which is the last one, or the first one for
+///   MSB0 structs.
+/// * `new`; This will generate a constructor that calls all of the bitfield's setter methods with an argument of the appropriate type,
+///   an array of all the elements for the array fields. The parameters are named after the fields, in the order of the
+///   declarations, with the fields split by `++` last
+/// * `new{constructor_name(setter_name: setter_type, ...)}`; This will generate a constructor that calls a given subset of the bitfield's setter methods
+/// * `From`; This will generate conversions from and to the storage type (`[T; N]` for slice-backed structs)
+/// * `TryFrom`; For slice-backed structs, this will generate `TryFrom<&[T]>` and `TryFrom<&mut [T]>` implementations
+///   checking that the slice is long enough to hold all the fields
+/// * `Hash`; This will generate an implementation of `hash::Hash` hashing the raw storage
+/// * `RawBits`; For slice-backed structs, this will generate `raw_bits` and `set_raw_bits` methods accessing the bits
+///   of the fields as an `u128`
+/// * `TrySet`; This will generate `try_set_*` versions of the setters of the multi-bit fields, returning an error for
+///   the values that don't fit in the field
+/// * `Pin`; This will generate `Pin<&mut Self>` versions of the setters, suffixed with `_pinned`
+/// * `With`; This will generate `with_*` methods taking and returning `self`, calling the setters
+/// * `Builder`; This will generate a `<Name>Builder` struct, whose `build` method checks that the fields without a
+///   default value are set
+/// * `Update`; This will generate `update_*` methods setting the fields to the value returned by a closure called with
+///   their current value
+/// * `Masks`; This will generate a `*_MASK` constant for every field without a `mask`
+/// * `Flags`; This will generate `is_*`, `raise_*`, `clear_*` and `toggle_*` methods for the single-bit fields
+/// * `Modify`; This will generate a `modify` method calling a closure with a `<Name>Writer`, dereferencing to a copy
+///   of the struct, and writing the copy back with a single assignment to the storage
+/// * `Serde`; With the `serde` feature, this will generate implementations of `Serialize` and `Deserialize` using the fields
+/// * `SerdeRaw`; With the `serde` feature, this will generate implementations of `Serialize` and `Deserialize` using the storage
+/// * `JsonSchema`; With the `schemars` feature, this will generate an implementation of `JsonSchema` matching `Serde`
+/// * `Pod`; With the `bytemuck` feature, this will make the struct `#[repr(transparent)]` and generate implementations of
+///   `Pod` and `Zeroable`
+/// * `CastSlice`; For integer storages, this will make the struct `#[repr(transparent)]` and generate the `cast_slice`
+///   and `cast_slice_mut` functions
+/// * `FromBytes`, `IntoBytes`, `Unaligned`, `KnownLayout`, `Immutable`; With the `zerocopy` feature, this will make the
+///   struct `#[repr(transparent)]` and derive the relevant `zerocopy` trait
+/// * `Distribution`, `Distribution{fields}`; With the `rand` feature, this will generate an implementation of
+///   `Distribution` for `Standard`, randomizing the whole storage, or only the bits of the fields
+/// * `Arbitrary`; With the `arbitrary` feature, this will generate an implementation of `Arbitrary` using the storage
+/// * `StorageCell`; This will generate `with_storage`/`with_storage_mut` methods for storages with interior mutability
+/// * `Decode`; This will generate a `decode_from` function reading the fields from a `BitReader`
+/// * `Encode`; This will generate an `encode_into` method writing the fields to a `BitWriter`
+/// * `FromUninit`; This will generate a `from_uninit_written` function writing the value built from the fields to a `MaybeUninit` at once
+/// * `LayoutHash`; This will generate a `LAYOUT_HASH` constant identifying the layout of the fields
+/// * `Default`; This will generate an implementation of `Default` using the default values of the fields
+/// * `Values{field: Enum{Name = value, ...}, ...}`; This will generate an enum of the named values of each given field
+///   and a `field_value` method returning it
+/// * `FromStr`; This will generate an implementation of `FromStr` parsing comma separated `field=value` assignments
+/// * `FieldsModule{module_name}`; This will generate a `module_name` module with a module of constants per field
+/// * `Cached{cached_name}`; This will generate a `cached_name` wrapper caching the values returned by the getters until
+///   a setter is called
+/// * `ReadOnly{view_name}`; This will generate a `view_name` struct with the getters of the fields but not the setters,
+///   and a conversion from the struct
+/// * `ReadOnlyStorage`; This will check that no field has a setter
+/// * `CRepr{c_type}`; This will generate `from_c_repr`/`to_c_repr` methods converting from and to a struct with a
+///   field per field, like a `#[repr(C, packed)]` struct mirroring C bit-fields
+/// * `Fields`; This will generate a field enum, a `FIELDS` table and methods to access the fields dynamically
+///
+/// The difference with calling those macros separately is that `bitfield_fields` is called
+/// from an appropriate `impl` block. If you use the non-slice form of `bitfield_bitrange`, the
+/// default type for `bitfield_fields` will be set to the wrapped fields.
+///
+/// For the array fields, `bitfield` also generates a setter suffixed by `_all`, writing every
+/// element from an array of `count` values: `set_field_all([1, 2, 3])`.
+///
+/// When `bitfield` implements `BitRange`, it checks at compile time that the `msb` of every field
+/// is not lower than its `lsb`:
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// bitfield!{
+///   pub struct BitField1(u32);
+///   u8;
+///   field1, set_field1: 3, 7;
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// bitfield!{
+///   pub struct BitField1([u8]);
+///   u8;
+///   field1, set_field1: 3, 7;
+/// }
+/// ```
+///
+/// For an integer storage, it also checks that the bits of every field are in the storage:
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// bitfield!{
+///   pub struct BitField1(u32);
+///   u8;
+///   field1, set_field1: 40, 36;
+/// }
+/// ```
+///
+/// The slice storages, like `[u8; N]`, are generic over the type of the buffer, so their size is
+/// only checked with `assert_size N;`.
+///
+/// The bounds of the ranges must follow the bit order of the storage, `msb..=lsb` unless the
+/// struct is MSB0, where they are `lsb..=msb`:
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// bitfield!{
+///   pub struct BitField1(u32);
+///   u8;
+///   field1, set_field1: 4..=7;
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// bitfield!{
+///   pub struct BitField1(MSB0 [u8]);
+///   u8;
+///   field1, set_field1: 7..=4;
+/// }
+/// ```
+///
+/// See the documentation of these macros for more information on their respective syntax.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {}
+/// bitfield!{
+///   pub struct BitField1(u16);
+///   impl Debug;
+///   // The fields default to u16
+///   field1, set_field1: 10, 0;
+///   pub field2, _ : 12, 3;
+/// }
+/// ```
+///
+/// or with a custom `BitRange` and `BitRangeMut` implementation :
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// # use bitfield::{BitRange, BitRangeMut};
+/// # fn main() {}
+/// bitfield!{
+///   pub struct BitField1(u16);
+///   no default BitRange;
+///   impl Debug;
+///   impl BitAnd;
+///   u8;
+///   field1, set_field1: 10, 0;
+///   pub field2, _ : 12, 3;
+/// }
+/// impl BitRange<u8> for BitField1 {
+///     fn bit_range(&self, msb: usize, lsb: usize) -> u8 {
+///         let width = msb - lsb + 1;
+///         let mask = (1 << width) - 1;
+///         ((self.0 >> lsb) & mask) as u8
+///     }
+/// }
+/// impl BitRangeMut<u8> for BitField1 {
+///     fn set_bit_range(&mut self, msb: usize, lsb: usize, value: u8) {
+///         self.0 = (value as u16) << lsb;
+///     }
+/// }
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! bitfield {
+    // `assert_size` checks the size of an integer storage. The slices have no size, so the fields
+    // are checked to fit in the given number of bits, which must be a whole number of elements.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($storage:ident); assert_size $bits:expr; $($rest:tt)*) => {
+        bitfield!{@assert_storage_size $name, $storage, $bits; $($rest)*}
+        bitfield!{$(#[$attribute])* $vis struct $name($storage); $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($order:ident)* [$t:ty]); assert_size $bits:expr;
+     $($rest:tt)*) => {
+        bitfield!{@assert_slice_size $name, $t, $bits; $($rest)*}
+        bitfield!{$(#[$attribute])* $vis struct $name($($order)* [$t]); $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($t:ty); assert_size $bits:expr; $($rest:tt)*) => {
+        bitfield!{@assert_size $name, $t, $bits}
+        bitfield!{$(#[$attribute])* $vis struct $name($t); $($rest)*}
+    };
+    (@assert_storage_size $name:ident, Segments, $bits:expr; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds ($bits));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (@assert_storage_size $name:ident, BitStorage, $bits:expr; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds ($bits));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (@assert_storage_size $name:ident, $t:ty, $bits:expr; $($rest:tt)*) => {
+        bitfield!{@assert_size $name, $t, $bits}
+    };
+    (@assert_size $name:ident, $t:ty, $bits:expr) => {
+        const _: () = $crate::__bitfield_assert!(
+            $crate::size_of::<$t>() * 8 == $bits,
+            __bitfield_concat!("the storage of `", __bitfield_stringify!($name), "` isn't ",
+                               __bitfield_stringify!($bits), " bits")
+        );
+    };
+    (@assert_slice_size $name:ident, $t:ty, $bits:expr; $($rest:tt)*) => {
+        const _: () = $crate::__bitfield_assert!(
+            ($bits) % ($crate::size_of::<$t>() * 8) == 0,
+            __bitfield_concat!("the size of `", __bitfield_stringify!($name), "` isn't a whole number of elements")
+        );
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds ($bits));
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+
+    // A second storage declares a slice-backed twin of the struct, converted bit by bit.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($t:ty);
+     storage $(#[$storage_attribute:meta])* $storage:ident([$storage_t:ty; $count:expr]); $($rest:tt)*) => {
+        bitfield!{$(#[$attribute])* $vis struct $name($t); $($rest)*}
+        bitfield!{$(#[$storage_attribute])* $vis struct $storage([$storage_t]); $($rest)*}
+
+        const _: () = $crate::__bitfield_assert!(
+            $crate::mem::size_of::<$t>() == $crate::mem::size_of::<[$storage_t; $count]>()
+        );
+
+        impl From<$name> for $storage<[$storage_t; $count]> {
+            fn from(value: $name) -> Self {
+                let mut storage = $storage([<$storage_t as Default>::default(); $count]);
+                for bit in 0..$crate::mem::size_of::<$t>() * 8 {
+                    $crate::BitMut::set_bit(&mut storage, bit, $crate::Bit::bit(&value, bit));
+                }
+                storage
+            }
+        }
+
+        impl From<$storage<[$storage_t; $count]>> for $name {
+            fn from(storage: $storage<[$storage_t; $count]>) -> Self {
+                let mut value = $name(<$t as Default>::default());
+                for bit in 0..$crate::mem::size_of::<$t>() * 8 {
+                    $crate::BitMut::set_bit(&mut value, bit, $crate::Bit::bit(&storage, bit));
+                }
+                value
+            }
+        }
+    };
+    // Force `impl <Trait>` to always be after `no default BitRange` it the two are present.
+    // This simplify the rest of the macro.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); $(impl $trait:ident$({$($trait_arg:tt)*})?;)+ no default BitRange; $($rest:tt)*) => {
+         bitfield!{$(#[$attribute])* $vis struct $name($($type)*); no default BitRange; $(impl $trait$({$($trait_arg)*})?;)* $($rest)*}
+     };
+
+    // The `Segments` storage wraps a `$crate::Segments`. The `impl` lines expect a single buffer, so
+    // they are not supported.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident(Segments); no default BitRange; impl $trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
+        __bitfield_compile_error!{"the `impl` lines are not supported with the `Segments` storage"}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident(Segments); no default BitRange; $($rest:tt)*) => {
+        $(#[$attribute])*
+        $vis struct $name<S>(pub $crate::Segments<S>);
+
+        bitfield_fields!{@parse bitfield_fields!(@newtypes);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+
+        impl<S: $crate::ops::Deref<Target = [B]>, B: $crate::ops::Deref<Target = [u8]>> $name<S> {
+           bitfield_fields!{only getter; $($rest)*}
+           bitfield_fields!{only consts; $($rest)*}
+        }
+        impl<S: $crate::ops::DerefMut<Target = [B]>, B: $crate::ops::DerefMut<Target = [u8]>> $name<S> {
+           bitfield_fields!{only setter; $($rest)*}
+           bitfield_fields!{only setter_all; $($rest)*}
+        }
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident(Segments); $($rest:tt)*) => {
+        bitfield_bitrange!(struct $name(Segments));
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds (usize::MAX) LSB0);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        bitfield!{$(#[$attribute])* $vis struct $name(Segments); no default BitRange; $($rest)*}
+    };
+
+    // The `BitStorage` storage wraps any type implementing `BitStorage`, read and written a word at a
+    // time. The `impl` lines expect a storage that can be copied or compared, so they are not
+    // supported.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident(BitStorage); no default BitRange; impl $trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
+        __bitfield_compile_error!{"the `impl` lines are not supported with the `BitStorage` storage"}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident(BitStorage); no default BitRange; $($rest:tt)*) => {
+        $(#[$attribute])*
+        $vis struct $name<S>(pub S);
+
+        bitfield_fields!{@parse bitfield_fields!(@newtypes);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+
+        impl<S: $crate::BitStorage> $name<S> {
+           bitfield_fields!{only getter; $($rest)*}
+           bitfield_fields!{only consts; $($rest)*}
+        }
+        impl<S: $crate::BitStorageMut> $name<S> {
+           bitfield_fields!{only setter; $($rest)*}
+           bitfield_fields!{only setter_all; $($rest)*}
+        }
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident(BitStorage); $($rest:tt)*) => {
+        bitfield_bitrange!(struct $name(BitStorage));
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds (usize::MAX) LSB0);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        bitfield!{$(#[$attribute])* $vis struct $name(BitStorage); no default BitRange; $($rest)*}
+    };
+
+    // `Pod`, `CastSlice` and the zerocopy traits require a transparent struct. The `@repr_transparent;` marker
+    // is kept until all the `impl` lines have been handled, so that `repr` is only added once.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; $(@repr_transparent;)?
+     impl Pod; $($rest:tt)*) => {
+        bitfield_impl!{Pod for $vis struct $name($($type)*); $($rest)*}
+
+        bitfield!{$(#[$attribute])* $vis struct $name($($type)*); no default BitRange; @repr_transparent; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; $(@repr_transparent;)?
+     impl CastSlice; $($rest:tt)*) => {
+        bitfield_impl!{CastSlice for $vis struct $name($($type)*); $($rest)*}
+
+        bitfield!{$(#[$attribute])* $vis struct $name($($type)*); no default BitRange; @repr_transparent; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; $(@repr_transparent;)?
+     impl FromBytes; $($rest:tt)*) => {
+        __bitfield_zerocopy!{FromBytes}
+
+        bitfield!{$(#[$attribute])* #[derive($crate::zerocopy::FromBytes)] #[zerocopy(crate = "::bitfield::zerocopy")]
+                  $vis struct $name($($type)*); no default BitRange; @repr_transparent; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; $(@repr_transparent;)?
+     impl IntoBytes; $($rest:tt)*) => {
+        __bitfield_zerocopy!{IntoBytes}
+
+        bitfield!{$(#[$attribute])* #[derive($crate::zerocopy::IntoBytes)] #[zerocopy(crate = "::bitfield::zerocopy")]
+                  $vis struct $name($($type)*); no default BitRange; @repr_transparent; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; $(@repr_transparent;)?
+     impl Unaligned; $($rest:tt)*) => {
+        __bitfield_zerocopy!{Unaligned}
+
+        bitfield!{$(#[$attribute])* #[derive($crate::zerocopy::Unaligned)] #[zerocopy(crate = "::bitfield::zerocopy")]
+                  $vis struct $name($($type)*); no default BitRange; @repr_transparent; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; $(@repr_transparent;)?
+     impl KnownLayout; $($rest:tt)*) => {
+        __bitfield_zerocopy!{KnownLayout}
+
+        bitfield!{$(#[$attribute])* #[derive($crate::zerocopy::KnownLayout)] #[zerocopy(crate = "::bitfield::zerocopy")]
+                  $vis struct $name($($type)*); no default BitRange; @repr_transparent; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; $(@repr_transparent;)?
+     impl Immutable; $($rest:tt)*) => {
+        __bitfield_zerocopy!{Immutable}
+
+        bitfield!{$(#[$attribute])* #[derive($crate::zerocopy::Immutable)] #[zerocopy(crate = "::bitfield::zerocopy")]
+                  $vis struct $name($($type)*); no default BitRange; @repr_transparent; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; @repr_transparent;
+     impl $trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
+        bitfield_impl!{$trait$({$($trait_arg)*})? for $vis struct $name($($type)*); $($rest)*}
+
+        bitfield!{$(#[$attribute])* $vis struct $name($($type)*); no default BitRange; @repr_transparent; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); no default BitRange; @repr_transparent; $($rest:tt)*) => {
+        bitfield!{$(#[$attribute])* #[repr(transparent)] $vis struct $name($($type)*); no default BitRange; $($rest)*}
+    };
+
+    // If we have `impl <Trait>` without `no default BitRange`, we will still match, because when
+    // we call `bitfield_bitrange`, we add `no default BitRange`.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident([$t:ty]); no default BitRange; impl $trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
+        bitfield_impl!{$trait$({$($trait_arg)*})? for $vis struct $name([$t]); $($rest)*}
+
+        bitfield!{$(#[$attribute])* $vis struct $name([$t]); no default BitRange;  $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident([$t:ty]); no default BitRange; $($rest:tt)*) => {
+        $(#[$attribute])*
+        $vis struct $name<T>(pub T);
+
+        bitfield_fields!{@parse bitfield_fields!(@newtypes);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+
+        //impl<T: AsMut<[$t]> + AsRef<[$t]>> $name<T> {
+        //    bitfield_fields!{$($rest)*}
+        //}
+        impl<T: AsRef<[$t]>> $name<T> {
+           bitfield_fields!{only getter; $($rest)*}
+           bitfield_fields!{only consts; $($rest)*}
+        }
+        bitfield_fields!{@parse bitfield_fields!(@bytes [impl<T: AsRef<[$t]>> $name<T>]
+                                                        [impl<T: AsMut<[$t]>> $name<T>] $t);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        impl<T: AsMut<[$t]>> $name<T> {
+           bitfield_fields!{only setter; $($rest)*}
+           bitfield_fields!{only setter_all; $($rest)*}
+        }
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
+        bitfield_bitrange!(struct $name([$t]));
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds (usize::MAX) LSB0);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        bitfield!{$(#[$attribute])* $vis struct $name([$t]); no default BitRange; $($rest)*}
+    };
+
+    // The only difference between the MSB0, `LE` and `BE` versions and the native version, is the
+    // BitRange implementation. We delegate everything else to the native version of the macro.
+    // The `impl` lines are still given the original storage, as the ordering depends on it.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($order:ident)+ [$t:ty]); no default BitRange; impl $trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
+        bitfield_impl!{$trait$({$($trait_arg)*})? for $vis struct $name($($order)+ [$t]); $($rest)*}
+
+        bitfield!{$(#[$attribute])* $vis struct $name($($order)+ [$t]); no default BitRange; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($order:ident)+ [$t:ty]); no default BitRange; $($rest:tt)*) => {
+        bitfield!{$(#[$attribute])* $vis struct $name([$t]); no default BitRange; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($order:ident)+ [$t:ty]); $($rest:tt)*) => {
+        bitfield_bitrange!(struct $name($($order)+ [$t]));
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds (usize::MAX) $($order)+);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        bitfield!{$(#[$attribute])* $vis struct $name($($order)+ [$t]); no default BitRange; $($rest)*}
+    };
+
+    // A generic struct holds a marker, usually a `PhantomData`, after its integer storage. The
+    // parameters are kept as token lists: with their bounds, without them, and the where clause.
+    // The `impl` lines would need to be generic, so they are not supported.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident<$($param:ident $(: $bound:path)?),+ $(,)?>($t:ty, $marker:ty)
+     $(where $($where_ty:ty: $where_bound:path),+ $(,)?)?; $($rest:tt)*) => {
+        bitfield!{@generic $(#[$attribute])* $vis struct $name [$($param $(: $bound)?),+] [$($param),+]
+                  [$($($where_ty: $where_bound,)+)?] ($t, $marker); $($rest)*}
+    };
+    (@generic $(#[$attribute:meta])* $vis:vis struct $name:ident [$($params:tt)*] [$($args:tt)*] [$($where:tt)*]
+     ($t:ty, $marker:ty); assert_size $bits:expr; $($rest:tt)*) => {
+        bitfield!{@assert_size $name, $t, $bits}
+        bitfield!{@generic $(#[$attribute])* $vis struct $name [$($params)*] [$($args)*] [$($where)*]
+                  ($t, $marker); $($rest)*}
+    };
+    (@generic $(#[$attribute:meta])* $vis:vis struct $name:ident [$($params:tt)*] [$($args:tt)*] [$($where:tt)*]
+     ($t:ty, $marker:ty); $(no default BitRange;)? impl $($_rest:tt)*) => {
+        __bitfield_compile_error!{"the `impl` lines are not supported with generic parameters"}
+    };
+    (@generic $(#[$attribute:meta])* $vis:vis struct $name:ident [$($params:tt)*] [$($args:tt)*] [$($where:tt)*]
+     ($t:ty, $marker:ty); no default BitRange; $($rest:tt)*) => {
+        $(#[$attribute])*
+        $vis struct $name<$($params)*>(pub $t, pub $marker) where $($where)*;
+
+        bitfield_fields!{@parse bitfield_fields!(@newtypes); $t; $($rest)*}
+
+        impl<$($params)*> $crate::BitfieldStorage for $name<$($args)*> where $($where)* {
+            type Storage = $t;
+        }
+
+        impl<$($params)*> $name<$($args)*> where $($where)* {
+            bitfield_fields!{$t; $($rest)*}
+            bitfield_fields!{only setter_all; $t; $($rest)*}
+        }
+    };
+    (@generic $(#[$attribute:meta])* $vis:vis struct $name:ident [$($params:tt)*] [$($args:tt)*] [$($where:tt)*]
+     ($t:ty, $marker:ty); $($rest:tt)*) => {
+        impl<$($params)*, __Value> $crate::BitRange<__Value> for $name<$($args)*>
+            where $t: $crate::BitRange<__Value>, $($where)* {
+            fn bit_range(&self, msb: usize, lsb: usize) -> __Value {
+                self.0.bit_range(msb, lsb)
+            }
+        }
+        impl<$($params)*, __Value> $crate::BitRangeMut<__Value> for $name<$($args)*>
+            where $t: $crate::BitRangeMut<__Value>, $($where)* {
+            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: __Value) {
+                self.0.set_bit_range(msb, lsb, value);
+            }
+        }
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds ($crate::size_of::<$t>() * 8) LSB0); $t; $($rest)*}
+        bitfield!{@generic $(#[$attribute])* $vis struct $name [$($params)*] [$($args)*] [$($where)*]
+                  ($t, $marker); no default BitRange; $($rest)*}
+    };
+
+    // A struct with a lifetime parameter borrows its slice storage. The setters are only generated
+    // for a mutable reference. The `impl` lines expect an owned storage, so they are not supported.
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident<$lt:lifetime>($($order:ident)* &$ref_lt:lifetime mut [$t:ty]);
+     $($rest:tt)*) => {
+        bitfield!{@reference $(#[$attribute])* $vis struct $name<$lt>($($order)* &$ref_lt [$t]) [mut]; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident<$lt:lifetime>($($order:ident)* &$ref_lt:lifetime [$t:ty]);
+     $($rest:tt)*) => {
+        bitfield!{@reference $(#[$attribute])* $vis struct $name<$lt>($($order)* &$ref_lt [$t]) []; $($rest)*}
+    };
+    (@reference $(#[$attribute:meta])* $vis:vis struct $name:ident<$lt:lifetime>($($order:ident)* &$ref_lt:lifetime [$t:ty])
+     $mutability:tt; assert_size $bits:expr; $($rest:tt)*) => {
+        bitfield!{@assert_slice_size $name, $t, $bits; $($rest)*}
+        bitfield!{@reference $(#[$attribute])* $vis struct $name<$lt>($($order)* &$ref_lt [$t]) $mutability;
+                  $($rest)*}
+    };
+    (@reference $(#[$attribute:meta])* $vis:vis struct $name:ident<$lt:lifetime>($($order:ident)* &$ref_lt:lifetime [$t:ty])
+     $mutability:tt; $(no default BitRange;)? impl $($_rest:tt)*) => {
+        __bitfield_compile_error!{"the `impl` lines are not supported with a borrowed storage"}
+    };
+    (@reference $(#[$attribute:meta])* $vis:vis struct $name:ident<$lt:lifetime>($($order:ident)* &$ref_lt:lifetime [$t:ty])
+     [$($mut:tt)?]; no default BitRange; $($rest:tt)*) => {
+        $(#[$attribute])*
+        $vis struct $name<$lt>(pub &$ref_lt $($mut)? [$t]);
+
+        bitfield_fields!{@parse bitfield_fields!(@newtypes);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+
+        impl<$lt> $name<$lt> {
+           bitfield_fields!{only getter; $($rest)*}
+           bitfield_fields!{only consts; $($rest)*}
+        }
+        bitfield!{@reference_accessors [$($mut)?] $name<$lt> $t; $($rest)*}
+    };
+    (@reference $(#[$attribute:meta])* $vis:vis struct $name:ident<$lt:lifetime>($($order:ident)* &$ref_lt:lifetime [$t:ty])
+     [$($mut:tt)?]; $($rest:tt)*) => {
+        bitfield_bitrange!(struct $name<$lt>($($order)* &$ref_lt $($mut)? [$t]));
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds (usize::MAX) $($order)* LSB0);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        bitfield!{@reference $(#[$attribute])* $vis struct $name<$lt>($($order)* &$ref_lt [$t]) [$($mut)?];
+                  no default BitRange; $($rest)*}
+    };
+    (@reference_accessors [] $name:ident<$lt:lifetime> $t:ty; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@bytes [impl<$lt> $name<$lt>] [] $t);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+    };
+    (@reference_accessors [mut] $name:ident<$lt:lifetime> $t:ty; $($rest:tt)*) => {
+        bitfield_fields!{@parse bitfield_fields!(@bytes [impl<$lt> $name<$lt>] [impl<$lt> $name<$lt>] $t);
+                         SET_A_DEFAULT_TYPE_OR_SPECIFY_THE_TYPE_FOR_EACH_FIELDS; $($rest)*}
+        impl<$lt> $name<$lt> {
+           bitfield_fields!{only setter; $($rest)*}
+           bitfield_fields!{only setter_all; $($rest)*}
+        }
+    };
+
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($t:ty); no default BitRange; impl $trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
+        bitfield_impl!{$trait$({$($trait_arg)*})? for $vis struct $name($t); $($rest)*}
+
+        bitfield!{$(#[$attribute])* $vis struct $name($t); no default BitRange; $($rest)*}
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($t:ty); no default BitRange; $($rest:tt)*) => {
+        $(#[$attribute])*
+        $vis struct $name(pub $t);
+
+        bitfield_fields!{@parse bitfield_fields!(@newtypes); $t; $($rest)*}
+
+        impl $crate::BitfieldStorage for $name {
+            type Storage = $t;
+        }
+
+        impl $name {
+            bitfield_fields!{$t; $($rest)*}
+            bitfield_fields!{only setter_all; $t; $($rest)*}
+         }
+    };
+    ($(#[$attribute:meta])* $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
+        bitfield_bitrange!(struct $name($t));
+        bitfield_fields!{@parse bitfield_impl!(@check_bounds ($crate::size_of::<$t>() * 8) LSB0); $t; $($rest)*}
+        bitfield!{$(#[$attribute])* $vis struct $name($t); no default BitRange; $($rest)*}
+    };
+}
+
+/// Asserts that a field of a bitfield has the expected value.
+///
+/// The syntax is `assert_field_eq!(value, getter, expected)`, or `assert_field_eq!(value,
+/// getter[index], expected)` for array fields. The bitfield must have `impl Fields;` and
+/// implement `Debug`, like its storage. On failure, the message contains the bits of the field,
+/// the storage in hexadecimal and the value of every field.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// bitfield! {
+///     struct Control(u16);
+///     impl Debug;
+///     impl Fields;
+///     u8;
+///     enable, set_enable: 0;
+///     mode, set_mode: 7, 4;
+///     lanes, set_lanes: 9, 8, 3;
+/// }
+///
+/// fn main() {
+///     let control = Control(0x0631);
+///     assert_field_eq!(control, mode, 3);
+///     assert_field_eq!(control, lanes[1], 1);
+///     assert_fields_eq!(control, { enable: true, mode: 3, lanes[0]: 2 });
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_field_eq {
+    ($bitfield:expr, $field:ident[$index:expr], $expected:expr $(,)?) => {
+        $crate::assert_fields_eq!($bitfield, { $field[$index]: $expected })
+    };
+    ($bitfield:expr, $field:ident, $expected:expr $(,)?) => {
+        $crate::assert_fields_eq!($bitfield, { $field: $expected })
+    };
+}
+
+/// Asserts that several fields of a bitfield have the expected values.
+///
+/// The syntax is `assert_fields_eq!(value, { getter: expected, array_getter[index]: expected })`.
+/// The requirements and the failure message are the same as for `assert_field_eq!`, with a line
+/// for every field that doesn't have the expected value.
+#[macro_export]
+macro_rules! assert_fields_eq {
+    // Binds the value of each field in turn, so that the checks can borrow them.
+    (@bind $bitfield:ident, $expr:expr, [$($checks:tt)*]) => {
+        match [$($checks)*] {
+            checks => {
+                if !$crate::FieldCheck::all_ok(&checks) {
+                    panic!(
+                        "assertion failed for the fields of `{}`:\n{}storage: {:#x?}\nfields: {:?}",
+                        stringify!($expr),
+                        $crate::FailedChecks(&checks),
+                        $bitfield.0,
+                        $bitfield,
+                    );
+                }
+            }
+        }
+    };
+    (@bind $bitfield:ident, $expr:expr, [$($checks:tt)*] $field:ident[$index:expr]: $expected:expr $(, $($rest:tt)*)?) => {
+        match $index {
+            index => match $bitfield.$field(index) {
+                value => $crate::assert_fields_eq!(@bind $bitfield, $expr, [$($checks)* $crate::FieldCheck::new(
+                    stringify!($field),
+                    $crate::FieldBits::new($crate::fields_of($bitfield), stringify!($field), index),
+                    &value,
+                    &$expected,
+                ),] $($($rest)*)?),
+            },
+        }
+    };
+    (@bind $bitfield:ident, $expr:expr, [$($checks:tt)*] $field:ident: $expected:expr $(, $($rest:tt)*)?) => {
+        match $bitfield.$field() {
+            value => $crate::assert_fields_eq!(@bind $bitfield, $expr, [$($checks)* $crate::FieldCheck::new(
+                stringify!($field),
+                $crate::FieldBits::new($crate::fields_of($bitfield), stringify!($field), 0),
+                &value,
+                &$expected,
+            ),] $($($rest)*)?),
+        }
+    };
+    ($bitfield:expr, { $($fields:tt)* }) => {
+        match &$bitfield {
+            bitfield => $crate::assert_fields_eq!(@bind bitfield, $bitfield, [] $($fields)*),
+        }
+    };
+}
+
+/// Checks that decoding bytes with `decode_from` and encoding the result with `encode_into` is
+/// symmetric, as the body of a fuzz target.
+///
+/// The syntax is `bitfield_fuzz_roundtrip!(TheType, data)`, with `TheType` having `impl Encode;`
+/// and `impl Decode;` (with the storage for slice-backed structs, like `Header<[u8; 4]>`), and
+/// `data` a `&[u8]`. Inputs too short to decode are ignored. Otherwise, this panics if:
+/// * encoding the decoded value doesn't give back the bits that were read
+/// * decoding the encoded bits doesn't give back the same storage
+///
+/// The bits that are not part of a field are not in the encoded bits, and are left to their
+/// default value by the decoding, so they don't affect the checks. The storage must implement
+/// `PartialEq` and `Debug`, and the `alloc` feature is required.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// fuzz_target!(|data: &[u8]| {
+///     bitfield_fuzz_roundtrip!(Header, data);
+/// });
+/// ```
+#[macro_export]
+macro_rules! bitfield_fuzz_roundtrip {
+    ($ty:ty, $data:expr $(,)?) => {{
+        let data: &[u8] = $data;
+        let mut reader = $crate::BitReader::new(data);
+        if let Ok(decoded) = <$ty>::decode_from(&mut reader) {
+            let bits = reader.position();
+            let mut encoded = $crate::alloc::vec![0u8; data.len()];
+            let mut writer = $crate::BitWriter::new(&mut encoded);
+            decoded.encode_into(&mut writer);
+            assert_eq!(
+                writer.position(),
+                bits,
+                "the number of encoded bits differs from the number of decoded bits",
+            );
+            assert!(
+                $crate::same_leading_bits(data, &encoded, bits),
+                "the encoded bits differ from the decoded bits: {:02x?} != {:02x?}",
+                data,
+                encoded,
+            );
+            let redecoded = <$ty>::decode_from(&mut $crate::BitReader::new(&encoded))
+                .expect("the encoded bits can't be decoded");
+            assert_eq!(redecoded.0, decoded.0, "decoding the encoded bits gives another value");
+        }
+    }};
+}
+
+/// Asserts at compile time that two bitfields have the same layout.
+///
+/// The syntax is `bitfield_assert_same_layout!(A, B)`, with `A` and `B` having `impl Fields;`
+/// (with the storage for slice-backed structs, like `Header<[u8; 4]>`). The layouts are the same
+/// if the fields have the same names, positions, widths, number of elements and kinds, in any
+/// order. The documentation, the default values and the presentation hints are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// bitfield! {
+///     struct Generated(u32);
+///     impl Fields;
+///     u8;
+///     enable, set_enable: 0;
+///     mode, set_mode: 7, 4;
+/// }
+///
+/// bitfield! {
+///     struct Handwritten(u32);
+///     impl Fields;
+///     u8;
+///     /// The mode.
+///     mode, set_mode: 7, 4;
+///     enable, set_enable: 0;
+/// }
+///
+/// bitfield_assert_same_layout!(Generated, Handwritten);
+/// # fn main() {}
+/// ```
+///
+/// A field at another position doesn't compile:
+///
+/// ```rust,compile_fail
+/// # #[macro_use] extern crate bitfield;
+/// bitfield! {
+///     struct Generated(u32);
+///     impl Fields;
+///     u8;
+///     mode, set_mode: 7, 4;
+/// }
+///
+/// bitfield! {
+///     struct Handwritten(u32);
+///     impl Fields;
+///     u8;
+///     mode, set_mode: 8, 4;
+/// }
+///
+/// bitfield_assert_same_layout!(Generated, Handwritten);
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! bitfield_assert_same_layout {
+    ($a:ty, $b:ty $(,)?) => {
+        const _: () = $crate::__bitfield_assert!($crate::LayoutDescriptor::same_layout(
+            &<$a>::LAYOUT,
+            &<$b>::LAYOUT
+        ));
+    };
+}
+
+/// Writes several segments back-to-back into a byte buffer, and returns the number of bits
+/// written.
+///
+/// The syntax is `bitfield_compose!(buffer; segment, ...)`. Each segment is either a bitfield
+/// with `impl Encode;`, written with `encode_into`, or a byte slice in brackets, `[bytes]`, written
+/// as is. The segments are written most significant bit first, without padding, like with a
+/// `BitWriter`. The `ENCODED_BITS` constants generated by `impl Encode;` give the size of a frame
+/// at compile time.
+///
+/// # Panics
+///
+/// Panics if the buffer is too small.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// bitfield! {
+///     struct Header(u8);
+///     impl Encode;
+///     u8;
+///     pub version, set_version: 7, 4;
+///     pub kind, set_kind: 3, 0;
+/// }
+///
+/// # fn main() {
+/// const FRAME_BITS: usize = Header::ENCODED_BITS + 2 * 8;
+///
+/// let mut buffer = [0u8; FRAME_BITS / 8];
+/// let mut header = Header(0);
+/// header.set_version(1);
+/// header.set_kind(2);
+/// let bits = bitfield_compose!(&mut buffer; header, [&[0xAB, 0xCD]]);
+/// assert_eq!(bits, FRAME_BITS);
+/// assert_eq!(buffer, [0x12, 0xAB, 0xCD]);
+/// # }
+/// ```
+#[macro_export(local_inner_macros)]
+macro_rules! bitfield_compose {
+    ($buffer:expr; $($segments:tt)*) => {{
+        let mut writer = $crate::BitWriter::new($buffer);
+        bitfield_compose!{@segments writer; $($segments)*}
+        writer.position()
+    }};
+    (@segments $writer:ident;) => {};
+    (@segments $writer:ident; [$bytes:expr] $(, $($rest:tt)*)?) => {
+        $writer.write_bytes($bytes);
+        bitfield_compose!{@segments $writer; $($($rest)*)?}
+    };
+    (@segments $writer:ident; $segment:expr $(, $($rest:tt)*)?) => {
+        $segment.encode_into(&mut $writer);
+        bitfield_compose!{@segments $writer; $($($rest)*)?}
+    };
+}
+
+/// Returns whether all the `values` are `false`.
+#[doc(hidden)]
+pub const fn all_false(values: &[bool]) -> bool {
+    let mut i = 0;
+    while i < values.len() {
+        if values[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Returns the `msb` and `lsb` of a `first..=last` range of positions, in any order, the order
+/// being checked with the bit order of the struct.
+#[doc(hidden)]
+pub const fn bit_range_bounds(first: usize, last: usize) -> (usize, usize) {
+    if first >= last {
+        (first, last)
+    } else {
+        (last, first)
+    }
+}
+
+/// Returns the first bit below `bits` that isn't in any of the `ranges` (with an exclusive end),
+/// or below the end of the last range if `bits` is `None`.
+#[doc(hidden)]
+pub const fn first_uncovered_bit(ranges: &[(usize, usize)], bits: Option<usize>) -> Option<usize> {
+    let bits = match bits {
+        Some(bits) => bits,
+        None => {
+            let mut end = 0;
+            let mut i = 0;
+            while i < ranges.len() {
+                if ranges[i].1 > end {
+                    end = ranges[i].1;
+                }
+                i += 1;
+            }
+            end
+        }
+    };
+    let mut bit = 0;
+    while bit < bits {
+        let mut i = 0;
+        while i < ranges.len() && !(ranges[i].0 <= bit && bit < ranges[i].1) {
+            i += 1;
+        }
+        if i == ranges.len() {
+            return Some(bit);
+        }
+        bit += 1;
+    }
+    None
+}
+
+/// Returns whether the first `bits` bits of `a` and `b`, in the order of `BitReader`, are equal.
+#[doc(hidden)]
+pub fn same_leading_bits(a: &[u8], b: &[u8], bits: usize) -> bool {
+    let (bytes, rest) = (bits / 8, bits % 8);
+    let mask = !(0xffu8 >> rest);
+    a[..bytes] == b[..bytes] && (rest == 0 || a[bytes] & mask == b[bytes] & mask)
+}
+
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub extern crate alloc;
+#[cfg(feature = "arbitrary")]
+#[doc(hidden)]
+pub extern crate arbitrary;
+#[cfg(feature = "bytemuck")]
+#[doc(hidden)]
+pub extern crate bytemuck;
+#[cfg(feature = "paste")]
+extern crate paste;
+#[cfg(feature = "rand")]
+#[doc(hidden)]
+pub extern crate rand;
+#[cfg(feature = "schemars")]
+#[doc(hidden)]
+pub extern crate schemars;
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub extern crate serde;
+#[cfg(feature = "std")]
+extern crate std;
+#[cfg(feature = "zerocopy")]
+#[doc(hidden)]
+pub extern crate zerocopy;
+
+#[doc(hidden)]
+pub use core::array;
+#[doc(hidden)]
+pub use core::cell;
+#[doc(hidden)]
+pub use core::clone;
+#[doc(hidden)]
+pub use core::cmp;
+#[doc(hidden)]
+pub use core::convert;
+#[doc(hidden)]
+pub use core::convert::Into;
+#[doc(hidden)]
+pub use core::fmt;
+#[doc(hidden)]
+pub use core::hash;
+#[doc(hidden)]
+pub use core::iter;
+#[doc(hidden)]
+pub use core::marker;
+#[doc(hidden)]
+pub use core::mem;
+#[doc(hidden)]
+pub use core::mem::size_of;
+#[doc(hidden)]
+pub use core::ops;
+#[doc(hidden)]
+pub use core::pin;
+#[doc(hidden)]
+pub use core::result;
+#[doc(hidden)]
+pub use core::slice;
+#[doc(hidden)]
+pub use core::str;
+#[cfg(feature = "paste")]
+#[doc(hidden)]
+pub use paste::paste;
+
+/// The description of a field, as generated by `impl Fields;`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Field {
+    /// The name of the field (its getter, or its setter for write-only fields).
+    pub name: &'static str,
+    /// The first line of the documentation of the field, or an empty string.
+    pub doc: &'static str,
+    /// The position of the least significant bit (of the first element for arrays).
+    pub lsb: usize,
+    /// The number of bits (of one element for arrays).
+    pub width: usize,
+    /// The number of elements, 1 if the field is not an array.
+    pub count: usize,
+    /// The number of bits from the start of an element to the start of the next one, which is
+    /// `width` unless the array has a stride.
+    pub stride: usize,
+    /// How the bits are interpreted.
+    pub kind: FieldKind,
+    /// The raw bits of the default value (of each element for arrays), if the field has a default
+    /// value that isn't of a `from into` type.
+    pub default: Option<u128>,
+    /// The unit of the values, given with `unit "..."`, or an empty string.
+    pub unit: &'static str,
+    /// The radix in which the values are preferably displayed, given with `radix ...`.
+    pub radix: Radix,
+}
+
+impl Field {
+    /// Returns whether the two fields have the same name and bits, ignoring the documentation, the
+    /// default value and the presentation hints.
+    pub const fn same_layout(&self, other: &Field) -> bool {
+        same_str(self.name, other.name)
+            && self.lsb == other.lsb
+            && self.width == other.width
+            && self.count == other.count
+            && self.stride == other.stride
+            && self.kind as u8 == other.kind as u8
+    }
+
+    /// Returns the msb and lsb of the element `index` (0 for non-array fields).
+    pub fn element_bits(&self, index: usize) -> (usize, usize) {
+        let lsb = self.lsb + index * self.stride;
+        (lsb + self.width - 1, lsb)
+    }
+}
+
+/// How the bits of a field are interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldKind {
+    /// A single bit field.
+    Bool,
+    /// An unsigned integer.
+    Unsigned,
+    /// A signed integer, in two's complement.
+    Signed,
+}
+
+/// The radix in which the values of a field are displayed, as given with `radix dec`, `radix hex`,
+/// `radix bin` or `radix oct` in the field declaration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Radix {
+    /// Decimal, the default.
+    #[default]
+    Decimal,
+    /// Hexadecimal, with a `0x` prefix.
+    Hexadecimal,
+    /// Binary, with a `0b` prefix.
+    Binary,
+    /// Octal, with a `0o` prefix.
+    Octal,
+}
+
+/// The layout of a bitfield, as generated by `impl Fields;` in the `LAYOUT` constant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LayoutDescriptor {
+    /// The fields, in declaration order.
+    pub fields: &'static [Field],
+    /// Whether no field has a setter.
+    pub read_only: bool,
+}
+
+impl LayoutDescriptor {
+    /// Creates a descriptor for the given fields, which are not read-only.
+    pub const fn new(fields: &'static [Field]) -> Self {
+        LayoutDescriptor {
+            fields,
+            read_only: false,
+        }
+    }
+
+    /// Returns the hash of the layout, see `layout_hash`.
+    pub const fn hash(&self) -> u64 {
+        layout_hash(self.fields)
+    }
+
+    /// Returns whether the two layouts have the same fields, in any order.
+    pub const fn same_layout(&self, other: &LayoutDescriptor) -> bool {
+        if self.fields.len() != other.fields.len() {
+            return false;
+        }
+        let mut i = 0;
+        while i < other.fields.len() {
+            let mut j = 0;
+            while j < self.fields.len() && !self.fields[j].same_layout(&other.fields[i]) {
+                j += 1;
+            }
+            if j == self.fields.len() {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Checks whether data written with the `other` layout can be read with this layout.
+    ///
+    /// The layouts are identical if they have the same fields. This layout extends `other` if every
+    /// field of `other` is also in this layout, at the same position and with the same width,
+    /// number of elements and kind. Fields are matched by name, so their order doesn't matter.
+    pub fn is_compatible_with(&self, other: &LayoutDescriptor) -> Compatibility {
+        let all_found = other
+            .fields
+            .iter()
+            .all(|old| self.fields.iter().any(|new| new.same_layout(old)));
+        if !all_found {
+            Compatibility::Incompatible
+        } else if self.fields.len() == other.fields.len() {
+            Compatibility::Identical
+        } else {
+            Compatibility::Extended
+        }
+    }
+}
+
+// `==` on `str` can't be used in a `const fn`.
+const fn same_str(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// The result of `LayoutDescriptor::is_compatible_with`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compatibility {
+    /// The layouts have the same fields.
+    Identical,
+    /// The new layout has all the fields of the old one, and some more.
+    Extended,
+    /// Some fields of the old layout are missing or different in the new one.
+    Incompatible,
+}
+
+/// Renders the value of the given fields as an aligned table, as done by the `render_table` method
+/// generated by `impl Fields;`.
+///
+/// `bit_range` is called with the msb and lsb of each field (of each element for arrays) and
+/// returns the raw bits. There is a row per field, and per element for arrays, with the name, the
+/// bits positions, the value in hexadecimal, the value in the radix of the field (decimal by
+/// default) followed by its unit, and the documentation of the field.
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// bitfield! {
+///     struct Status(u8);
+///     impl Fields;
+///     /// Device ready.
+///     ready, _: 0;
+///     i8, error, _: 7, 4;
+///     unit "ms", timeout, _: 3, 1;
+/// }
+///
+/// # fn main() {
+/// assert_eq!(
+///     Status(0xF5).render_table(),
+///     "Field    Bits  Hex  Value  Description\n\
+///      ready    0     0x1  1      Device ready.\n\
+///      error    7:4   0xf  -1\n\
+///      timeout  3:1   0x2  2 ms\n"
+/// );
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+pub fn render_table<F: Fn(usize, usize) -> u128>(
+    fields: &[Field],
+    bit_range: F,
+) -> alloc::string::String {
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    let mut rows = Vec::new();
+    for field in fields {
+        for index in 0..field.count {
+            let (msb, lsb) = field.element_bits(index);
+            let raw = bit_range(msb, lsb);
+            rows.push([
+                render_name(field, index),
+                render_bits(field, index),
+                format!("{:#x}", raw),
+                render_value(field, raw),
+                field.doc.into(),
+            ]);
+        }
+    }
+    render_rows(
+        ["Field", "Bits", "Hex", "Value", "Description"],
+        &rows,
+        ["", "", "", "", ""],
+    )
+}
+
+/// Renders the fields that differ between two values as an aligned table, as done by the
+/// `render_diff` method generated by `impl Fields;`.
+///
+/// There is a row per changed field, and per changed element for arrays, with the name, the bits
+/// positions, and the old and new values in hexadecimal. With `colors`, the old values are
+/// rendered in red and the new ones in green, using ANSI escape codes.
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// bitfield! {
+///     struct Status(u8);
+///     impl Fields;
+///     ready, _: 0;
+///     error, _: 7, 4;
+/// }
+///
+/// # fn main() {
+/// assert_eq!(
+///     Status(0x01).render_diff(&Status(0x31), false),
+///     "Field  Bits  Old  New\n\
+///      error  7:4   0x0  0x3\n"
+/// );
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+pub fn render_diff<A: Fn(usize, usize) -> u128, B: Fn(usize, usize) -> u128>(
+    fields: &[Field],
+    old: A,
+    new: B,
+    colors: bool,
+) -> alloc::string::String {
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    let rows: Vec<_> = diff_fields(fields, old, new)
+        .map(|change| {
+            [
+                render_name(&change.field, change.index),
+                render_bits(&change.field, change.index),
+                format!("{:#x}", change.old),
+                format!("{:#x}", change.new),
+            ]
+        })
+        .collect();
+    let styles = if colors {
+        ["", "", "\x1b[31m", "\x1b[32m"]
+    } else {
+        ["", "", "", ""]
+    };
+    render_rows(["Field", "Bits", "Old", "New"], &rows, styles)
+}
+
+#[cfg(feature = "alloc")]
+fn render_name(field: &Field, index: usize) -> alloc::string::String {
+    if field.count == 1 {
+        field.name.into()
+    } else {
+        alloc::format!("{}[{}]", field.name, index)
+    }
+}
+
+// Renders the raw bits in the radix of the field, followed by its unit. Signed fields in decimal
+// are sign-extended.
+#[cfg(feature = "alloc")]
+fn render_value(field: &Field, raw: u128) -> alloc::string::String {
+    use alloc::format;
+
+    let value = match field.radix {
+        Radix::Decimal
+            if field.kind == FieldKind::Signed
+                && field.width < 128
+                && raw >> (field.width - 1) & 1 == 1 =>
+        {
+            format!("{}", raw as i128 - (1i128 << field.width))
+        }
+        Radix::Decimal => format!("{}", raw),
+        Radix::Hexadecimal => format!("{:#x}", raw),
+        Radix::Binary => format!("{:#b}", raw),
+        Radix::Octal => format!("{:#o}", raw),
+    };
+    if field.unit.is_empty() {
+        value
+    } else {
+        format!("{} {}", value, field.unit)
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn render_bits(field: &Field, index: usize) -> alloc::string::String {
+    let (msb, lsb) = field.element_bits(index);
+    if field.width == 1 {
+        alloc::format!("{}", lsb)
+    } else {
+        alloc::format!("{}:{}", msb, lsb)
+    }
+}
+
+// Renders aligned columns, separated by two spaces. The cells of the rows (but not of the header)
+// are wrapped in the ANSI escape code of their column, if any.
+#[cfg(feature = "alloc")]
+fn render_rows<const N: usize>(
+    header: [&str; N],
+    rows: &[[alloc::string::String; N]],
+    styles: [&str; N],
+) -> alloc::string::String {
+    use alloc::string::String;
+    use core::fmt::Write;
+
+    let mut widths = header.map(str::len);
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut table = String::new();
+    let mut render_row = |cells: [&str; N], styles: [&str; N]| {
+        let mut line = String::new();
+        for (i, cell) in cells.iter().enumerate() {
+            let padding = if i + 1 == N {
+                0
+            } else {
+                widths[i] - cell.len() + 2
+            };
+            if styles[i].is_empty() || cell.is_empty() {
+                let _ = write!(line, "{}{:padding$}", cell, "", padding = padding);
+            } else {
+                let _ = write!(
+                    line,
+                    "{}{}\x1b[0m{:padding$}",
+                    styles[i],
+                    cell,
+                    "",
+                    padding = padding
+                );
+            }
+        }
+        table.push_str(line.trim_end());
+        table.push('\n');
+    };
+    render_row(header, [""; N]);
+    for row in rows {
+        let mut cells = [""; N];
+        for (cell, value) in cells.iter_mut().zip(row) {
+            *cell = value;
+        }
+        render_row(cells, styles);
+    }
+    table
+}
+
+/// A field, or an element of an array field, whose value differs between two values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldChange {
+    /// The field.
+    pub field: Field,
+    /// The index of the element for arrays, 0 otherwise.
+    pub index: usize,
+    /// The raw bits of the old value.
+    pub old: u128,
+    /// The raw bits of the new value.
+    pub new: u128,
+}
+
+/// Returns the fields that differ between two values, as done by the `diff` method generated by
+/// `impl Fields;`.
+///
+/// `old` and `new` are called with the msb and lsb of each field (of each element for arrays) and
+/// return the raw bits.
+pub fn diff_fields<'a, A: Fn(usize, usize) -> u128 + 'a, B: Fn(usize, usize) -> u128 + 'a>(
+    fields: &'a [Field],
+    old: A,
+    new: B,
+) -> impl Iterator<Item = FieldChange> + 'a {
+    fields
+        .iter()
+        .flat_map(|field| (0..field.count).map(move |index| (field, index)))
+        .filter_map(move |(field, index)| {
+            let (msb, lsb) = field.element_bits(index);
+            let (old, new) = (old(msb, lsb), new(msb, lsb));
+            if old == new {
+                None
+            } else {
+                Some(FieldChange {
+                    field: *field,
+                    index,
+                    old,
+                    new,
+                })
+            }
+        })
+}
+
+/// A type with a table describing its fields, implemented by `impl Fields;`.
+pub trait HasFields {
+    /// The description of every field, in declaration order.
+    const FIELDS: &'static [Field];
+}
+
+#[doc(hidden)]
+pub fn fields_of<B: HasFields>(_: &B) -> &'static [Field] {
+    B::FIELDS
+}
+
+/// Keeps the last value read of a bitfield with `impl Fields;`, and reports the fields that change
+/// between successive reads, for example in a polling loop.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// # fn main() {
+/// use bitfield::Watcher;
+///
+/// bitfield! {
+///     struct Status(u8);
+///     impl Fields;
+///     u8;
+///     ready, _: 0;
+///     errors, _: 7, 4;
+/// }
+///
+/// let mut watcher = Watcher::new(Status(0x00));
+/// let changed: Vec<_> = watcher.update(Status(0x21)).map(|change| change.field.name).collect();
+/// assert_eq!(changed, ["ready", "errors"]);
+/// assert_eq!(watcher.update(Status(0x21)).count(), 0);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Watcher<B> {
+    value: B,
+}
+
+impl<B: HasFields + BitRange<u128>> Watcher<B> {
+    /// Creates a watcher from a first value, against which the next update is compared.
+    pub fn new(value: B) -> Self {
+        Watcher { value }
+    }
+
+    /// Returns the last value given to the watcher.
+    pub fn value(&self) -> &B {
+        &self.value
+    }
+
+    /// Stores `value`, and returns the fields that differ from the previous value.
+    pub fn update(&mut self, value: B) -> impl Iterator<Item = FieldChange> + '_ {
+        let old = mem::replace(&mut self.value, value);
+        let new = &self.value;
+        diff_fields(
+            B::FIELDS,
+            move |msb, lsb| old.bit_range(msb, lsb),
+            move |msb, lsb| new.bit_range(msb, lsb),
+        )
+    }
+
+    /// Consumes the watcher and returns the last value.
+    pub fn into_inner(self) -> B {
+        self.value
+    }
+}
+
+/// The bits of a field in the message of `assert_field_eq!` and `assert_fields_eq!`.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug)]
+pub struct FieldBits(pub Option<(usize, usize)>);
+
+impl FieldBits {
+    /// Finds the bits of the element `index` of the field `name`.
+    pub fn new(fields: &[Field], name: &str, index: usize) -> Self {
+        FieldBits(
+            fields
+                .iter()
+                .find(|field| field.name == name && index < field.count)
+                .map(|field| field.element_bits(index)),
+        )
+    }
+}
+
+impl fmt::Display for FieldBits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some((msb, lsb)) if msb == lsb => write!(f, "bit {}", lsb),
+            Some((msb, lsb)) => write!(f, "bits {}..={}", msb, lsb),
+            None => f.write_str("unknown bits"),
+        }
+    }
+}
+
+/// The comparison of a field with its expected value, in `assert_field_eq!` and
+/// `assert_fields_eq!`.
+#[doc(hidden)]
+pub struct FieldCheck<'a> {
+    name: &'a str,
+    bits: FieldBits,
+    value: &'a dyn fmt::Debug,
+    expected: &'a dyn fmt::Debug,
+    ok: bool,
+}
+
+impl<'a> FieldCheck<'a> {
+    pub fn new<A: fmt::Debug + PartialEq<B>, B: fmt::Debug>(
+        name: &'a str,
+        bits: FieldBits,
+        value: &'a A,
+        expected: &'a B,
+    ) -> Self {
+        FieldCheck {
+            name,
+            bits,
+            value,
+            expected,
+            ok: value == expected,
+        }
+    }
+
+    pub fn all_ok(checks: &[FieldCheck]) -> bool {
+        checks.iter().all(|check| check.ok)
+    }
+}
+
+/// Displays the failed checks, one per line.
+#[doc(hidden)]
+pub struct FailedChecks<'a>(pub &'a [FieldCheck<'a>]);
+
+impl<'a> fmt::Display for FailedChecks<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in self.0.iter().filter(|check| !check.ok) {
+            writeln!(
+                f,
+                "  {} ({}): {:?}, expected {:?}",
+                check.name, check.bits, check.value, check.expected
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the leading and trailing ASCII whitespaces of a line of documentation.
+#[doc(hidden)]
+pub const fn trim_doc(doc: &'static str) -> &'static str {
+    let mut bytes = doc.as_bytes();
+    while let [first, rest @ ..] = bytes {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+    while let [rest @ .., last] = bytes {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+    match str::from_utf8(bytes) {
+        Ok(trimmed) => trimmed,
+        Err(_) => doc,
+    }
+}
+
+/// Returns the address of the byte containing the bit `bit` of a storage made of elements of
+/// `element_size` bytes, in the given endianness, starting at `base`, and the index of the bit in
+/// this byte.
+#[doc(hidden)]
+pub const fn bit_address(
+    base: usize,
+    bit: usize,
+    element_size: usize,
+    msb0: bool,
+    big_endian: bool,
+) -> (usize, u8) {
+    let element_bits = element_size * 8;
+    let element = bit / element_bits;
+    let mut bit_in_element = bit % element_bits;
+    if msb0 {
+        bit_in_element = element_bits - 1 - bit_in_element;
+    }
+    let byte_in_element = if big_endian {
+        element_size - 1 - bit_in_element / 8
+    } else {
+        bit_in_element / 8
+    };
+    (
+        base + element * element_size + byte_in_element,
+        (bit_in_element % 8) as u8,
+    )
+}
+
+/// Computes a hash of the given fields, as used for the `LAYOUT_HASH` constant of `impl LayoutHash;`.
+///
+/// The hash covers the name, position, width, number of elements, stride and kind of each field,
+/// in order, the stride only for the arrays that have one. The documentation of the fields is not included. It is a 64-bit FNV-1a hash and doesn't depend on the target or on the version of the
+/// compiler, so it can be persisted to detect layout changes between builds.
+pub const fn layout_hash(fields: &[Field]) -> u64 {
+    const fn write(mut hash: u64, bytes: &[u8]) -> u64 {
+        let mut i = 0;
+        while i < bytes.len() {
+            hash ^= bytes[i] as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            i += 1;
+        }
+        hash
+    }
+
+    let mut hash = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < fields.len() {
+        let field = &fields[i];
+        hash = write(hash, field.name.as_bytes());
+        hash = write(hash, &[0xff]);
+        hash = write(hash, &(field.lsb as u64).to_le_bytes());
+        hash = write(hash, &(field.width as u64).to_le_bytes());
+        hash = write(hash, &(field.count as u64).to_le_bytes());
+        // Only the strided arrays hash their stride, so the other layouts keep their hash.
+        if field.stride != field.width {
+            hash = write(hash, &(field.stride as u64).to_le_bytes());
+        }
+        hash = write(hash, &[field.kind as u8]);
+        i += 1;
+    }
+    hash
+}
+
+/// Writes bits sequentially to a byte buffer.
+///
+/// Bits are written most significant bit first, both within a byte and within a value.
+#[derive(Debug)]
+pub struct BitWriter<'a> {
+    buffer: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> BitWriter<'a> {
+    /// Creates a writer that starts at the first bit of `buffer`.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        BitWriter {
+            buffer,
+            position: 0,
+        }
+    }
+
+    /// Returns the number of bits written so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Writes the `width` least significant bits of `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is greater than 128 or if the buffer is too small.
+    pub fn write_bits(&mut self, width: usize, value: u128) {
+        assert!(width <= 128);
+        assert!(self.position + width <= self.buffer.len() * 8);
+        for i in (0..width).rev() {
+            let byte = &mut self.buffer[self.position / 8];
+            let mask = 0x80 >> (self.position % 8);
+            if (value >> i) & 1 != 0 {
+                *byte |= mask;
+            } else {
+                *byte &= !mask;
+            }
+            self.position += 1;
+        }
+    }
+
+    /// Writes the bits of every byte of `bytes`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too small.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_bits(8, byte.into());
+        }
+    }
+
+    /// Returns the underlying buffer.
+    pub fn into_inner(self) -> &'a mut [u8] {
+        self.buffer
+    }
+}
+
+/// Reads bits sequentially from a byte buffer.
+///
+/// Bits are read most significant bit first, both within a byte and within a value, like they are
+/// written by `BitWriter`.
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// Creates a reader that starts at the first bit of `buffer`.
+    pub fn new(buffer: &'a [u8]) -> Self {
+        BitReader {
+            buffer,
+            position: 0,
+        }
+    }
+
+    /// Returns the number of bits read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of bits left to read.
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() * 8 - self.position
+    }
+
+    /// Reads `width` bits and returns them as the least significant bits of the result.
+    ///
+    /// Nothing is consumed if there are less than `width` bits left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is greater than 128.
+    pub fn read_bits(&mut self, width: usize) -> Result<u128, EndOfStream> {
+        assert!(width <= 128);
+        if width > self.remaining() {
+            return Err(EndOfStream);
+        }
+        let mut value = 0;
+        for _ in 0..width {
+            let byte = self.buffer[self.position / 8];
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | u128::from(bit);
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// The error returned when a `BitReader` doesn't have enough bits left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EndOfStream;
+
+impl fmt::Display for EndOfStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("unexpected end of bit stream")
+    }
+}
+
+/// The error returned when a slice is too short to hold all the fields of a bitfield.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BufferTooShort {
+    /// The number of elements needed.
+    pub required: usize,
+    /// The number of elements of the slice.
+    pub actual: usize,
+}
+
+impl BufferTooShort {
+    #[doc(hidden)]
+    pub fn check(required: usize, actual: usize) -> Result<(), Self> {
+        if actual < required {
+            Err(BufferTooShort { required, actual })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for BufferTooShort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "buffer too short: {} elements required, got {}",
+            self.required, self.actual
+        )
+    }
+}
+
+/// Calls `poll` until `condition` returns `true` for the value read, at most `max_polls` times,
+/// and returns that value.
+///
+/// This is the loop used by the `wait_for_*` functions generated by `impl Poll;`.
+///
+/// ```rust
+/// # #[macro_use] extern crate bitfield;
+/// bitfield! {
+///     struct Status(u8);
+///     busy, _: 0;
+///     error, _: 7, 4;
+/// }
+///
+/// # fn main() {
+/// let mut reads = [0x01, 0x01, 0x30].iter().map(|&raw| Status(raw));
+/// let status = bitfield::poll_until(|| reads.next().unwrap(), |status| !status.busy(), 5);
+/// assert_eq!(status.map(|status| status.error()), Ok(3));
+/// let timeout = bitfield::poll_until(|| Status(0x01), |status| !status.busy(), 5);
+/// assert_eq!(timeout.err(), Some(bitfield::Timeout { polls: 5 }));
+/// # }
+/// ```
+pub fn poll_until<B, P: FnMut() -> B, C: FnMut(&B) -> bool>(
+    mut poll: P,
+    mut condition: C,
+    max_polls: usize,
+) -> Result<B, Timeout> {
+    for _ in 0..max_polls {
+        let value = poll();
+        if condition(&value) {
+            return Ok(value);
+        }
+    }
+    Err(Timeout { polls: max_polls })
+}
+
+/// The error returned by `poll_until` when the condition isn't met after the maximum number of
+/// polls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Timeout {
+    /// The number of times the value was read.
+    pub polls: usize,
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "condition not met after {} polls", self.polls)
+    }
+}
+
+/// The error returned by the `try_set_*` methods generated by `impl TrySet;`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ValueTooLarge {
+    /// The name of the field.
+    pub field: &'static str,
+    /// The number of bits of the field, or of an element for arrays.
+    pub width: usize,
+}
+
+impl fmt::Display for ValueTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "value too large for the {} bits of the field `{}`",
+            self.width, self.field
+        )
+    }
+}
+
+/// The error returned by the `build` method of the builders generated by `impl Builder;`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MissingField {
+    /// The name of the field.
+    pub field: &'static str,
+}
+
+impl fmt::Display for MissingField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the field `{}` without a default value isn't set",
+            self.field
+        )
+    }
+}
+
+/// The error returned by the `FromStr` implementations generated by `impl FromStr;`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ParseFieldsError {
+    /// An assignment isn't of the form `field=value` or `field[index]=value`.
+    InvalidAssignment {
+        /// The position of the assignment in the string, in bytes.
+        position: usize,
+    },
+    /// There is no field with a setter with that name.
+    UnknownField {
+        /// The position of the assignment in the string, in bytes.
+        position: usize,
+    },
+    /// The index is missing for an array, given for another field, or out of bounds.
+    InvalidIndex {
+        /// The name of the field.
+        field: &'static str,
+    },
+    /// The value isn't an integer, or a boolean for single bit fields, or it can't be converted to
+    /// the type taken by the setter.
+    InvalidValue {
+        /// The name of the field.
+        field: &'static str,
+    },
+    /// The value doesn't fit in the bits of the field.
+    OutOfRange {
+        /// The name of the field.
+        field: &'static str,
+    },
+}
+
+impl fmt::Display for ParseFieldsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseFieldsError::InvalidAssignment { position } => {
+                write!(f, "invalid assignment at position {}", position)
+            }
+            ParseFieldsError::UnknownField { position } => {
+                write!(f, "unknown field at position {}", position)
+            }
+            ParseFieldsError::InvalidIndex { field } => {
+                write!(f, "invalid index for the field `{}`", field)
+            }
+            ParseFieldsError::InvalidValue { field } => {
+                write!(f, "invalid value for the field `{}`", field)
+            }
+            ParseFieldsError::OutOfRange { field } => {
+                write!(f, "value out of range for the field `{}`", field)
+            }
+        }
+    }
+}
+
+// The `field=value` or `field[index]=value` assignments separated by commas, as parsed by the
+// implementations of `impl FromStr;`.
+#[doc(hidden)]
+pub struct FieldAssignments<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> FieldAssignments<'a> {
+    pub fn new(input: &'a str) -> Self {
+        FieldAssignments { input, position: 0 }
+    }
+}
+
+impl<'a> Iterator for FieldAssignments<'a> {
+    type Item = Result<FieldAssignment<'a>, ParseFieldsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.position > self.input.len() {
+                return None;
+            }
+            let rest = &self.input[self.position..];
+            let end = rest.find(',').unwrap_or(rest.len());
+            let position = self.position;
+            self.position += end + 1;
+            let assignment = rest[..end].trim();
+            if !assignment.is_empty() {
+                return Some(FieldAssignment::parse(assignment, position));
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct FieldAssignment<'a> {
+    pub name: &'a str,
+    pub position: usize,
+    index: Option<&'a str>,
+    value: &'a str,
+}
+
+impl<'a> FieldAssignment<'a> {
+    fn parse(assignment: &'a str, position: usize) -> Result<Self, ParseFieldsError> {
+        let invalid = ParseFieldsError::InvalidAssignment { position };
+        let (target, value) = assignment.split_once('=').ok_or(invalid)?;
+        let target = target.trim();
+        let (name, index) = match target.strip_suffix(']') {
+            Some(target) => {
+                let (name, index) = target.split_once('[').ok_or(invalid)?;
+                (name.trim_end(), Some(index.trim()))
+            }
+            None => (target, None),
+        };
+        if name.is_empty() {
+            return Err(invalid);
+        }
+        Ok(FieldAssignment {
+            name,
+            position,
+            index,
+            value: value.trim(),
+        })
+    }
+
+    /// Returns the index of the element (0 if `count` is `None`) and the raw bits of the value.
+    pub fn raw_value(
+        &self,
+        field: &'static str,
+        width: usize,
+        count: Option<usize>,
+        signed: bool,
+    ) -> Result<(usize, u128), ParseFieldsError> {
+        let index = match (self.index, count) {
+            (None, None) => 0,
+            (Some(index), Some(count)) => match index.parse() {
+                Ok(index) if index < count => index,
+                _ => return Err(ParseFieldsError::InvalidIndex { field }),
+            },
+            _ => return Err(ParseFieldsError::InvalidIndex { field }),
+        };
+        let invalid = ParseFieldsError::InvalidValue { field };
+        let (negative, value) = match self.value.strip_prefix('-') {
+            Some(value) => (true, value),
+            None => (false, self.value),
+        };
+        let magnitude = match value {
+            "true" if !negative && width == 1 => 1,
+            "false" if !negative && width == 1 => 0,
+            _ => {
+                let (radix, digits) = if let Some(digits) = value.strip_prefix("0x") {
+                    (16, digits)
+                } else if let Some(digits) = value.strip_prefix("0o") {
+                    (8, digits)
+                } else if let Some(digits) = value.strip_prefix("0b") {
+                    (2, digits)
+                } else {
+                    (10, value)
+                };
+                if digits.starts_with('+') {
+                    return Err(invalid);
+                }
+                u128::from_str_radix(digits, radix).map_err(|_| invalid)?
+            }
+        };
+        let out_of_range = ParseFieldsError::OutOfRange { field };
+        let mask = u128::MAX >> (128 - width);
+        let raw = if !signed {
+            if negative || magnitude > mask {
+                return Err(out_of_range);
+            }
+            magnitude
+        } else {
+            // The magnitude of the minimum and maximum values.
+            let min = 1 << (width - 1);
+            let max = min - 1;
+            if negative && magnitude > min || !negative && magnitude > max {
+                return Err(out_of_range);
+            }
+            if negative {
+                magnitude.wrapping_neg() & mask
+            } else {
+                magnitude
+            }
+        };
+        Ok((index, raw))
+    }
+}
+
+/// An object-safe view of a bitfield, to handle different bitfield types uniformly, for example in
+/// a `Vec<Box<dyn AnyBitfield>>`.
+///
+/// It is implemented by `impl AnyBitfield;`, which requires `impl Fields;`. The fields are
+/// identified by their index in `fields()`, and accessed as raw bits.
+pub trait AnyBitfield {
+    /// Returns the name of the type.
+    fn name(&self) -> &'static str;
+
+    /// Returns the number of bits of the storage.
+    fn bit_len(&self) -> usize;
+
+    /// Returns the description of the fields.
+    fn fields(&self) -> &'static [Field];
+
+    /// Gets the raw bits of the element `element` of the field `index` (0 for non-array fields).
+    ///
+    /// Panics if the field or the element doesn't exist.
+    fn get_field(&self, index: usize, element: usize) -> u128;
+
+    /// Sets the raw bits of the element `element` of the field `index` (0 for non-array fields).
+    ///
+    /// Panics if the field or the element doesn't exist.
+    fn set_field(&mut self, index: usize, element: usize, value: u128);
+}
+
+/// The integer storage of a bitfield struct.
+///
+/// This is implemented by `bitfield` for the integer storages, and gives the type of the masks
+/// declared without a type, like `mask ENABLE_MASK, enable, set_enable: 0;`.
+pub trait BitfieldStorage {
+    /// The type of the storage.
+    type Storage;
+}
+
+/// A storage with interior mutability, like a `RefCell`.
+///
+/// This is used by `impl StorageCell;` to access the fields through a shared reference. With the
+/// `std` feature, it is also implemented for `Mutex` and `RwLock`.
+pub trait StorageCell {
+    /// The type of the storage behind the cell.
+    type Storage;
+
+    /// Calls `f` with a shared reference to the storage.
+    fn with_storage<R, F: FnOnce(&Self::Storage) -> R>(&self, f: F) -> R;
+
+    /// Calls `f` with a mutable reference to the storage.
+    fn with_storage_mut<R, F: FnOnce(&mut Self::Storage) -> R>(&self, f: F) -> R;
+}
+
+impl<T> StorageCell for cell::RefCell<T> {
+    type Storage = T;
+
+    fn with_storage<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        f(&self.borrow())
+    }
+
+    fn with_storage_mut<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self.borrow_mut())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> StorageCell for std::sync::Mutex<T> {
+    type Storage = T;
+
+    fn with_storage<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        f(&self
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner))
+    }
+
+    fn with_storage_mut<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> StorageCell for std::sync::RwLock<T> {
+    type Storage = T;
+
+    fn with_storage<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        f(&self
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner))
+    }
+
+    fn with_storage_mut<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner))
+    }
+}
+
+/// A bitfield shared between threads, behind a `Mutex`.
+///
+/// A poisoned lock is not an error: the bitfield is plain data, so it is always in a valid state.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct SharedBitfield<B> {
+    inner: std::sync::Mutex<B>,
+}
+
+#[cfg(feature = "std")]
+impl<B> SharedBitfield<B> {
+    /// Creates a new shared bitfield.
+    pub fn new(bitfield: B) -> Self {
+        SharedBitfield {
+            inner: std::sync::Mutex::new(bitfield),
+        }
+    }
+
+    /// Calls `f` with the locked bitfield and returns its result.
+    pub fn read<R, F: FnOnce(&B) -> R>(&self, f: F) -> R {
+        self.inner.with_storage(f)
+    }
+
+    /// Calls `f` with the locked bitfield, allowing it to be modified, and returns its result.
+    pub fn modify<R, F: FnOnce(&mut B) -> R>(&self, f: F) -> R {
+        self.inner.with_storage_mut(f)
+    }
+
+    /// Consumes the wrapper and returns the bitfield.
+    pub fn into_inner(self) -> B {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B> From<B> for SharedBitfield<B> {
+    fn from(bitfield: B) -> Self {
+        SharedBitfield::new(bitfield)
+    }
+}
+
+/// Deserializes the name of a field, as one of the given names.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub struct SerdeFieldName(pub &'static [&'static str]);
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::DeserializeSeed<'de> for SerdeFieldName {
+    type Value = Option<&'static str>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for SerdeFieldName {
+    type Value = Option<&'static str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a field name")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(convert::TryFrom::try_from(value)
+            .ok()
+            .and_then(|index: usize| self.0.get(index))
+            .copied())
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(self.0.iter().find(|name| **name == value).copied())
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(self.0.iter().find(|name| name.as_bytes() == value).copied())
+    }
+}
+
+/// Restricts the schema of a field to the values that fit in its bits, and wraps it in an array
+/// schema for array fields.
+#[cfg(feature = "schemars")]
+#[doc(hidden)]
+pub fn json_schema_field(
+    mut schema: schemars::Schema,
+    width: usize,
+    signed: bool,
+    count: Option<usize>,
+) -> schemars::Schema {
+    if schema.get("type").and_then(serde_json::Value::as_str) == Some("integer") {
+        let (min, max) = if signed {
+            (-1i128 << (width - 1), (1u128 << (width - 1)) - 1)
+        } else if width >= 128 {
+            (0, u128::MAX)
+        } else {
+            (0, (1u128 << width) - 1)
+        };
+        let min = convert::TryFrom::try_from(min)
+            .map_or_else(|_| (min as f64).into(), |min: i64| min.into());
+        let max = convert::TryFrom::try_from(max)
+            .map_or_else(|_| (max as f64).into(), |max: u64| max.into());
+        schema.insert("minimum".into(), min);
+        schema.insert("maximum".into(), max);
+    }
+    match count {
+        Some(count) => {
+            let mut array = schemars::Schema::default();
+            array.insert("type".into(), "array".into());
+            array.insert("items".into(), schema.to_value());
+            array.insert("minItems".into(), count.into());
+            array.insert("maxItems".into(), count.into());
+            array
+        }
+        None => schema,
+    }
+}
+
+/// Creates the schema of an object with the given required properties, skipping the `None`.
+#[cfg(feature = "schemars")]
+#[doc(hidden)]
+pub fn json_schema_object<I: IntoIterator<Item = Option<(&'static str, schemars::Schema)>>>(
+    title: &'static str,
+    properties: I,
+) -> schemars::Schema {
+    let mut schema = schemars::Schema::default();
+    let mut map = serde_json::Map::new();
+    let mut required = alloc::vec::Vec::new();
+    for (name, property) in properties.into_iter().flatten() {
+        map.insert(name.into(), property.to_value());
+        required.push(serde_json::Value::from(name));
+    }
+    schema.insert("title".into(), title.into());
+    schema.insert("type".into(), "object".into());
+    schema.insert("properties".into(), map.into());
+    schema.insert("required".into(), required.into());
+    schema.insert("additionalProperties".into(), false.into());
+    schema
+}
+
+/// Gives the signed integer of the same size, used by the `signed` fields.
+#[doc(hidden)]
+pub trait ToSigned {
+    type Signed;
+}
+
+macro_rules! impl_to_signed {
+    ($($t:ty => $signed:ty),*) => {
+        $(impl ToSigned for $t {
+            type Signed = $signed;
+        })*
+    };
+}
+
+impl_to_signed! {
+    u8 => i8, u16 => i16, u32 => i32, u64 => i64, u128 => i128,
+    i8 => i8, i16 => i16, i32 => i32, i64 => i64, i128 => i128
+}
+
+/// Gives the value of a field compared by `impl Values{...}`, which is `None` for a `valid_if` field
+/// whose bit is clear.
+#[doc(hidden)]
+pub trait NamedValue {
+    type Value;
+    fn named_value(self) -> Option<Self::Value>;
+}
+
+impl<T> NamedValue for Option<T> {
+    type Value = T;
+    fn named_value(self) -> Option<T> {
+        self
+    }
+}
+
+macro_rules! impl_named_value {
+    ($($t:ty),*) => {
+        $(impl NamedValue for $t {
+            type Value = $t;
+            fn named_value(self) -> Option<$t> {
+                Some(self)
+            }
+        })*
+    };
+}
+
+impl_named_value! {u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize}
+
+/// A trait to get ranges of bits.
+///
+/// When `T` is a signed integer, the implementations of this crate sign-extend the value from
+/// `msb`.
+pub trait BitRange<T> {
+    /// Get a range of bits.
+    fn bit_range(&self, msb: usize, lsb: usize) -> T;
+}
+
+/// A trait to set ranges of bits.
+pub trait BitRangeMut<T> {
+    /// Set a range of bits.
+    fn set_bit_range(&mut self, msb: usize, lsb: usize, value: T);
+}
+
+/// The bit numbering of a type, used by the fields with a byte order (`be` or `le`).
 ///
-/// The second optional element is a set of lines of the form `impl <Trait>;`. The following traits are supported:
-/// * `Debug`; This will generate an implementation of `fmt::Debug` with the `bitfield_debug` macro.
-/// * `BitAnd`, `BitOr`, `BitXor`; These will generate implementations of the relevant `ops::Bit___` and `ops::Bit___Assign` traits.
-/// * `new`; This will generate a constructor that calls all of the bitfield's setter methods with an argument of the appropriate type
-/// * `new{constructor_name(setter_name: setter_type, ...)}`; This will generate a constructor that calls a given subset of the bitfield's setter methods
+/// `bitfield_bitrange!` implements this trait for the slice-backed structs.
+pub trait BitOrder {
+    /// Whether the bit 0 is the most significant bit of the first element.
+    const MSB0: bool;
+}
+
+/// A trait to get a single bit.
 ///
-/// The difference with calling those macros separately is that `bitfield_fields` is called
-/// from an appropriate `impl` block. If you use the non-slice form of `bitfield_bitrange`, the
-/// default type for `bitfield_fields` will be set to the wrapped fields.
+/// This trait is implemented for all type that implement `BitRange<u8>`.
+pub trait Bit {
+    /// Get a single bit.
+    fn bit(&self, bit: usize) -> bool;
+}
+
+/// A trait to set a single bit.
 ///
-/// See the documentation of these macros for more information on their respective syntax.
+/// This trait is implemented for all type that implement `BitRangeMut<u8>`.
+pub trait BitMut {
+    /// Set a single bit.
+    fn set_bit(&mut self, bit: usize, value: bool);
+}
+
+impl<T: BitRange<u8>> Bit for T {
+    fn bit(&self, bit: usize) -> bool {
+        self.bit_range(bit, bit) != 0
+    }
+}
+
+impl<T: BitRangeMut<u8>> BitMut for T {
+    fn set_bit(&mut self, bit: usize, value: bool) {
+        self.set_bit_range(bit, bit, value as u8);
+    }
+}
+
+macro_rules! impl_bitrange_for_u {
+    ($t:ty, $bitrange_ty:ty) => {
+        impl BitRange<$bitrange_ty> for $t {
+            #[inline]
+            #[allow(clippy::cast_lossless)]
+            #[allow(clippy::manual_bits)]
+            fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
+                let bit_len = size_of::<$t>()*8;
+                let result_bit_len = size_of::<$bitrange_ty>()*8;
+                let result = ((*self << (bit_len - msb - 1)) >> (bit_len - msb - 1 + lsb))
+                    as $bitrange_ty;
+                result << (result_bit_len - (msb - lsb + 1)) >> (result_bit_len - (msb - lsb + 1))
+            }
+        }
+
+        impl BitRangeMut<$bitrange_ty> for $t {
+            #[inline]
+            #[allow(clippy::cast_lossless)]
+            #[allow(clippy::manual_bits)]
+            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
+                let bit_len = size_of::<$t>()*8;
+                let mask: $t = !(0 as $t)
+                    << (bit_len - msb - 1)
+                    >> (bit_len - msb - 1 + lsb)
+                    << (lsb);
+                *self &= !mask;
+                *self |= (value as $t << lsb) & mask;
+            }
+        }
+    }
+}
+
+macro_rules! impl_bitrange_for_u_combinations {
+((),($($bitrange_ty:ty),*)) => {
+
+};
+(($t:ty),($($bitrange_ty:ty),*)) => {
+        $(impl_bitrange_for_u!{$t, $bitrange_ty})*
+};
+    (($t_head:ty, $($t_rest:ty),*),($($bitrange_ty:ty),*)) => {
+        impl_bitrange_for_u_combinations!{($t_head), ($($bitrange_ty),*)}
+        impl_bitrange_for_u_combinations!{($($t_rest),*), ($($bitrange_ty),*)}
+    };
+}
+
+impl_bitrange_for_u_combinations! {(u8, u16, u32, u64, u128), (u8, u16, u32, u64, u128)}
+impl_bitrange_for_u_combinations! {(u8, u16, u32, u64, u128), (i8, i16, i32, i64, i128)}
+
+/// A storage made of several byte buffers, accessed as if they were concatenated.
 ///
-/// # Example
+/// `S` dereferences to a slice of buffers, like `&[&[u8]]`, `&mut [&mut [u8]]`, `Vec<Vec<u8>>` or
+/// `&[IoSlice]`, so that a layout can be read from chained buffers without copying them into a
+/// contiguous one. The bits are numbered like for the `[u8]` storage: the bit `i` is the bit
+/// `i % 8` of the byte `i / 8` of the concatenation.
 ///
-/// ```rust
-/// # #[macro_use] extern crate bitfield;
-/// # fn main() {}
-/// bitfield!{
-///   pub struct BitField1(u16);
-///   impl Debug;
-///   // The fields default to u16
-///   field1, set_field1: 10, 0;
-///   pub field2, _ : 12, 3;
-/// }
-/// ```
+/// `BitRange` and `BitRangeMut` are implemented for all the integer types, and the `Segments`
+/// storage of `bitfield!` wraps this type.
 ///
-/// or with a custom `BitRange` and `BitRangeMut` implementation :
-/// ```rust
-/// # #[macro_use] extern crate bitfield;
-/// # use bitfield::{BitRange, BitRangeMut};
-/// # fn main() {}
-/// bitfield!{
-///   pub struct BitField1(u16);
-///   no default BitRange;
-///   impl Debug;
-///   impl BitAnd;
-///   u8;
-///   field1, set_field1: 10, 0;
-///   pub field2, _ : 12, 3;
-/// }
-/// impl BitRange<u8> for BitField1 {
-///     fn bit_range(&self, msb: usize, lsb: usize) -> u8 {
-///         let width = msb - lsb + 1;
-///         let mask = (1 << width) - 1;
-///         ((self.0 >> lsb) & mask) as u8
-///     }
-/// }
-/// impl BitRangeMut<u8> for BitField1 {
-///     fn set_bit_range(&mut self, msb: usize, lsb: usize, value: u8) {
-///         self.0 = (value as u16) << lsb;
-///     }
-/// }
-/// ```
+/// # Panics
+///
+/// Accessing bits past the end of the last buffer panics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Segments<S>(pub S);
+
+impl<S: ops::Deref<Target = [B]>, B: ops::Deref<Target = [u8]>> Segments<S> {
+    /// Returns the number of bytes of all the buffers.
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|buffer| buffer.len()).sum()
+    }
+
+    /// Returns `true` if all the buffers are empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|buffer| buffer.is_empty())
+    }
+
+    // Returns the bits `lsb..=msb` in the least significant bits of the result, reading a byte at
+    // a time.
+    fn bits(&self, msb: usize, lsb: usize) -> u128 {
+        let mut value = 0;
+        let mut bit = lsb;
+        let mut start = 0;
+        for buffer in self.0.iter() {
+            let end = start + buffer.len() * 8;
+            while bit <= msb && bit < end {
+                let count = cmp::min(8 - bit % 8, msb + 1 - bit);
+                let byte = u128::from(buffer[(bit - start) / 8] >> (bit % 8));
+                value |= (byte & ((1 << count) - 1)) << (bit - lsb);
+                bit += count;
+            }
+            if bit > msb {
+                return value;
+            }
+            start = end;
+        }
+        panic!("the bit {} is out of the segments", bit);
+    }
+}
+
+impl<S: ops::DerefMut<Target = [B]>, B: ops::DerefMut<Target = [u8]>> Segments<S> {
+    // Sets the bits `lsb..=msb` to the least significant bits of `value`.
+    fn set_bits(&mut self, msb: usize, lsb: usize, value: u128) {
+        let mut bit = lsb;
+        let mut start = 0;
+        for buffer in self.0.iter_mut() {
+            let end = start + buffer.len() * 8;
+            while bit <= msb && bit < end {
+                let count = cmp::min(8 - bit % 8, msb + 1 - bit);
+                let mask = (((1u16 << count) - 1) << (bit % 8)) as u8;
+                let bits = ((value >> (bit - lsb)) as u8) << (bit % 8);
+                let byte = &mut buffer[(bit - start) / 8];
+                *byte = (*byte & !mask) | (bits & mask);
+                bit += count;
+            }
+            if bit > msb {
+                return;
+            }
+            start = end;
+        }
+        panic!("the bit {} is out of the segments", bit);
+    }
+}
+
+macro_rules! impl_bitrange_for_segments {
+    ($($bitrange_ty:ty),*) => {
+        $(
+            impl<S: ops::Deref<Target = [B]>, B: ops::Deref<Target = [u8]>> BitRange<$bitrange_ty> for Segments<S> {
+                #[allow(clippy::manual_bits)]
+                fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
+                    let value_bit_len = size_of::<$bitrange_ty>() * 8;
+                    let value = self.bits(msb, lsb) as $bitrange_ty;
+                    value << (value_bit_len - (msb - lsb + 1)) >> (value_bit_len - (msb - lsb + 1))
+                }
+            }
+
+            impl<S: ops::DerefMut<Target = [B]>, B: ops::DerefMut<Target = [u8]>> BitRangeMut<$bitrange_ty> for Segments<S> {
+                #[allow(clippy::cast_lossless)]
+                fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
+                    self.set_bits(msb, lsb, value as u128);
+                }
+            }
+        )*
+    };
+}
+
+impl_bitrange_for_segments! {u8, u16, u32, u64, u128, i8, i16, i32, i64, i128}
+
+/// A storage read and written a word at a time, like memory-mapped or bus-proxied registers.
+///
+/// The `BitStorage` storage of `bitfield!` and `bitfield_bitrange!` wraps a type implementing this
+/// trait. The bit `i` is the bit `i % bits` of the word `i / bits`, where `bits` is the width of
+/// `Word`. Accessing a field reads each of the words holding it once, and setting a field reads
+/// and writes them once.
+///
+/// It is implemented for the references to the types implementing it.
+pub trait BitStorage {
+    /// The type of the words, an unsigned integer.
+    type Word: Copy + BitRange<u128> + BitRangeMut<u128>;
+
+    /// Reads the word at `index`.
+    fn read_word(&self, index: usize) -> Self::Word;
+}
+
+/// A `BitStorage` that can be written.
+pub trait BitStorageMut: BitStorage {
+    /// Writes the word at `index`.
+    fn write_word(&mut self, index: usize, word: Self::Word);
+}
+
+impl<S: BitStorage + ?Sized> BitStorage for &S {
+    type Word = S::Word;
+
+    fn read_word(&self, index: usize) -> S::Word {
+        (**self).read_word(index)
+    }
+}
+
+impl<S: BitStorage + ?Sized> BitStorage for &mut S {
+    type Word = S::Word;
+
+    fn read_word(&self, index: usize) -> S::Word {
+        (**self).read_word(index)
+    }
+}
+
+impl<S: BitStorageMut + ?Sized> BitStorageMut for &mut S {
+    fn write_word(&mut self, index: usize, word: S::Word) {
+        (**self).write_word(index, word);
+    }
+}
+
+/// Returns the bits `lsb..=msb` of a `BitStorage` in the least significant bits of the result.
+#[doc(hidden)]
+pub fn read_storage_bits<S: BitStorage + ?Sized>(storage: &S, msb: usize, lsb: usize) -> u128 {
+    let bits = size_of::<S::Word>() * 8;
+    let mut value = 0;
+    let mut bit = lsb;
+    while bit <= msb {
+        let start = bit % bits;
+        let end = cmp::min(bits - 1, start + (msb - bit));
+        let word: u128 = storage.read_word(bit / bits).bit_range(end, start);
+        value |= word << (bit - lsb);
+        bit += end - start + 1;
+    }
+    value
+}
+
+/// Sets the bits `lsb..=msb` of a `BitStorage` to the least significant bits of `value`.
+#[doc(hidden)]
+pub fn write_storage_bits<S: BitStorageMut + ?Sized>(
+    storage: &mut S,
+    msb: usize,
+    lsb: usize,
+    value: u128,
+) {
+    let bits = size_of::<S::Word>() * 8;
+    let mut bit = lsb;
+    while bit <= msb {
+        let start = bit % bits;
+        let end = cmp::min(bits - 1, start + (msb - bit));
+        let mut word = storage.read_word(bit / bits);
+        word.set_bit_range(end, start, value >> (bit - lsb));
+        storage.write_word(bit / bits, word);
+        bit += end - start + 1;
+    }
+}
+
+// Generates the serde implementations of `impl Serde;`.
+#[cfg(feature = "serde")]
 #[macro_export(local_inner_macros)]
-macro_rules! bitfield {
-    // Force `impl <Trait>` to always be after `no default BitRange` it the two are present.
-    // This simplify the rest of the macro.
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident($($type:tt)*); $(impl $trait:ident$({$($trait_arg:tt)*})?;)+ no default BitRange; $($rest:tt)*) => {
-         bitfield!{$(#[$attribute])* $vis struct $name($($type)*); no default BitRange; $(impl $trait$({$($trait_arg)*})?;)* $($rest)*}
-     };
+#[doc(hidden)]
+macro_rules! __bitfield_serde {
+    (@serde $name:ident([$t:ty]) $records:tt) => {
+        impl<T: AsRef<[$t]>> $crate::serde::Serialize for $name<T> {
+            __bitfield_serde!{@serialize $name $records}
+        }
+
+        impl<'de, T: AsMut<[$t]> + Default> $crate::serde::Deserialize<'de> for $name<T> {
+            fn deserialize<D: $crate::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor<T>($crate::marker::PhantomData<T>);
 
-    // If we have `impl <Trait>` without `no default BitRange`, we will still match, because when
-    // we call `bitfield_bitrange`, we add `no default BitRange`.
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident([$t:ty]); no default BitRange; impl $trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
-        bitfield_impl!{$trait$({$($trait_arg)*})? for struct $name([$t]); $($rest)*}
+                impl<'de, T: AsMut<[$t]> + Default> $crate::serde::de::Visitor<'de> for Visitor<T> {
+                    type Value = $name<T>;
+                    __bitfield_serde!{@visit $name(T::default()) $records}
+                }
 
-        bitfield!{$(#[$attribute])* $vis struct $name([$t]); no default BitRange;  $($rest)*}
+                __bitfield_serde!{@deserialize deserializer, $name, Visitor($crate::marker::PhantomData), $records}
+            }
+        }
     };
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident([$t:ty]); no default BitRange; $($rest:tt)*) => {
-        $(#[$attribute])*
-        $vis struct $name<T>(pub T);
-
-        //impl<T: AsMut<[$t]> + AsRef<[$t]>> $name<T> {
-        //    bitfield_fields!{$($rest)*}
-        //}
-        impl<T: AsRef<[$t]>> $name<T> {
-           bitfield_fields!{only getter; $($rest)*}
+    (@serde $name:ident($t:ty) $records:tt) => {
+        impl $crate::serde::Serialize for $name {
+            __bitfield_serde!{@serialize $name $records}
         }
-        impl<T: AsMut<[$t]>> $name<T> {
-           bitfield_fields!{only setter; $($rest)*}
+
+        impl<'de> $crate::serde::Deserialize<'de> for $name {
+            fn deserialize<D: $crate::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor;
+
+                impl<'de> $crate::serde::de::Visitor<'de> for Visitor {
+                    type Value = $name;
+                    __bitfield_serde!{@visit $name(<$t as Default>::default()) $records}
+                }
+
+                __bitfield_serde!{@deserialize deserializer, $name, Visitor, $records}
+            }
         }
     };
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident([$t:ty]); $($rest:tt)*) => {
-        bitfield_bitrange!(struct $name([$t]));
-        bitfield!{$(#[$attribute])* $vis struct $name([$t]); no default BitRange; $($rest)*}
+    (@serde_raw $name:ident([$t:ty])) => {
+        impl<T: $crate::serde::Serialize> $crate::serde::Serialize for $name<T> {
+            fn serialize<S: $crate::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                $crate::serde::Serialize::serialize(&self.0, serializer)
+            }
+        }
+
+        impl<'de, T: $crate::serde::Deserialize<'de>> $crate::serde::Deserialize<'de> for $name<T> {
+            fn deserialize<D: $crate::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <T as $crate::serde::Deserialize<'de>>::deserialize(deserializer).map($name)
+            }
+        }
     };
+    (@serde_raw $name:ident($t:ty)) => {
+        impl $crate::serde::Serialize for $name {
+            fn serialize<S: $crate::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                $crate::serde::Serialize::serialize(&self.0, serializer)
+            }
+        }
 
-    // The only difference between the MSB0 version anf the non-MSB0 version, is the BitRange
-    // implementation. We delegate everything else to the non-MSB0 version of the macro.
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident(MSB0 [$t:ty]); no default BitRange; $($rest:tt)*) => {
-        bitfield!{$(#[$attribute])* $vis struct $name([$t]); no default BitRange; $($rest)*}
+        impl<'de> $crate::serde::Deserialize<'de> for $name {
+            fn deserialize<D: $crate::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <$t as $crate::serde::Deserialize<'de>>::deserialize(deserializer).map($name)
+            }
+        }
     };
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident(MSB0 [$t:ty]); $($rest:tt)*) => {
-        bitfield_bitrange!(struct $name(MSB0 [$t]));
-        bitfield!{$(#[$attribute])* $vis struct $name([$t]); no default BitRange; $($rest)*}
+    (@serialize $name:ident [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter [$getter:tt] setter $_setter:tt pos $pos:tt $($_rest:tt)*
+    })*]) => {
+        fn serialize<S: $crate::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            #[allow(unused_mut)]
+            let mut len = 0;
+            $(
+                $(#[$cfg])*
+                __bitfield_serde!{@count len, $getter}
+            )*
+            let mut state = $crate::serde::Serializer::serialize_struct(serializer, __bitfield_stringify!($name), len)?;
+            $(
+                $(#[$cfg])*
+                __bitfield_serde!{@serialize_field self, state, $getter, $pos}
+            )*
+            $crate::serde::ser::SerializeStruct::end(state)
+        }
+    };
+    (@count $len:ident, _) => {};
+    (@count $len:ident, $getter:ident) => {
+        $len += 1;
+    };
+    (@serialize_field $self:ident, $state:ident, _, $pos:tt) => {};
+    (@serialize_field $self:ident, $state:ident, $getter:ident, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        let mut array = [$self.$getter(0); $count];
+        for (i, e) in (&mut array).into_iter().enumerate() {
+            *e = $self.$getter(i);
+        }
+        $crate::serde::ser::SerializeStruct::serialize_field(&mut $state, __bitfield_stringify!($getter), &array)?;
     };
+    (@serialize_field $self:ident, $state:ident, $getter:ident, $pos:tt) => {
+        $crate::serde::ser::SerializeStruct::serialize_field(&mut $state, __bitfield_stringify!($getter), &$self.$getter())?;
+    };
+    (@field_names [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from $_from:tt
+        into $_into:tt name [$field:ident] $($_rest:tt)*
+    })*]) => {
+        &[$($(#[$cfg])* __bitfield_stringify!($field),)*]
+    };
+    (@deserialize $deserializer:ident, $name:ident, $visitor:expr, $records:tt) => {
+        $crate::serde::Deserializer::deserialize_struct(
+            $deserializer,
+            __bitfield_stringify!($name),
+            __bitfield_serde!{@field_names $records},
+            $visitor,
+        )
+    };
+    (@visit $name:ident($storage:expr) [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty $_t:tt mask $_mask:tt from [$from:ty]
+        into $_into:tt name [$field:ident] getter [$getter:tt] setter [$setter:tt] pos $pos:tt $($_rest:tt)*
+    })*]) => {
+        fn expecting(&self, f: &mut $crate::fmt::Formatter) -> $crate::fmt::Result {
+            f.write_str(__bitfield_concat!("struct ", __bitfield_stringify!($name)))
+        }
 
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident($t:ty); no default BitRange; impl $trait:ident$({$($trait_arg:tt)*})?; $($rest:tt)*) => {
-        bitfield_impl!{$trait$({$($trait_arg)*})? for struct $name($t); $($rest)*}
+        fn visit_map<A: $crate::serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut value = $name($storage);
+            let names: &'static [&'static str] = __bitfield_serde!{@field_names [$({
+                attrs [] cfgs [$(#[$cfg])*] vis [] ty [] mask [] from [] into [] name [$field]
+            })*]};
+            while let Some(key) = map.next_key_seed($crate::SerdeFieldName(names))? {
+                $(
+                    $(#[$cfg])*
+                    __bitfield_serde!{@visit_map_field value, map, key, $field, $setter, $from, $pos}
+                )*
+                map.next_value::<$crate::serde::de::IgnoredAny>()?;
+            }
+            Ok(value)
+        }
 
-        bitfield!{$(#[$attribute])* $vis struct $name($t); no default BitRange; $($rest)*}
+        fn visit_seq<A: $crate::serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut value = $name($storage);
+            #[allow(unused_mut, unused_variables)]
+            let mut index = 0;
+            $(
+                $(#[$cfg])*
+                __bitfield_serde!{@visit_seq_field self, value, seq, index, $getter, $setter, $from, $pos}
+            )*
+            Ok(value)
+        }
     };
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident($t:ty); no default BitRange; $($rest:tt)*) => {
-        $(#[$attribute])*
-        $vis struct $name(pub $t);
-
-        impl $name {
-            bitfield_fields!{$t; $($rest)*}
-         }
+    (@visit_map_field $value:ident, $map:ident, $key:ident, $field:ident, _, $from:ty, $pos:tt) => {};
+    (@visit_map_field $value:ident, $map:ident, $key:ident, $field:ident, $setter:ident, $from:ty,
+     [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        if $key == Some(__bitfield_stringify!($field)) {
+            let array: [$from; $count] = $map.next_value()?;
+            for (i, e) in IntoIterator::into_iter(array).enumerate() {
+                $value.$setter(i, e);
+            }
+            continue;
+        }
     };
-    ($(#[$attribute:meta])* $vis:vis struct $name:ident($t:ty); $($rest:tt)*) => {
-        bitfield_bitrange!(struct $name($t));
-        bitfield!{$(#[$attribute])* $vis struct $name($t); no default BitRange; $($rest)*}
+    (@visit_map_field $value:ident, $map:ident, $key:ident, $field:ident, $setter:ident, $from:ty, [$bit:expr]) => {
+        __bitfield_serde!{@visit_map_field $value, $map, $key, $field, $setter, bool, []}
+    };
+    (@visit_map_field $value:ident, $map:ident, $key:ident, $field:ident, $setter:ident, $from:ty, $pos:tt) => {
+        if $key == Some(__bitfield_stringify!($field)) {
+            $value.$setter($map.next_value::<$from>()?);
+            continue;
+        }
+    };
+    (@visit_seq_field $self:ident, $value:ident, $seq:ident, $index:ident, _, $setter:tt, $from:ty, $pos:tt) => {};
+    (@visit_seq_field $self:ident, $value:ident, $seq:ident, $index:ident, $getter:ident, _, $from:ty, $pos:tt) => {
+        $seq.next_element::<$crate::serde::de::IgnoredAny>()?
+            .ok_or_else(|| $crate::serde::de::Error::invalid_length($index, &$self))?;
+        $index += 1;
+    };
+    (@visit_seq_field $self:ident, $value:ident, $seq:ident, $index:ident, $getter:ident, $setter:ident, $from:ty,
+     [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        let array: [$from; $count] = $seq.next_element()?
+            .ok_or_else(|| $crate::serde::de::Error::invalid_length($index, &$self))?;
+        for (i, e) in IntoIterator::into_iter(array).enumerate() {
+            $value.$setter(i, e);
+        }
+        $index += 1;
+    };
+    (@visit_seq_field $self:ident, $value:ident, $seq:ident, $index:ident, $getter:ident, $setter:ident, $from:ty,
+     [$bit:expr]) => {
+        __bitfield_serde!{@visit_seq_field $self, $value, $seq, $index, $getter, $setter, bool, []}
+    };
+    (@visit_seq_field $self:ident, $value:ident, $seq:ident, $index:ident, $getter:ident, $setter:ident, $from:ty,
+     $pos:tt) => {
+        let element: $from = $seq.next_element()?
+            .ok_or_else(|| $crate::serde::de::Error::invalid_length($index, &$self))?;
+        $value.$setter(element);
+        $index += 1;
     };
 }
 
+#[cfg(not(feature = "serde"))]
+#[macro_export]
 #[doc(hidden)]
-pub use core::convert::Into;
-#[doc(hidden)]
-pub use core::fmt;
-#[doc(hidden)]
-pub use core::mem::size_of;
+macro_rules! __bitfield_serde {
+    ($($_tokens:tt)*) => {
+        compile_error!(
+            "`impl Serde;` and `impl SerdeRaw;` require the `serde` feature of the bitfield crate"
+        );
+    };
+}
+
+// Generates the implementation of `impl JsonSchema;`.
+#[cfg(feature = "schemars")]
+#[macro_export(local_inner_macros)]
 #[doc(hidden)]
-pub use core::ops;
+macro_rules! __bitfield_schemars {
+    ($name:ident([$t:ty]) $records:tt) => {
+        impl<T> $crate::schemars::JsonSchema for $name<T> {
+            __bitfield_schemars!{@json_schema $name $records}
+        }
+    };
+    ($name:ident($t:ty) $records:tt) => {
+        impl $crate::schemars::JsonSchema for $name {
+            __bitfield_schemars!{@json_schema $name $records}
+        }
+    };
+    (@json_schema $name:ident [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty [$t:ty] mask $_mask:tt from $_from:tt
+        into [$into:ty] name $_name:tt getter [$getter:tt] setter $_setter:tt pos $pos:tt $($_rest:tt)*
+    })*]) => {
+        fn schema_name() -> $crate::alloc::borrow::Cow<'static, str> {
+            $crate::alloc::borrow::Cow::Borrowed(__bitfield_stringify!($name))
+        }
 
-/// A trait to get ranges of bits.
-pub trait BitRange<T> {
-    /// Get a range of bits.
-    fn bit_range(&self, msb: usize, lsb: usize) -> T;
+        fn json_schema(generator: &mut $crate::schemars::SchemaGenerator) -> $crate::schemars::Schema {
+            $crate::json_schema_object(__bitfield_stringify!($name), [
+                $(
+                    $(#[$cfg])*
+                    __bitfield_schemars!{@property generator, $t, $into, $getter, $pos},
+                )*
+            ])
+        }
+    };
+    (@property $generator:ident, $t:ty, $into:ty, _, $pos:tt) => {
+        None
+    };
+    (@property $generator:ident, $t:ty, $into:ty, $getter:ident, $pos:tt) => {
+        Some((
+            __bitfield_stringify!($getter),
+            __bitfield_schemars!{@field_schema $generator, $t, $into, $pos},
+        ))
+    };
+    (@field_schema $generator:ident, $t:ty, $into:ty, [$bit:expr]) => {
+        $generator.subschema_for::<bool>()
+    };
+    (@field_schema $generator:ident, $t:ty, $into:ty, [$msb:expr, $lsb:expr]) => {
+        $crate::json_schema_field($generator.subschema_for::<$into>(), $msb - $lsb + 1, <$t>::MIN != 0, None)
+    };
+    (@field_schema $generator:ident, $t:ty, $into:ty, [$msb:expr, $lsb:expr, $count:expr $(, $_stride:expr)?]) => {
+        $crate::json_schema_field($generator.subschema_for::<$into>(), $msb - $lsb + 1, <$t>::MIN != 0, Some($count))
+    };
 }
 
-/// A trait to set ranges of bits.
-pub trait BitRangeMut<T> {
-    /// Set a range of bits.
-    fn set_bit_range(&mut self, msb: usize, lsb: usize, value: T);
+#[cfg(not(feature = "schemars"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_schemars {
+    ($($_tokens:tt)*) => {
+        compile_error!("`impl JsonSchema;` requires the `schemars` feature of the bitfield crate");
+    };
 }
 
-/// A trait to get a single bit.
-///
-/// This trait is implemented for all type that implement `BitRange<u8>`.
-pub trait Bit {
-    /// Get a single bit.
-    fn bit(&self, bit: usize) -> bool;
+// Generates the implementation of `impl Arbitrary;`.
+#[cfg(feature = "arbitrary")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_arbitrary {
+    ($name:ident([$t:ty])) => {
+        impl<'a, T: $crate::arbitrary::Arbitrary<'a>> $crate::arbitrary::Arbitrary<'a>
+            for $name<T>
+        {
+            fn arbitrary(
+                u: &mut $crate::arbitrary::Unstructured<'a>,
+            ) -> $crate::arbitrary::Result<Self> {
+                <T as $crate::arbitrary::Arbitrary<'a>>::arbitrary(u).map($name)
+            }
+
+            fn size_hint(depth: usize) -> (usize, Option<usize>) {
+                <T as $crate::arbitrary::Arbitrary<'a>>::size_hint(depth)
+            }
+        }
+    };
+    ($name:ident($t:ty)) => {
+        impl<'a> $crate::arbitrary::Arbitrary<'a> for $name {
+            fn arbitrary(
+                u: &mut $crate::arbitrary::Unstructured<'a>,
+            ) -> $crate::arbitrary::Result<Self> {
+                <$t as $crate::arbitrary::Arbitrary<'a>>::arbitrary(u).map($name)
+            }
+
+            fn size_hint(depth: usize) -> (usize, Option<usize>) {
+                <$t as $crate::arbitrary::Arbitrary<'a>>::size_hint(depth)
+            }
+        }
+    };
 }
 
-/// A trait to set a single bit.
-///
-/// This trait is implemented for all type that implement `BitRangeMut<u8>`.
-pub trait BitMut {
-    /// Set a single bit.
-    fn set_bit(&mut self, bit: usize, value: bool);
+#[cfg(not(feature = "arbitrary"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_arbitrary {
+    ($($_tokens:tt)*) => {
+        compile_error!("`impl Arbitrary;` requires the `arbitrary` feature of the bitfield crate");
+    };
 }
 
-impl<T: BitRange<u8>> Bit for T {
-    fn bit(&self, bit: usize) -> bool {
-        self.bit_range(bit, bit) != 0
-    }
+// Checks that the `zerocopy` feature is enabled, the implementations are derived by `bitfield!`.
+#[cfg(feature = "zerocopy")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_zerocopy {
+    ($trait:ident) => {};
 }
 
-impl<T: BitRangeMut<u8>> BitMut for T {
-    fn set_bit(&mut self, bit: usize, value: bool) {
-        self.set_bit_range(bit, bit, value as u8);
-    }
+#[cfg(not(feature = "zerocopy"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_zerocopy {
+    ($trait:ident) => {
+        compile_error!(concat!(
+            "`impl ",
+            stringify!($trait),
+            ";` requires the `zerocopy` feature of the bitfield crate"
+        ));
+    };
 }
 
-macro_rules! impl_bitrange_for_u {
-    ($t:ty, $bitrange_ty:ty) => {
-        impl BitRange<$bitrange_ty> for $t {
-            #[inline]
-            #[allow(clippy::cast_lossless)]
-            #[allow(clippy::manual_bits)]
-            fn bit_range(&self, msb: usize, lsb: usize) -> $bitrange_ty {
-                let bit_len = size_of::<$t>()*8;
-                let result_bit_len = size_of::<$bitrange_ty>()*8;
-                let result = ((*self << (bit_len - msb - 1)) >> (bit_len - msb - 1 + lsb))
-                    as $bitrange_ty;
-                result << (result_bit_len - (msb - lsb + 1)) >> (result_bit_len - (msb - lsb + 1))
+// Generates the implementations of `impl Distribution;`.
+#[cfg(feature = "rand")]
+#[macro_export(local_inner_macros)]
+#[doc(hidden)]
+macro_rules! __bitfield_rand {
+    (@storage $name:ident([$t:ty])) => {
+        impl<T> $crate::rand::distributions::Distribution<$name<T>> for $crate::rand::distributions::Standard
+        where
+            $crate::rand::distributions::Standard: $crate::rand::distributions::Distribution<T>,
+        {
+            fn sample<R: $crate::rand::Rng + ?Sized>(&self, rng: &mut R) -> $name<T> {
+                $name(rng.gen())
             }
         }
-
-        impl BitRangeMut<$bitrange_ty> for $t {
-            #[inline]
-            #[allow(clippy::cast_lossless)]
-            #[allow(clippy::manual_bits)]
-            fn set_bit_range(&mut self, msb: usize, lsb: usize, value: $bitrange_ty) {
-                let bit_len = size_of::<$t>()*8;
-                let mask: $t = !(0 as $t)
-                    << (bit_len - msb - 1)
-                    >> (bit_len - msb - 1 + lsb)
-                    << (lsb);
-                *self &= !mask;
-                *self |= (value as $t << lsb) & mask;
+    };
+    (@storage $name:ident($t:ty)) => {
+        impl $crate::rand::distributions::Distribution<$name> for $crate::rand::distributions::Standard {
+            fn sample<R: $crate::rand::Rng + ?Sized>(&self, rng: &mut R) -> $name {
+                $name(rng.gen())
             }
         }
-    }
+    };
+    (@fields $name:ident([$t:ty]) $records:tt) => {
+        impl<T: AsMut<[$t]> + Default> $crate::rand::distributions::Distribution<$name<T>>
+            for $crate::rand::distributions::Standard
+        {
+            fn sample<R: $crate::rand::Rng + ?Sized>(&self, rng: &mut R) -> $name<T> {
+                let mut value = $name(T::default());
+                __bitfield_rand!{@randomize value, rng, $records}
+                value
+            }
+        }
+    };
+    (@fields $name:ident($t:ty) $records:tt) => {
+        impl $crate::rand::distributions::Distribution<$name> for $crate::rand::distributions::Standard {
+            fn sample<R: $crate::rand::Rng + ?Sized>(&self, rng: &mut R) -> $name {
+                let mut value = $name(<$t as Default>::default());
+                __bitfield_rand!{@randomize value, rng, $records}
+                value
+            }
+        }
+    };
+    (@randomize $value:ident, $rng:ident, [$({
+        attrs $_attrs:tt cfgs [$(#[$cfg:meta])*] vis $_vis:tt ty [$t:ty] mask $_mask:tt from $_from:tt
+        into $_into:tt name $_name:tt getter $_getter:tt setter $_setter:tt pos $pos:tt $($_rest:tt)*
+    })*]) => {
+        $(
+            $(#[$cfg])*
+            __bitfield_rand!{@randomize_field $value, $rng, $t, $pos}
+        )*
+    };
+    (@randomize_field $value:ident, $rng:ident, $t:ty, [$bit:expr]) => {
+        $crate::BitMut::set_bit(&mut $value, $bit, $rng.gen());
+    };
+    (@randomize_field $value:ident, $rng:ident, $t:ty, [$msb:expr, $lsb:expr]) => {
+        $crate::BitRangeMut::<$t>::set_bit_range(&mut $value, $msb, $lsb, $rng.gen());
+    };
+    (@randomize_field $value:ident, $rng:ident, $t:ty, [$msb:expr, $lsb:expr, $count:expr $(, $stride:expr)?]) => {
+        let width = $msb - $lsb + 1;
+        for index in 0..$count {
+            let lsb = $lsb + index * bitfield_fields!(@stride width $(, $stride)?);
+            $crate::BitRangeMut::<$t>::set_bit_range(&mut $value, lsb + width - 1, lsb, $rng.gen());
+        }
+    };
 }
 
-macro_rules! impl_bitrange_for_u_combinations {
-((),($($bitrange_ty:ty),*)) => {
+#[cfg(not(feature = "rand"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_rand {
+    ($($_tokens:tt)*) => {
+        compile_error!("`impl Distribution;` requires the `rand` feature of the bitfield crate");
+    };
+}
 
-};
-(($t:ty),($($bitrange_ty:ty),*)) => {
-        $(impl_bitrange_for_u!{$t, $bitrange_ty})*
-};
-    (($t_head:ty, $($t_rest:ty),*),($($bitrange_ty:ty),*)) => {
-        impl_bitrange_for_u_combinations!{($t_head), ($($bitrange_ty),*)}
-        impl_bitrange_for_u_combinations!{($($t_rest),*), ($($bitrange_ty),*)}
+// Generates the implementations of `impl Pod;`, the struct is `#[repr(transparent)]`.
+#[cfg(feature = "bytemuck")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_bytemuck {
+    ($name:ident([$t:ty])) => {
+        unsafe impl<T: $crate::bytemuck::Zeroable> $crate::bytemuck::Zeroable for $name<T> {}
+        unsafe impl<T: $crate::bytemuck::Pod> $crate::bytemuck::Pod for $name<T> {}
+    };
+    ($name:ident($t:ty)) => {
+        const _: () = {
+            $crate::__bitfield_assert!(
+                $crate::mem::size_of::<$name>() == $crate::mem::size_of::<$t>()
+            );
+            $crate::__bitfield_assert!(
+                $crate::mem::align_of::<$name>() == $crate::mem::align_of::<$t>()
+            );
+        };
+        unsafe impl $crate::bytemuck::Zeroable for $name {}
+        unsafe impl $crate::bytemuck::Pod for $name {}
     };
 }
 
-impl_bitrange_for_u_combinations! {(u8, u16, u32, u64, u128), (u8, u16, u32, u64, u128)}
-impl_bitrange_for_u_combinations! {(u8, u16, u32, u64, u128), (i8, i16, i32, i64, i128)}
+#[cfg(not(feature = "bytemuck"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_bytemuck {
+    ($($_tokens:tt)*) => {
+        compile_error!("`impl Pod;` requires the `bytemuck` feature of the bitfield crate");
+    };
+}
+
+// Same as std::concat but callable from local_inner_macros macros defined inside
+// this crate.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_concat {
+    ($($args:tt)*) => {
+        concat!($($args)*)
+    };
+}
+
+// Same as std::compile_error but callable from local_inner_macros macros defined inside
+// this crate.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_compile_error {
+    ($($args:tt)*) => {
+        compile_error!{$($args)*}
+    };
+}
+
+// Outputs the tokens only if the `alloc` feature is enabled.
+#[cfg(feature = "alloc")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_if_alloc {
+    ($($tokens:tt)*) => {
+        $($tokens)*
+    };
+}
+
+#[cfg(not(feature = "alloc"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_if_alloc {
+    ($($tokens:tt)*) => {};
+}
+
+// Without the `paste` feature, the declarations needing to build an identifier are rejected.
+#[cfg(not(feature = "paste"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! paste {
+    ($($tokens:tt)*) => {
+        compile_error! {"this declaration requires the `paste` feature of `bitfield`"}
+    };
+}
 
 // Same as std::stringify but callable from local_inner_macros macros defined inside
 // this crate.
 #[macro_export]
 #[doc(hidden)]
 macro_rules! __bitfield_stringify {
-    ($s:ident) => {
-        stringify!($s)
+    ($($s:tt)*) => {
+        stringify!($($s)*)
+    };
+}
+
+// Same as std::format_args but callable from local_inner_macros macros defined inside
+// this crate.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_format_args {
+    ($($args:tt)*) => {
+        format_args!($($args)*)
+    };
+}
+
+// Same as std::assert but callable from macros defined inside this crate.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __bitfield_assert {
+    ($e:expr) => {
+        assert!($e)
+    };
+    ($e:expr, $message:expr) => {
+        assert!($e, "{}", $message)
     };
 }
 
@@ -980,7 +8200,7 @@ macro_rules! __bitfield_stringify {
 #[macro_export]
 #[doc(hidden)]
 macro_rules! __bitfield_debug_assert {
-    ($e:expr) => {
-        debug_assert!($e)
+    ($($arg:tt)*) => {
+        debug_assert!($($arg)*)
     };
 }
@@ -0,0 +1,39 @@
+#![cfg(feature = "arbitrary")]
+
+#[macro_use]
+extern crate bitfield;
+extern crate arbitrary;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+bitfield! {
+    #[derive(Debug, PartialEq)]
+    struct Header(u16);
+    impl Arbitrary;
+    u8;
+    kind, set_kind: 3, 0;
+    length, set_length: 15, 4;
+}
+
+bitfield! {
+    struct Packet([u8]);
+    impl Arbitrary;
+    u8;
+    kind, set_kind: 3, 0;
+    payload, set_payload: 23, 16;
+}
+
+#[test]
+fn test_arbitrary() {
+    let data = [0x34, 0x12, 0xab, 0xcd, 0xef];
+    let mut u = Unstructured::new(&data);
+    let header = Header::arbitrary(&mut u).unwrap();
+    assert_eq!(header, Header(0x1234));
+    assert_eq!(header.kind(), 0x4);
+    assert_eq!(Header::size_hint(0), (2, Some(2)));
+
+    let packet = Packet::<[u8; 3]>::arbitrary(&mut u).unwrap();
+    assert_eq!(packet.0, [0xab, 0xcd, 0xef]);
+    assert_eq!(packet.kind(), 0xb);
+    assert_eq!(packet.payload(), 0xef);
+}
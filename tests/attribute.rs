@@ -0,0 +1,78 @@
+#![cfg(feature = "paste")]
+
+extern crate bitfield;
+extern crate bitfield_macros;
+
+use bitfield_macros::bitfield;
+
+#[bitfield(u32, impl Debug)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct Control {
+    /// The mode of operation.
+    #[bits(3..=0)]
+    pub mode: u8,
+    #[bits(4)]
+    pub enable: bool,
+    #[bits(7, 5)]
+    pub(crate) level: i8,
+    #[bits(11..=8)]
+    pub lanes: [u8; 2],
+    #[bits(16)]
+    flags: [bool; 4],
+    #[bits(31, 24)]
+    pub r#type: u8,
+}
+
+#[bitfield(MSB0 [u8], impl Debug)]
+struct Header {
+    #[bits(0..=3)]
+    version: u8,
+    #[bits(4..=7)]
+    ihl: u8,
+    #[bits(16..=31)]
+    total_length: u16,
+}
+
+#[test]
+fn test_attribute() {
+    let mut control = Control(0);
+    control.set_mode(5);
+    control.set_enable(true);
+    control.set_level(-1);
+    control.set_lanes(1, 3);
+    control.set_flags(2, true);
+    control.set_type(0xAB);
+    assert_eq!(control.mode(), 5);
+    assert!(control.enable());
+    assert_eq!(control.level(), -1);
+    assert_eq!(control.lanes(1), 3);
+    assert!(control.flags(2));
+    assert_eq!(control.r#type(), 0xAB);
+    assert_eq!(control.0, 0xAB04_30F5);
+    assert_eq!(
+        format!("{:?}", control),
+        "Control { .0: 2869178613, mode: 5, enable: true, level: -1, lanes: [0, 3], \
+         flags: [false, false, true, false], r#type: 171 }"
+    );
+
+    let header = Header([0x45, 0x00, 0x00, 0x54]);
+    assert_eq!(header.version(), 4);
+    assert_eq!(header.ihl(), 5);
+    assert_eq!(header.total_length(), 84);
+}
+
+#[bitfield(u8, impl new)]
+struct Flags {
+    #[bits(0)]
+    ready: bool,
+    #[bits(7, 4)]
+    code: u8,
+}
+
+#[test]
+fn test_attribute_constructor() {
+    let flags = Flags::new(true, 0xA);
+    assert!(flags.ready());
+    assert_eq!(flags.code(), 0xA);
+    assert_eq!(flags.0, 0xA1);
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "bytemuck")]
+
+#[macro_use]
+extern crate bitfield;
+extern crate bytemuck;
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    struct Descriptor(u32);
+    impl Pod;
+    u16;
+    length, set_length: 15, 0;
+    u8;
+    flags, set_flags: 23, 16;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    struct Frame(MSB0 [u8]);
+    impl Pod;
+    impl Debug;
+    u8;
+    kind, set_kind: 3, 0;
+}
+
+#[test]
+fn test_pod() {
+    let descriptors = [0x0001_0040u32, 0x0003_0080];
+    let descriptors: &[Descriptor] = bytemuck::cast_slice(&descriptors);
+    assert_eq!(descriptors.len(), 2);
+    assert_eq!(descriptors[0].length(), 0x40);
+    assert_eq!(descriptors[1].flags(), 3);
+
+    let zeroed: Descriptor = bytemuck::Zeroable::zeroed();
+    assert_eq!(zeroed.0, 0);
+
+    let bytes = [0x12u8, 0x34, 0x56, 0x78];
+    let frames: &[Frame<[u8; 2]>] = bytemuck::cast_slice(&bytes);
+    assert_eq!(frames[1].kind(), 0x5);
+}
@@ -1,8 +1,10 @@
+#![cfg(feature = "paste")]
 #![recursion_limit = "128"]
 #![allow(clippy::cognitive_complexity)]
 
 #[macro_use]
 extern crate bitfield;
+extern crate bitflags;
 
 // We use a constant to make sure bits positions don't need to be literals but
 // can also be constants or expressions.
@@ -863,405 +865,4041 @@ fn test_arraybitfield_constructor() {
     assert_eq!(b.signed_foo4(), -1);
 }
 
+bitfield! {
+    struct FieldsTable(u32);
+    impl Fields;
+    u8;
+    #[allow(dead_code)]
+    ///   A single bit.
+    flag, set_flag: 0;
+    /// The second nibble.
+    ///
+    /// More details.
+    nibble, set_nibble: 7, 4;
+    i8, signed, set_signed: 15, 8;
+    array, set_array: 17, 16, 4;
+    #[cfg(not(test))]
+    not_compiled, set_not_compiled: 1;
+    _, write_only: 31, 30;
+}
+
+bitfield! {
+    struct FieldsTableArray(MSB0 [u8]);
+    impl Fields;
+    u16;
+    first, set_first: 3, 0;
+    second, set_second: 15, 4;
+}
+
+#[test]
+fn test_fields_table() {
+    use bitfield::{Field, FieldKind};
+
+    let names: Vec<_> = FieldsTable::FIELDS.iter().map(|f| f.name).collect();
+    assert_eq!(names, ["flag", "nibble", "signed", "array", "write_only"]);
+    let nibble: &Field = &FieldsTable::FIELDS[FieldsTableField::Nibble as usize];
+    assert_eq!((nibble.lsb, nibble.width, nibble.count), (4, 4, 1));
+    assert_eq!(nibble.kind, FieldKind::Unsigned);
+    let array = &FieldsTable::FIELDS[FieldsTableField::Array as usize];
+    assert_eq!((array.lsb, array.width, array.count), (16, 2, 4));
+    assert_eq!(FieldsTable::FIELDS[0].kind, FieldKind::Bool);
+    assert_eq!(FieldsTable::FIELDS[2].kind, FieldKind::Signed);
+    assert_eq!(FieldsTable::FIELDS[4].name, "write_only");
+    let docs: Vec<_> = FieldsTable::FIELDS.iter().map(|f| f.doc).collect();
+    assert_eq!(docs, ["A single bit.", "The second nibble.", "", "", ""]);
+
+    let mut fb = FieldsTable(0);
+    fb.set_field_raw(FieldsTableField::Nibble, 0, 0xA);
+    fb.set_field_raw(FieldsTableField::Array, 2, 0b11);
+    fb.set_field_raw(FieldsTableField::WriteOnly, 0, 0b10);
+    assert_eq!(fb.0, 0x8030_00A0);
+    assert_eq!(fb.nibble(), 0xA);
+    assert_eq!(fb.array(2), 0b11);
+    fb.set_signed(-1);
+    assert_eq!(fb.get_field_raw(FieldsTableField::Signed, 0), 0xFF);
+    assert_eq!(fb.get_field_raw(FieldsTableField::Array, 2), 0b11);
+    assert_eq!(fb.get_field_raw(FieldsTableField::Flag, 0), 0);
+
+    let mut array = FieldsTableArray([0u8; 2]);
+    array.set_field_raw(FieldsTableArrayField::Second, 0, 0x123);
+    assert_eq!(array.second(), 0x123);
+    assert_eq!(array.get_field_raw(FieldsTableArrayField::Second, 0), 0x123);
+    assert_eq!(array.get_field_raw(FieldsTableArrayField::First, 0), 0);
+}
+
+bitfield! {
+    struct DefaultValues(u32);
+    impl Default;
+    impl Fields;
+    u8;
+    flag, set_flag: 0 = true;
+    no_default, set_no_default: 3, 1;
+    nibble, set_nibble: 7, 4 = 0xA;
+    array, set_array: 9, 8, 3 = 2;
+    i8, signed, set_signed: 23, 16 = (-(THREE as i8));
+    from into Foo, foo, set_foo: 31, 24 = Foo(7);
+}
+
+bitfield! {
+    struct DefaultValuesSlice([u8]);
+    impl Default;
+    u16;
+    first, set_first: 3, 0 = 5;
+    second, set_second: 15, 4 = (THREE as u16 * 2);
+}
+
+#[test]
+fn test_default() {
+    let value = DefaultValues::default();
+    assert!(value.flag());
+    assert_eq!(value.no_default(), 0);
+    assert_eq!(value.nibble(), 0xA);
+    assert_eq!(value.array(0), 2);
+    assert_eq!(value.array(2), 2);
+    assert_eq!(value.signed(), -3);
+    assert_eq!(value.foo().0, 7);
+
+    let value = DefaultValuesSlice::<[u8; 2]>::default();
+    assert_eq!(value.first(), 5);
+    assert_eq!(value.second(), 6);
+    assert_eq!(value.0, [0x65, 0x00]);
+}
+
+bitfield! {
+    #[derive(Debug, PartialEq)]
+    struct ResetValues(u16);
+    impl Default;
+    impl new{with_mode(set_mode: u8)};
+    u8;
+    enable, set_enable: 0 = true;
+    mode, set_mode: 3, 1;
+    divider, set_divider: 11, 4 = 0x1F;
+}
+
+bitfield! {
+    struct ResetValuesSlice([u8]);
+    impl Default;
+    impl new{with_low(set_low: u8)};
+    u8;
+    low, set_low: 3, 0;
+    high, set_high: 7, 4 = 0x9;
+}
+
+#[test]
+fn test_reset_values() {
+    assert_eq!(ResetValues::with_mode(5), ResetValues(0x01FB));
+
+    let mut value = ResetValues(0xFFFF);
+    value.reset();
+    assert_eq!(value, ResetValues::default());
+    assert_eq!(value.0, 0x01F1);
+
+    assert_eq!(ResetValuesSlice::<[u8; 2]>::with_low(3).0, [0x93, 0]);
+    let mut slice = ResetValuesSlice([0xFFu8, 0xFF]);
+    slice.reset();
+    assert_eq!(slice.0, [0x90, 0]);
+}
+
+#[test]
+fn test_field_defaults() {
+    assert_eq!(
+        (DefaultValues::FLAG_DEFAULT, DefaultValues::NIBBLE_DEFAULT),
+        (true, 0xA)
+    );
+    assert_eq!(DefaultValues::ARRAY_DEFAULT, 2);
+    assert_eq!(DefaultValues::SIGNED_DEFAULT, -3);
+    assert_eq!(DefaultValues::FOO_DEFAULT.0, 7);
+
+    let defaults: Vec<_> = DefaultValues::FIELDS
+        .iter()
+        .map(|field| (field.name, field.default))
+        .collect();
+    assert_eq!(
+        defaults,
+        [
+            ("flag", Some(1)),
+            ("no_default", None),
+            ("nibble", Some(0xA)),
+            ("array", Some(2)),
+            ("signed", Some(0xfd)),
+            ("foo", None),
+        ]
+    );
+}
+
+bitfield! {
+    struct AutoLayout(u16);
+    impl Fields;
+    impl Complete;
+    u8;
+    pub low, set_low: width 4;
+    reserved: width 4;
+    pub nibbles, set_nibble: width 2, 2;
+    pub bool, flags, set_flags: width 1, 2;
+    pub top, set_top: width 2 = 3;
+    pub overlay, _: 7, 4;
+}
+
+bitfield! {
+    struct AutoLayoutMsb0(MSB0 [u8]);
+    u8;
+    pub first, set_first: width 4;
+    pub second, set_second: width 4;
+}
+
+#[test]
+fn test_auto_layout() {
+    let lsbs: Vec<_> = AutoLayout::FIELDS
+        .iter()
+        .map(|field| (field.lsb, field.width, field.count))
+        .collect();
+    assert_eq!(
+        lsbs,
+        [(0, 4, 1), (8, 2, 2), (12, 1, 2), (14, 2, 1), (4, 4, 1)]
+    );
+    assert_eq!(AutoLayout::TOP_DEFAULT, 3);
+
+    let mut value = AutoLayout(0);
+    value.set_low(0xF);
+    value.set_nibble(1, 2);
+    value.set_flags(1, true);
+    value.set_top(1);
+    assert_eq!(value.0, 0x680F);
+
+    let mut msb0 = AutoLayoutMsb0([0u8]);
+    msb0.set_first(0x1);
+    msb0.set_second(0xF);
+    assert_eq!(msb0.0, [0x1F]);
+}
+
+bitfield! {
+    struct SegmentDescriptor(u64);
+    impl Debug;
+    u32;
+    /// The base address.
+    pub base, set_base: [63, 56] ++ [39, 16];
+    pub limit, set_limit: [51, 48] ++ [15, 0];
+    pub u8, kind, set_kind: 43, 40;
+    pub u8, high_nibbles, _: [63, 60] ++ [55, 52];
+}
+
+bitfield! {
+    struct SegmentDescriptorSlice([u8]);
+    u32;
+    pub base, set_base: [63, 56] ++ [39, 16];
+}
+
+#[test]
+fn test_split_fields() {
+    let mut descriptor = SegmentDescriptor(0);
+    descriptor.set_base(0x1234_5678);
+    descriptor.set_limit(0xA_BCDE);
+    descriptor.set_kind(0x9);
+    assert_eq!(descriptor.0, 0x120A_0934_5678_BCDE);
+    assert_eq!(descriptor.base(), 0x1234_5678);
+    assert_eq!(descriptor.limit(), 0xA_BCDE);
+    assert_eq!(descriptor.high_nibbles(), 0x10);
+    assert_eq!(
+        format!("{:?}", descriptor),
+        "SegmentDescriptor { .0: 1299861562843512030, kind: 9 }"
+    );
+
+    let mut slice = SegmentDescriptorSlice([0u8; 8]);
+    slice.set_base(0x1234_5678);
+    assert_eq!(slice.0, [0, 0, 0x78, 0x56, 0x34, 0, 0, 0x12]);
+    assert_eq!(slice.base(), 0x1234_5678);
+}
+
+bitfield! {
+    struct EthernetHeader([u8]);
+    u64;
+    pub bytes, destination, set_destination: 47, 0;
+    pub bytes, source, _: 95, 48;
+    pub bytes, u8, octets, set_octet: 103, 96, 2;
+    pub u8, kind, set_kind: 107, 104;
+}
+
+bitfield! {
+    struct EthernetHeaderMsb0(MSB0 [u8]);
+    u64;
+    pub bytes, destination, set_destination: 47, 0;
+}
+
+#[test]
+fn test_bytes_fields() {
+    let mut header = EthernetHeader([0u8; 14]);
+    header
+        .destination_bytes_mut()
+        .copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+    header.0[6..12].copy_from_slice(&[7, 8, 9, 10, 11, 12]);
+    header.octets_bytes_mut()[1] = 0xAB;
+    assert_eq!(header.destination_bytes(), &[1, 2, 3, 4, 5, 6]);
+    assert_eq!(header.destination(), 0x0605_0403_0201);
+    assert_eq!(header.source_bytes(), &[7, 8, 9, 10, 11, 12]);
+    assert_eq!(header.octets_bytes(), &[0, 0xAB]);
+    assert_eq!(header.octets(1), 0xAB);
+
+    let mut msb0 = EthernetHeaderMsb0([0u8; 6]);
+    msb0.destination_bytes_mut()[0] = 0x12;
+    assert_eq!(msb0.destination(), 0x1200_0000_0000);
+    assert_eq!(msb0.destination_bytes(), &[0x12, 0, 0, 0, 0, 0]);
+}
+
+bitfield! {
+    struct ByteOrderFields([u8]);
+    u32;
+    pub be, u16, port, set_port: 15, 0;
+    pub le, u16, little, set_little: 31, 16;
+    pub be, length, set_length: 55, 32;
+    pub be, i32, offset, set_offset: 79, 56;
+}
+
+bitfield! {
+    struct ByteOrderFieldsMsb0(MSB0 [u8]);
+    u32;
+    pub be, u16, port, set_port: 15, 0;
+    pub le, u16, little, set_little: 31, 16;
+    pub le, length, set_length: 55, 32;
+}
+
+#[test]
+fn test_byte_order_fields() {
+    let mut fields = ByteOrderFields([0u8; 10]);
+    fields.set_port(0x1234);
+    fields.set_little(0x5678);
+    fields.set_length(0xAB_CDEF);
+    fields.set_offset(-2);
+    assert_eq!(
+        fields.0,
+        [0x12, 0x34, 0x78, 0x56, 0xAB, 0xCD, 0xEF, 0xFF, 0xFF, 0xFE]
+    );
+    assert_eq!(fields.port(), 0x1234);
+    assert_eq!(fields.little(), 0x5678);
+    assert_eq!(fields.length(), 0xAB_CDEF);
+    assert_eq!(fields.offset(), -2);
+
+    let mut msb0 = ByteOrderFieldsMsb0([0u8; 7]);
+    msb0.set_port(0x1234);
+    msb0.set_little(0x5678);
+    msb0.set_length(0xAB_CDEF);
+    assert_eq!(msb0.0, [0x12, 0x34, 0x78, 0x56, 0xEF, 0xCD, 0xAB]);
+    assert_eq!(msb0.port(), 0x1234);
+    assert_eq!(msb0.little(), 0x5678);
+    assert_eq!(msb0.length(), 0xAB_CDEF);
+}
+
+bitfield! {
+    struct ChainedHeader(Segments);
+    u32;
+    pub u8, kind, set_kind: 3, 0;
+    pub length, set_length: 27, 4;
+    pub u16, checksum, set_checksum: 43, 28;
+    pub be, u16, port, set_port: 55, 40;
+}
+
+#[test]
+fn test_segments() {
+    let first = [0x21, 0x43];
+    let second = [0x65];
+    let third = [0x87, 0xA9, 0xCB, 0xED];
+    let header = ChainedHeader(bitfield::Segments(&[&first[..], &second, &third][..]));
+    assert_eq!(header.kind(), 0x1);
+    assert_eq!(header.length(), 0x765_432);
+    assert_eq!(header.checksum(), 0xBA98);
+    assert_eq!(header.port(), 0xCBED);
+    assert_eq!(header.0.len(), 7);
+
+    let io_slices = [
+        std::io::IoSlice::new(&first),
+        std::io::IoSlice::new(&second),
+        std::io::IoSlice::new(&third),
+    ];
+    let header = ChainedHeader(bitfield::Segments(&io_slices[..]));
+    assert_eq!(header.length(), 0x765_432);
+
+    let mut first = [0u8; 1];
+    let mut second = [0u8; 0];
+    let mut third = [0u8; 6];
+    let mut buffers = [&mut first[..], &mut second[..], &mut third[..]];
+    let mut header = ChainedHeader(bitfield::Segments(&mut buffers[..]));
+    header.set_kind(0xF);
+    header.set_length(0xBC_DEF1);
+    header.set_checksum(0x1234);
+    assert_eq!(header.length(), 0xBC_DEF1);
+    assert_eq!(header.checksum(), 0x1234);
+    assert_eq!(first, [0x1F]);
+    assert_eq!(third, [0xEF, 0xCD, 0x4B, 0x23, 0x01, 0]);
+}
+
+bitfield! {
+    struct ValidFields(u32);
+    impl Debug;
+    pub u16, valid_if 15, value, set_value: 14, 0;
+    pub u8, into u32, valid_if 31, index, set_index: 23, 16;
+    pub u8, valid_if 30, status, _: 27, 24;
+}
+
+#[test]
+fn test_valid_if() {
+    let mut fields = ValidFields(0x4000_7FFF);
+    assert_eq!(fields.value(), None);
+    assert_eq!(fields.index(), None);
+    assert_eq!(fields.status(), Some(0));
+
+    fields.set_value(Some(0x1234));
+    fields.set_index(Some(0xAB));
+    assert_eq!(fields.0, 0xC0AB_9234);
+    assert_eq!(fields.value(), Some(0x1234));
+    assert_eq!(fields.index(), Some(0xAB));
+
+    fields.set_value(None);
+    assert_eq!(fields.0, 0xC0AB_1234);
+    assert_eq!(fields.value(), None);
+    assert_eq!(
+        format!("{:?}", fields),
+        "ValidFields { .0: 3232436788, value: None, index: Some(171), status: Some(0) }"
+    );
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    struct ValidGenerated(u16);
+    impl With;
+    impl Builder;
+    impl Pin;
+    impl Cached{CachedValidGenerated};
+    impl ReadOnly{ValidGeneratedView};
+    impl Values{v: ValidMode {Idle = 0, Busy = 7}};
+    impl new;
+    u8;
+    valid_if 15, v, set_v: 7, 0;
+}
+
+#[test]
+fn test_valid_if_with() {
+    assert_eq!(ValidGenerated(0).with_v(Some(5)).0, 0x8005);
+    assert_eq!(ValidGenerated(0x8005).with_v(None).0, 0x0005);
+}
+
+#[test]
+fn test_valid_if_builder() {
+    let value = ValidGenerated::builder().v(Some(5)).build().unwrap();
+    assert_eq!(value.0, 0x8005);
+    assert_eq!(ValidGenerated::builder().v(None).build().unwrap().0, 0);
+}
+
+#[test]
+fn test_valid_if_pin() {
+    let mut value = ValidGenerated(0);
+    std::pin::Pin::new(&mut value).set_v_pinned(Some(5));
+    assert_eq!(value.0, 0x8005);
+}
+
+#[test]
+fn test_valid_if_cached() {
+    let mut cached = CachedValidGenerated::new(ValidGenerated(0x8005));
+    assert_eq!(cached.v(), Some(5));
+    cached.set_v(None);
+    assert_eq!(cached.v(), None);
+}
+
+#[test]
+fn test_valid_if_read_only() {
+    assert_eq!(
+        ValidGeneratedView::from(ValidGenerated(0x8007)).v(),
+        Some(7)
+    );
+    assert_eq!(ValidGeneratedView::from(ValidGenerated(0x0007)).v(), None);
+}
+
+#[test]
+fn test_valid_if_values() {
+    assert_eq!(ValidGenerated(0x8007).v_value(), Some(ValidMode::Busy));
+    assert_eq!(ValidGenerated(0x8000).v_value(), Some(ValidMode::Idle));
+    assert_eq!(ValidGenerated(0x0007).v_value(), None);
+}
+
+#[test]
+fn test_valid_if_new() {
+    assert_eq!(ValidGenerated::new(Some(5)).0, 0x8005);
+    assert_eq!(ValidGenerated::new(None).0, 0);
+}
+
+bitfield! {
+    struct AccessRegister(u32);
+    impl Debug;
+    pub ro, u8, version, set_version: 7, 0;
+    pub wo, u8, command, set_command: 11, 8;
+    pub rc, u8, errors, _: 15, 12;
+    pub rc, overflow, _: 16;
+    pub w1s, u8, enable, set_enable: 23, 20;
+    pub w1s, start, set_start: 24;
+}
+
+impl AccessRegister {
+    // `ro` and `wo` don't generate these accessors, so the names are free.
+    fn set_version(&mut self, version: u8) {
+        self.0 = (self.0 & !0xFF) | u32::from(version);
+    }
+
+    fn command(&self) -> u32 {
+        (self.0 >> 8) & 0xF
+    }
+}
+
+#[test]
+fn test_access_keywords() {
+    let mut register = AccessRegister(0x0031_F012);
+    register.set_version(0x34);
+    register.set_command(0xA);
+    assert_eq!(register.version(), 0x34);
+    assert_eq!(register.command(), 0xA);
+
+    assert_eq!(register.errors(), 0xF);
+    assert_eq!(register.errors(), 0);
+    assert!(register.overflow());
+    assert!(!register.overflow());
+
+    register.set_enable(0x4);
+    assert_eq!(register.enable(), 0x7);
+    register.set_start(false);
+    assert!(!register.start());
+    register.set_start(true);
+    assert!(register.start());
+    assert_eq!(register.0, 0x0170_0A34);
+    assert_eq!(
+        format!("{:?}", register),
+        "AccessRegister { .0: 24119860, version: 52, enable: 7, start: true }"
+    );
+}
+
+use std::num::{NonZeroI8, NonZeroU8};
+
+bitfield! {
+    struct NonZeroFields(u32);
+    impl Debug;
+    pub u8, into NonZeroU8, id, set_id: 7, 0;
+    pub u16, length, set_length: 23, 8;
+    pub i8, into NonZeroI8, offset, set_offset: 31, 24;
+}
+
+#[test]
+fn test_nonzero_fields() {
+    let mut fields = NonZeroFields(0);
+    assert_eq!(fields.id(), None);
+    assert_eq!(fields.offset(), None);
+    fields.set_id(NonZeroU8::new(0x12).unwrap());
+    fields.set_offset(NonZeroI8::new(-2).unwrap());
+    assert_eq!(fields.0, 0xFE00_0012);
+    assert_eq!(fields.id(), NonZeroU8::new(0x12));
+    assert_eq!(fields.offset(), NonZeroI8::new(-2));
+    assert_eq!(
+        format!("{:?}", fields),
+        "NonZeroFields { .0: 4261412882, id: Some(18), length: 0, offset: Some(-2) }"
+    );
+}
+
+bitfield! {
+    struct FloatFields([u8]);
+    impl Debug;
+    u8;
+    pub f32, temperature, set_temperature: 31, 0;
+    pub f64, pressure, set_pressure: 95, 32;
+    pub status, set_status: 103, 96;
+}
+
+#[test]
+fn test_float_fields() {
+    let mut fields = FloatFields([0u8; 13]);
+    fields.set_temperature(21.5);
+    fields.set_pressure(-1013.25);
+    fields.set_status(0x5A);
+    assert_eq!(fields.0[..4], 21.5f32.to_le_bytes());
+    assert_eq!(fields.0[4..12], (-1013.25f64).to_le_bytes());
+    assert_eq!(fields.temperature(), 21.5);
+    assert_eq!(fields.pressure(), -1013.25);
+    assert_eq!(fields.status(), 0x5A);
+}
+
+const RANGE_TOP: usize = 15;
+
+bitfield! {
+    struct RangePositions(u16);
+    impl Fields;
+    u8;
+    pub low, set_low: 3..=0;
+    pub reversed, set_reversed: 7..=4;
+    pub nibbles, set_nibble: 9..=8, 2;
+    pub top, set_top: RANGE_TOP..=(RANGE_TOP - 2) = 5;
+}
+
+bitfield! {
+    struct OffsetWidthPositions(u16);
+    impl Fields;
+    u8;
+    pub low, set_low: @0; 4;
+    pub reversed, set_reversed: @4; 4;
+    pub nibbles, set_nibble: @8; 2, 2;
+    pub top, set_top: @(RANGE_TOP - 2); 3 = 5;
+}
+
+bitfield! {
+    struct RangePositionsMsb0(MSB0 [u8]);
+    u8;
+    pub first, set_first: 0..=3;
+    pub second, set_second: 4..=7;
+}
+
+#[test]
+fn test_range_positions() {
+    let mut value = RangePositions(0);
+    value.set_low(0x3);
+    value.set_reversed(0xA);
+    value.set_nibble(1, 2);
+    value.set_top(7);
+    assert_eq!(value.0, 0xE8A3);
+    assert_eq!(
+        (value.low(), value.reversed(), value.nibbles(1), value.top()),
+        (3, 0xA, 2, 7)
+    );
+    assert_eq!(RangePositions::TOP_DEFAULT, 5);
+    let bits: Vec<_> = RangePositions::FIELDS
+        .iter()
+        .map(|field| (field.lsb, field.width))
+        .collect();
+    assert_eq!(bits, [(0, 4), (4, 4), (8, 2), (13, 3)]);
+
+    let mut offset_width = OffsetWidthPositions(0);
+    offset_width.set_low(0x3);
+    offset_width.set_reversed(0xA);
+    offset_width.set_nibble(1, 2);
+    offset_width.set_top(7);
+    assert_eq!(offset_width.0, 0xE8A3);
+    assert_eq!(OffsetWidthPositions::TOP_DEFAULT, 5);
+    assert!(OffsetWidthPositions::LAYOUT.same_layout(&RangePositions::LAYOUT));
+
+    let mut msb0 = RangePositionsMsb0([0u8]);
+    msb0.set_first(0x1);
+    msb0.set_second(0xF);
+    assert_eq!(msb0.0, [0x1F]);
+}
+
+bitfield! {
+    struct CompleteRegister(u16);
+    impl Complete;
+    u8;
+    pub mode, set_mode: 3, 0;
+    reserved: 7, 4;
+    pub bool, enabled, set_enabled: 8;
+    reserved: 9;
+    pub channels, set_channel: 11, 10, 2;
+    /// Reserved for future use.
+    reserved: 15, 14;
+}
+
+bitfield! {
+    struct CompleteSlice(MSB0 [u8]);
+    impl Complete;
+    u8;
+    reserved: 3, 0;
+    pub kind, set_kind: 11, 4;
+}
+
+#[test]
+fn test_complete() {
+    let mut value = CompleteRegister(0);
+    value.set_mode(0xF);
+    value.set_enabled(true);
+    value.set_channel(1, 3);
+    assert_eq!(value.0, 0x310F);
+    assert_eq!(value.channels(1), 3);
+
+    let mut slice = CompleteSlice([0u8; 2]);
+    slice.set_kind(0xAB);
+    assert_eq!(slice.0, [0x0A, 0xB0]);
+}
+
+bitfield! {
+    struct EqInt(u16);
+    impl PartialEq;
+    impl Eq;
+    u8;
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+}
+
+bitfield! {
+    struct EqSlice([u8]);
+    impl PartialEq;
+    impl Eq;
+    u8;
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+}
+
+// A storage type that implements neither `PartialEq` nor `Eq`.
+struct Storage([u8; 2]);
+
+impl AsRef<[u8]> for Storage {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+fn assert_eq_impl<T: Eq>(_: &T) {}
+
+#[test]
+fn test_partial_eq() {
+    let mut a = EqInt(0);
+    let b = EqInt(0x1234);
+    assert!(a != b);
+    a.set_low(0x34);
+    a.set_high(0x12);
+    assert!(a == b);
+    assert_eq_impl(&a);
+
+    assert!(EqSlice([1u8, 2]) == EqSlice([1u8, 2]));
+    assert!(EqSlice(vec![1u8, 2]) != EqSlice(vec![2u8, 1]));
+    assert!(EqSlice(Storage([3, 4])) == EqSlice(Storage([3, 4])));
+    assert!(EqSlice(Storage([3, 4])) != EqSlice(Storage([4, 4])));
+    assert_eq_impl(&EqSlice(Storage([0, 0])));
+}
+
+bitfield! {
+    struct FromUninitArray([u8]);
+    impl FromUninit;
+    u8;
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+}
+
+bitfield! {
+    struct FromUninitInt(u16);
+    impl FromUninit;
+    impl new;
+    u8;
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+}
+
+#[test]
+fn test_from_uninit_written() {
+    let mut slot = core::mem::MaybeUninit::uninit();
+    let value = FromUninitArray::<[u8; 2]>::from_uninit_written(&mut slot, 0x34, 0x12);
+    assert_eq!(value.0, [0x34, 0x12]);
+
+    let mut slot = core::mem::MaybeUninit::uninit();
+    let value = FromUninitArray::<[u8; 64]>::from_uninit_written(&mut slot, 0x34, 0x12);
+    assert_eq!(value.0[..2], [0x34, 0x12]);
+    assert!(value.0[2..].iter().all(|&byte| byte == 0));
+
+    let mut slot = core::mem::MaybeUninit::uninit();
+    let value = FromUninitInt::from_uninit_written(&mut slot, 0, 0x12);
+    assert_eq!(value.0, FromUninitInt::new(0, 0x12).0);
+    assert_eq!(value.0, 0x1200);
+}
+
+bitfield! {
+    struct EncodeInt(u32);
+    impl Encode;
+    impl Decode;
+    u8;
+    flag, set_flag: 31;
+    nibble, set_nibble: 3, 0;
+    array, set_array: 15, 8, 2;
+    #[cfg(not(test))]
+    not_compiled, set_not_compiled: 30, 28;
+}
+
+bitfield! {
+    struct EncodeSlice([u8]);
+    impl Encode;
+    impl Decode;
+    u8;
+    high, set_high: 15, 12;
+    low, set_low: 3, 0;
+}
+
+#[test]
+fn test_encode_into() {
+    let mut value = EncodeInt(0);
+    value.set_flag(true);
+    value.set_nibble(0x5);
+    value.set_array(0, 0xAB);
+    value.set_array(1, 0xCD);
+    let mut buffer = [0xFFu8; 4];
+    let mut writer = bitfield::BitWriter::new(&mut buffer);
+    value.encode_into(&mut writer);
+    assert_eq!(writer.position(), 21);
+    let slice = EncodeSlice([0x0Bu8, 0xA0]);
+    slice.encode_into(&mut writer);
+    assert_eq!(writer.position(), 29);
+    assert_eq!(buffer, [0b1010_1101, 0b0101_1110, 0b0110_1101, 0b0101_1111]);
+}
+
+#[test]
+fn test_compose() {
+    assert_eq!(EncodeInt::ENCODED_BITS, 21);
+    assert_eq!(EncodeSlice::<[u8; 2]>::ENCODED_BITS, 8);
+
+    let mut value = EncodeInt(0);
+    value.set_flag(true);
+    value.set_nibble(0x5);
+    value.set_array(0, 0xAB);
+    value.set_array(1, 0xCD);
+    let slice = EncodeSlice([0x0Bu8, 0xA0]);
+    let mut buffer = [0xFFu8; 5];
+    let bits = bitfield_compose!(&mut buffer; value, slice, [&[0x12]],);
+    assert_eq!(bits, 37);
+    assert_eq!(
+        buffer,
+        [
+            0b1010_1101,
+            0b0101_1110,
+            0b0110_1101,
+            0b0101_1000,
+            0b1001_0111
+        ]
+    );
+}
+
+bitfield! {
+    struct HashInt(u16);
+    impl PartialEq;
+    impl Eq;
+    impl Hash;
+    u8;
+    low, set_low: 7, 0;
+}
+
+bitfield! {
+    struct HashSlice([u8]);
+    impl PartialEq;
+    impl Eq;
+    impl Hash;
+    u8;
+    low, set_low: 7, 0;
+}
+
+#[test]
+fn test_hash() {
+    let mut int_set = std::collections::HashSet::new();
+    assert!(int_set.insert(HashInt(1)));
+    assert!(int_set.insert(HashInt(2)));
+    assert!(!int_set.insert(HashInt(1)));
+
+    let mut slice_set = std::collections::HashSet::new();
+    assert!(slice_set.insert(HashSlice(vec![1u8, 2])));
+    assert!(!slice_set.insert(HashSlice(vec![1u8, 2])));
+    assert!(slice_set.contains(&HashSlice(vec![1u8, 2])));
+    assert!(!slice_set.contains(&HashSlice(vec![2u8, 1])));
+}
+
+bitfield! {
+    struct OrdInt(u16);
+    impl PartialEq;
+    impl Eq;
+    impl PartialOrd;
+    impl Ord;
+    u8;
+    low, set_low: 7, 0;
+}
+
+bitfield! {
+    struct OrdSlice([u8]);
+    impl PartialEq;
+    impl Eq;
+    impl PartialOrd;
+    impl Ord;
+    u16;
+    value, set_value: 15, 0;
+}
+
+bitfield! {
+    struct OrdSliceMsb0(MSB0 [u8]);
+    impl Debug;
+    impl PartialEq;
+    impl Eq;
+    impl PartialOrd;
+    impl Ord;
+    u16;
+    value, set_value: 15, 0;
+}
+
+#[test]
+fn test_ord() {
+    assert!(OrdInt(0x100) > OrdInt(0xff));
+    let mut values = [OrdInt(3), OrdInt(1), OrdInt(2)];
+    values.sort();
+    assert_eq!(values.binary_search(&OrdInt(2)), Ok(1));
+
+    let lhs = OrdSlice([0x01u8, 0x02]);
+    let rhs = OrdSlice([0x02u8, 0x01]);
+    assert!(lhs.value() > rhs.value());
+    assert!(lhs > rhs);
+    assert_eq!(lhs.cmp(&lhs), std::cmp::Ordering::Equal);
+
+    let lhs = OrdSliceMsb0([0x01u8, 0x02]);
+    let rhs = OrdSliceMsb0([0x02u8, 0x01]);
+    assert!(lhs < rhs);
+    assert_eq!(lhs.partial_cmp(&rhs), Some(std::cmp::Ordering::Less));
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Permissions: u8 {
+        const READ = 0b001;
+        const WRITE = 0b010;
+        const EXECUTE = 0b100;
+    }
+}
+
+bitfield! {
+    struct FlagsField(u16);
+    impl Debug;
+    u8;
+    flags Permissions, permissions, set_permissions: 3, 0;
+    flags Permissions, array_permissions, set_array_permissions: 7, 4, 2;
+    flags, set_flags: 15, 12;
+}
+
+#[test]
+fn test_flags_field() {
+    let mut value = FlagsField(0);
+    value.set_permissions(Permissions::READ | Permissions::EXECUTE);
+    assert_eq!(value.0, 0b0101);
+    assert_eq!(
+        value.permissions(),
+        Permissions::READ | Permissions::EXECUTE
+    );
+
+    // The bits that aren't flags are dropped.
+    let value = FlagsField(0b1010);
+    assert_eq!(value.permissions(), Permissions::WRITE);
+
+    let mut value = FlagsField(0);
+    value.set_array_permissions(1, Permissions::WRITE);
+    value.set_flags(0xf);
+    assert_eq!(value.0, 0xf200);
+    assert_eq!(value.array_permissions(0), Permissions::empty());
+    assert_eq!(value.array_permissions(1), Permissions::WRITE);
+    assert_eq!(value.flags(), 0xf);
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RawControl {
+    enable: bool,
+    mode: u8,
+    channels: [u8; 2],
+    rate: u16,
+}
+
+bitfield! {
+    struct Control(u32);
+    impl CRepr{RawControl};
+    u8;
+    enable, set_enable: 0;
+    mode, set_mode: 3, 1;
+    channels, set_channels: 7, 4, 2;
+    u16, rate, set_rate: 27, 12;
+}
+
+bitfield! {
+    struct ControlSlice([u8]);
+    impl CRepr{RawControl};
+    u8;
+    enable, set_enable: 0;
+    mode, set_mode: 3, 1;
+    channels, set_channels: 7, 4, 2;
+    u16, rate, set_rate: 27, 12;
+}
+
+#[test]
+fn test_c_repr() {
+    let raw = RawControl {
+        enable: true,
+        mode: 0b101,
+        channels: [0x3, 0xc],
+        rate: 0x1234,
+    };
+    let value = Control::from_c_repr(&raw);
+    assert_eq!(value.0, 0x0123_4c3b);
+    assert_eq!(value.to_c_repr(), raw);
+
+    let value = ControlSlice::<[u8; 4]>::from_c_repr(&raw);
+    assert_eq!(value.0, [0x3b, 0x4c, 0x23, 0x01]);
+    assert_eq!(value.to_c_repr(), raw);
+}
+
+bitfield! {
+    #[derive(Clone, Copy, Debug)]
+    struct Status(u16);
+    impl ReadOnly{#[derive(Clone, Copy, Debug)] StatusView};
+    u8;
+    ready, set_ready: 0;
+    code, set_code: 7, 4;
+    lanes, set_lanes: 9, 8, 2;
+    _, set_command: 15, 12;
+}
+
+bitfield! {
+    struct StatusSlice([u8]);
+    impl ReadOnly{StatusSliceView};
+    u8;
+    ready, set_ready: 0;
+    code, set_code: 7, 4;
+}
+
+bitfield! {
+    struct ReadOnlyHeader([u8]);
+    impl Fields;
+    impl ReadOnlyStorage;
+    u8;
+    version, _: 3, 0;
+    bool, flags, _: 15, 8, 8;
+}
+
+#[test]
+fn test_read_only_storage() {
+    let bytes = [0x42u8, 0x81];
+    let header = ReadOnlyHeader(&bytes[..]);
+    assert_eq!(header.version(), 2);
+    assert!(header.flags(0) && header.flags(7) && !header.flags(1));
+    assert_eq!(
+        (
+            ReadOnlyHeader::<&[u8]>::LAYOUT.read_only,
+            LayoutV1::LAYOUT.read_only
+        ),
+        (true, false)
+    );
+}
+
+#[test]
+fn test_read_only_view() {
+    let mut status = Status(0);
+    status.set_ready(true);
+    status.set_code(0xa);
+    status.set_lanes(1, 2);
+    status.set_command(0xf);
+
+    let view = StatusView::from(status);
+    assert!(view.ready());
+    assert_eq!(view.code(), 0xa);
+    assert_eq!(view.lanes(0), 0);
+    assert_eq!(view.lanes(1), 2);
+
+    let view: StatusSliceView<_> = StatusSlice([0xa1u8]).into();
+    assert!(view.ready());
+    assert_eq!(view.code(), 0xa);
+}
+
+bitfield! {
+    #[derive(Debug, PartialEq)]
+    struct Ipv4Header(u32);
+    impl FromStr;
+    u8;
+    version, set_version: 3, 0;
+    ihl, set_ihl: 7, 4;
+    ttl, set_ttl: 15, 8;
+    i8, offset, set_offset: 19, 16;
+    lanes, set_lanes: 23, 20, 2;
+    flag, set_flag: 24;
+    status, _: 31, 28;
+}
+
+bitfield! {
+    struct Ipv4HeaderSlice([u8]);
+    impl FromStr;
+    u8;
+    version, set_version: 3, 0;
+    ttl, set_ttl: 15, 8;
+}
+
+bitfield! {
+    struct FromStrOrdered([u8]);
+    impl FromStr;
+    u16;
+    pub be, x, set_x: 15, 0;
+    pub valid_if 31, v, set_v: 23, 16;
+    pub u8, into NonZeroU8, id, set_id: 30, 24;
+}
+
+#[test]
+fn test_from_str() {
+    use bitfield::ParseFieldsError;
+
+    let header: Ipv4Header = "version=4, ihl=5,ttl=0x40".parse().unwrap();
+    assert_eq!(header.version(), 4);
+    assert_eq!(header.ihl(), 5);
+    assert_eq!(header.ttl(), 0x40);
+
+    let header: Ipv4Header = "offset=-8,lanes[1]=0b11,flag=true,".parse().unwrap();
+    assert_eq!(header.offset(), -8);
+    assert_eq!(header.lanes(1), 3);
+    assert!(header.flag());
+    assert_eq!("".parse::<Ipv4Header>(), Ok(Ipv4Header(0)));
+
+    let error = |s: &str| s.parse::<Ipv4Header>().err();
+    assert_eq!(
+        error("version=4,size=3"),
+        Some(ParseFieldsError::UnknownField { position: 10 })
+    );
+    assert_eq!(
+        error("status=1"),
+        Some(ParseFieldsError::UnknownField { position: 0 })
+    );
+    assert_eq!(
+        error("version"),
+        Some(ParseFieldsError::InvalidAssignment { position: 0 })
+    );
+    assert_eq!(
+        error("version=16"),
+        Some(ParseFieldsError::OutOfRange { field: "version" })
+    );
+    assert_eq!(
+        error("offset=8"),
+        Some(ParseFieldsError::OutOfRange { field: "offset" })
+    );
+    assert_eq!(
+        error("ttl=x"),
+        Some(ParseFieldsError::InvalidValue { field: "ttl" })
+    );
+    assert_eq!(
+        error("lanes=1"),
+        Some(ParseFieldsError::InvalidIndex { field: "lanes" })
+    );
+    assert_eq!(
+        error("lanes[2]=1"),
+        Some(ParseFieldsError::InvalidIndex { field: "lanes" })
+    );
+    assert_eq!(
+        ParseFieldsError::OutOfRange { field: "version" }.to_string(),
+        "value out of range for the field `version`"
+    );
+
+    let header: Ipv4HeaderSlice<[u8; 2]> = "version=4,ttl=64".parse().unwrap();
+    assert_eq!(header.0, [0x04, 0x40]);
+}
+
+#[test]
+fn test_from_str_setters() {
+    let parsed: FromStrOrdered<[u8; 4]> = "x=0x1234".parse().unwrap();
+    let mut expected = FromStrOrdered([0u8; 4]);
+    expected.set_x(0x1234);
+    assert_eq!(parsed.0, expected.0);
+    assert_eq!(parsed.0, [0x12, 0x34, 0, 0]);
+    assert_eq!(parsed.x(), 0x1234);
+
+    let parsed: FromStrOrdered<[u8; 4]> = "v=5".parse().unwrap();
+    assert_eq!(parsed.v(), Some(5));
+    let parsed: FromStrOrdered<[u8; 4]> = "".parse().unwrap();
+    assert_eq!(parsed.v(), None);
+
+    let parsed: FromStrOrdered<[u8; 4]> = "id=3".parse().unwrap();
+    assert_eq!(parsed.id().map(u8::from), Some(3));
+    assert_eq!(
+        "id=0".parse::<FromStrOrdered<[u8; 4]>>().err(),
+        Some(bitfield::ParseFieldsError::InvalidValue { field: "id" })
+    );
+}
+
+bitfield! {
+    struct ModeControl(u16);
+    impl Values{
+        mode: Mode {
+            /// The device is off.
+            Disabled = 0,
+            Slow = 1,
+            Fast = 3,
+        },
+        level: Level { Low = -1, High = 1 },
+    };
+    u8;
+    mode, set_mode: 1, 0;
+    i8, level, set_level: 7, 4;
+}
+
+#[test]
+fn test_values() {
+    let mut value = ModeControl(0);
+    value.set_mode(3);
+    value.set_level(-1);
+    let speed = match value.mode_value() {
+        Some(Mode::Disabled) => 0,
+        Some(Mode::Slow) => 1,
+        Some(Mode::Fast) => 10,
+        None => 100,
+    };
+    assert_eq!(speed, 10);
+    assert_eq!(value.level_value(), Some(Level::Low));
+
+    value.set_mode(2);
+    value.set_level(0);
+    assert_eq!(value.mode_value(), None);
+    assert_eq!(value.level_value(), None);
+    assert_eq!(Mode::Fast as u8, 3);
+}
+
+#[test]
+fn test_decode_from() {
+    let buffer = [0b1010_1101, 0b0101_1110, 0b0110_1101, 0b0101_1111];
+    let mut reader = bitfield::BitReader::new(&buffer);
+    let value = EncodeInt::decode_from(&mut reader).unwrap();
+    assert_eq!(reader.position(), 21);
+    assert!(value.flag());
+    assert_eq!(value.nibble(), 0x5);
+    assert_eq!(value.array(0), 0xAB);
+    assert_eq!(value.array(1), 0xCD);
+    assert_eq!(value.0, 0x80CD_AB05);
+    let slice = EncodeSlice::<[u8; 2]>::decode_from(&mut reader).unwrap();
+    assert_eq!(slice.0, [0x0B, 0xA0]);
+    assert_eq!(reader.remaining(), 3);
+    assert_eq!(
+        EncodeSlice::<[u8; 2]>::decode_from(&mut reader).err(),
+        Some(bitfield::EndOfStream)
+    );
+}
+
+bitfield! {
+    struct DisplayInt(u32);
+    impl Display;
+    u8;
+    flag, _: 0;
+    nibble, set_nibble: 7, 4;
+    array, _: 15, 8, 2;
+    _, write_only: 31, 24;
+    #[cfg(not(test))]
+    not_compiled, _: 31, 28;
+}
+
+bitfield! {
+    struct DisplaySlice([u8]);
+    impl Display;
+    u8;
+    low, _: 7, 0;
+    high, _: 15, 8;
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(
+        DisplayInt(0x0012_34A1).to_string(),
+        "flag: true, nibble: 10, array: [52, 18]"
+    );
+    assert_eq!(DisplaySlice([1u8, 2]).to_string(), "low: 1, high: 2");
+}
+
+bitfield! {
+    struct CellStorage([u8]);
+    impl StorageCell;
+    u8;
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+}
+
+#[test]
+fn test_storage_cell() {
+    let value = CellStorage(std::cell::RefCell::new([0u8; 2]));
+    value.with_storage_mut(|value| {
+        value.set_low(0x34);
+        value.set_high(0x12);
+    });
+    assert_eq!(value.with_storage(|value| value.high()), 0x12);
+    assert_eq!(*value.0.borrow(), [0x34, 0x12]);
+}
+
+bitfield! {
+    struct RadixInt(u16);
+    impl LowerHex;
+    impl UpperHex;
+    impl Binary;
+    impl Octal;
+    u8;
+    low, _: 7, 0;
+}
+
+bitfield! {
+    struct RadixSlice([u8]);
+    impl LowerHex;
+    impl UpperHex;
+    impl Binary;
+    impl Octal;
+    u8;
+    low, _: 7, 0;
+}
+
+#[test]
+fn test_radix_formatting() {
+    let value = RadixInt(0xAB);
+    assert_eq!(format!("{:#06x}", value), "0x00ab");
+    assert_eq!(format!("{:X}", value), "AB");
+    assert_eq!(format!("{:b}", value), "10101011");
+    assert_eq!(format!("{:o}", value), "253");
+
+    let value = RadixSlice([0x0Au8, 0xB1]);
+    assert_eq!(format!("{:#x}", value), "0x0ab1");
+    assert_eq!(format!("{:X}", value), "0AB1");
+    assert_eq!(format!("{:#b}", value), "0b0000101010110001");
+    assert_eq!(format!("{:o}", value), "012261");
+}
+
+bitfield! {
+    struct PinnedInt(u16);
+    impl Pin;
+    u8;
+    flag, set_flag: 0;
+    nibble, set_nibble: 7, 4;
+    array, set_array: 11, 8, 2;
+    read_only, _: 3, 1;
+}
+
+bitfield! {
+    struct PinnedSlice([u8]);
+    impl Pin;
+    u8;
+    low, set_low: 7, 0;
+}
+
+#[test]
+fn test_pin_setters() {
+    let mut value = PinnedInt(0);
+    let mut pinned = std::pin::Pin::new(&mut value);
+    pinned.as_mut().set_flag_pinned(true);
+    pinned.as_mut().set_nibble_pinned(0xA);
+    pinned.as_mut().set_array_pinned(1, 0x1);
+    assert_eq!(pinned.array(1), 0x1);
+    assert_eq!(value.0, 0x10A1);
+
+    let mut value = PinnedSlice([0u8; 1]);
+    std::pin::Pin::new(&mut value).set_low_pinned(0x42);
+    assert_eq!(value.0, [0x42]);
+}
+
+bitfield_compile_fail_tests! {
+    mod compile_fail_tests;
+    pub struct CompileFailChecked([u8]);
+    no default BitRange;
+    impl Debug;
+    u8;
+    flag, set_flag: 0;
+    array, set_array: 15, 8, 2;
+    read_only, _: 7, 4;
+    #[cfg(not(test))]
+    not_compiled, set_not_compiled: 3, 1;
+}
+
+bitfield_compile_fail_tests! {
+    mod sized_compile_fail_tests;
+    pub struct CompileFailSized(MSB0 [u8]);
+    assert_size 16;
+    u8;
+    flag, set_flag: 0;
+    low, set_low: 15, 8;
+}
+
+bitfield! {
+    struct LayoutHashed(u32);
+    impl LayoutHash;
+    impl Fields;
+    u8;
+    flag, set_flag: 0;
+    nibble, set_nibble: 7, 4;
+    array, set_array: 15, 8, 2;
+    #[cfg(not(test))]
+    not_compiled, set_not_compiled: 31, 28;
+}
+
+bitfield! {
+    struct LayoutHashedMoved(u32);
+    impl LayoutHash;
+    u8;
+    flag, set_flag: 0;
+    nibble, set_nibble: 7, 3;
+    array, set_array: 15, 8, 2;
+}
+
+bitfield! {
+    struct LayoutHashedSlice([u8]);
+    impl LayoutHash;
+    u8;
+    flag, set_flag: 0;
+    nibble, set_nibble: 7, 4;
+    array, set_array: 15, 8, 2;
+}
+
+#[test]
+fn test_layout_hash() {
+    const HASH: u64 = LayoutHashed::LAYOUT_HASH;
+    assert_eq!(HASH, bitfield::layout_hash(LayoutHashed::FIELDS));
+    assert_eq!(HASH, LayoutHashedSlice::<[u8; 4]>::LAYOUT_HASH);
+    assert_ne!(HASH, LayoutHashedMoved::LAYOUT_HASH);
+    assert_eq!(bitfield::layout_hash(&[]), 0xcbf2_9ce4_8422_2325);
+    // The hash must stay the same across versions.
+    let field = bitfield::Field {
+        name: "a",
+        doc: "",
+        lsb: 3,
+        width: 4,
+        count: 1,
+        stride: 4,
+        kind: bitfield::FieldKind::Unsigned,
+        default: None,
+        unit: "",
+        radix: bitfield::Radix::Decimal,
+    };
+    assert_eq!(bitfield::layout_hash(&[field]), 0x5dd2_01df_a52f_7e8a);
+}
+
+bitfield! {
+    struct FromInt(u32);
+    impl From;
+    u8;
+    low, set_low: 7, 0;
+}
+
+bitfield! {
+    struct FromArray([u8]);
+    impl From;
+    u8;
+    low, set_low: 7, 0;
+}
+
+#[test]
+fn test_from_storage() {
+    let value = FromInt::from(0x1234);
+    assert_eq!(value.low(), 0x34);
+    let raw: u32 = value.into();
+    assert_eq!(raw, 0x1234);
+
+    let value: FromArray<[u8; 2]> = [0x34, 0x12].into();
+    assert_eq!(value.low(), 0x34);
+    assert_eq!(<[u8; 2]>::from(value), [0x34, 0x12]);
+}
+
+bitfield! {
+    struct LayoutV1(u16);
+    impl Fields;
+    u8;
+    kind, set_kind: 3, 0;
+    flag, set_flag: 4;
+}
+
+bitfield! {
+    struct LayoutV2(u16);
+    impl Fields;
+    u8;
+    flag, set_flag: 4;
+    kind, set_kind: 3, 0;
+    extra, set_extra: 15, 8;
+}
+
+bitfield! {
+    struct LayoutV3(u16);
+    impl Fields;
+    u8;
+    kind, set_kind: 4, 1;
+    flag, set_flag: 0;
+}
+
+bitfield! {
+    struct LayoutV1Reordered([u8]);
+    impl Fields;
+    u8;
+    /// Documented.
+    flag, _: 4;
+    kind, set_kind: 3, 0 = 2;
+}
+
+bitfield_assert_same_layout!(LayoutV1, LayoutV1Reordered<[u8; 2]>);
+
+#[test]
+fn test_same_layout() {
+    assert!(LayoutV1::LAYOUT.same_layout(&LayoutV1Reordered::<[u8; 2]>::LAYOUT));
+    assert!(!LayoutV1::LAYOUT.same_layout(&LayoutV2::LAYOUT));
+    assert!(!LayoutV2::LAYOUT.same_layout(&LayoutV1::LAYOUT));
+    assert!(!LayoutV1::LAYOUT.same_layout(&LayoutV3::LAYOUT));
+}
+
+#[test]
+fn test_layout_compatibility() {
+    use bitfield::Compatibility;
+    assert_eq!(
+        LayoutV1::LAYOUT.is_compatible_with(&LayoutV1::LAYOUT),
+        Compatibility::Identical
+    );
+    assert_eq!(
+        LayoutV2::LAYOUT.is_compatible_with(&LayoutV1::LAYOUT),
+        Compatibility::Extended
+    );
+    assert_eq!(
+        LayoutV1::LAYOUT.is_compatible_with(&LayoutV2::LAYOUT),
+        Compatibility::Incompatible
+    );
+    assert_eq!(
+        LayoutV3::LAYOUT.is_compatible_with(&LayoutV1::LAYOUT),
+        Compatibility::Incompatible
+    );
+    assert_eq!(
+        LayoutV1::LAYOUT.hash(),
+        bitfield::layout_hash(LayoutV1::FIELDS)
+    );
+}
+
+bitfield! {
+    struct CheckedSlice([u8]);
+    impl TryFrom;
+    u8;
+    flag, set_flag: 0;
+    array, set_array: 11, 8, 2;
+    #[cfg(not(test))]
+    not_compiled, set_not_compiled: 31, 24;
+}
+
+#[test]
+fn test_try_from_slice() {
+    use std::convert::TryFrom;
+    assert_eq!(CheckedSlice::<&[u8]>::required_len(), 2);
+
+    let bytes = [0x01u8, 0x21, 0xFF];
+    let value = CheckedSlice::try_from(&bytes[..2]).unwrap();
+    assert!(value.flag());
+    assert_eq!(value.array(1), 0x2);
+    assert_eq!(
+        CheckedSlice::try_from_slice(&bytes[..1]).err(),
+        Some(bitfield::BufferTooShort {
+            required: 2,
+            actual: 1
+        })
+    );
+
+    let mut bytes = [0u8; 2];
+    let mut value = CheckedSlice::try_from(&mut bytes[..]).unwrap();
+    value.set_array(0, 0x3);
+    assert_eq!(bytes, [0x00, 0x03]);
+    assert!(CheckedSlice::try_from_mut_slice(&mut []).is_err());
+}
+
+bitfield! {
+    struct NotInt(u16);
+    impl Not;
+    u8;
+    low, _: 7, 0;
+}
+
+bitfield! {
+    struct NotSlice([u8]);
+    impl Not;
+    u8;
+    low, _: 7, 0;
+}
+
+#[test]
+fn test_not() {
+    assert_eq!((!NotInt(0x00F0)).0, 0xFF0F);
+    assert_eq!((!NotInt(0x00F0)).low(), 0x0F);
+    assert_eq!((!NotSlice([0x0Fu8, 0xFF])).0, [0xF0, 0x00]);
+}
+
+bitfield! {
+    struct ShiftInt(u16);
+    impl Shl;
+    impl Shr;
+    u8;
+    low, _: 7, 0;
+}
+
+bitfield! {
+    struct ShiftSlice([u8]);
+    impl Shl;
+    impl Shr;
+    u8;
+    low, _: 7, 0;
+    middle, _: 11, 4;
+}
+
+bitfield! {
+    struct ShiftSliceMsb0(MSB0 [u8]);
+    impl Shl;
+    impl Shr;
+    u8;
+    first, _: 7, 0;
+    middle, _: 11, 4;
+}
+
+#[test]
+fn test_shift() {
+    assert_eq!((ShiftInt(0x00F1) << 4).0, 0x0F10);
+    assert_eq!((ShiftInt(0x0F10) >> 8).low(), 0x0F);
+    assert_eq!((ShiftInt(0xFFFF) << 16).0, 0);
+    let mut value = ShiftInt(0x8001);
+    value >>= 15;
+    assert_eq!(value.0, 1);
+    value <<= 100;
+    assert_eq!(value.0, 0);
+
+    let value = ShiftSlice([0xABu8, 0x00, 0x00]) << 4;
+    assert_eq!(value.0, [0xB0, 0x0A, 0x00]);
+    assert_eq!(value.middle(), 0xAB);
+    assert_eq!((value << 12).0, [0x00, 0x00, 0xAB]);
+    let mut value = ShiftSlice([0x00u8, 0x00, 0xAB]);
+    value >>= 12;
+    assert_eq!(value.0, [0xB0, 0x0A, 0x00]);
+    value >>= 4;
+    assert_eq!(value.0, [0xAB, 0x00, 0x00]);
+    assert_eq!((value >> 8).0, [0x00, 0x00, 0x00]);
+    assert_eq!((ShiftSlice([0xFFu8; 3]) << 24).0, [0x00; 3]);
+    assert_eq!((ShiftSlice([0xFFu8; 3]) >> 100).0, [0x00; 3]);
+}
+
+#[test]
+fn test_shift_msb0() {
+    let value = ShiftSliceMsb0([0x00u8, 0x00, 0xAB]) << 4;
+    assert_eq!(value.0, [0x00, 0x0A, 0xB0]);
+    assert_eq!((value << 12).0, [0xAB, 0x00, 0x00]);
+    assert_eq!((ShiftSliceMsb0([0x00u8, 0x00, 0xAB]) << 16).first(), 0xAB);
+    assert_eq!(
+        (ShiftSliceMsb0([0x01u8, 0x80, 0x00]) << 1).0,
+        [0x03, 0x00, 0x00]
+    );
+    let mut value = ShiftSliceMsb0([0xABu8, 0x00, 0x00]);
+    value >>= 4;
+    assert_eq!(value.0, [0x0A, 0xB0, 0x00]);
+    assert_eq!(value.middle(), 0xAB);
+    value >>= 12;
+    assert_eq!(value.0, [0x00, 0x00, 0xAB]);
+    assert_eq!((value >> 8).0, [0x00, 0x00, 0x00]);
+    assert_eq!((ShiftSliceMsb0([0xFFu8; 3]) << 100).0, [0x00; 3]);
+}
+
 mod some_module {
     bitfield! {
-        pub(super) struct PubBitFieldInAModule(u32);
-        impl Debug;
-        /// Attribute works on pub fields
-        pub field1, set_field1: 1;
-        pub field2, _: 1;
-        pub _, set_field3: 1;
-        pub u16, field4, set_field4: 1;
-        /// Check if multiple attributes are applied
-        #[cfg(not(test))]
-        pub u16, field4, set_field4: 1;
-        pub u16, _, set_field5: 1;
-        pub u16, field6, _: 1;
-        pub field7, set_field7: 1;
-        pub field8, set_field8: 1, 1;
-        #[cfg(not(test))]
-        /// And make sure not only the last attributes is applied
-        pub field8, set_field8: 1, 1;
-        pub field9, set_field9: 1, 1, 1;
-        pub u32, field10, set_field10: 1;
-        pub u32, field11, set_field11: 1, 1;
-        pub u32, field12, set_field12: 1, 1, 1;
+        pub(super) struct PubBitFieldInAModule(u32);
+        impl Debug;
+        /// Attribute works on pub fields
+        pub field1, set_field1: 1;
+        pub field2, _: 1;
+        pub _, set_field3: 1;
+        pub u16, field4, set_field4: 1;
+        /// Check if multiple attributes are applied
+        #[cfg(not(test))]
+        pub u16, field4, set_field4: 1;
+        pub u16, _, set_field5: 1;
+        pub u16, field6, _: 1;
+        pub field7, set_field7: 1;
+        pub field8, set_field8: 1, 1;
+        #[cfg(not(test))]
+        /// And make sure not only the last attributes is applied
+        pub field8, set_field8: 1, 1;
+        pub field9, set_field9: 1, 1, 1;
+        pub u32, field10, set_field10: 1;
+        pub u32, field11, set_field11: 1, 1;
+        pub u32, field12, set_field12: 1, 1, 1;
+    }
+}
+
+#[test]
+fn struct_can_be_public() {
+    let _ = some_module::PubBitFieldInAModule(0);
+}
+#[test]
+fn field_can_be_public() {
+    let mut a = some_module::PubBitFieldInAModule(0);
+    let _ = a.field1();
+    a.set_field1(true);
+    let _ = a.field2();
+    a.set_field3(true);
+    let _ = a.field4();
+    a.set_field4(true);
+    a.set_field5(true);
+    let _ = a.field6();
+    let _ = a.field7();
+    a.set_field7(true);
+    let _ = a.field8();
+    a.set_field8(0);
+    let _ = a.field9(0);
+    a.set_field9(0, 0);
+    let _ = a.field10();
+    a.set_field10(true);
+    let _ = a.field11();
+    a.set_field11(0);
+    let _ = a.field12(0);
+    a.set_field12(0, 0);
+}
+
+mod split_visibility {
+    bitfield! {
+        pub struct StatusRegister(u32);
+        impl With;
+        u8;
+        pub ready, pub(crate) set_ready: 0;
+        pub(crate) mode, set_mode: 7, 4;
+        pub level, pub(self) set_level: 15, 8;
+        pub(in crate::split_visibility) lanes, pub set_lanes: 17, 16, 2;
+    }
+
+    impl StatusRegister {
+        pub fn reset_level(&mut self) {
+            self.set_level(0x5A);
+        }
+    }
+}
+
+#[test]
+fn test_split_visibility() {
+    let mut status = split_visibility::StatusRegister(0);
+    status.set_ready(true);
+    status.set_mode(3);
+    status.set_lanes(1, 1);
+    status.reset_level();
+    assert!(status.ready());
+    assert_eq!(status.mode(), 3);
+    assert_eq!(status.level(), 0x5A);
+    assert_eq!(status.0, 0x0004_5A31);
+    let status = status.with_ready(false).with_mode(0);
+    assert_eq!(status.0, 0x0004_5A00);
+}
+
+// Everything in this module is to make sure that its possible to specify types
+// in most of the possible ways.
+#[allow(dead_code)]
+mod test_types {
+    use bitfield::{BitRange, BitRangeMut};
+    use std::sync::atomic::{self, AtomicUsize};
+
+    struct Foo;
+
+    impl Foo {
+        bitfield_fields! {
+            std::sync::atomic::AtomicUsize, field1, set_field1: 0, 0;
+            std::sync::atomic::AtomicUsize;
+            field2, set_field2: 0, 0;
+            ::std::sync::atomic::AtomicUsize, field3, set_field3: 0, 0;
+            ::std::sync::atomic::AtomicUsize;
+            field4, set_field4: 0, 0;
+            atomic::AtomicUsize, field5, set_field5: 0, 0;
+            atomic::AtomicUsize;
+            field6, set_field6: 0, 0;
+            AtomicUsize, field7, set_field7: 0, 0;
+            AtomicUsize;
+            field8, set_field8: 0, 0;
+            Vec<std::sync::atomic::AtomicUsize>, field9, set_field9: 0, 0;
+            Vec<std::sync::atomic::AtomicUsize>;
+            field10, set_field10: 0, 0;
+            Vec<::std::sync::atomic::AtomicUsize>, field11, set_field11: 0, 0;
+            Vec<::std::sync::atomic::AtomicUsize>;
+            field12, set_field12: 0, 0;
+            Vec<atomic::AtomicUsize>, field13, set_field13: 0, 0;
+            Vec<atomic::AtomicUsize>;
+            field14, set_field14: 0, 0;
+            Vec<AtomicUsize>, field15, set_field15: 0, 0;
+            Vec<AtomicUsize>;
+            field16, set_field16: 0, 0;
+            &str, field17, set_field17: 0, 0;
+            &str;
+            field18, set_field18: 0, 0;
+            &'static str, field19, set_field19: 0, 0;
+            &'static str;
+            field20, set_field20: 0, 0;
+        }
+    }
+
+    impl BitRange<AtomicUsize> for Foo {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> AtomicUsize {
+            AtomicUsize::new(0)
+        }
+    }
+
+    impl BitRangeMut<AtomicUsize> for Foo {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: AtomicUsize) {}
+    }
+
+    impl BitRange<Vec<AtomicUsize>> for Foo {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> Vec<AtomicUsize> {
+            vec![AtomicUsize::new(0)]
+        }
+    }
+
+    impl BitRangeMut<Vec<AtomicUsize>> for Foo {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: Vec<AtomicUsize>) {}
+    }
+
+    impl<'a> BitRange<&'a str> for Foo {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> &'a str {
+            ""
+        }
+    }
+
+    impl<'a> BitRangeMut<&'a str> for Foo {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: &'a str) {}
+    }
+
+    #[test]
+    fn test_field_type() {
+        let test = Foo;
+        let _: AtomicUsize = test.field1();
+        let _: AtomicUsize = test.field2();
+        let _: AtomicUsize = test.field3();
+        let _: AtomicUsize = test.field4();
+        let _: AtomicUsize = test.field5();
+        let _: AtomicUsize = test.field6();
+        let _: AtomicUsize = test.field7();
+        let _: AtomicUsize = test.field8();
+        let _: Vec<AtomicUsize> = test.field9();
+        let _: Vec<AtomicUsize> = test.field10();
+        let _: Vec<AtomicUsize> = test.field11();
+        let _: Vec<AtomicUsize> = test.field12();
+        let _: Vec<AtomicUsize> = test.field13();
+        let _: Vec<AtomicUsize> = test.field14();
+        let _: Vec<AtomicUsize> = test.field15();
+        let _: Vec<AtomicUsize> = test.field16();
+        let _: &str = test.field17();
+        let _: &str = test.field18();
+        let _: &'static str = test.field19();
+        let _: &'static str = test.field20();
+    }
+}
+
+#[allow(dead_code)]
+mod test_no_default_bitrange {
+    use bitfield::{BitRange, BitRangeMut};
+    use std::fmt::Debug;
+    use std::fmt::Error;
+    use std::fmt::Formatter;
+
+    use crate::FooBar;
+    bitfield! {
+      #[derive(Eq, PartialEq)]
+      pub struct BitField1(u16);
+      no default BitRange;
+      impl Debug;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 2;
+    }
+
+    impl BitRange<u8> for BitField1 {
+        fn bit_range(&self, msb: usize, lsb: usize) -> u8 {
+            (msb + lsb) as u8
+        }
+    }
+
+    impl BitRangeMut<u8> for BitField1 {
+        fn set_bit_range(&mut self, msb: usize, lsb: usize, value: u8) {
+            self.0 = msb as u16 + lsb as u16 + u16::from(value)
+        }
+    }
+
+    #[allow(clippy::identity_op)]
+    #[test]
+    fn custom_bitrange_implementation_is_used() {
+        let mut bf = BitField1(0);
+        assert_eq!(bf.field1(), 10 + 0);
+        assert_eq!(bf.field2(), 12 + 3);
+        assert!(bf.field3());
+        bf.set_field1(42);
+        assert_eq!(bf, BitField1(10 + 0 + 42));
+    }
+
+    bitfield! {
+      pub(crate) struct BitField2(u16);
+      no default BitRange;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 0;
+    }
+
+    impl BitRange<u8> for BitField2 {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
+            0
+        }
+    }
+
+    impl BitRangeMut<u8> for BitField2 {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
+    }
+
+    // Make sure Debug wasn't implemented by implementing it.
+    impl Debug for BitField2 {
+        fn fmt(&self, _: &mut Formatter) -> Result<(), Error> {
+            unimplemented!()
+        }
+    }
+
+    // Check that we can put `impl Debug` before `no default BitRange`
+    bitfield! {
+      pub struct BitField3(u16);
+      impl Debug;
+      no default BitRange;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 0;
+    }
+
+    impl BitRange<u8> for BitField3 {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
+            0
+        }
+    }
+
+    impl BitRangeMut<u8> for BitField3 {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
+    }
+
+    bitfield! {
+      #[derive(Eq, PartialEq)]
+      pub struct BitField4([u16]);
+      no default BitRange;
+      impl Debug;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 2;
+    }
+
+    impl<T> BitRange<u8> for BitField4<T> {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
+            0
+        }
+    }
+
+    impl<T> BitRangeMut<u8> for BitField4<T> {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
+    }
+
+    bitfield! {
+      pub struct BitField5([u16]);
+      no default BitRange;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 0;
+    }
+
+    impl<T> BitRange<u8> for BitField5<T> {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
+            0
+        }
+    }
+
+    impl<T> BitRangeMut<u8> for BitField5<T> {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
+    }
+
+    // Make sure Debug wasn't implemented by implementing it.
+    impl<T> Debug for BitField5<T> {
+        fn fmt(&self, _: &mut Formatter) -> Result<(), Error> {
+            unimplemented!()
+        }
+    }
+
+    // Check that we can put `impl Debug` before `no default BitRange`
+    bitfield! {
+      pub struct BitField6([u16]);
+      impl Debug;
+      no default BitRange;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 0;
+    }
+
+    impl<T> BitRange<u8> for BitField6<T> {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
+            0
+        }
+    }
+
+    impl<T> BitRangeMut<u8> for BitField6<T> {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
+    }
+
+    bitfield! {
+      #[derive(Eq, PartialEq)]
+      pub struct BitField7(MSB0 [u16]);
+      no default BitRange;
+      impl Debug;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 2;
+    }
+
+    impl<T> BitRange<u8> for BitField7<T> {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
+            0
+        }
+    }
+
+    impl<T> BitRangeMut<u8> for BitField7<T> {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
+    }
+
+    bitfield! {
+      pub struct BitField8(MSB0 [u16]);
+      no default BitRange;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 0;
+    }
+
+    impl<T> BitRange<u8> for BitField8<T> {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
+            0
+        }
+    }
+
+    impl<T> BitRangeMut<u8> for BitField8<T> {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
+    }
+
+    // Make sure Debug wasn't implemented by implementing it.
+    impl<T> Debug for BitField8<T> {
+        fn fmt(&self, _: &mut Formatter) -> Result<(), Error> {
+            unimplemented!()
+        }
+    }
+
+    // Check that we can put `impl Debug` before `no default BitRange`
+    bitfield! {
+      pub struct BitField9([u16]);
+      impl Debug;
+      no default BitRange;
+      u8;
+      field1, set_field1: 10, 0;
+      pub field2, _ : 12, 3;
+      field3, set_field3: 0;
+    }
+
+    impl<T> BitRange<u8> for BitField9<T> {
+        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
+            0
+        }
+    }
+
+    impl<T> BitRangeMut<u8> for BitField9<T> {
+        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
+    }
+
+    #[test]
+    fn test_debug_is_implemented_with_no_default_bitrange() {
+        let _ = format!("{:?}", BitField1(0));
+        let _ = format!("{:?}", BitField3(0));
+        let _ = format!("{:?}", BitField4([0; 1]));
+        let _ = format!("{:?}", BitField6([0; 1]));
+        let _ = format!("{:?}", BitField7([0; 1]));
+        let _ = format!("{:?}", BitField9([0; 1]));
+    }
+
+    #[test]
+    fn masks() {
+        assert_eq!(FooBar::I128_MASK, 0b111111110i128);
+        assert_eq!(FooBar::U128_MASK, 0b111111110u128);
+        assert_eq!(FooBar::SETTER_MASK, 0b11100u32);
+        assert_eq!(FooBar::GETTER_MASK, 0b1110u32);
+        assert_eq!(FooBar::PUB_SETTER_MASK, 0b11100u32);
+        assert_eq!(FooBar::PUB_GETTER_MASK, 0b1110u32);
+        assert_eq!(FooBar::SINGLE_BIT_MASK, 1 << 3);
+        assert_eq!(FooBar::PUB_MASK, 1 << 31);
+    }
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    struct Descriptor(u32);
+    impl CastSlice;
+    impl Debug;
+    u16;
+    length, set_length: 15, 0;
+    u8;
+    kind, set_kind: 23, 16;
+    valid, set_valid: 31;
+}
+
+#[test]
+fn test_cast_slice() {
+    let mut table = [0x8001_0004u32, 0x0002_0010];
+    {
+        let descriptors = Descriptor::cast_slice(&table);
+        assert_eq!(descriptors.len(), 2);
+        assert_eq!(
+            (
+                descriptors[0].length(),
+                descriptors[0].kind(),
+                descriptors[0].valid()
+            ),
+            (4, 1, true)
+        );
+        assert_eq!(
+            (
+                descriptors[1].length(),
+                descriptors[1].kind(),
+                descriptors[1].valid()
+            ),
+            (0x10, 2, false)
+        );
+    }
+    {
+        let descriptors = Descriptor::cast_slice_mut(&mut table);
+        descriptors[1].set_valid(true);
+        descriptors[0].set_length(8);
+    }
+    assert_eq!(table, [0x8001_0008, 0x8002_0010]);
+}
+
+trait DmaChannel {
+    const ENABLE_MASK: u32;
+
+    fn enabled(&self) -> bool;
+    fn set_enabled(&mut self, value: bool);
+    fn burst(&self) -> u8;
+    fn set_burst(&mut self, value: u8);
+    fn priority(&self, index: usize) -> u8;
+    fn set_priority(&mut self, index: usize, value: u8);
+}
+
+struct DmaControl(u32);
+bitfield_bitrange! {struct DmaControl(u32)}
+
+impl DmaChannel for DmaControl {
+    bitfield_fields! {
+        u8;
+        mask ENABLE_MASK(u32), enabled, set_enabled: 0;
+        burst, set_burst: 7, 4;
+        priority, set_priority: 9, 8, 2;
+    }
+}
+
+fn configure<T: DmaChannel>(channel: &mut T) {
+    channel.set_enabled(true);
+    channel.set_burst(4);
+    channel.set_priority(1, 3);
+}
+
+#[test]
+fn test_fields_in_trait_impl() {
+    let mut control = DmaControl(0);
+    configure(&mut control);
+    assert_eq!(control.0, 0b1100_0100_0001);
+    assert_eq!(
+        (control.enabled(), control.burst(), control.priority(1)),
+        (true, 4, 3)
+    );
+    assert_eq!(DmaControl::ENABLE_MASK, 1);
+}
+
+bitfield! {
+    struct CopyableSlice([u8]);
+    impl Clone;
+    impl Copy;
+    u8;
+    kind, set_kind: 3, 0;
+    length, set_length: 15, 8;
+}
+
+bitfield! {
+    struct CopyableInt(u16);
+    impl Clone;
+    impl Copy;
+    u8;
+    kind, set_kind: 3, 0;
+}
+
+#[test]
+fn test_clone_copy() {
+    let mut owned = CopyableSlice([0x05u8, 0x20]);
+    let copy = owned;
+    owned.set_kind(7);
+    assert_eq!((copy.kind(), owned.kind()), (5, 7));
+    #[allow(clippy::clone_on_copy)]
+    let clone = owned.clone();
+    assert_eq!(clone.0, [0x07, 0x20]);
+
+    let storage = [0x03u8, 0x10];
+    let borrowed = CopyableSlice(&storage[..]);
+    let copy = borrowed;
+    assert_eq!((borrowed.kind(), copy.length()), (3, 0x10));
+
+    let mut int = CopyableInt(2);
+    let copy = int;
+    int.set_kind(9);
+    assert_eq!((copy.kind(), int.kind()), (2, 9));
+}
+
+bitfield! {
+    struct AdcSample(u16);
+    u16;
+    raw, set_raw: 11, 0;
+    signed, offset, set_offset: 11, 0;
+    signed, into i32, wide_offset, _: 11, 0;
+}
+
+bitfield! {
+    struct SignedSlice([u8]);
+    i8, low, set_low: 3, 0;
+    i16, middle, set_middle: 13, 4;
+    u8, signed, high, set_high: 15, 14;
+}
+
+bitfield! {
+    struct SignedSliceMsb0(MSB0 [u8]);
+    i8, first, set_first: 3, 0;
+    i16, second, set_second: 13, 4;
+}
+
+#[test]
+fn test_sign_extension() {
+    let mut sample = AdcSample(0);
+    sample.set_offset(-3);
+    assert_eq!(sample.raw(), 0xffd);
+    assert_eq!((sample.offset(), sample.wide_offset()), (-3, -3));
+    sample.set_raw(0x7ff);
+    assert_eq!(sample.offset(), 0x7ff);
+
+    let mut slice = SignedSlice([0u8; 2]);
+    slice.set_low(-2);
+    slice.set_middle(-300);
+    slice.set_high(-1);
+    assert_eq!(slice.0, [0x4e, 0xed]);
+    assert_eq!((slice.low(), slice.middle(), slice.high()), (-2, -300, -1));
+    slice.set_middle(300);
+    assert_eq!((slice.low(), slice.middle(), slice.high()), (-2, 300, -1));
+
+    let mut msb0 = SignedSliceMsb0([0u8; 2]);
+    msb0.set_first(-8);
+    msb0.set_second(-1);
+    assert_eq!(msb0.0, [0x8f, 0xfc]);
+    assert_eq!((msb0.first(), msb0.second()), (-8, -1));
+}
+
+mod registers {
+    pub const CHANNEL_LSB: usize = 8;
+
+    bitfield! {
+        pub struct DmaConfig(u32);
+        impl FieldsModule{dma_config};
+        u8;
+        pub enabled, set_enabled: 0;
+        pub burst, set_burst: 7, 4;
+        pub channel, set_channel: CHANNEL_LSB + 3, CHANNEL_LSB;
+        pub priority, set_priority: 17, 16, 4;
+        pub _, set_trigger: 31;
+    }
+
+    bitfield! {
+        pub struct DmaDescriptor([u8]);
+        impl FieldsModule{dma_descriptor};
+        u16;
+        pub length, set_length: 15, 0;
+        pub lanes, set_lane: 19, 16, 3;
+    }
+}
+
+#[test]
+fn test_fields_module() {
+    use registers::{dma_config, dma_descriptor};
+
+    assert_eq!(
+        (
+            dma_config::enabled::OFFSET,
+            dma_config::enabled::WIDTH,
+            dma_config::enabled::MASK
+        ),
+        (0, 1, 1)
+    );
+    assert_eq!(
+        (
+            dma_config::burst::OFFSET,
+            dma_config::burst::WIDTH,
+            dma_config::burst::MASK
+        ),
+        (4, 4, 0xf0)
+    );
+    assert_eq!(
+        (
+            dma_config::channel::OFFSET,
+            dma_config::channel::WIDTH,
+            dma_config::channel::MASK
+        ),
+        (8, 4, 0xf00)
+    );
+    assert_eq!(
+        (
+            dma_config::priority::OFFSET,
+            dma_config::priority::WIDTH,
+            dma_config::priority::COUNT,
+            dma_config::priority::MASK
+        ),
+        (16, 2, 4, 0xff_0000)
+    );
+    assert_eq!(dma_config::set_trigger::MASK, 0x8000_0000);
+
+    assert_eq!(
+        (
+            dma_descriptor::length::OFFSET,
+            dma_descriptor::length::WIDTH
+        ),
+        (0, 16)
+    );
+    assert_eq!(
+        (
+            dma_descriptor::lanes::OFFSET,
+            dma_descriptor::lanes::WIDTH,
+            dma_descriptor::lanes::COUNT
+        ),
+        (16, 4, 3)
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ClockSource {
+    Internal,
+    External,
+    Pll,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ReservedEncoding(u8);
+
+impl std::convert::TryFrom<u8> for ClockSource {
+    type Error = ReservedEncoding;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ClockSource::Internal),
+            1 => Ok(ClockSource::External),
+            2 => Ok(ClockSource::Pll),
+            _ => Err(ReservedEncoding(value)),
+        }
+    }
+}
+
+bitfield! {
+    struct ClockControl(u16);
+    impl Debug;
+    u8;
+    try_into ClockSource, source, set_source: 1, 0;
+    try_into ClockSource, sources, set_sources: 5, 4, 2;
+}
+
+#[test]
+fn test_try_into() {
+    let mut control = ClockControl(0);
+    assert_eq!(control.source(), Ok(ClockSource::Internal));
+    control.set_source(2);
+    assert_eq!(control.source(), Ok(ClockSource::Pll));
+    control.set_source(3);
+    assert_eq!(control.source(), Err(ReservedEncoding(3)));
+
+    control.set_sources(1, 1);
+    control.set_sources(0, 3);
+    assert_eq!(control.sources(0), Err(ReservedEncoding(3)));
+    assert_eq!(control.sources(1), Ok(ClockSource::External));
+    assert_eq!(
+        format!("{:?}", control),
+        "ClockControl { .0: 115, source: Err(ReservedEncoding(3)), sources: [Err(ReservedEncoding(3)), Ok(External)] }"
+    );
+}
+
+static DECODE_COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DecodedKind(u8);
+
+impl From<u8> for DecodedKind {
+    fn from(value: u8) -> Self {
+        DECODE_COUNT.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        DecodedKind(value)
+    }
+}
+
+bitfield! {
+    struct FrameHeader(u16);
+    impl Debug;
+    impl Cached{#[derive(Debug)] CachedFrameHeader};
+    u8;
+    into DecodedKind, kind, set_kind: 3, 0;
+    into DecodedKind, lanes, set_lane: 11, 8, 2;
+    flag, set_flag: 15;
+    _, set_write_only: 14;
+}
+
+bitfield! {
+    struct FrameSlice([u8]);
+    impl Cached{CachedFrameSlice};
+    u8;
+    kind, set_kind: 3, 0;
+}
+
+#[test]
+fn test_cached() {
+    let mut cached = CachedFrameHeader::new(FrameHeader(0x8305));
+    let decodes = DECODE_COUNT.load(std::sync::atomic::Ordering::SeqCst);
+    assert_eq!(
+        (cached.kind(), cached.kind()),
+        (DecodedKind(5), DecodedKind(5))
+    );
+    assert_eq!(
+        (cached.lanes(0), cached.lanes(0)),
+        (DecodedKind(3), DecodedKind(3))
+    );
+    assert_eq!(
+        DECODE_COUNT.load(std::sync::atomic::Ordering::SeqCst),
+        decodes + 2
+    );
+    assert!(cached.flag());
+
+    cached.set_kind(7);
+    assert_eq!(cached.kind(), DecodedKind(7));
+    assert_eq!(
+        DECODE_COUNT.load(std::sync::atomic::Ordering::SeqCst),
+        decodes + 3
+    );
+    cached.get_mut().set_flag(false);
+    cached.set_write_only(true);
+    assert!(!cached.flag());
+    assert_eq!(cached.get_ref().0, 0x4307);
+    assert_eq!(cached.into_inner().0, 0x4307);
+
+    let mut slice = CachedFrameSlice::from(FrameSlice([0x12u8, 0x34]));
+    assert_eq!(slice.kind(), 2);
+    slice.set_kind(9);
+    assert_eq!(slice.kind(), 9);
+}
+
+bitfield! {
+    struct CheckedControl(u32);
+    impl TrySet;
+    u8;
+    mode, set_mode: 3, 0;
+    i8, trim, set_trim: 7, 4;
+    enabled, set_enabled: 8;
+    lanes, set_lane: 13, 12, 2;
+    u32, full, set_full: 31, 0;
+}
+
+bitfield! {
+    struct TrySetSlice([u8]);
+    impl TrySet;
+    u16;
+    length, set_length: 11, 0;
+}
+
+#[test]
+fn test_try_set() {
+    let mut control = CheckedControl(0);
+    assert_eq!(control.try_set_mode(15), Ok(()));
+    assert_eq!(
+        control.try_set_mode(16),
+        Err(bitfield::ValueTooLarge {
+            field: "mode",
+            width: 4
+        })
+    );
+    assert_eq!(control.mode(), 15);
+    assert_eq!(control.try_set_trim(-8), Ok(()));
+    assert_eq!(control.trim(), -8);
+    assert!(control.try_set_trim(8).is_err());
+    assert!(control.try_set_trim(-9).is_err());
+    assert_eq!(control.try_set_lane(1, 3), Ok(()));
+    assert!(control.try_set_lane(0, 4).is_err());
+    assert_eq!(control.0, 0xc08f);
+    assert_eq!(control.try_set_full(u32::MAX), Ok(()));
+
+    let mut slice = TrySetSlice([0u8; 2]);
+    assert_eq!(slice.try_set_length(0xfff), Ok(()));
+    let error = slice.try_set_length(0x1000).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "value too large for the 12 bits of the field `length`"
+    );
+    assert_eq!(slice.0, [0xff, 0x0f]);
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    struct StatusRegister(u32);
+    storage #[derive(Clone, Copy)] StatusBytes([u8; 4]);
+    impl Debug;
+    impl PartialEq;
+    u8;
+    kind, set_kind: 3, 0;
+    u16, length, set_length: 19, 8;
+    ready, set_ready: 31;
+}
+
+bitfield! {
+    struct WideRegister(u64);
+    storage WideWords([u16; 4]);
+    u16;
+    low, set_low: 11, 4;
+    high, set_high: 63, 56;
+}
+
+#[test]
+fn test_multiple_storages() {
+    let mut register = StatusRegister(0);
+    register.set_kind(5);
+    register.set_length(0xabc);
+    register.set_ready(true);
+    register.0 |= 0x0070_0000;
+
+    let bytes = StatusBytes::from(register);
+    assert_eq!(bytes.0, [0x05, 0xbc, 0x7a, 0x80]);
+    assert_eq!(
+        (bytes.kind(), bytes.length(), bytes.ready()),
+        (5, 0xabc, true)
+    );
+    assert_eq!(StatusRegister::from(bytes), register);
+
+    let mut words = WideWords([0u16; 4]);
+    words.set_low(0xff);
+    words.set_high(0x12);
+    words.0[2] = 0x5555;
+    let expected = words.0;
+    let wide = WideRegister::from(words);
+    assert_eq!(wide.0, 0x1200_5555_0000_0ff0);
+    assert_eq!((wide.low(), wide.high()), (0xff, 0x12));
+    assert_eq!(WideWords::from(wide).0, expected);
+}
+
+bitfield! {
+    struct Counters(u32);
+    u8;
+    saturating, events, set_events: 3, 0;
+    i8, saturating, drift, set_drift: 7, 4;
+    saturating, bins, set_bin: 9, 8, 2;
+    u32, saturating, total, set_total: 31, 0;
+    saturating, set_write_only, _: 12;
+    wrapping, set_wrapping: 15, 13;
+}
+
+bitfield! {
+    struct CountersSlice([u8]);
+    u16;
+    saturating, from into u16, events, set_events: 11, 0;
+}
+
+#[test]
+fn test_saturating() {
+    let mut counters = Counters(0);
+    counters.set_events(20);
+    assert_eq!(counters.events(), 15);
+    counters.set_events(9);
+    assert_eq!(counters.events(), 9);
+    counters.set_drift(100);
+    assert_eq!(counters.drift(), 7);
+    counters.set_drift(-100);
+    assert_eq!(counters.drift(), -8);
+    counters.set_drift(-3);
+    assert_eq!(counters.drift(), -3);
+    counters.set_bin(1, 7);
+    assert_eq!((counters.bins(0), counters.bins(1)), (0, 3));
+    counters.set_wrapping(9);
+    assert_eq!(counters.wrapping(), 1);
+    counters.set_total(u32::MAX);
+    assert_eq!(counters.total(), u32::MAX);
+
+    let mut slice = CountersSlice([0u8; 2]);
+    slice.set_events(0x1234);
+    assert_eq!(slice.events(), 0xfff);
+}
+
+bitfield! {
+    struct WireHeader(MSB0 [u8]);
+    impl RawBits;
+    u8;
+    version, set_version: 3, 0;
+    u16, length, set_length: 15, 4;
+    flag, set_flag: 19;
+}
+
+bitfield! {
+    struct LsbHeader([u8]);
+    impl RawBits;
+    u8;
+    version, set_version: 3, 0;
+    flag, set_flag: 11;
+}
+
+#[test]
+fn test_raw_bits() {
+    let mut header = WireHeader([0x4a, 0xbc, 0xd0, 0xff]);
+    assert_eq!(WireHeader::<[u8; 4]>::raw_bits_width(), 20);
+    assert_eq!(header.raw_bits(), 0x4abcd);
+    assert_eq!(
+        (header.version(), header.length(), header.flag()),
+        (4, 0xabc, true)
+    );
+    header.set_raw_bits(0x12345);
+    assert_eq!(header.0, [0x12, 0x34, 0x50, 0xff]);
+    assert_eq!((header.version(), header.length()), (1, 0x234));
+
+    let mut lsb = LsbHeader([0x21u8, 0xf8, 0xff]);
+    assert_eq!(lsb.raw_bits(), 0x821);
+    lsb.set_raw_bits(0x7ff);
+    assert_eq!(lsb.0, [0xff, 0xf7, 0xff]);
+}
+
+bitfield! {
+    struct CheckedRegister(u32);
+    impl Debug;
+    u8;
+    unchecked, set_unchecked: 3, 0;
+    checked;
+    mode, set_mode: 7, 4;
+    i8, offset, set_offset: 11, 8;
+    u8, saturating, level, set_level: 15, 12;
+    u16, into u16, length, set_length: 27, 16;
+    checked, set_checked: 28;
+}
+
+#[test]
+fn test_checked() {
+    let mut reg = CheckedRegister(0);
+    reg.set_unchecked(0x1f);
+    reg.set_mode(0xf);
+    reg.set_offset(-8);
+    reg.set_level(0xff);
+    reg.set_length(0xfff);
+    reg.set_checked(true);
+    assert_eq!(reg.0, 0x1fff_f8ff);
+    assert_eq!(reg.offset(), -8);
+}
+
+#[test]
+#[should_panic]
+fn test_checked_setter_too_large() {
+    CheckedRegister(0).set_mode(0x10);
+}
+
+#[test]
+#[should_panic]
+fn test_checked_signed_setter_too_large() {
+    CheckedRegister(0).set_offset(8);
+}
+
+#[test]
+#[should_panic]
+fn test_checked_conversion_setter_too_large() {
+    CheckedRegister(0).set_length(0x1000);
+}
+
+#[test]
+fn test_field_bit_address() {
+    let nibble = FieldsTable::field_bit_address(0x1000, FieldsTableField::Nibble);
+    let signed = FieldsTable::field_bit_address(0x1000, FieldsTableField::Signed);
+    if cfg!(target_endian = "big") {
+        assert_eq!((nibble, signed), ((0x1003, 4), (0x1002, 0)));
+    } else {
+        assert_eq!((nibble, signed), ((0x1000, 4), (0x1001, 0)));
     }
+
+    type Msb0 = FieldsTableArray<[u8; 2]>;
+    assert_eq!(
+        Msb0::field_bit_address(0x40, FieldsTableArrayField::First),
+        (0x40, 7)
+    );
+    assert_eq!(
+        Msb0::field_bit_address(0x40, FieldsTableArrayField::Second),
+        (0x40, 3)
+    );
+}
+
+bitfield! {
+    struct SimControl(u16);
+    impl Fields;
+    impl AnyBitfield;
+    u8;
+    enable, set_enable: 0;
+    mode, set_mode: 7, 4;
+}
+
+bitfield! {
+    struct SimBuffer(MSB0 [u8]);
+    impl Fields;
+    impl AnyBitfield;
+    u8;
+    lanes, set_lanes: 3, 0, 3;
+}
+
+#[test]
+fn test_any_bitfield() {
+    use bitfield::AnyBitfield;
+
+    let mut registers: Vec<Box<dyn AnyBitfield>> = vec![
+        Box::new(SimControl(0x31)),
+        Box::new(SimBuffer([0x12, 0x30])),
+    ];
+
+    let names: Vec<_> = registers.iter().map(|r| (r.name(), r.bit_len())).collect();
+    assert_eq!(names, [("SimControl", 16), ("SimBuffer", 16)]);
+
+    assert_eq!(registers[0].fields()[1].name, "mode");
+    assert_eq!(registers[0].get_field(0, 0), 1);
+    assert_eq!(registers[0].get_field(1, 0), 3);
+    assert_eq!(registers[1].get_field(0, 2), 3);
+
+    registers[0].set_field(1, 0, 0xa);
+    registers[1].set_field(0, 1, 0xf);
+    assert_eq!(registers[0].get_field(1, 0), 0xa);
+    assert_eq!(
+        (0..3)
+            .map(|i| registers[1].get_field(0, i))
+            .collect::<Vec<_>>(),
+        [1, 0xf, 3]
+    );
+}
+
+bitfield! {
+    struct InterruptRouting(u64);
+    impl Debug;
+    impl Fields;
+    u8;
+    pub enabled, set_enabled: 0;
+    pub bool, route_cap, set_route_cap: 63, 32, 32;
+}
+
+bitfield! {
+    struct InterruptRoutingSlice(MSB0 [u8]);
+    bool, route, set_route: 15, 4, 12;
+}
+
+#[test]
+fn test_bool_array() {
+    let mut routing = InterruptRouting(0x0000_0005_0000_0000);
+    assert!(routing.route_cap(0));
+    assert!(!routing.route_cap(1));
+    assert!(routing.route_cap(2));
+    routing.set_route_cap(31, true);
+    routing.set_route_cap(0, false);
+    assert_eq!(routing.0, 0x8000_0004_0000_0000);
+
+    let field = &InterruptRouting::FIELDS[InterruptRoutingField::RouteCap as usize];
+    assert_eq!((field.lsb, field.width, field.count), (32, 1, 32));
+    assert_eq!(field.kind, bitfield::FieldKind::Bool);
+
+    let mut slice = InterruptRoutingSlice([0x08, 0x00]);
+    assert!(slice.route(0));
+    slice.set_route(11, true);
+    assert_eq!(slice.0, [0x08, 0x01]);
+}
+
+#[test]
+fn test_iter_fields() {
+    use FieldsTableField::*;
+
+    assert_eq!(
+        FieldsTableField::ALL,
+        [Flag, Nibble, Signed, Array, WriteOnly]
+    );
+    let fields: Vec<_> = FieldsTable(0xc0e4_f351).iter_fields().collect();
+    assert_eq!(
+        fields,
+        [
+            (Flag, 1),
+            (Nibble, 5),
+            (Signed, 0xf3),
+            (Array, 0),
+            (Array, 1),
+            (Array, 2),
+            (Array, 3),
+            (WriteOnly, 3),
+        ]
+    );
+}
+
+bitfield! {
+    struct RouteEntry([u8]);
+    u8;
+    address, set_address: 7, 0, 4;
+    u16, mask_bits, set_mask_bits: 35, 32, 2;
+}
+
+#[test]
+fn test_array_setter_all() {
+    let mut entry = RouteEntry([0u8; 5]);
+    entry.set_address_all([192, 168, 1, 254]);
+    entry.set_mask_bits_all([0x3, 0xc]);
+    assert_eq!(entry.0, [192, 168, 1, 254, 0xc3]);
+    assert_eq!(
+        (0..4).map(|i| entry.address(i)).collect::<Vec<_>>(),
+        [192, 168, 1, 254]
+    );
+
+    let mut routing = InterruptRouting(0);
+    let mut caps = [false; 32];
+    caps[1] = true;
+    routing.set_route_cap_all(caps);
+    assert_eq!(routing.0, 0x0000_0002_0000_0000);
+}
+
+struct MergedRegister(u32);
+bitfield_bitrange! {struct MergedRegister(u32)}
+
+bitfield_fields! {
+    struct MergedRegister as merged_control;
+    u8;
+    enable, set_enable: 0;
+    mode, set_mode: 7, 4;
+}
+
+bitfield_fields! {
+    struct MergedRegister as merged_all: merged_control;
+    u16;
+    mask MERGED_LENGTH_MASK(u32), length, set_length: 31, 16;
+}
+
+merged_all! {bitfield_impl!{Fields for struct MergedRegister(u32);}}
+merged_all! {bitfield::bitfield_impl!{LayoutHash for struct MergedRegister(u32);}}
+
+#[test]
+fn test_merged_fields() {
+    use MergedRegisterField::*;
+
+    let mut reg = MergedRegister(0);
+    reg.set_mode(0x5);
+    reg.set_length(0x1234);
+    assert_eq!(MergedRegister::MERGED_LENGTH_MASK, 0xffff_0000);
+    assert_eq!(MergedRegisterField::ALL, [Enable, Mode, Length]);
+    assert_eq!(
+        reg.iter_fields().collect::<Vec<_>>(),
+        [(Enable, 0), (Mode, 5), (Length, 0x1234)]
+    );
+    assert_eq!(
+        MergedRegister::LAYOUT_HASH,
+        bitfield::layout_hash(MergedRegister::FIELDS)
+    );
+}
+
+bitfield! {
+    struct AssertedRegister(u16);
+    impl Debug;
+    impl Fields;
+    u8;
+    ready, set_ready: 0;
+    state, set_state: 7, 4;
+    channels, set_channels: 9, 8, 3;
+}
+
+bitfield! {
+    struct AssertedSlice([u8]);
+    impl Debug;
+    impl Fields;
+    u8;
+    kind, set_kind: 3, 0;
+}
+
+#[test]
+fn test_assert_field_eq() {
+    let reg = AssertedRegister(0x0631);
+    assert_field_eq!(reg, ready, true);
+    assert_field_eq!(reg, state, 3);
+    assert_field_eq!(reg, channels[0], 2);
+    assert_fields_eq!(reg, { ready: true, state: 3, channels[1]: 1, channels[2]: 0 });
+    assert_fields_eq!(AssertedSlice([0x5u8]), { kind: 5 });
+
+    let message = std::panic::catch_unwind(|| {
+        assert_fields_eq!(reg, { ready: true, state: 4, channels[1]: 3 });
+    })
+    .unwrap_err();
+    assert_eq!(
+        message.downcast_ref::<String>().unwrap(),
+        "assertion failed for the fields of `reg`:\n\
+         \x20 state (bits 7..=4): 3, expected 4\n\
+         \x20 channels (bits 11..=10): 1, expected 3\n\
+         storage: 0x631\n\
+         fields: AssertedRegister { .0: 1585, ready: true, state: 3, channels: [2, 1, 0] }"
+    );
+}
+
+#[test]
+#[should_panic(expected = "ready (bit 0): true, expected false")]
+fn test_assert_field_eq_failure() {
+    assert_field_eq!(AssertedRegister(0x1), ready, false);
+}
+
+bitfield! {
+    #[derive(PartialEq)]
+    struct FluentControl(u32);
+    impl Debug;
+    impl With;
+    u8;
+    pub enable, set_enable: 0;
+    mode, set_mode: 7, 4;
+    lanes, set_lanes: 11, 8, 2;
+    _, set_trigger: 31;
+}
+
+bitfield! {
+    struct FluentSlice(MSB0 [u8]);
+    impl With;
+    u8;
+    kind, set_kind: 3, 0;
+}
+
+#[test]
+fn test_with_setters() {
+    let control = FluentControl(0)
+        .with_mode(0xa)
+        .with_enable(true)
+        .with_lanes(1, 0x3)
+        .with_set_trigger(true);
+    assert_eq!(control, FluentControl(0x8000_30a1));
+
+    let slice = FluentSlice([0u8; 2]).with_kind(0x9);
+    assert_eq!(slice.0, [0x90, 0]);
+}
+
+bitfield! {
+    struct BatchedControl(u32);
+    impl Modify;
+    u8;
+    pub enable, set_enable: 0;
+    pub mode, set_mode: 7, 4;
+    pub lanes, set_lanes: 11, 8, 2;
+}
+
+bitfield! {
+    struct BatchedSlice([u8]);
+    impl Modify;
+    u8;
+    pub kind, set_kind: 3, 0;
+    pub flags, set_flags: 11, 4;
+}
+
+#[test]
+fn test_modify() {
+    let mut control = BatchedControl(0x0000_0F00);
+    control.modify(|writer| {
+        writer.set_enable(true);
+        let mode = writer.lanes(1) + 1;
+        writer.set_mode(mode);
+        writer.set_lanes(0, 0x2);
+    });
+    assert_eq!(control.0, 0x0000_0211);
+
+    let mut slice = BatchedSlice([0u8; 2]);
+    slice.modify(|writer| {
+        writer.set_kind(0x5);
+        writer.set_flags(0xAB);
+    });
+    assert_eq!(slice.0, [0xB5, 0x0A]);
+}
+
+bitfield! {
+    struct InterruptEnable(u16);
+    impl Flags;
+    u8;
+    pub rx, set_rx: 0;
+    pub tx, set_tx: 1;
+    pub w1c, overrun, set_overrun: 2;
+    pub level, set_level: 7, 4;
+}
+
+bitfield! {
+    struct InterruptEnableSlice([u8]);
+    impl Flags;
+    pub rx, set_rx: 9;
+}
+
+#[test]
+fn test_flags() {
+    let mut ie = InterruptEnable(0);
+    ie.raise_rx();
+    assert!(ie.is_rx());
+    assert_eq!(ie.0, 0x01);
+    ie.toggle_tx();
+    assert!(ie.is_tx());
+    ie.toggle_rx();
+    assert!(!ie.is_rx());
+    assert_eq!(ie.0, 0x02);
+    ie.clear_tx();
+    assert_eq!(ie.0, 0);
+
+    let mut slice = InterruptEnableSlice([0u8; 2]);
+    slice.toggle_rx();
+    assert!(slice.is_rx());
+    assert_eq!(slice.0, [0x00, 0x02]);
+    slice.clear_rx();
+    assert_eq!(slice.0, [0x00, 0x00]);
+}
+
+bitfield! {
+    struct AutoMasks(u32);
+    impl Masks;
+    u8;
+    pub enable, set_enable: 0;
+    pub mode, set_mode: 7, 4;
+    pub lanes, set_lanes: 9, 8, 3;
+    _, set_trigger: 16;
+    mask PRESCALER_BITS(u32), prescaler, set_prescaler: 31, 24;
 }
 
 #[test]
-fn struct_can_be_public() {
-    let _ = some_module::PubBitFieldInAModule(0);
+fn test_masks() {
+    assert_eq!(AutoMasks::ENABLE_MASK, 0x0000_0001);
+    assert_eq!(AutoMasks::MODE_MASK, 0x0000_00F0);
+    assert_eq!(AutoMasks::LANES_MASK, 0x0000_3F00);
+    assert_eq!(AutoMasks::SET_TRIGGER_MASK, 0x0001_0000);
+    assert_eq!(AutoMasks::PRESCALER_BITS, 0xFF00_0000);
+}
+
+bitfield! {
+    struct UntypedMasks(u64);
+    u8;
+    mask READY_MASK, ready, set_ready: 0;
+    pub mask LEVEL_MASK, level, set_level: 39, 32;
+    mask WIDE_MASK(u128), wide, set_wide: 63, 60;
 }
+
 #[test]
-fn field_can_be_public() {
-    let mut a = some_module::PubBitFieldInAModule(0);
-    let _ = a.field1();
-    a.set_field1(true);
-    let _ = a.field2();
-    a.set_field3(true);
-    let _ = a.field4();
-    a.set_field4(true);
-    a.set_field5(true);
-    let _ = a.field6();
-    let _ = a.field7();
-    a.set_field7(true);
-    let _ = a.field8();
-    a.set_field8(0);
-    let _ = a.field9(0);
-    a.set_field9(0, 0);
-    let _ = a.field10();
-    a.set_field10(true);
-    let _ = a.field11();
-    a.set_field11(0);
-    let _ = a.field12(0);
-    a.set_field12(0, 0);
+fn test_untyped_masks() {
+    let ready: u64 = UntypedMasks::READY_MASK;
+    let level: u64 = UntypedMasks::LEVEL_MASK;
+    let wide: u128 = UntypedMasks::WIDE_MASK;
+    assert_eq!(ready, 0x1);
+    assert_eq!(level, 0xFF_0000_0000);
+    assert_eq!(wide, 0xF000_0000_0000_0000);
 }
 
-// Everything in this module is to make sure that its possible to specify types
-// in most of the possible ways.
-#[allow(dead_code)]
-mod test_types {
-    use bitfield::{BitRange, BitRangeMut};
-    use std::sync::atomic::{self, AtomicUsize};
+bitfield! {
+    struct ShortNames(u32);
+    impl new;
+    field enable: 0;
+    u8;
+    pub field mode: 7, 4;
+    get_prefix;
+    pub u16, field divider: 31, 16;
+}
 
-    struct Foo;
+#[test]
+fn test_single_name_fields() {
+    let mut short = ShortNames::new(true, 3, 0x1234);
+    assert_eq!(short.0, 0x1234_0031);
+    short.set_enable(false);
+    short.set_mode(0xA);
+    short.set_divider(0xBEEF);
+    assert!(!short.enable());
+    assert_eq!(short.mode(), 0xA);
+    assert_eq!(short.get_divider(), 0xBEEF);
+    assert_eq!(short.0, 0xBEEF_00A0);
+}
 
-    impl Foo {
-        bitfield_fields! {
-            std::sync::atomic::AtomicUsize, field1, set_field1: 0, 0;
-            std::sync::atomic::AtomicUsize;
-            field2, set_field2: 0, 0;
-            ::std::sync::atomic::AtomicUsize, field3, set_field3: 0, 0;
-            ::std::sync::atomic::AtomicUsize;
-            field4, set_field4: 0, 0;
-            atomic::AtomicUsize, field5, set_field5: 0, 0;
-            atomic::AtomicUsize;
-            field6, set_field6: 0, 0;
-            AtomicUsize, field7, set_field7: 0, 0;
-            AtomicUsize;
-            field8, set_field8: 0, 0;
-            Vec<std::sync::atomic::AtomicUsize>, field9, set_field9: 0, 0;
-            Vec<std::sync::atomic::AtomicUsize>;
-            field10, set_field10: 0, 0;
-            Vec<::std::sync::atomic::AtomicUsize>, field11, set_field11: 0, 0;
-            Vec<::std::sync::atomic::AtomicUsize>;
-            field12, set_field12: 0, 0;
-            Vec<atomic::AtomicUsize>, field13, set_field13: 0, 0;
-            Vec<atomic::AtomicUsize>;
-            field14, set_field14: 0, 0;
-            Vec<AtomicUsize>, field15, set_field15: 0, 0;
-            Vec<AtomicUsize>;
-            field16, set_field16: 0, 0;
-            &str, field17, set_field17: 0, 0;
-            &str;
-            field18, set_field18: 0, 0;
-            &'static str, field19, set_field19: 0, 0;
-            &'static str;
-            field20, set_field20: 0, 0;
-        }
-    }
+bitfield! {
+    struct PciId(u32);
+    impl Debug;
+    u16;
+    pub newtype VendorId, vendor, set_vendor: 15, 0;
+    pub newtype DeviceId, device, set_device: 31, 16;
+}
 
-    impl BitRange<AtomicUsize> for Foo {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> AtomicUsize {
-            AtomicUsize::new(0)
-        }
-    }
+#[test]
+fn test_newtype_fields() {
+    let mut id = PciId(0);
+    id.set_vendor(VendorId(0x8086));
+    id.set_device(0x1237.into());
+    assert_eq!(id.vendor(), VendorId(0x8086));
+    assert_eq!(u16::from(id.device()), 0x1237);
+    assert_eq!(id.0, 0x1237_8086);
+    assert_eq!(
+        format!("{:?}", id),
+        "PciId { .0: 305627270, vendor: VendorId(32902), device: DeviceId(4663) }"
+    );
+}
 
-    impl BitRangeMut<AtomicUsize> for Foo {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: AtomicUsize) {}
-    }
+bitfield! {
+    struct ChannelRegister(u16);
+    impl Debug;
+    u8;
+    mask CHANNELS_MASK(u16), channel, set_channel: 2, 0, 4, 4;
+    enabled, set_enabled: 3;
+}
 
-    impl BitRange<Vec<AtomicUsize>> for Foo {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> Vec<AtomicUsize> {
-            vec![AtomicUsize::new(0)]
-        }
-    }
+bitfield! {
+    struct ConstChannelRegister(u16);
+    const;
+    u8;
+    channel, set_channel: 2, 0, 4, 4;
+}
 
-    impl BitRangeMut<Vec<AtomicUsize>> for Foo {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: Vec<AtomicUsize>) {}
-    }
+#[test]
+fn test_strided_array_fields() {
+    assert_eq!(ChannelRegister::CHANNELS_MASK, 0x7777);
+
+    let mut config = ChannelRegister(0xFFFF);
+    assert_eq!(config.channel(3), 7);
+    config.set_channel(0, 2);
+    config.set_channel(1, 5);
+    assert_eq!(config.0, 0xFFDA);
+    assert!(config.enabled());
+
+    config.set_channel_all([0, 1, 2, 3]);
+    assert_eq!(config.0, 0xBA98);
+    assert_eq!(
+        format!("{:?}", config),
+        "ChannelRegister { .0: 47768, channel: [0, 1, 2, 3], enabled: true }"
+    );
+
+    let mut config = ConstChannelRegister(0);
+    config.set_channel(2, 6);
+    assert_eq!(config.channel(2), 6);
+    assert_eq!(config.0, 0x0600);
+}
 
-    impl<'a> BitRange<&'a str> for Foo {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> &'a str {
-            ""
-        }
-    }
+bitfield! {
+    struct StridedChannels(u16);
+    impl Encode;
+    impl Decode;
+    impl Fields;
+    impl FieldsModule{strided_channels};
+    u8;
+    channel, set_channel: 2, 0, 4, 4;
+    enabled, set_enabled: 3;
+}
 
-    impl<'a> BitRangeMut<&'a str> for Foo {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: &'a str) {}
-    }
+bitfield! {
+    struct StridedChannelsSlice([u8]);
+    impl Encode;
+    impl Decode;
+    impl Fields;
+    impl FieldsModule{strided_channels_slice};
+    u8;
+    channel, set_channel: 2, 0, 4, 4;
+}
 
-    #[test]
-    fn test_field_type() {
-        let test = Foo;
-        let _: AtomicUsize = test.field1();
-        let _: AtomicUsize = test.field2();
-        let _: AtomicUsize = test.field3();
-        let _: AtomicUsize = test.field4();
-        let _: AtomicUsize = test.field5();
-        let _: AtomicUsize = test.field6();
-        let _: AtomicUsize = test.field7();
-        let _: AtomicUsize = test.field8();
-        let _: Vec<AtomicUsize> = test.field9();
-        let _: Vec<AtomicUsize> = test.field10();
-        let _: Vec<AtomicUsize> = test.field11();
-        let _: Vec<AtomicUsize> = test.field12();
-        let _: Vec<AtomicUsize> = test.field13();
-        let _: Vec<AtomicUsize> = test.field14();
-        let _: Vec<AtomicUsize> = test.field15();
-        let _: Vec<AtomicUsize> = test.field16();
-        let _: &str = test.field17();
-        let _: &str = test.field18();
-        let _: &'static str = test.field19();
-        let _: &'static str = test.field20();
-    }
+#[test]
+fn test_strided_array_encode_decode() {
+    assert_eq!(StridedChannels::ENCODED_BITS, 13);
+
+    let value = StridedChannels(0x4329);
+    let mut buffer = [0xFFu8; 2];
+    let mut writer = bitfield::BitWriter::new(&mut buffer);
+    value.encode_into(&mut writer);
+    assert_eq!(writer.position(), 13);
+    assert_eq!(buffer, [0b0010_1001, 0b1100_1111]);
+
+    let mut reader = bitfield::BitReader::new(&buffer);
+    let decoded = StridedChannels::decode_from(&mut reader).unwrap();
+    assert_eq!(decoded.0, 0x4329);
+
+    assert_eq!(StridedChannelsSlice::<[u8; 2]>::ENCODED_BITS, 12);
+    let value = StridedChannelsSlice([0x21u8, 0x43]);
+    let mut buffer = [0xFFu8; 2];
+    let mut writer = bitfield::BitWriter::new(&mut buffer);
+    value.encode_into(&mut writer);
+    assert_eq!(buffer, [0b0010_1001, 0b1100_1111]);
+
+    let mut reader = bitfield::BitReader::new(&buffer);
+    let decoded = StridedChannelsSlice::<[u8; 2]>::decode_from(&mut reader).unwrap();
+    assert_eq!(decoded.0, [0x21, 0x43]);
 }
 
-#[allow(dead_code)]
-mod test_no_default_bitrange {
-    use bitfield::{BitRange, BitRangeMut};
-    use std::fmt::Debug;
-    use std::fmt::Error;
-    use std::fmt::Formatter;
+#[test]
+fn test_strided_array_fields_table() {
+    let field = &StridedChannels::FIELDS[StridedChannelsField::Channel as usize];
+    assert_eq!(
+        (field.lsb, field.width, field.count, field.stride),
+        (0, 3, 4, 4)
+    );
+    assert_eq!(field.element_bits(3), (14, 12));
+    assert_eq!(StridedChannels::FIELDS[1].stride, 1);
+
+    let mut value = StridedChannels(0x4329);
+    assert_eq!(value.get_field_raw(StridedChannelsField::Channel, 2), 3);
+    value.set_field_raw(StridedChannelsField::Channel, 3, 7);
+    assert_eq!(value.0, 0x7329);
+    assert_eq!(value.channel(3), 7);
+
+    assert_eq!(strided_channels::channel::OFFSET, 0);
+    assert_eq!(strided_channels::channel::WIDTH, 3);
+    assert_eq!(strided_channels::channel::COUNT, 4);
+    assert_eq!(strided_channels::channel::STRIDE, 4);
+    assert_eq!(strided_channels::channel::MASK, 0x7777);
+
+    let mut value = StridedChannelsSlice([0x21u8, 0x43]);
+    assert_eq!(
+        value.get_field_raw(StridedChannelsSliceField::Channel, 3),
+        4
+    );
+    value.set_field_raw(StridedChannelsSliceField::Channel, 1, 5);
+    assert_eq!(value.0, [0x51, 0x43]);
+    assert_eq!(strided_channels_slice::channel::STRIDE, 4);
+}
 
-    use crate::FooBar;
-    bitfield! {
-      #[derive(Eq, PartialEq)]
-      pub struct BitField1(u16);
-      no default BitRange;
-      impl Debug;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 2;
-    }
+// The line appended to the documentation of the accessors, from the positions of the field.
+#[test]
+fn test_position_doc() {
+    assert_eq!(bitfield_fields!(@pos_doc 31), "Bit `31`.");
+    assert_eq!(bitfield_fields!(@pos_doc 7, 4), "Bits `7:4`.");
+    assert_eq!(
+        bitfield_fields!(@pos_doc 9, 8, 2),
+        "Bits `9:8` for the element 0, of `2` contiguous elements."
+    );
+    assert_eq!(
+        bitfield_fields!(@pos_doc 2, 0, 4, 4),
+        "Bits `2:0` for the element 0, of `4` elements every `4` bits."
+    );
+}
 
-    impl BitRange<u8> for BitField1 {
-        fn bit_range(&self, msb: usize, lsb: usize) -> u8 {
-            (msb + lsb) as u8
-        }
-    }
+bitfield! {
+    struct RenamedFields(u32);
+    impl Debug;
+    u8;
+    /// The mode.
+    pub mode, set_mode: 7, 4;
+    #[deprecated(note = "renamed to `mode`")]
+    pub alias old_mode = mode;
+    pub level, _: 11, 8;
+    alias lvl = level;
+    /// The wide field.
+    pub u16, wide, set_wide: 27, 12;
+    alias w, put_w = wide;
+}
 
-    impl BitRangeMut<u8> for BitField1 {
-        fn set_bit_range(&mut self, msb: usize, lsb: usize, value: u8) {
-            self.0 = msb as u16 + lsb as u16 + u16::from(value)
-        }
+#[test]
+#[allow(deprecated)]
+fn test_aliases() {
+    let mut fields = RenamedFields(0);
+    fields.set_old_mode(5);
+    assert_eq!(fields.mode(), 5);
+    assert_eq!(fields.old_mode(), 5);
+    fields.put_w(0x1234);
+    assert_eq!(fields.wide(), 0x1234);
+    assert_eq!(fields.w(), 0x1234);
+    fields.0 |= 0x300;
+    assert_eq!(fields.lvl(), 3);
+    assert_eq!(
+        format!("{:?}", fields),
+        "RenamedFields { .0: 19088208, mode: 5, level: 3, wide: 4660 }"
+    );
+}
+
+bitfield! {
+    struct EventCounters(u32);
+    impl Update;
+    u8;
+    pub count, set_count: 7, 0;
+    pub armed, set_armed: 8;
+    pub nibbles, set_nibble: 19, 16, 2;
+    pub valid_if 31, limit, set_limit: 27, 24;
+    pub level, _: 30, 28;
+}
+
+bitfield! {
+    struct EventCountersSlice([u8]);
+    impl Update;
+    u8;
+    pub count, set_count: 7, 0;
+}
+
+#[test]
+fn test_update() {
+    let mut counters = EventCounters(0);
+    counters.update_count(|count| count + 1);
+    counters.update_count(|count| count.wrapping_sub(2));
+    assert_eq!(counters.count(), 0xFF);
+    counters.update_armed(|armed| !armed);
+    assert!(counters.armed());
+    counters.update_nibbles(1, |nibble| nibble | 0b101);
+    assert_eq!(counters.nibbles(1), 0b101);
+    counters.update_limit(|limit| Some(limit.unwrap_or(3) * 2));
+    assert_eq!(counters.limit(), Some(6));
+    assert_eq!(counters.0, 0x8650_01FF);
+
+    let mut slice = EventCountersSlice([41u8]);
+    slice.update_count(|count| count + 1);
+    assert_eq!(slice.0, [42]);
+}
+
+trait PinMode {
+    const OUTPUT: bool;
+}
+
+struct Input;
+struct Output;
+
+impl PinMode for Input {
+    const OUTPUT: bool = false;
+}
+
+impl PinMode for Output {
+    const OUTPUT: bool = true;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    struct PinRegister<M: PinMode>(u32, std::marker::PhantomData<M>);
+    u8;
+    pub level, set_level: 0;
+    pub drive, set_drive: 5, 4;
+    pub pulls, set_pulls: 9, 8, 2;
+}
+
+impl<M: PinMode> PinRegister<M> {
+    fn is_output(&self) -> bool {
+        M::OUTPUT
     }
+}
+
+bitfield! {
+    struct TaggedWord<A, B>(u16, std::marker::PhantomData<(A, B)>) where A: Copy, B: Default;
+    u8, low, set_low: 7, 0;
+    u8, high, set_high: 15, 8;
+}
+
+#[test]
+fn test_generic_parameters() {
+    let mut pin: PinRegister<Output> = PinRegister(0, std::marker::PhantomData);
+    pin.set_level(true);
+    pin.set_drive(2);
+    pin.set_pulls(1, 3);
+    assert_eq!(pin.0, 0x0000_0C21);
+    assert_eq!((pin.level(), pin.drive(), pin.pulls(1)), (true, 2, 3));
+    assert!(pin.is_output());
+    assert!(!PinRegister::<Input>(pin.0, std::marker::PhantomData).is_output());
+
+    let mut word: TaggedWord<u8, u16> = TaggedWord(0, std::marker::PhantomData);
+    word.set_high(0x12);
+    word.set_low(0x34);
+    assert_eq!(word.0, 0x1234);
+    assert_eq!(bitfield::BitRange::<u8>::bit_range(&word, 15, 8), 0x12);
+}
+
+bitfield! {
+    struct PacketHeader<'a>(&'a mut [u8]);
+    u8;
+    pub version, set_version: 3, 0;
+    pub urgent, set_urgent: 4;
+    pub u16, length, set_length: 23, 8;
+    pub bytes, u32, payload, set_payload: 55, 24;
+}
+
+bitfield! {
+    struct PacketHeaderView<'a>(MSB0 &'a [u8]);
+    u8;
+    pub version, _: 3, 0;
+    pub u16, length, _: 23, 8;
+}
+
+#[test]
+fn test_borrowed_storage() {
+    let mut packet = [0u8; 8];
+    let mut header = PacketHeader(&mut packet[..7]);
+    header.set_version(4);
+    header.set_urgent(true);
+    header.set_length(0x0123);
+    header.payload_bytes_mut().copy_from_slice(&[1, 2, 3, 4]);
+    assert_eq!(header.payload(), 0x0403_0201);
+    assert_eq!(header.payload_bytes(), &[1, 2, 3, 4]);
+    assert_eq!(packet, [0x14, 0x23, 0x01, 1, 2, 3, 4, 0]);
+
+    let view = PacketHeaderView(&packet);
+    assert_eq!(view.version(), 1);
+    assert_eq!(view.length(), 0x2301);
+    assert_eq!(
+        (
+            <PacketHeader as bitfield::BitOrder>::MSB0,
+            <PacketHeaderView as bitfield::BitOrder>::MSB0
+        ),
+        (false, true)
+    );
+}
+
+bitfield! {
+    struct SizedRegister(u64);
+    assert_size 64;
+    storage SizedRegisterBytes([u8; 8]);
+    impl Debug;
+    u32;
+    pub low, set_low: 31, 0;
+    pub high, set_high: 63, 32;
+}
+
+bitfield! {
+    struct SizedFrame(MSB0 [u16]);
+    assert_size 48;
+    u16;
+    pub kind, set_kind: 3, 0;
+    pub length, set_length: 47, 32;
+}
+
+bitfield! {
+    struct SizedPinRegister<M: PinMode>(u16, std::marker::PhantomData<M>);
+    assert_size 16;
+    u8, drive, set_drive: 15, 8;
+}
+
+bitfield! {
+    struct SizedPacketHeader<'a>(&'a mut [u8]);
+    assert_size 16;
+    u8, kind, set_kind: 15, 8;
+}
+
+#[test]
+fn test_assert_size() {
+    let mut register = SizedRegister(0);
+    register.set_high(0x1234_5678);
+    assert_eq!(SizedRegisterBytes::from(register).high(), 0x1234_5678);
+
+    let mut frame = SizedFrame([0u16; 3]);
+    frame.set_length(0xABCD);
+    assert_eq!(frame.0, [0, 0, 0xABCD]);
+
+    let mut pin: SizedPinRegister<Output> = SizedPinRegister(0, std::marker::PhantomData);
+    pin.set_drive(3);
+    assert_eq!(pin.0, 0x0300);
+
+    let mut bytes = [0u8; 2];
+    SizedPacketHeader(&mut bytes).set_kind(7);
+    assert_eq!(bytes, [0, 7]);
+}
+
+bitfield! {
+    struct LittleEndianWords(LE [u32]);
+    impl Debug;
+    impl Fields;
+    u16;
+    pub low, set_low: 15, 0;
+    pub u32, straddling, set_straddling: 47, 16;
+    pub flag, set_flag: 63;
+}
+
+bitfield! {
+    struct BigEndianWords(BE [u16]);
+    impl Debug;
+    impl PartialEq;
+    u8;
+    pub high, set_high: 15, 8;
+    pub low, set_low: 7, 0;
+    pub next, set_next: 23, 16;
+}
+
+bitfield! {
+    struct NetworkWords(MSB0 BE [u16]);
+    impl Fields;
+    u8;
+    pub version, set_version: 3, 0;
+    pub u16, length, set_length: 19, 4;
+}
 
-    #[allow(clippy::identity_op)]
-    #[test]
-    fn custom_bitrange_implementation_is_used() {
-        let mut bf = BitField1(0);
-        assert_eq!(bf.field1(), 10 + 0);
-        assert_eq!(bf.field2(), 12 + 3);
-        assert!(bf.field3());
-        bf.set_field1(42);
-        assert_eq!(bf, BitField1(10 + 0 + 42));
+// The bytes are reinterpreted as elements, as when the storage is mapped from a buffer.
+fn words_from_bytes<const N: usize>(bytes: &[u8]) -> [u16; N] {
+    let mut words = [0; N];
+    for (word, bytes) in words.iter_mut().zip(bytes.chunks(2)) {
+        *word = u16::from_ne_bytes([bytes[0], bytes[1]]);
     }
+    words
+}
 
-    bitfield! {
-      pub(crate) struct BitField2(u16);
-      no default BitRange;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 0;
-    }
+#[test]
+fn test_endian_slices() {
+    let mut words = LittleEndianWords([0u32; 2]);
+    words.set_low(0x1234);
+    words.set_straddling(0x89AB_CDEF);
+    words.set_flag(true);
+    assert_eq!(words.0[0].to_ne_bytes(), [0x34, 0x12, 0xEF, 0xCD]);
+    assert_eq!(words.0[1].to_ne_bytes(), [0xAB, 0x89, 0x00, 0x80]);
+    assert_eq!(words.straddling(), 0x89AB_CDEF);
+    assert_eq!(
+        LittleEndianWords::<[u32; 2]>::field_bit_address(0, LittleEndianWordsField::Flag),
+        (7, 7)
+    );
+
+    let words = BigEndianWords(words_from_bytes::<2>(&[0x12, 0x34, 0x56, 0x78]));
+    assert_eq!(
+        (words.high(), words.low(), words.next()),
+        (0x12, 0x34, 0x78)
+    );
+    let mut other = BigEndianWords([0u16; 2]);
+    other.set_high(0x12);
+    other.set_low(0x34);
+    other.set_next(0x78);
+    assert_ne!(words, other);
+    other.0[1] = words.0[1];
+    assert_eq!(words, other);
+
+    let mut header = NetworkWords([0u16; 2]);
+    header.set_version(4);
+    header.set_length(0x0ABC);
+    let bytes: Vec<u8> = header
+        .0
+        .iter()
+        .flat_map(|word| word.to_ne_bytes())
+        .collect();
+    assert_eq!(bytes, [0x40, 0xAB, 0xC0, 0x00]);
+    assert_eq!(
+        NetworkWords::<[u16; 2]>::field_bit_address(0, NetworkWordsField::Length),
+        (0, 3)
+    );
+}
 
-    impl BitRange<u8> for BitField2 {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
-            0
-        }
-    }
+// Registers behind a bus, counting the accesses.
+#[derive(Default)]
+struct BusRegisters {
+    words: [std::cell::Cell<u16>; 2],
+    reads: std::cell::Cell<usize>,
+    writes: usize,
+}
 
-    impl BitRangeMut<u8> for BitField2 {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
-    }
+impl bitfield::BitStorage for BusRegisters {
+    type Word = u16;
 
-    // Make sure Debug wasn't implemented by implementing it.
-    impl Debug for BitField2 {
-        fn fmt(&self, _: &mut Formatter) -> Result<(), Error> {
-            unimplemented!()
-        }
+    fn read_word(&self, index: usize) -> u16 {
+        self.reads.set(self.reads.get() + 1);
+        self.words[index].get()
     }
+}
 
-    // Check that we can put `impl Debug` before `no default BitRange`
-    bitfield! {
-      pub struct BitField3(u16);
-      impl Debug;
-      no default BitRange;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 0;
+impl bitfield::BitStorageMut for BusRegisters {
+    fn write_word(&mut self, index: usize, word: u16) {
+        self.writes += 1;
+        self.words[index].set(word);
     }
+}
 
-    impl BitRange<u8> for BitField3 {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
-            0
-        }
-    }
+bitfield! {
+    struct BusControl(BitStorage);
+    u8;
+    pub enable, set_enable: 0;
+    pub mode, set_mode: 7, 4;
+    pub u32, threshold, set_threshold: 27, 8;
+    pub i8, offset, set_offset: 31, 28;
+}
 
-    impl BitRangeMut<u8> for BitField3 {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
-    }
+#[test]
+fn test_bit_storage() {
+    let mut registers = BusRegisters::default();
+    let mut control = BusControl(&mut registers);
+    control.set_enable(true);
+    control.set_mode(0xA);
+    control.set_threshold(0xF_1234);
+    control.set_offset(-2);
+    assert_eq!(control.threshold(), 0xF_1234);
+    assert_eq!(
+        (registers.words[0].get(), registers.words[1].get()),
+        (0x34A1, 0xEF12)
+    );
+    // Each word holding a field is read once, and written once by the setters.
+    assert_eq!((registers.reads.get(), registers.writes), (7, 5));
+
+    let control = BusControl(&registers);
+    assert!(control.enable());
+    assert_eq!((control.mode(), control.offset()), (0xA, -2));
+}
 
-    bitfield! {
-      #[derive(Eq, PartialEq)]
-      pub struct BitField4([u16]);
-      no default BitRange;
-      impl Debug;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 2;
-    }
+bitfield! {
+    struct ClockConfig(u32);
+    impl Display;
+    impl Fields;
+    u16;
+    unit "MHz", frequency, set_frequency: 15, 0;
+    radix hex, divider, set_divider: 23, 16;
+    radix bin, unit "ns", delays, set_delay: 27, 24, 2;
+    locked, _: 31;
+}
 
-    impl<T> BitRange<u8> for BitField4<T> {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
-            0
-        }
-    }
+#[test]
+fn test_field_presentation() {
+    let fields = ClockConfig::FIELDS;
+    assert_eq!(
+        (fields[0].unit, fields[0].radix),
+        ("MHz", bitfield::Radix::Decimal)
+    );
+    assert_eq!(
+        (fields[1].unit, fields[1].radix),
+        ("", bitfield::Radix::Hexadecimal)
+    );
+    assert_eq!(
+        (fields[2].unit, fields[2].radix),
+        ("ns", bitfield::Radix::Binary)
+    );
+    assert_eq!(
+        (fields[3].unit, fields[3].radix),
+        ("", bitfield::Radix::Decimal)
+    );
+
+    let mut config = ClockConfig(0);
+    config.set_frequency(400);
+    config.set_divider(0x1f);
+    config.set_delay(1, 2);
+    assert_eq!(
+        config.to_string(),
+        "frequency: 400 MHz, divider: 0x1f, delays: [0b0, 0b10] ns, locked: false"
+    );
+}
 
-    impl<T> BitRangeMut<u8> for BitField4<T> {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
-    }
+bitfield! {
+    #[derive(Clone, Copy)]
+    struct BootEntry(u32);
+    impl Debug;
+    const;
+    u8;
+    pub valid, set_valid: 0;
+    pub kind, set_kind: 7, 4;
+    i8, pub offset, set_offset: 11, 8;
+    saturating, pub level, set_level: 15, 12;
+    pub slots, set_slot: 19, 16, 2;
+    pub bool, flags, set_flag: 31, 28, 4;
+}
 
-    bitfield! {
-      pub struct BitField5([u16]);
-      no default BitRange;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 0;
-    }
+const BOOT_ENTRY: BootEntry = {
+    let mut entry = BootEntry(0);
+    entry.set_valid(true);
+    entry.set_kind(0xA);
+    entry.set_offset(-2);
+    entry.set_level(0x1F);
+    entry.set_slot(1, 0x3);
+    entry.set_flag(2, true);
+    entry
+};
+const BOOT_KIND: u8 = BOOT_ENTRY.kind();
+const BOOT_OFFSET: i8 = BOOT_ENTRY.offset();
 
-    impl<T> BitRange<u8> for BitField5<T> {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
-            0
-        }
-    }
+#[test]
+fn test_const_accessors() {
+    assert_eq!(BOOT_ENTRY.0, 0x4030_FEA1);
+    assert_eq!(BOOT_KIND, 0xA);
+    assert_eq!(BOOT_OFFSET, -2);
+    assert!(BOOT_ENTRY.valid());
+    assert_eq!(BOOT_ENTRY.level(), 0xF);
+    assert_eq!((BOOT_ENTRY.slots(0), BOOT_ENTRY.slots(1)), (0, 3));
+    assert!(BOOT_ENTRY.flags(2));
+    assert!(!BOOT_ENTRY.flags(3));
+
+    let mut entry = BOOT_ENTRY;
+    entry.set_offset(7);
+    entry.set_flag(2, false);
+    assert_eq!(entry.offset(), 7);
+    assert_eq!(entry.0, 0x0030_F7A1);
+}
 
-    impl<T> BitRangeMut<u8> for BitField5<T> {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
-    }
+bitfield! {
+    struct ChannelConfig(u32);
+    u8;
+    consts ENABLE, enable, set_enable: 0;
+    pub mask CHANNEL_MASK(u32), consts CHANNEL, channel, set_channel: 7, 4;
+    consts BURST, bursts, set_burst: 11, 10, 3;
+}
 
-    // Make sure Debug wasn't implemented by implementing it.
-    impl<T> Debug for BitField5<T> {
-        fn fmt(&self, _: &mut Formatter) -> Result<(), Error> {
-            unimplemented!()
-        }
-    }
+bitfield! {
+    struct ChannelConfigSlice([u8]);
+    u8;
+    pub consts CHANNEL, channel, set_channel: 7, 4;
+}
 
-    // Check that we can put `impl Debug` before `no default BitRange`
-    bitfield! {
-      pub struct BitField6([u16]);
-      impl Debug;
-      no default BitRange;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 0;
-    }
+#[test]
+fn test_field_consts() {
+    assert_eq!(
+        (ChannelConfig::ENABLE_OFFSET, ChannelConfig::ENABLE_WIDTH),
+        (0, 1)
+    );
+    assert_eq!(
+        (ChannelConfig::ENABLE_MSB, ChannelConfig::ENABLE_LSB),
+        (0, 0)
+    );
+    assert_eq!(
+        (ChannelConfig::CHANNEL_OFFSET, ChannelConfig::CHANNEL_WIDTH),
+        (4, 4)
+    );
+    assert_eq!(
+        (ChannelConfig::CHANNEL_MSB, ChannelConfig::CHANNEL_LSB),
+        (7, 4)
+    );
+    assert_eq!(
+        ChannelConfig::CHANNEL_MASK,
+        ((1 << ChannelConfig::CHANNEL_WIDTH) - 1) << ChannelConfig::CHANNEL_OFFSET
+    );
+    assert_eq!(
+        (ChannelConfig::BURST_MSB, ChannelConfig::BURST_LSB),
+        (11, 10)
+    );
+    assert_eq!(ChannelConfig::BURST_WIDTH, 2);
+    assert_eq!(ChannelConfigSlice::<[u8; 1]>::CHANNEL_OFFSET, 4);
+
+    let mut control = ChannelConfig(0);
+    control.set_enable(true);
+    control.set_channel(3);
+    control.set_burst(2, 1);
+    assert_eq!(
+        control.0,
+        1 << ChannelConfig::ENABLE_OFFSET
+            | 3 << ChannelConfig::CHANNEL_OFFSET
+            | 1 << (ChannelConfig::BURST_OFFSET + 2 * ChannelConfig::BURST_WIDTH)
+    );
+}
 
-    impl<T> BitRange<u8> for BitField6<T> {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
-            0
-        }
-    }
+bitfield! {
+    #[derive(Debug, PartialEq)]
+    struct LinkStatus(u16);
+    impl Poll;
+    u8;
+    pub up, _: 0;
+    pub state, _: 7, 4;
+    pub lanes, _: 11, 8, 2;
+    _, set_reset: 1;
+}
 
-    impl<T> BitRangeMut<u8> for BitField6<T> {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
-    }
+bitfield! {
+    struct LinkStatusSlice([u8]);
+    impl Poll;
+    u8;
+    state, _: 7, 4;
+}
 
-    bitfield! {
-      #[derive(Eq, PartialEq)]
-      pub struct BitField7(MSB0 [u16]);
-      no default BitRange;
-      impl Debug;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 2;
-    }
+#[test]
+fn test_poll() {
+    let mut reads = [0x0000, 0x0030, 0x0031, 0x2031].iter().copied();
+    assert_eq!(
+        LinkStatus::wait_for_up(true, || reads.next().unwrap(), 10),
+        Ok(LinkStatus(0x0031))
+    );
+    assert_eq!(
+        LinkStatus::wait_for_lanes(1, 2, || reads.next().unwrap(), 10),
+        Ok(LinkStatus(0x2031))
+    );
+
+    let mut polls = 0;
+    let result = LinkStatus::wait_for_state(
+        5,
+        || {
+            polls += 1;
+            0x0030
+        },
+        4,
+    );
+    assert_eq!(result, Err(bitfield::Timeout { polls: 4 }));
+    assert_eq!(polls, 4);
+
+    assert!(LinkStatusSlice::wait_for_state(3, || [0x30u8], 1).is_ok());
+}
 
-    impl<T> BitRange<u8> for BitField7<T> {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
-            0
-        }
-    }
+bitfield! {
+    struct InterruptStatus(u32);
+    impl Pending;
+    pub bool, rx, set_rx: 7, 0, 8;
+    pub w1c, bool, errors, set_error: 11, 8, 4;
+    pub bool, masked, _: 15, 12, 4;
+}
 
-    impl<T> BitRangeMut<u8> for BitField7<T> {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
-    }
+bitfield! {
+    struct InterruptStatusSlice([u8]);
+    impl Pending;
+    bool, rx, set_rx: 7, 0, 8;
+}
 
-    bitfield! {
-      pub struct BitField8(MSB0 [u16]);
-      no default BitRange;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 0;
-    }
+#[test]
+fn test_take_pending() {
+    let mut status = InterruptStatus(0x0000_F5A1);
+    assert_eq!(status.take_rx().collect::<Vec<_>>(), [0, 5, 7]);
+    assert_eq!(status.0, 0x0000_F500);
+    assert_eq!(status.take_rx().count(), 0);
+
+    assert_eq!(status.take_errors().collect::<Vec<_>>(), [0, 2]);
+    assert_eq!(status.0, 0x0000_F500);
+
+    let mut slice = InterruptStatusSlice([0x81u8]);
+    assert_eq!(slice.take_rx().collect::<Vec<_>>(), [0, 7]);
+    assert_eq!(slice.0, [0]);
+}
 
-    impl<T> BitRange<u8> for BitField8<T> {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
-            0
-        }
-    }
+bitfield! {
+    struct ForwardingEntry(u64);
+    impl new;
+    u8;
+    pub kind, set_kind: 3, 0;
+    pub octets, set_octet: 11, 4, 4;
+    pub bool, valid, set_valid: 40, 40, 2;
+    field priority: 47, 44, 2, 8;
+}
 
-    impl<T> BitRangeMut<u8> for BitField8<T> {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
-    }
+bitfield! {
+    struct ForwardingEntrySlice([u8]);
+    impl new;
+    u8;
+    pub octets, set_octet: 7, 0, 2;
+}
 
-    // Make sure Debug wasn't implemented by implementing it.
-    impl<T> Debug for BitField8<T> {
-        fn fmt(&self, _: &mut Formatter) -> Result<(), Error> {
-            unimplemented!()
-        }
-    }
+#[test]
+fn test_constructor_array_fields() {
+    let entry = ForwardingEntry::new(4, [192, 168, 0, 1], [true, false], [3, 9]);
+    assert_eq!(entry.kind(), 4);
+    assert_eq!(
+        (0..4).map(|index| entry.octets(index)).collect::<Vec<_>>(),
+        [192, 168, 0, 1]
+    );
+    assert_eq!((entry.valid(0), entry.valid(1)), (true, false));
+    assert_eq!((entry.priority(0), entry.priority(1)), (3, 9));
+    assert_eq!(entry.0, 0x0090_3100_100A_8C04);
+
+    let slice = ForwardingEntrySlice::<[u8; 2]>::new([10, 20]);
+    assert_eq!(slice.0, [10, 20]);
+}
 
-    // Check that we can put `impl Debug` before `no default BitRange`
-    bitfield! {
-      pub struct BitField9([u16]);
-      impl Debug;
-      no default BitRange;
-      u8;
-      field1, set_field1: 10, 0;
-      pub field2, _ : 12, 3;
-      field3, set_field3: 0;
-    }
+bitfield! {
+    struct QueueDescriptor(u64);
+    impl new;
+    u8;
+    pub low, set_low: width 4;
+    reserved: width 4;
+    pub nibble, set_nibble: width 4;
+    pub offset, set_offset: @12; 4;
+    saturating, level, set_level: 19, 16;
+    alias lvl = level;
+    i8, signed, set_signed: 23, 20;
+    consts FLAG, flag, set_flag: 24;
+    radix hex, unit "ns", delay, set_delay: 27, 25;
+    newtype QueueId, queue, set_queue: 31, 28;
+    pub base, set_base: [47, 44] ++ [35, 32];
+    reserved: 39, 36;
+    pub rc, errors, _: 43, 40;
+    pub w1s, start, set_start: 48;
+    try_into ClockSource, source, set_source: 50, 49;
+    const;
+    tail, set_tail: 63, 56;
+}
 
-    impl<T> BitRange<u8> for BitField9<T> {
-        fn bit_range(&self, _msb: usize, _lsb: usize) -> u8 {
-            0
-        }
-    }
+#[test]
+fn test_constructor_field_keywords() {
+    let descriptor = QueueDescriptor::new(
+        0x1,
+        0x2,
+        0x3,
+        0xFF,
+        -2,
+        true,
+        5,
+        QueueId(9),
+        true,
+        2,
+        0xAB,
+        0x5A,
+    );
+    assert_eq!((descriptor.low(), descriptor.nibble()), (0x1, 0x2));
+    assert_eq!((descriptor.offset(), descriptor.lvl()), (0x3, 0xF));
+    assert_eq!((descriptor.signed(), descriptor.flag()), (-2, true));
+    assert_eq!((descriptor.delay(), descriptor.queue()), (5, QueueId(9)));
+    assert_eq!(descriptor.source(), Ok(ClockSource::Pll));
+    assert_eq!((descriptor.tail(), descriptor.base()), (0xAB, 0x5A));
+    assert_eq!(descriptor.0, 0xAB05_500A_9BEF_3201);
+}
 
-    impl<T> BitRangeMut<u8> for BitField9<T> {
-        fn set_bit_range(&mut self, _msb: usize, _lsb: usize, _value: u8) {}
-    }
+bitfield! {
+    pub struct PortConfig(u16);
+    impl Builder;
+    impl Debug;
+    pub u8, speed, set_speed: 3, 0;
+    pub bool, duplex, set_duplex: 4 = true;
+    pub u8, mtu_class, set_mtu_class: 7, 5 = 2;
+    pub u8, lanes, set_lanes: 9, 8, 3;
+    pub u8, link, _: 15, 14;
+}
 
-    #[test]
-    fn test_debug_is_implemented_with_no_default_bitrange() {
-        let _ = format!("{:?}", BitField1(0));
-        let _ = format!("{:?}", BitField3(0));
-        let _ = format!("{:?}", BitField4([0; 1]));
-        let _ = format!("{:?}", BitField6([0; 1]));
-        let _ = format!("{:?}", BitField7([0; 1]));
-        let _ = format!("{:?}", BitField9([0; 1]));
-    }
+bitfield! {
+    pub struct PortConfigSlice([u8]);
+    impl Builder;
+    pub u8, speed, set_speed: 3, 0;
+    pub u8, mode, set_mode: 7, 4 = 5;
+    pub bool, enabled, set_enabled: 8, 8, 3;
+}
 
-    #[test]
-    fn masks() {
-        assert_eq!(FooBar::I128_MASK, 0b111111110i128);
-        assert_eq!(FooBar::U128_MASK, 0b111111110u128);
-        assert_eq!(FooBar::SETTER_MASK, 0b11100u32);
-        assert_eq!(FooBar::GETTER_MASK, 0b1110u32);
-        assert_eq!(FooBar::PUB_SETTER_MASK, 0b11100u32);
-        assert_eq!(FooBar::PUB_GETTER_MASK, 0b1110u32);
-        assert_eq!(FooBar::SINGLE_BIT_MASK, 1 << 3);
-        assert_eq!(FooBar::PUB_MASK, 1 << 31);
-    }
+#[test]
+fn test_builder() {
+    let config = PortConfig::builder()
+        .speed(9)
+        .lanes([1, 2, 3])
+        .build()
+        .unwrap();
+    assert_eq!(config.speed(), 9);
+    assert!(config.duplex());
+    assert_eq!(config.mtu_class(), 2);
+    assert_eq!(
+        (config.lanes(0), config.lanes(1), config.lanes(2)),
+        (1, 2, 3)
+    );
+    assert_eq!(config.0, 0x3959);
+
+    let config = PortConfigBuilder::default()
+        .speed(1)
+        .duplex(false)
+        .mtu_class(7)
+        .lanes([0; 3])
+        .build()
+        .unwrap();
+    assert_eq!(config.0, 0xE1);
+
+    let missing = PortConfig::builder().lanes([0; 3]).build().unwrap_err();
+    assert_eq!(missing, bitfield::MissingField { field: "speed" });
+    assert_eq!(
+        missing.to_string(),
+        "the field `speed` without a default value isn't set"
+    );
+    assert_eq!(
+        PortConfig::builder().speed(1).build().unwrap_err().field,
+        "lanes"
+    );
+
+    let slice: PortConfigSlice<[u8; 2]> = PortConfigSliceBuilder::default()
+        .speed(3)
+        .enabled([true, false, true])
+        .build()
+        .unwrap();
+    assert_eq!(slice.0, [0x53, 0x05]);
+    let missing = PortConfigSliceBuilder::default()
+        .speed(3)
+        .build::<[u8; 2]>()
+        .err();
+    assert_eq!(missing.map(|missing| missing.field), Some("enabled"));
+}
+
+// The fields are parsed in one step each, so a struct with a field for each bit of a `u64` is
+// within the recursion limit.
+bitfield! {
+    struct ManyDocumentedFields(u64);
+    impl Debug;
+    bool;
+    /// The flag 0.
+    flag0, set_flag0: 0;
+    /// The flag 1.
+    flag1, set_flag1: 1;
+    /// The flag 2.
+    flag2, set_flag2: 2;
+    /// The flag 3.
+    flag3, set_flag3: 3;
+    /// The flag 4.
+    flag4, set_flag4: 4;
+    /// The flag 5.
+    flag5, set_flag5: 5;
+    /// The flag 6.
+    flag6, set_flag6: 6;
+    /// The flag 7.
+    flag7, set_flag7: 7;
+    /// The flag 8.
+    flag8, set_flag8: 8;
+    /// The flag 9.
+    flag9, set_flag9: 9;
+    /// The flag 10.
+    flag10, set_flag10: 10;
+    /// The flag 11.
+    flag11, set_flag11: 11;
+    /// The flag 12.
+    flag12, set_flag12: 12;
+    /// The flag 13.
+    flag13, set_flag13: 13;
+    /// The flag 14.
+    flag14, set_flag14: 14;
+    /// The flag 15.
+    flag15, set_flag15: 15;
+    /// The flag 16.
+    flag16, set_flag16: 16;
+    /// The flag 17.
+    flag17, set_flag17: 17;
+    /// The flag 18.
+    flag18, set_flag18: 18;
+    /// The flag 19.
+    flag19, set_flag19: 19;
+    /// The flag 20.
+    flag20, set_flag20: 20;
+    /// The flag 21.
+    flag21, set_flag21: 21;
+    /// The flag 22.
+    flag22, set_flag22: 22;
+    /// The flag 23.
+    flag23, set_flag23: 23;
+    /// The flag 24.
+    flag24, set_flag24: 24;
+    /// The flag 25.
+    flag25, set_flag25: 25;
+    /// The flag 26.
+    flag26, set_flag26: 26;
+    /// The flag 27.
+    flag27, set_flag27: 27;
+    /// The flag 28.
+    flag28, set_flag28: 28;
+    /// The flag 29.
+    flag29, set_flag29: 29;
+    /// The flag 30.
+    flag30, set_flag30: 30;
+    /// The flag 31.
+    flag31, set_flag31: 31;
+    /// The flag 32.
+    flag32, set_flag32: 32;
+    /// The flag 33.
+    flag33, set_flag33: 33;
+    /// The flag 34.
+    flag34, set_flag34: 34;
+    /// The flag 35.
+    flag35, set_flag35: 35;
+    /// The flag 36.
+    flag36, set_flag36: 36;
+    /// The flag 37.
+    flag37, set_flag37: 37;
+    /// The flag 38.
+    flag38, set_flag38: 38;
+    /// The flag 39.
+    flag39, set_flag39: 39;
+    /// The flag 40.
+    flag40, set_flag40: 40;
+    /// The flag 41.
+    flag41, set_flag41: 41;
+    /// The flag 42.
+    flag42, set_flag42: 42;
+    /// The flag 43.
+    flag43, set_flag43: 43;
+    /// The flag 44.
+    flag44, set_flag44: 44;
+    /// The flag 45.
+    flag45, set_flag45: 45;
+    /// The flag 46.
+    flag46, set_flag46: 46;
+    /// The flag 47.
+    flag47, set_flag47: 47;
+    /// The flag 48.
+    flag48, set_flag48: 48;
+    /// The flag 49.
+    flag49, set_flag49: 49;
+    /// The flag 50.
+    flag50, set_flag50: 50;
+    /// The flag 51.
+    flag51, set_flag51: 51;
+    /// The flag 52.
+    flag52, set_flag52: 52;
+    /// The flag 53.
+    flag53, set_flag53: 53;
+    /// The flag 54.
+    flag54, set_flag54: 54;
+    /// The flag 55.
+    flag55, set_flag55: 55;
+    /// The flag 56.
+    flag56, set_flag56: 56;
+    /// The flag 57.
+    flag57, set_flag57: 57;
+    /// The flag 58.
+    flag58, set_flag58: 58;
+    /// The flag 59.
+    flag59, set_flag59: 59;
+    /// The flag 60.
+    flag60, set_flag60: 60;
+    /// The flag 61.
+    flag61, set_flag61: 61;
+    /// The flag 62.
+    flag62, set_flag62: 62;
+    /// The flag 63.
+    flag63, set_flag63: 63;
+}
+
+#[test]
+fn test_many_documented_fields() {
+    let mut fields = ManyDocumentedFields(0);
+    fields.set_flag0(true);
+    fields.set_flag63(true);
+    assert!(fields.flag0());
+    assert!(!fields.flag31());
+    assert!(fields.flag63());
+    assert_eq!(fields.0, 0x8000_0000_0000_0001);
+    assert!(format!("{:?}", fields).contains("flag63: true"));
 }
@@ -0,0 +1,27 @@
+#![cfg(not(feature = "paste"))]
+
+#[macro_use]
+extern crate bitfield;
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    struct Control(u32);
+    impl Debug;
+    impl new;
+    u8;
+    /// The low byte.
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+    flag, set_flag: 31;
+}
+
+#[test]
+fn test_without_paste() {
+    let mut control = Control(0);
+    control.set_low(0x12);
+    control.set_high(0x34);
+    control.set_flag(true);
+    assert_eq!(control.0, 0x8000_3412);
+    assert_eq!((control.low(), control.high()), (0x12, 0x34));
+    assert_eq!(Control::new(0x12, 0x34, true).0, control.0);
+}
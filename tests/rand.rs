@@ -0,0 +1,60 @@
+#![cfg(feature = "rand")]
+
+#[macro_use]
+extern crate bitfield;
+extern crate rand;
+
+use rand::rngs::mock::StepRng;
+use rand::Rng;
+
+bitfield! {
+    #[derive(Debug, PartialEq)]
+    struct Header(u16);
+    impl Distribution;
+    u8;
+    kind, set_kind: 3, 0;
+    length, set_length: 11, 4;
+}
+
+bitfield! {
+    #[derive(Debug, PartialEq)]
+    struct SparseHeader(u16);
+    impl Distribution{fields};
+    u8;
+    kind, set_kind: 3, 0;
+    flag, set_flag: 6;
+    lanes, set_lane: 9, 8, 3;
+}
+
+bitfield! {
+    struct Packet([u8]);
+    impl Distribution;
+    u8;
+    kind, set_kind: 3, 0;
+}
+
+bitfield! {
+    struct SparsePacket([u8]);
+    impl Distribution{fields};
+    u8;
+    kind, set_kind: 3, 0;
+    payload, set_payload: 23, 16;
+}
+
+#[test]
+fn test_distribution() {
+    let mut rng = StepRng::new(u64::MAX, 0);
+    let header: Header = rng.gen();
+    assert_eq!(header, Header(0xffff));
+    let packet: Packet<[u8; 3]> = rng.gen();
+    assert_eq!(packet.0, [0xff; 3]);
+}
+
+#[test]
+fn test_distribution_fields() {
+    let mut rng = StepRng::new(u64::MAX, 0);
+    let header: SparseHeader = rng.gen();
+    assert_eq!(header, SparseHeader(0x3f4f));
+    let packet: SparsePacket<[u8; 4]> = rng.gen();
+    assert_eq!(packet.0, [0x0f, 0x00, 0xff, 0x00]);
+}
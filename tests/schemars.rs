@@ -0,0 +1,61 @@
+#![cfg(feature = "schemars")]
+
+#[macro_use]
+extern crate bitfield;
+extern crate schemars;
+#[macro_use]
+extern crate serde_json;
+
+use schemars::SchemaGenerator;
+
+bitfield! {
+    struct Header(u16);
+    impl JsonSchema;
+    u8;
+    kind, set_kind: 3, 0;
+    i8, offset, set_offset: 7, 4;
+    flag, set_flag: 8;
+    _, set_write_only: 9;
+    lanes, set_lane: 11, 10, 2;
+}
+
+bitfield! {
+    struct Packet([u8]);
+    impl JsonSchema;
+    u8;
+    kind, set_kind: 3, 0;
+    u16, length, set_length: 19, 8;
+}
+
+#[test]
+fn test_json_schema() {
+    let schema = SchemaGenerator::default().into_root_schema_for::<Header>();
+    assert_eq!(
+        schema.get("properties").unwrap(),
+        &json!({
+            "kind": {"type": "integer", "format": "uint8", "minimum": 0, "maximum": 15},
+            "offset": {"type": "integer", "format": "int8", "minimum": -8, "maximum": 7},
+            "flag": {"type": "boolean"},
+            "lanes": {
+                "type": "array",
+                "items": {"type": "integer", "format": "uint8", "minimum": 0, "maximum": 3},
+                "minItems": 2,
+                "maxItems": 2,
+            },
+        })
+    );
+    assert_eq!(
+        schema.get("required").unwrap(),
+        &json!(["kind", "offset", "flag", "lanes"])
+    );
+    assert_eq!(schema.get("title").unwrap(), &json!("Header"));
+
+    let schema = SchemaGenerator::default().into_root_schema_for::<Packet<[u8; 3]>>();
+    assert_eq!(
+        schema.get("properties").unwrap(),
+        &json!({
+            "kind": {"type": "integer", "format": "uint8", "minimum": 0, "maximum": 15},
+            "length": {"type": "integer", "format": "uint16", "minimum": 0, "maximum": 4095},
+        })
+    );
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "serde")]
+
+#[macro_use]
+extern crate bitfield;
+extern crate serde_json;
+
+bitfield! {
+    #[derive(Debug)]
+    struct Header(u32);
+    impl Serde;
+    u8;
+    flag, set_flag: 0;
+    kind, set_kind: 7, 4;
+    array, set_array: 15, 8, 2;
+    read_only, _: 31, 28;
+    _, write_only: 27, 24;
+    #[cfg(not(test))]
+    not_compiled, set_not_compiled: 23, 20;
+}
+
+bitfield! {
+    struct Packet([u8]);
+    impl Serde;
+    u8;
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+}
+
+#[test]
+fn test_serialize() {
+    let value = Header(0x5000_2102 | 0x1);
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        r#"{"flag":true,"kind":0,"array":[33,0],"read_only":5}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Packet([1u8, 2])).unwrap(),
+        r#"{"low":1,"high":2}"#
+    );
+}
+
+#[test]
+fn test_deserialize() {
+    let value: Header = serde_json::from_str(
+        r#"{"kind":3,"array":[1,2],"read_only":5,"write_only":6,"unknown":1,"flag":true}"#,
+    )
+    .unwrap();
+    assert_eq!(value.0, 0x0602_0131);
+
+    let value: Packet<[u8; 2]> = serde_json::from_str(r#"{"high":2,"low":1}"#).unwrap();
+    assert_eq!(value.0, [1, 2]);
+
+    let value: Packet<[u8; 2]> = serde_json::from_str("[3,4]").unwrap();
+    assert_eq!(value.0, [3, 4]);
+    assert!(serde_json::from_str::<Packet<[u8; 2]>>("[3]").is_err());
+
+    let value: Header = serde_json::from_str("[true,2,[3,4],5]").unwrap();
+    assert_eq!(value.0, 0x0004_0321);
+}
+
+bitfield! {
+    #[derive(Debug, PartialEq)]
+    struct RawHeader(u32);
+    impl SerdeRaw;
+    u8;
+    kind, set_kind: 7, 4;
+}
+
+bitfield! {
+    #[derive(Debug, PartialEq)]
+    struct RawPacket([u8]);
+    impl SerdeRaw;
+    u8;
+    low, set_low: 7, 0;
+}
+
+#[test]
+fn test_serde_raw() {
+    assert_eq!(serde_json::to_string(&RawHeader(0x1234)).unwrap(), "4660");
+    assert_eq!(
+        serde_json::from_str::<RawHeader>("4660").unwrap(),
+        RawHeader(0x1234)
+    );
+
+    assert_eq!(
+        serde_json::to_string(&RawPacket([1u8, 2])).unwrap(),
+        "[1,2]"
+    );
+    assert_eq!(
+        serde_json::from_str::<RawPacket<[u8; 2]>>("[1,2]").unwrap(),
+        RawPacket([1, 2])
+    );
+    assert_eq!(
+        serde_json::from_str::<RawPacket<Vec<u8>>>("[1,2,3]").unwrap(),
+        RawPacket(vec![1, 2, 3])
+    );
+}
+
+bitfield! {
+    struct ValidHeader(u16);
+    impl Serde;
+    u8;
+    valid_if 15, v, set_v: 7, 0;
+}
+
+#[test]
+fn test_valid_if() {
+    assert_eq!(
+        serde_json::to_string(&ValidHeader(0x8005)).unwrap(),
+        r#"{"v":5}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&ValidHeader(0x0005)).unwrap(),
+        r#"{"v":null}"#
+    );
+
+    let value: ValidHeader = serde_json::from_str(r#"{"v":5}"#).unwrap();
+    assert_eq!(value.0, 0x8005);
+    let value: ValidHeader = serde_json::from_str(r#"{"v":null}"#).unwrap();
+    assert_eq!(value.0, 0);
+}
@@ -0,0 +1,164 @@
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate bitfield;
+
+use bitfield::SharedBitfield;
+use std::sync::Arc;
+use std::thread;
+
+bitfield! {
+    struct Counters(u32);
+    u8;
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+}
+
+#[test]
+fn test_shared_bitfield() {
+    let shared = Arc::new(SharedBitfield::new(Counters(0)));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    shared.modify(|value| {
+                        let low = value.low();
+                        value.set_low(low + 1);
+                    });
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(shared.read(|value| value.low()), 40);
+    assert_eq!(shared.read(|value| value.high()), 0);
+}
+
+bitfield! {
+    struct LockedRegisters([u8]);
+    impl StorageCell;
+    u8;
+    low, set_low: 7, 0;
+    high, set_high: 15, 8;
+}
+
+#[test]
+fn test_lock_storage() {
+    let value = LockedRegisters(std::sync::Mutex::new([0u8; 2]));
+    value.with_storage_mut(|value| value.set_high(0x12));
+    assert_eq!(value.with_storage(|value| value.high()), 0x12);
+
+    let value = LockedRegisters(std::sync::RwLock::new([0u8; 2]));
+    value.with_storage_mut(|value| value.set_low(0x34));
+    assert_eq!(value.with_storage(|value| value.low()), 0x34);
+}
+
+bitfield! {
+    struct Dump([u8]);
+    impl Fields;
+    u8;
+    /// Enables the device.
+    enable, set_enable: 0;
+    /// Interrupt priority.
+    priority, set_priority: 3, 1;
+    i8, offset, set_offset: 7, 4;
+    lanes, set_lanes: 9, 8, 2;
+}
+
+#[test]
+fn test_render_table() {
+    let value = Dump([0b1000_0101u8, 0b0000_0011]);
+    assert_eq!(
+        value.render_table(),
+        "Field     Bits   Hex  Value  Description\n\
+         enable    0      0x1  1      Enables the device.\n\
+         priority  3:1    0x2  2      Interrupt priority.\n\
+         offset    7:4    0x8  -8\n\
+         lanes[0]  9:8    0x3  3\n\
+         lanes[1]  11:10  0x0  0\n"
+    );
+}
+
+#[test]
+fn test_render_diff() {
+    let old = Dump([0b1000_0101u8, 0b0000_0011]);
+    let new = Dump([0b1000_0011u8, 0b0000_0001]);
+    let changes: Vec<_> = old
+        .diff(&new)
+        .map(|change| (change.field.name, change.index, change.old, change.new))
+        .collect();
+    assert_eq!(changes, [("priority", 0, 2, 1), ("lanes", 0, 3, 1)]);
+    assert_eq!(old.diff(&old).count(), 0);
+
+    assert_eq!(
+        old.render_diff(&new, false),
+        "Field     Bits  Old  New\n\
+         priority  3:1   0x2  0x1\n\
+         lanes[0]  9:8   0x3  0x1\n"
+    );
+    assert_eq!(
+        old.render_diff(&new, true),
+        "Field     Bits  Old  New\n\
+         priority  3:1   \x1b[31m0x2\x1b[0m  \x1b[32m0x1\x1b[0m\n\
+         lanes[0]  9:8   \x1b[31m0x3\x1b[0m  \x1b[32m0x1\x1b[0m\n"
+    );
+    assert_eq!(old.render_diff(&old, false), "Field  Bits  Old  New\n");
+}
+
+#[test]
+fn test_watcher() {
+    let mut watcher = bitfield::Watcher::new(Dump([0b1000_0101u8, 0b0000_0011]));
+    let changes: Vec<_> = watcher
+        .update(Dump([0b1000_0011u8, 0b0000_0001]))
+        .map(|change| (change.field.name, change.index, change.old, change.new))
+        .collect();
+    assert_eq!(changes, [("priority", 0, 2, 1), ("lanes", 0, 3, 1)]);
+    assert_eq!(watcher.value().0, [0b1000_0011u8, 0b0000_0001]);
+
+    assert_eq!(
+        watcher.update(Dump([0b1000_0011u8, 0b0000_0001])).count(),
+        0
+    );
+    let changes: Vec<_> = watcher
+        .update(Dump([0b1000_0010u8, 0b0000_0001]))
+        .map(|change| change.field.name)
+        .collect();
+    assert_eq!(changes, ["enable"]);
+    assert_eq!(watcher.into_inner().0, [0b1000_0010u8, 0b0000_0001]);
+}
+
+bitfield! {
+    struct FuzzedHeader(u32);
+    impl Encode;
+    impl Decode;
+    u8;
+    version, set_version: 31, 28;
+    flags, set_flags: 11, 8, 2;
+    last, set_last: 0;
+}
+
+bitfield! {
+    struct FuzzedOverlap(u16);
+    impl Encode;
+    impl Decode;
+    u8;
+    low, set_low: 7, 0;
+    nibble, set_nibble: 3, 0;
+}
+
+#[test]
+fn test_fuzz_roundtrip() {
+    for data in [&[][..], &[0xff], &[0x12, 0x34, 0x56], &[0xa5; 8]] {
+        bitfield_fuzz_roundtrip!(FuzzedHeader, data);
+    }
+    bitfield_fuzz_roundtrip!(FuzzedOverlap, &[0x13, 0x30]);
+}
+
+#[test]
+#[should_panic(expected = "the encoded bits differ from the decoded bits")]
+fn test_fuzz_roundtrip_overlap() {
+    bitfield_fuzz_roundtrip!(FuzzedOverlap, &[0x12, 0x34]);
+}
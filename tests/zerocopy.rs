@@ -0,0 +1,49 @@
+#![cfg(feature = "zerocopy")]
+
+#[macro_use]
+extern crate bitfield;
+extern crate zerocopy;
+
+use zerocopy::{FromBytes, IntoBytes, Ref};
+
+bitfield! {
+    struct Flags(u8);
+    impl FromBytes;
+    impl Debug;
+    impl IntoBytes;
+    impl Unaligned;
+    impl KnownLayout;
+    impl Immutable;
+    u8;
+    version, set_version: 7, 4;
+    ihl, set_ihl: 3, 0;
+}
+
+bitfield! {
+    struct Header([u8]);
+    impl Debug;
+    impl FromBytes;
+    impl IntoBytes;
+    impl Unaligned;
+    impl KnownLayout;
+    impl Immutable;
+    u8;
+    version, set_version: 7, 4;
+    u16, length, set_length: 23, 8;
+}
+
+#[test]
+fn test_zerocopy() {
+    let bytes = [0x45u8, 0x34, 0x12];
+    let flags = Ref::<_, Flags>::from_prefix(&bytes[..]).unwrap().0;
+    assert_eq!(flags.version(), 4);
+    assert_eq!(flags.ihl(), 5);
+
+    let header = Header::<[u8; 3]>::ref_from_bytes(&bytes).unwrap();
+    assert_eq!(header.version(), 4);
+    assert_eq!(header.length(), 0x1234);
+
+    let mut header = Header([0u8; 3]);
+    header.set_length(0xabcd);
+    assert_eq!(header.as_bytes(), [0x00, 0xcd, 0xab]);
+}